@@ -3,15 +3,16 @@ extern crate criterion;
 
 use criterion::{black_box, Criterion};
 use rust_bert::gpt2::{
-    Gpt2ConfigResources, Gpt2MergesResources, Gpt2ModelResources, Gpt2VocabResources,
+    GPT2Generator, Gpt2ConfigResources, Gpt2MergesResources, Gpt2ModelResources, Gpt2VocabResources,
 };
 use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
 use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
 use rust_bert::resources::RemoteResource;
 use std::time::{Duration, Instant};
 use tch::Device;
 
-fn create_text_generation_model() -> TextGenerationModel {
+fn create_text_generation_model(static_cache: bool) -> TextGenerationModel {
     let config = TextGenerationConfig {
         model_type: ModelType::GPT2,
         model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
@@ -34,11 +35,32 @@ fn create_text_generation_model() -> TextGenerationModel {
         num_beam_groups: None,
         diversity_penalty: None,
         num_return_sequences: 5,
+        static_cache,
         device: Device::cuda_if_available(),
+        ..Default::default()
     };
     TextGenerationModel::new(config).unwrap()
 }
 
+fn create_gpt2_generator() -> GPT2Generator {
+    let config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+        config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            Gpt2MergesResources::GPT2,
+        ))),
+        max_length: Some(30),
+        do_sample: true,
+        num_beams: 1,
+        top_k: 0,
+        top_p: 0.9,
+        device: Device::cuda_if_available(),
+        ..Default::default()
+    };
+    GPT2Generator::new(config).unwrap()
+}
+
 fn generation_forward_pass(iters: u64, model: &TextGenerationModel, data: &[&str]) -> Duration {
     let mut duration = Duration::new(0, 0);
     for _i in 0..iters {
@@ -49,18 +71,54 @@ fn generation_forward_pass(iters: u64, model: &TextGenerationModel, data: &[&str
     duration
 }
 
+fn sampling_forward_pass(
+    iters: u64,
+    model: &GPT2Generator,
+    data: &[&str],
+    seeded: bool,
+) -> Duration {
+    let mut duration = Duration::new(0, 0);
+    for i in 0..iters {
+        let generate_options = GenerateOptions {
+            seed: if seeded { Some(i) } else { None },
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let _ = model.generate(Some(data), Some(generate_options));
+        duration = duration.checked_add(start.elapsed()).unwrap();
+    }
+    duration
+}
+
 fn bench_generation(c: &mut Criterion) {
     //    Set-up summarization model
     unsafe {
         torch_sys::dummy_cuda_dependency();
     }
-    let model = create_text_generation_model();
+    let model = create_text_generation_model(false);
+    let static_cache_model = create_text_generation_model(true);
+    let sampling_model = create_gpt2_generator();
 
     //    Define input
     let input = ["Hello, I'm a language model,"];
     c.bench_function("Generation", |b| {
         b.iter_custom(|iters| black_box(generation_forward_pass(iters, &model, &input)))
     });
+    c.bench_function("Generation (static cache)", |b| {
+        b.iter_custom(|iters| {
+            black_box(generation_forward_pass(iters, &static_cache_model, &input))
+        })
+    });
+    c.bench_function("Generation (sampling)", |b| {
+        b.iter_custom(|iters| {
+            black_box(sampling_forward_pass(iters, &sampling_model, &input, false))
+        })
+    });
+    c.bench_function("Generation (seeded sampling)", |b| {
+        b.iter_custom(|iters| {
+            black_box(sampling_forward_pass(iters, &sampling_model, &input, true))
+        })
+    });
 }
 
 criterion_group! {
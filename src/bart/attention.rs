@@ -12,6 +12,7 @@
 // limitations under the License.
 
 use crate::common::dropout::Dropout;
+use crate::common::kv_cache::{new_static_buffer, static_attention_mask, write_static_states};
 use std::borrow::Borrow;
 use tch::{nn, Tensor};
 
@@ -23,6 +24,9 @@ pub struct LayerState {
     pub prev_key: Tensor,
     /// Cached values
     pub prev_value: Tensor,
+    /// Number of filled positions if the keys and values are preallocated buffers updated in place
+    /// (static cache), `None` if they are extended at each step
+    pub static_length: Option<i64>,
 }
 
 impl Clone for LayerState {
@@ -30,6 +34,7 @@ impl Clone for LayerState {
         LayerState {
             prev_key: self.prev_key.copy(),
             prev_value: self.prev_value.copy(),
+            static_length: self.static_length,
         }
     }
 }
@@ -39,6 +44,15 @@ impl LayerState {
         self.prev_key = self.prev_key.index_select(0, new_indices);
         self.prev_value = self.prev_value.index_select(0, new_indices);
     }
+
+    pub(crate) fn preallocate(&mut self, max_length: i64) {
+        let length = self.prev_key.size()[2];
+        if self.static_length.is_none() & (length < max_length) {
+            self.prev_key = new_static_buffer(&self.prev_key, 2, max_length);
+            self.prev_value = new_static_buffer(&self.prev_value, 2, max_length);
+            self.static_length = Some(length);
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -113,33 +127,71 @@ impl BartAttention {
 
         let query_states = hidden_states.apply(&self.q_proj) * self.scaling;
 
-        let (key_states, value_states) = if self.encoder_decoder_attention {
+        let mut static_mask = None;
+        let (key_states, value_states, static_length) = if self.encoder_decoder_attention {
             if let Some(layer_state_value) = layer_state {
-                (layer_state_value.prev_key, layer_state_value.prev_value)
+                (
+                    layer_state_value.prev_key,
+                    layer_state_value.prev_value,
+                    None,
+                )
             } else {
                 (
                     self._shape(key_value_states.unwrap().apply(&self.k_proj), -1, bs),
                     self._shape(key_value_states.unwrap().apply(&self.v_proj), -1, bs),
+                    None,
                 )
             }
         } else if let Some(layer_state_value) = layer_state {
             let key_states = self._shape(hidden_states.apply(&self.k_proj), -1, bs);
             let value_states = self._shape(hidden_states.apply(&self.v_proj), -1, bs);
-            (
-                Tensor::cat(&[layer_state_value.prev_key, key_states], 2),
-                Tensor::cat(&[layer_state_value.prev_value, value_states], 2),
-            )
+            if let Some(static_length) = layer_state_value.static_length {
+                write_static_states(&layer_state_value.prev_key, &key_states, 2, static_length);
+                write_static_states(
+                    &layer_state_value.prev_value,
+                    &value_states,
+                    2,
+                    static_length,
+                );
+                static_mask = Some(static_attention_mask(
+                    attention_mask,
+                    &query_states,
+                    static_length + target_length,
+                    layer_state_value.prev_key.size()[2],
+                ));
+                (
+                    layer_state_value.prev_key,
+                    layer_state_value.prev_value,
+                    Some(static_length + target_length),
+                )
+            } else {
+                (
+                    Tensor::cat(&[layer_state_value.prev_key, key_states], 2),
+                    Tensor::cat(&[layer_state_value.prev_value, value_states], 2),
+                    None,
+                )
+            }
         } else {
             (
                 self._shape(hidden_states.apply(&self.k_proj), -1, bs),
                 self._shape(hidden_states.apply(&self.v_proj), -1, bs),
+                None,
             )
         };
+        let attention_mask = static_mask.as_ref().or(attention_mask);
 
         let new_layer_state = if self.store_cache {
-            Some(LayerState {
-                prev_key: key_states.copy(),
-                prev_value: value_states.copy(),
+            Some(match static_length {
+                Some(_) => LayerState {
+                    prev_key: key_states.shallow_clone(),
+                    prev_value: value_states.shallow_clone(),
+                    static_length,
+                },
+                None => LayerState {
+                    prev_key: key_states.copy(),
+                    prev_value: value_states.copy(),
+                    static_length,
+                },
             })
         } else {
             None
@@ -25,6 +25,15 @@ use crate::{
 use std::borrow::{Borrow, BorrowMut};
 use tch::{nn, Tensor};
 
+/// Output of a decoder layer: hidden state, self-attention weights, cross-attention weights and
+/// updated self-attention and cross-attention caches
+pub type DecoderLayerOutput = (
+    Tensor,
+    Option<Tensor>,
+    Option<Tensor>,
+    (Option<LayerState>, Option<LayerState>),
+);
+
 pub struct DecoderLayer {
     self_attention: BartAttention,
     encoder_attention: BartAttention,
@@ -124,24 +133,21 @@ impl DecoderLayer {
         decoder_attention_mask: Option<&Tensor>,
         layer_states: (Option<LayerState>, Option<LayerState>),
         train: bool,
-    ) -> (
-        Tensor,
-        Option<Tensor>,
-        (Option<LayerState>, Option<LayerState>),
-    ) {
+    ) -> DecoderLayerOutput {
         let (output, attention_weights, new_self_layer_states) =
             self.self_attention
                 .forward_t(x, None, decoder_attention_mask, layer_states.0, train);
         let output: Tensor = output.apply_t(&self.dropout, train) + x;
         let output = output.apply(&self.self_attention_layer_norm);
 
-        let (output1, _, new_encoder_layer_states) = self.encoder_attention.forward_t(
-            &output,
-            Some(encoder_hidden_states),
-            encoder_attention_mask,
-            layer_states.1,
-            train,
-        );
+        let (output1, cross_attention_weights, new_encoder_layer_states) =
+            self.encoder_attention.forward_t(
+                &output,
+                Some(encoder_hidden_states),
+                encoder_attention_mask,
+                layer_states.1,
+                train,
+            );
         let output1: Tensor = output1.apply_t(&self.dropout, train) + output;
         let output1 = output1.apply(&self.encoder_attention_layer_norm);
         let output2 = (self.activation.get_fn())(&output1.apply(&self.fc1));
@@ -153,6 +159,7 @@ impl DecoderLayer {
         (
             output2.apply(&self.final_layer_norm),
             attention_weights,
+            cross_attention_weights,
             (new_self_layer_states, new_encoder_layer_states),
         )
     }
@@ -251,7 +258,9 @@ impl BartDecoder {
     ) -> BartDecoderOutput {
         let past_key_values_length = if let Some(old_layer_states_values) = &old_layer_states {
             if let Some(old_value_state) = &old_layer_states_values[0].0 {
-                old_value_state.prev_key.size()[2]
+                old_value_state
+                    .static_length
+                    .unwrap_or_else(|| old_value_state.prev_key.size()[2])
             } else {
                 0
             }
@@ -291,6 +300,11 @@ impl BartDecoder {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
         let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.output_past {
                 if old_layer_states.is_some() {
@@ -303,10 +317,11 @@ impl BartDecoder {
             };
 
         let mut attention_weights: Option<Tensor>;
+        let mut cross_attention_weights: Option<Tensor>;
 
         for (layer_idx, layer) in self.layers.iter().enumerate() {
-            let layer_state = match &next_decoder_cache {
-                Some(values) => values[layer_idx].to_owned(),
+            let layer_state = match &mut next_decoder_cache {
+                Some(values) => std::mem::take(&mut values[layer_idx]),
                 None => (None, None),
             };
             let temp = layer.forward_t(
@@ -319,14 +334,18 @@ impl BartDecoder {
             );
             hidden_state = temp.0;
             attention_weights = temp.1;
+            cross_attention_weights = temp.2;
             if let Some(hidden_states) = all_hidden_states.borrow_mut() {
                 hidden_states.push(hidden_state.as_ref().copy());
             };
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(std::mem::take(&mut attention_weights.unwrap()));
             };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(std::mem::take(&mut cross_attention_weights.unwrap()));
+            };
             if let Some(value) = &mut next_decoder_cache {
-                value[layer_idx] = temp.2
+                value[layer_idx] = temp.3
             };
         }
 
@@ -336,6 +355,7 @@ impl BartDecoder {
             next_decoder_cache,
             all_hidden_states,
             all_attentions,
+            all_cross_attentions,
         }
     }
 }
@@ -352,4 +372,6 @@ pub struct BartDecoderOutput {
     pub all_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all intermediate layers
     pub all_attentions: Option<Vec<Tensor>>,
+    /// Cross-attention weights (over the encoder positions) for all intermediate layers
+    pub all_cross_attentions: Option<Vec<Tensor>>,
 }
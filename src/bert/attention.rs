@@ -14,6 +14,8 @@
 use crate::bert::bert_model::BertConfig;
 use crate::common::activations::TensorFunction;
 use crate::common::dropout::Dropout;
+use crate::common::quantization::{quantizable_linear, QuantizableLinear, QuantizationMode};
+use crate::RustBertError;
 use std::borrow::Borrow;
 use tch::{nn, Tensor};
 
@@ -23,9 +25,9 @@ pub struct BertSelfAttention {
     attention_head_size: i64,
     dropout: Dropout,
     output_attentions: bool,
-    query: nn::Linear,
-    key: nn::Linear,
-    value: nn::Linear,
+    query: QuantizableLinear,
+    key: QuantizableLinear,
+    value: QuantizableLinear,
 }
 
 impl BertSelfAttention {
@@ -40,19 +42,19 @@ impl BertSelfAttention {
         );
         let p = p.borrow();
 
-        let query = nn::linear(
+        let query = quantizable_linear(
             p / "query",
             config.hidden_size,
             config.hidden_size,
             Default::default(),
         );
-        let key = nn::linear(
+        let key = quantizable_linear(
             p / "key",
             config.hidden_size,
             config.hidden_size,
             Default::default(),
         );
-        let value = nn::linear(
+        let value = quantizable_linear(
             p / "value",
             config.hidden_size,
             config.hidden_size,
@@ -74,6 +76,12 @@ impl BertSelfAttention {
         }
     }
 
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.query.quantize(mode)?;
+        self.key.quantize(mode)?;
+        self.value.quantize(mode)
+    }
+
     fn split_heads(&self, x: Tensor, bs: i64, dim_per_head: i64) -> Tensor {
         x.view((bs, -1, self.num_attention_heads, dim_per_head))
             .transpose(1, 2)
@@ -138,7 +146,7 @@ impl BertSelfAttention {
 
 #[derive(Debug)]
 pub struct BertSelfOutput {
-    linear: nn::Linear,
+    linear: QuantizableLinear,
     layer_norm: nn::LayerNorm,
     dropout: Dropout,
 }
@@ -150,7 +158,7 @@ impl BertSelfOutput {
     {
         let p = p.borrow();
 
-        let linear = nn::linear(
+        let linear = quantizable_linear(
             p / "dense",
             config.hidden_size,
             config.hidden_size,
@@ -171,6 +179,10 @@ impl BertSelfOutput {
         }
     }
 
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.linear.quantize(mode)
+    }
+
     pub fn forward_t(&self, hidden_states: &Tensor, input_tensor: &Tensor, train: bool) -> Tensor {
         let hidden_states: Tensor = input_tensor
             + hidden_states
@@ -198,6 +210,11 @@ impl BertAttention {
         BertAttention { _self, output }
     }
 
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self._self.quantize(mode)?;
+        self.output.quantize(mode)
+    }
+
     pub fn forward_t(
         &self,
         hidden_states: &Tensor,
@@ -220,7 +237,7 @@ impl BertAttention {
 }
 
 pub struct BertIntermediate {
-    lin: nn::Linear,
+    lin: QuantizableLinear,
     activation: TensorFunction,
 }
 
@@ -231,7 +248,7 @@ impl BertIntermediate {
     {
         let p = p.borrow();
 
-        let lin = nn::linear(
+        let lin = quantizable_linear(
             p / "dense",
             config.hidden_size,
             config.intermediate_size,
@@ -241,13 +258,17 @@ impl BertIntermediate {
         BertIntermediate { lin, activation }
     }
 
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.lin.quantize(mode)
+    }
+
     pub fn forward(&self, hidden_states: &Tensor) -> Tensor {
         (self.activation.get_fn())(&hidden_states.apply(&self.lin))
     }
 }
 
 pub struct BertOutput {
-    lin: nn::Linear,
+    lin: QuantizableLinear,
     layer_norm: nn::LayerNorm,
     dropout: Dropout,
 }
@@ -259,7 +280,7 @@ impl BertOutput {
     {
         let p = p.borrow();
 
-        let lin = nn::linear(
+        let lin = quantizable_linear(
             p / "dense",
             config.intermediate_size,
             config.hidden_size,
@@ -280,6 +301,10 @@ impl BertOutput {
         }
     }
 
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.lin.quantize(mode)
+    }
+
     pub fn forward_t(&self, hidden_states: &Tensor, input_tensor: &Tensor, train: bool) -> Tensor {
         let hidden_states: Tensor =
             input_tensor + hidden_states.apply(&self.lin).apply_t(&self.dropout, train);
@@ -16,6 +16,7 @@ use crate::common::activations::Activation;
 use crate::common::dropout::Dropout;
 use crate::common::embeddings::get_shape_and_device_from_ids_embeddings_pair;
 use crate::common::linear::{linear_no_bias, LinearNoBias};
+use crate::common::quantization::QuantizationMode;
 use crate::{
     bert::embeddings::{BertEmbedding, BertEmbeddings},
     common::activations::TensorFunction,
@@ -293,6 +294,35 @@ impl<T: BertEmbedding> BertModel<T> {
         }
     }
 
+    /// Quantizes the linear layers of the encoder stack (the embeddings and pooler keep full
+    /// precision weights).
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::bert::{BertConfig, BertEmbeddings, BertModel};
+    /// # use rust_bert::quantization::QuantizationMode;
+    /// # use rust_bert::weights::load_weights;
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::{nn, Device};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config = BertConfig::from_file(Path::new("path/to/config.json"));
+    /// let mut vs = nn::VarStore::new(Device::Cpu);
+    /// let mut bert_model: BertModel<BertEmbeddings> = BertModel::new(&vs.root(), &config);
+    /// load_weights(Path::new("path/to/rust_model.ot"), &mut vs)?;
+    /// bert_model.quantize(QuantizationMode::DynamicInt8)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.encoder.quantize(mode)
+    }
+
     /// Forward pass through the model
     ///
     /// # Arguments
@@ -711,6 +741,16 @@ impl BertForSequenceClassification {
         }
     }
 
+    /// Quantizes the linear layers of the encoder stack, the task-specific head keeping full
+    /// precision weights.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.bert.quantize(mode)
+    }
+
     /// Forward pass through the model
     ///
     /// # Arguments
@@ -1000,6 +1040,16 @@ impl BertForTokenClassification {
         }
     }
 
+    /// Quantizes the linear layers of the encoder stack, the task-specific head keeping full
+    /// precision weights.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.bert.quantize(mode)
+    }
+
     /// Forward pass through the model
     ///
     /// # Arguments
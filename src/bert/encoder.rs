@@ -13,6 +13,8 @@
 
 use crate::bert::attention::{BertAttention, BertIntermediate, BertOutput};
 use crate::bert::bert_model::BertConfig;
+use crate::common::quantization::QuantizationMode;
+use crate::RustBertError;
 use std::borrow::{Borrow, BorrowMut};
 use tch::{nn, Tensor};
 
@@ -87,6 +89,20 @@ impl BertLayer {
         }
     }
 
+    /// Quantizes the linear layers of the attention, intermediate and output blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the layer.
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.attention.quantize(mode)?;
+        if let Some(cross_attention) = self.cross_attention.as_mut() {
+            cross_attention.quantize(mode)?;
+        }
+        self.intermediate.quantize(mode)?;
+        self.output.quantize(mode)
+    }
+
     /// Forward pass through the layer
     ///
     /// # Arguments
@@ -215,6 +231,36 @@ impl BertEncoder {
         }
     }
 
+    /// Quantizes the linear layers of all encoder layers.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the encoder.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::bert::{BertConfig, BertEncoder};
+    /// # use rust_bert::quantization::QuantizationMode;
+    /// # use tch::{nn, Device};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let vs = nn::VarStore::new(Device::Cpu);
+    /// # let config = BertConfig::from_file(config_path);
+    /// let mut encoder: BertEncoder = BertEncoder::new(&vs.root(), &config);
+    /// encoder.quantize(QuantizationMode::DynamicInt8)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        for layer in self.layers.iter_mut() {
+            layer.quantize(mode)?;
+        }
+        Ok(())
+    }
+
     /// Forward pass through the encoder
     ///
     /// # Arguments
@@ -9,6 +9,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::RustBertError;
 use serde::Deserialize;
 use std::fs::File;
 use std::io::BufReader;
@@ -26,6 +27,10 @@ where
     ///
     /// * `path` - `Path` to the configuration JSON file.
     ///
+    /// # Panics
+    ///
+    /// If the configuration file cannot be opened or parsed. See `try_from_file` for a fallible version of this method.
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -37,9 +42,49 @@ where
     /// let config = Gpt2Config::from_file(config_path);
     /// ```
     fn from_file<P: AsRef<Path>>(path: P) -> Self {
-        let f = File::open(path).expect("Could not open configuration file.");
+        Self::try_from_file(path).unwrap()
+    }
+
+    /// Loads a `Config` object from a JSON file, returning an error if the file cannot be opened or
+    /// parsed. The format is expected to be aligned with the [Transformers library](https://github.com/huggingface/transformers) configuration files for each model.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - `Path` to the configuration JSON file.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, RustBertError>` containing the configuration, or a `RustBertError::InvalidConfigurationError` indicating the file or key that could not be read
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::Gpt2Config;
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let config = Gpt2Config::try_from_file(config_path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn try_from_file<P: AsRef<Path>>(path: P) -> Result<Self, RustBertError> {
+        let path = path.as_ref();
+        let f = File::open(path).map_err(|error| {
+            RustBertError::InvalidConfigurationError(format!(
+                "Could not open configuration file {}: {}",
+                path.display(),
+                error
+            ))
+        })?;
         let br = BufReader::new(f);
-        let config: Self = serde_json::from_reader(br).expect("could not parse configuration");
-        config
+        serde_json::from_reader(br).map_err(|error| {
+            RustBertError::InvalidConfigurationError(format!(
+                "Could not parse configuration file {}: {}",
+                path.display(),
+                error
+            ))
+        })
     }
 }
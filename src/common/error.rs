@@ -1,3 +1,4 @@
+use crate::common::weights::WeightsLoadingReport;
 use rust_tokenizers::error::TokenizerError;
 use tch::TchError;
 use thiserror::Error;
@@ -22,6 +23,9 @@ pub enum RustBertError {
 
     #[error("Value error: {0}")]
     ValueError(String),
+
+    #[error("Weights loading error: {0}")]
+    WeightsLoadingError(WeightsLoadingReport),
 }
 
 impl From<std::io::Error> for RustBertError {
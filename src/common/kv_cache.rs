@@ -0,0 +1,64 @@
+// Copyright 2019 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers for the preallocated (static) key/value caches: the keys and values of the
+//! self-attention layers are stored in buffers sized to the maximum generation length, written in
+//! place at each decoding step. The attention layers attend over the full buffers, with an attention
+//! mask over the filled positions.
+
+use crate::common::kind::get_negative_infinity;
+use tch::Tensor;
+
+/// Allocates a zero-initialized buffer with the shape and type of `states`, extended to
+/// `max_length` positions along `dim`, and writes `states` at its first positions.
+pub(crate) fn new_static_buffer(states: &Tensor, dim: i64, max_length: i64) -> Tensor {
+    let mut buffer_shape = states.size();
+    buffer_shape[dim as usize] = max_length;
+    let buffer = Tensor::zeros(&buffer_shape, (states.kind(), states.device()));
+    write_static_states(&buffer, states, dim, 0);
+    buffer
+}
+
+/// Writes `states` in place in the preallocated `buffer`, starting at position `start` along `dim`.
+pub(crate) fn write_static_states(buffer: &Tensor, states: &Tensor, dim: i64, start: i64) {
+    let length = states.size()[dim as usize];
+    let mut target = buffer.narrow(dim, start, length);
+    target.copy_(states);
+}
+
+/// Extends an additive attention mask over the `filled_length` first positions of a preallocated
+/// key/value buffer to the full `buffer_length`, masking the positions not filled yet. If no mask is
+/// provided, all the filled positions are attended to.
+pub(crate) fn static_attention_mask(
+    attention_mask: Option<&Tensor>,
+    reference: &Tensor,
+    filled_length: i64,
+    buffer_length: i64,
+) -> Tensor {
+    let (kind, device) = (reference.kind(), reference.device());
+    let filled_mask = match attention_mask {
+        Some(attention_mask) => attention_mask.to_kind(kind),
+        None => Tensor::zeros(&[1, 1, 1, filled_length], (kind, device)),
+    };
+    let mut padding_shape = filled_mask.size();
+    *padding_shape.last_mut().unwrap() = buffer_length - filled_length;
+    Tensor::cat(
+        &[
+            filled_mask,
+            Tensor::full(
+                &padding_shape,
+                get_negative_infinity(kind).unwrap(),
+                (kind, device),
+            ),
+        ],
+        -1,
+    )
+}
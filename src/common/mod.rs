@@ -4,9 +4,13 @@ pub(crate) mod dropout;
 pub(crate) mod embeddings;
 pub mod error;
 pub(crate) mod kind;
+pub(crate) mod kv_cache;
 pub(crate) mod linear;
+pub(crate) mod pytorch_checkpoint;
+pub mod quantization;
 pub mod resources;
 pub(crate) mod summary;
+pub mod weights;
 
 pub use activations::Activation;
 pub use config::Config;
@@ -0,0 +1,566 @@
+// Copyright 2019-present Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reader for Pytorch checkpoints saved with `torch.save` (zip archive format, Pytorch >= 1.6).
+//!
+//! The archive contains a pickle file (`<archive>/data.pkl`) describing the saved object and the
+//! raw storages of the tensors (`<archive>/data/<key>`). The pickle is interpreted by a minimal
+//! unpickler supporting the opcodes and classes used by `torch.save` for state dictionaries:
+//! tensors are rebuilt from a view (offset, size and stride) on their storage.
+
+use crate::RustBertError;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tch::{Kind, Tensor};
+use zip::ZipArchive;
+
+/// Storage reference of a tensor, loaded from `<archive>/data/<key>`
+#[derive(Debug, Clone)]
+struct StorageReference {
+    kind: Kind,
+    key: String,
+}
+
+/// Tensor view on a storage, as rebuilt by `torch._utils._rebuild_tensor_v2`
+#[derive(Debug, Clone)]
+struct TensorReference {
+    storage: StorageReference,
+    offset: i64,
+    size: Vec<i64>,
+    stride: Vec<i64>,
+}
+
+/// Values of the unpickled objects. The floating point and bytes values are not needed to rebuild
+/// the tensors and are not decoded.
+#[derive(Debug, Clone)]
+enum PickleValue {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float,
+    String(String),
+    Bytes,
+    Tuple(Vec<PickleValue>),
+    List(Vec<PickleValue>),
+    Dict(Vec<(PickleValue, PickleValue)>),
+    Global(String, String),
+    Storage(StorageReference),
+    Tensor(TensorReference),
+}
+
+impl PickleValue {
+    fn into_int(self) -> Result<i64, RustBertError> {
+        match self {
+            PickleValue::Int(value) => Ok(value),
+            PickleValue::Bool(value) => Ok(value as i64),
+            value => Err(invalid_pickle(format!(
+                "expected an integer, got {:?}",
+                value
+            ))),
+        }
+    }
+
+    fn into_int_vec(self) -> Result<Vec<i64>, RustBertError> {
+        match self {
+            PickleValue::Tuple(values) | PickleValue::List(values) => {
+                values.into_iter().map(PickleValue::into_int).collect()
+            }
+            value => Err(invalid_pickle(format!(
+                "expected a sequence of integers, got {:?}",
+                value
+            ))),
+        }
+    }
+}
+
+fn invalid_pickle(message: String) -> RustBertError {
+    RustBertError::IOError(format!("Invalid Pytorch checkpoint: {}", message))
+}
+
+fn storage_kind(storage_type: &str) -> Result<Kind, RustBertError> {
+    Ok(match storage_type {
+        "FloatStorage" => Kind::Float,
+        "DoubleStorage" => Kind::Double,
+        "HalfStorage" => Kind::Half,
+        "BFloat16Storage" => Kind::BFloat16,
+        "LongStorage" => Kind::Int64,
+        "IntStorage" => Kind::Int,
+        "ShortStorage" => Kind::Int16,
+        "CharStorage" => Kind::Int8,
+        "ByteStorage" => Kind::Uint8,
+        "BoolStorage" => Kind::Bool,
+        _ => {
+            return Err(invalid_pickle(format!(
+                "unsupported storage type {}",
+                storage_type
+            )))
+        }
+    })
+}
+
+/// Minimal unpickler for the objects serialized by `torch.save`
+struct Unpickler<'a> {
+    data: &'a [u8],
+    position: usize,
+    stack: Vec<PickleValue>,
+    marks: Vec<usize>,
+    memo: HashMap<u32, PickleValue>,
+}
+
+impl<'a> Unpickler<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Unpickler {
+            data,
+            position: 0,
+            stack: vec![],
+            marks: vec![],
+            memo: HashMap::new(),
+        }
+    }
+
+    fn read(&mut self, length: usize) -> Result<&'a [u8], RustBertError> {
+        let end = self.position + length;
+        if end > self.data.len() {
+            return Err(invalid_pickle("unexpected end of pickle data".to_string()));
+        }
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, RustBertError> {
+        Ok(self.read(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, RustBertError> {
+        let bytes = self.read(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, RustBertError> {
+        let bytes = self.read(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_line(&mut self) -> Result<String, RustBertError> {
+        let length = self.data[self.position..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or_else(|| invalid_pickle("unterminated line".to_string()))?;
+        let line = self.read(length)?;
+        self.position += 1;
+        Ok(String::from_utf8_lossy(line).into_owned())
+    }
+
+    fn read_string(&mut self, length: usize) -> Result<String, RustBertError> {
+        let bytes = self.read(length)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|error| invalid_pickle(format!("invalid string: {}", error)))
+    }
+
+    fn pop(&mut self) -> Result<PickleValue, RustBertError> {
+        self.stack
+            .pop()
+            .ok_or_else(|| invalid_pickle("empty stack".to_string()))
+    }
+
+    fn pop_mark(&mut self) -> Result<Vec<PickleValue>, RustBertError> {
+        let mark = self
+            .marks
+            .pop()
+            .ok_or_else(|| invalid_pickle("missing mark".to_string()))?;
+        if mark > self.stack.len() {
+            return Err(invalid_pickle("invalid mark".to_string()));
+        }
+        Ok(self.stack.split_off(mark))
+    }
+
+    fn last_mut(&mut self) -> Result<&mut PickleValue, RustBertError> {
+        self.stack
+            .last_mut()
+            .ok_or_else(|| invalid_pickle("empty stack".to_string()))
+    }
+
+    fn memoize(&mut self, index: u32) -> Result<(), RustBertError> {
+        let value = self
+            .stack
+            .last()
+            .ok_or_else(|| invalid_pickle("empty stack".to_string()))?
+            .clone();
+        self.memo.insert(index, value);
+        Ok(())
+    }
+
+    fn get_memo(&mut self, index: u32) -> Result<(), RustBertError> {
+        let value = self
+            .memo
+            .get(&index)
+            .ok_or_else(|| invalid_pickle(format!("missing memo entry {}", index)))?
+            .clone();
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn set_items(&mut self, items: Vec<PickleValue>) -> Result<(), RustBertError> {
+        if items.len() % 2 == 1 {
+            return Err(invalid_pickle("odd number of dictionary items".to_string()));
+        }
+        match self.last_mut()? {
+            PickleValue::Dict(entries) => {
+                let mut items = items.into_iter();
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    entries.push((key, value));
+                }
+                Ok(())
+            }
+            value => Err(invalid_pickle(format!("cannot set items on {:?}", value))),
+        }
+    }
+
+    fn append(&mut self, items: Vec<PickleValue>) -> Result<(), RustBertError> {
+        match self.last_mut()? {
+            PickleValue::List(values) => {
+                values.extend(items);
+                Ok(())
+            }
+            value => Err(invalid_pickle(format!("cannot append to {:?}", value))),
+        }
+    }
+
+    fn persistent_load(&self, pid: PickleValue) -> Result<PickleValue, RustBertError> {
+        match pid {
+            PickleValue::Tuple(values) if values.len() >= 3 => {
+                let mut values = values.into_iter();
+                match (values.next(), values.next(), values.next()) {
+                    (
+                        Some(PickleValue::String(typename)),
+                        Some(PickleValue::Global(_, storage_type)),
+                        Some(PickleValue::String(key)),
+                    ) if typename == "storage" => Ok(PickleValue::Storage(StorageReference {
+                        kind: storage_kind(&storage_type)?,
+                        key,
+                    })),
+                    _ => Err(invalid_pickle("unsupported persistent id".to_string())),
+                }
+            }
+            pid => Err(invalid_pickle(format!(
+                "unsupported persistent id {:?}",
+                pid
+            ))),
+        }
+    }
+
+    fn reduce(
+        &self,
+        callable: PickleValue,
+        args: PickleValue,
+    ) -> Result<PickleValue, RustBertError> {
+        let args = match args {
+            PickleValue::Tuple(args) => args,
+            args => {
+                return Err(invalid_pickle(format!(
+                    "expected a tuple of arguments, got {:?}",
+                    args
+                )))
+            }
+        };
+        let (module, name) = match callable {
+            PickleValue::Global(module, name) => (module, name),
+            callable => return Err(invalid_pickle(format!("cannot call {:?}", callable))),
+        };
+        match (module.as_str(), name.as_str()) {
+            ("collections", "OrderedDict") | ("builtins", "dict") => Ok(PickleValue::Dict(vec![])),
+            ("torch._utils", "_rebuild_tensor") | ("torch._utils", "_rebuild_tensor_v2") => {
+                let mut args = args.into_iter();
+                match (args.next(), args.next(), args.next(), args.next()) {
+                    (
+                        Some(PickleValue::Storage(storage)),
+                        Some(offset),
+                        Some(size),
+                        Some(stride),
+                    ) => Ok(PickleValue::Tensor(TensorReference {
+                        storage,
+                        offset: offset.into_int()?,
+                        size: size.into_int_vec()?,
+                        stride: stride.into_int_vec()?,
+                    })),
+                    _ => Err(invalid_pickle(format!(
+                        "invalid arguments for {}.{}",
+                        module, name
+                    ))),
+                }
+            }
+            ("torch._utils", "_rebuild_parameter")
+            | ("torch._utils", "_rebuild_parameter_with_state") => args
+                .into_iter()
+                .next()
+                .ok_or_else(|| invalid_pickle(format!("missing arguments for {}", name))),
+            _ => Err(invalid_pickle(format!(
+                "unsupported class {}.{}",
+                module, name
+            ))),
+        }
+    }
+
+    fn load(mut self) -> Result<PickleValue, RustBertError> {
+        loop {
+            let opcode = self.read_u8()?;
+            match opcode {
+                // PROTO
+                0x80 => {
+                    self.read_u8()?;
+                }
+                // FRAME
+                0x95 => {
+                    self.read(8)?;
+                }
+                // STOP
+                b'.' => return self.pop(),
+                // MARK
+                b'(' => self.marks.push(self.stack.len()),
+                b'N' => self.stack.push(PickleValue::None),
+                0x88 => self.stack.push(PickleValue::Bool(true)),
+                0x89 => self.stack.push(PickleValue::Bool(false)),
+                // BININT, BININT1, BININT2
+                b'J' => {
+                    let value = self.read_u32()? as i32;
+                    self.stack.push(PickleValue::Int(value as i64));
+                }
+                b'K' => {
+                    let value = self.read_u8()?;
+                    self.stack.push(PickleValue::Int(value as i64));
+                }
+                b'M' => {
+                    let value = self.read_u16()?;
+                    self.stack.push(PickleValue::Int(value as i64));
+                }
+                // LONG1
+                0x8a => {
+                    let length = self.read_u8()? as usize;
+                    let bytes = self.read(length)?;
+                    if length > 8 {
+                        return Err(invalid_pickle("integer overflow".to_string()));
+                    }
+                    let mut value = 0i64;
+                    for (index, &byte) in bytes.iter().enumerate() {
+                        value |= (byte as i64) << (8 * index);
+                    }
+                    if length > 0 && length < 8 && bytes[length - 1] & 0x80 != 0 {
+                        value -= 1i64 << (8 * length);
+                    }
+                    self.stack.push(PickleValue::Int(value));
+                }
+                // BINFLOAT
+                b'G' => {
+                    self.read(8)?;
+                    self.stack.push(PickleValue::Float);
+                }
+                // BINUNICODE, SHORT_BINUNICODE, BINSTRING, SHORT_BINSTRING
+                b'X' | b'T' => {
+                    let length = self.read_u32()? as usize;
+                    let value = self.read_string(length)?;
+                    self.stack.push(PickleValue::String(value));
+                }
+                0x8c | b'U' => {
+                    let length = self.read_u8()? as usize;
+                    let value = self.read_string(length)?;
+                    self.stack.push(PickleValue::String(value));
+                }
+                // BINBYTES, SHORT_BINBYTES
+                b'B' => {
+                    let length = self.read_u32()? as usize;
+                    self.read(length)?;
+                    self.stack.push(PickleValue::Bytes);
+                }
+                b'C' => {
+                    let length = self.read_u8()? as usize;
+                    self.read(length)?;
+                    self.stack.push(PickleValue::Bytes);
+                }
+                // EMPTY_TUPLE, TUPLE, TUPLE1, TUPLE2, TUPLE3
+                b')' => self.stack.push(PickleValue::Tuple(vec![])),
+                b't' => {
+                    let values = self.pop_mark()?;
+                    self.stack.push(PickleValue::Tuple(values));
+                }
+                0x85..=0x87 => {
+                    let length = (opcode - 0x84) as usize;
+                    if self.stack.len() < length {
+                        return Err(invalid_pickle("empty stack".to_string()));
+                    }
+                    let values = self.stack.split_off(self.stack.len() - length);
+                    self.stack.push(PickleValue::Tuple(values));
+                }
+                // EMPTY_LIST, APPEND, APPENDS
+                b']' => self.stack.push(PickleValue::List(vec![])),
+                b'a' => {
+                    let value = self.pop()?;
+                    self.append(vec![value])?;
+                }
+                b'e' => {
+                    let values = self.pop_mark()?;
+                    self.append(values)?;
+                }
+                // EMPTY_DICT, SETITEM, SETITEMS
+                b'}' => self.stack.push(PickleValue::Dict(vec![])),
+                b's' => {
+                    let value = self.pop()?;
+                    let key = self.pop()?;
+                    self.set_items(vec![key, value])?;
+                }
+                b'u' => {
+                    let items = self.pop_mark()?;
+                    self.set_items(items)?;
+                }
+                // BINPUT, LONG_BINPUT, MEMOIZE
+                b'q' => {
+                    let index = self.read_u8()? as u32;
+                    self.memoize(index)?;
+                }
+                b'r' => {
+                    let index = self.read_u32()?;
+                    self.memoize(index)?;
+                }
+                0x94 => {
+                    let index = self.memo.len() as u32;
+                    self.memoize(index)?;
+                }
+                // BINGET, LONG_BINGET
+                b'h' => {
+                    let index = self.read_u8()? as u32;
+                    self.get_memo(index)?;
+                }
+                b'j' => {
+                    let index = self.read_u32()?;
+                    self.get_memo(index)?;
+                }
+                // GLOBAL, STACK_GLOBAL
+                b'c' => {
+                    let module = self.read_line()?;
+                    let name = self.read_line()?;
+                    self.stack.push(PickleValue::Global(module, name));
+                }
+                0x93 => {
+                    let name = self.pop()?;
+                    let module = self.pop()?;
+                    match (module, name) {
+                        (PickleValue::String(module), PickleValue::String(name)) => {
+                            self.stack.push(PickleValue::Global(module, name))
+                        }
+                        _ => return Err(invalid_pickle("invalid global".to_string())),
+                    }
+                }
+                // BINPERSID
+                b'Q' => {
+                    let pid = self.pop()?;
+                    let value = self.persistent_load(pid)?;
+                    self.stack.push(value);
+                }
+                // REDUCE
+                b'R' => {
+                    let args = self.pop()?;
+                    let callable = self.pop()?;
+                    let value = self.reduce(callable, args)?;
+                    self.stack.push(value);
+                }
+                // BUILD: the state of the objects saved by `torch.save` (e.g. the `_metadata` of a
+                // state dictionary) is not needed to read the tensors
+                b'b' => {
+                    self.pop()?;
+                }
+                _ => {
+                    return Err(invalid_pickle(format!(
+                        "unsupported pickle opcode 0x{:02x}",
+                        opcode
+                    )))
+                }
+            }
+        }
+    }
+}
+
+/// Reads all tensors of a Pytorch checkpoint (e.g. `pytorch_model.bin`) saved with `torch.save`
+/// (zip archive format). The checkpoint must contain a dictionary of tensors (state dictionary),
+/// other entries are ignored. The tensors are created on the CPU with the data type they were
+/// saved with.
+///
+/// # Arguments
+///
+/// * `path` - Path to the Pytorch checkpoint
+///
+/// # Returns
+///
+/// * `Vec<(String, Tensor)>` containing the name and value of the tensors stored in the checkpoint
+pub fn read_pytorch_checkpoint<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<(String, Tensor)>, RustBertError> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    let mut archive = ZipArchive::new(file).map_err(|error| {
+        RustBertError::IOError(format!(
+            "Could not read Pytorch checkpoint {} as a zip archive ({}). Checkpoints saved with \
+            Pytorch < 1.6 must be converted with `python ./utils/convert_model.py`",
+            path.display(),
+            error
+        ))
+    })?;
+    let pickle_name = archive
+        .file_names()
+        .find(|name| *name == "data.pkl" || name.ends_with("/data.pkl"))
+        .map(String::from)
+        .ok_or_else(|| invalid_pickle("missing data.pkl".to_string()))?;
+    let archive_prefix = pickle_name.trim_end_matches("data.pkl").to_string();
+
+    let mut read_entry = |name: &str| -> Result<Vec<u8>, RustBertError> {
+        let mut entry = archive
+            .by_name(name)
+            .map_err(|error| invalid_pickle(format!("cannot read {}: {}", name, error)))?;
+        let mut buffer = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    };
+
+    let pickle_data = read_entry(&pickle_name)?;
+    let entries = match Unpickler::new(&pickle_data).load()? {
+        PickleValue::Dict(entries) => entries,
+        value => {
+            return Err(invalid_pickle(format!(
+                "expected a dictionary of tensors, got {:?}",
+                value
+            )))
+        }
+    };
+
+    let mut storages: HashMap<String, Tensor> = HashMap::new();
+    let mut named_tensors = Vec::with_capacity(entries.len());
+    for (name, value) in entries {
+        let (name, tensor) = match (name, value) {
+            (PickleValue::String(name), PickleValue::Tensor(tensor)) => (name, tensor),
+            _ => continue,
+        };
+        if !storages.contains_key(&tensor.storage.key) {
+            let data = read_entry(&format!("{}data/{}", archive_prefix, tensor.storage.key))?;
+            let numel = (data.len() / tensor.storage.kind.elt_size_in_bytes()) as i64;
+            storages.insert(
+                tensor.storage.key.clone(),
+                Tensor::f_of_data_size(&data, &[numel], tensor.storage.kind)?,
+            );
+        }
+        let storage = &storages[&tensor.storage.key];
+        let value = storage
+            .f_as_strided(&tensor.size, &tensor.stride, tensor.offset)?
+            .contiguous();
+        named_tensors.push((name, value));
+    }
+    Ok(named_tensors)
+}
@@ -0,0 +1,238 @@
+// Copyright 2019-present Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Dynamic quantization utilities
+//!
+//! The linear layers of the encoder stacks of some models can be converted to int8 weights once
+//! their weights are loaded. The activations are quantized dynamically (with a scale computed for
+//! each forward pass) and the matrix multiplications are executed with int8 arithmetic using the
+//! FBGEMM backend of Torch. This reduces the inference latency and the memory footprint on CPU at
+//! the cost of a small accuracy loss: the full precision weights of the quantized layers are
+//! released once quantized.
+//!
+//! Dynamic quantization requires a Torch build with FBGEMM support (the default for the pre-built
+//! x86 libtorch distributions, not available on ARM) and a CPU with AVX2 support. It relies on the
+//! `fbgemm_linear_int8_weight_fp32_activation` Torch operator, deprecated in Torch in favour of the
+//! quantized engine API that is not exposed by `tch`. An error is returned when quantizing a model
+//! if the operator is not available.
+//!
+//! Quantization is enabled in the pipelines configurations by setting their `quantization` field
+//! to `QuantizationMode::DynamicInt8`. It is currently supported for BERT, RoBERTa and DistilBERT
+//! models in the sequence classification, token classification and sentence embeddings pipelines.
+
+use crate::common::linear::LinearNoBias;
+use crate::RustBertError;
+use std::borrow::Borrow;
+use tch::nn::{Module, Path};
+use tch::{nn, no_grad, Device, Kind, Scalar, Tensor};
+
+/// Minimum value of the int8 quantized weights
+const QUANTIZED_MIN: i64 = -128;
+/// Maximum value of the int8 quantized weights
+const QUANTIZED_MAX: i64 = 127;
+
+/// # Quantization mode
+/// Controls the conversion of the model linear layers to a reduced precision after loading the
+/// weights.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuantizationMode {
+    /// Full precision weights (default).
+    #[default]
+    None,
+    /// Int8 weights with dynamic quantization of the activations. Only supported on CPU.
+    DynamicInt8,
+}
+
+/// Int8 weights of a linear layer, packed for the FBGEMM int8 matrix multiplication
+#[derive(Debug)]
+struct QuantizedWeights {
+    weight: Tensor,
+    packed_weight: Tensor,
+    col_offsets: Tensor,
+    scale: f64,
+    zero_point: i64,
+    bias: Tensor,
+}
+
+impl QuantizedWeights {
+    /// Quantizes a float weight matrix of shape (*out_dim*, *in_dim*) with a per-tensor affine
+    /// quantization to int8 values. The range of the quantization always includes zero.
+    fn new(ws: &Tensor, bs: Option<&Tensor>) -> Result<QuantizedWeights, RustBertError> {
+        no_grad(|| {
+            let ws = ws.to_kind(Kind::Float).contiguous();
+            let min_value = ws.min().double_value(&[]).min(0.0);
+            let max_value = ws.max().double_value(&[]).max(0.0);
+            let scale = match (max_value - min_value) / (QUANTIZED_MAX - QUANTIZED_MIN) as f64 {
+                scale if scale > 0.0 => scale,
+                _ => 0.1,
+            };
+            let zero_point = ((QUANTIZED_MIN as f64 - min_value / scale).round() as i64)
+                .clamp(QUANTIZED_MIN, QUANTIZED_MAX);
+
+            let weight = ws
+                .f_quantize_per_tensor(scale, zero_point, Kind::QInt8)?
+                .f_int_repr()?
+                .contiguous();
+            // Sum of the quantized weights for each output feature, including the zero point
+            // correction term expected by FBGEMM
+            let in_dim = weight.size()[1];
+            let col_offsets = (weight.sum_dim_intlist([1].as_slice(), false, Kind::Int)
+                - zero_point * in_dim)
+                .to_kind(Kind::Int);
+            let packed_weight = weight.f_fbgemm_pack_quantized_matrix()?;
+            let bias = match bs {
+                Some(bs) => bs.to_kind(Kind::Float),
+                None => Tensor::zeros(&[weight.size()[0]], (Kind::Float, weight.device())),
+            };
+            Ok(QuantizedWeights {
+                weight,
+                packed_weight,
+                col_offsets,
+                scale,
+                zero_point,
+                bias,
+            })
+        })
+    }
+
+    /// Runs the int8 matrix multiplication on a dummy input, returning an error if the FBGEMM
+    /// operators are not available
+    fn check_support(&self) -> Result<(), RustBertError> {
+        let xs = Tensor::zeros(&[1, self.weight.size()[1]], (Kind::Float, Device::Cpu));
+        let _ = xs.f_fbgemm_linear_int8_weight_fp32_activation(
+            &self.weight,
+            &self.packed_weight,
+            &self.col_offsets,
+            Scalar::float(self.scale),
+            Scalar::int(self.zero_point),
+            &self.bias,
+        )?;
+        Ok(())
+    }
+
+    fn forward(&self, xs: &Tensor) -> Tensor {
+        xs.to_kind(Kind::Float)
+            .contiguous()
+            .fbgemm_linear_int8_weight_fp32_activation(
+                &self.weight,
+                &self.packed_weight,
+                &self.col_offsets,
+                Scalar::float(self.scale),
+                Scalar::int(self.zero_point),
+                &self.bias,
+            )
+            .to_kind(xs.kind())
+    }
+}
+
+/// # Linear layer supporting dynamic quantization
+/// Behaves as a `nn::Linear` (or `LinearNoBias` if built without bias) layer until `quantize` is
+/// called, after which the forward pass uses int8 weights and dynamically quantized activations.
+/// The full precision weight is then released: the corresponding variable of the variable store is
+/// emptied, and the layer can neither be restored to full precision nor moved to another device.
+#[derive(Debug)]
+pub struct QuantizableLinear {
+    ws: Tensor,
+    bs: Option<Tensor>,
+    quantized_weights: Option<QuantizedWeights>,
+}
+
+/// Creates a new linear layer supporting dynamic quantization.
+pub fn quantizable_linear<'a, T: Borrow<Path<'a>>>(
+    vs: T,
+    in_dim: i64,
+    out_dim: i64,
+    c: nn::LinearConfig,
+) -> QuantizableLinear {
+    nn::linear(vs, in_dim, out_dim, c).into()
+}
+
+impl From<nn::Linear> for QuantizableLinear {
+    fn from(linear: nn::Linear) -> Self {
+        QuantizableLinear {
+            ws: linear.ws,
+            bs: linear.bs,
+            quantized_weights: None,
+        }
+    }
+}
+
+impl From<LinearNoBias> for QuantizableLinear {
+    fn from(linear: LinearNoBias) -> Self {
+        QuantizableLinear {
+            ws: linear.ws,
+            bs: None,
+            quantized_weights: None,
+        }
+    }
+}
+
+impl QuantizableLinear {
+    /// Converts the layer to the quantization mode provided. The weights must be loaded before
+    /// quantizing the layer: the full precision weight is released once quantized. Returns an
+    /// error if the Torch build does not support FBGEMM int8 operations, or when restoring the full
+    /// precision of a quantized layer (`QuantizationMode::None`).
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        match (mode, self.is_quantized()) {
+            (QuantizationMode::None, true) => Err(RustBertError::ValueError(
+                "The full precision weights of a quantized layer are released and cannot be restored"
+                    .to_string(),
+            )),
+            (QuantizationMode::None, false) | (QuantizationMode::DynamicInt8, true) => Ok(()),
+            (QuantizationMode::DynamicInt8, false) => {
+                if self.ws.device() != Device::Cpu {
+                    return Err(RustBertError::ValueError(format!(
+                        "Dynamic int8 quantization is only supported on CPU, got {:?}",
+                        self.ws.device()
+                    )));
+                }
+                let quantized_weights = QuantizedWeights::new(&self.ws, self.bs.as_ref())
+                    .and_then(|quantized_weights| {
+                        quantized_weights.check_support()?;
+                        Ok(quantized_weights)
+                    })
+                    .map_err(|error| {
+                        RustBertError::ValueError(format!(
+                            "Dynamic int8 quantization requires a Torch build with FBGEMM support and a CPU with AVX2 support: {}",
+                            error
+                        ))
+                    })?;
+                // Release the full precision weight, shared with the variable store
+                no_grad(|| {
+                    self.ws
+                        .set_data(&Tensor::zeros(&[0], (self.ws.kind(), Device::Cpu)))
+                });
+                self.quantized_weights = Some(quantized_weights);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns `true` if the layer uses quantized weights
+    pub fn is_quantized(&self) -> bool {
+        self.quantized_weights.is_some()
+    }
+}
+
+impl Module for QuantizableLinear {
+    fn forward(&self, xs: &Tensor) -> Tensor {
+        match &self.quantized_weights {
+            Some(quantized_weights) => quantized_weights.forward(xs),
+            None => {
+                let output = xs.matmul(&self.ws.tr());
+                match &self.bs {
+                    Some(bs) => output + bs,
+                    None => output,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,398 @@
+// Copyright 2019-present Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Model weights loading utilities
+//!
+//! Model weights can be provided either in the Torch format used by this library (`.ot` files,
+//! typically named `rust_model.ot`) or as [safetensors](https://github.com/huggingface/safetensors)
+//! files (e.g. `model.safetensors` files published on the Hugging Face model hub).
+//! Safetensors files are read natively and do not require a conversion step: the tensor names are
+//! remapped to the naming convention of this library when loading the weights.
+//!
+//! The weights file is validated against the model variables before loading: a
+//! `RustBertError::WeightsLoadingError` reporting the missing, unexpected and shape-mismatched
+//! tensors is returned if the weights do not match the model architecture. A partial loading mode
+//! (`WeightsLoadingMode::Partial`) keeps the initial values of the variables that cannot be loaded,
+//! for example to load a base model checkpoint with a newly initialized task-specific head.
+
+pub use crate::common::pytorch_checkpoint::read_pytorch_checkpoint;
+use crate::RustBertError;
+use safetensors::tensor::{Dtype, SafeTensors};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::Path;
+use tch::nn::VarStore;
+use tch::{no_grad, Kind, Tensor};
+
+/// Extension of safetensors weight files
+const SAFETENSORS_EXTENSION: &str = "safetensors";
+/// Extension of numpy weight archives
+const NPZ_EXTENSION: &str = "npz";
+/// Base model prefixes of the Hugging Face checkpoints that may be added to (or missing from) the
+/// tensor names of safetensors files compared to the variable names of this library
+const HF_BASE_MODEL_PREFIXES: [&str; 16] = [
+    "model",
+    "transformer",
+    "bert",
+    "roberta",
+    "distilbert",
+    "albert",
+    "electra",
+    "mobilebert",
+    "longformer",
+    "deberta",
+    "fnet",
+    "reformer",
+    "prophetnet",
+    "xlnet",
+    "gpt_neox",
+    "funnel",
+];
+
+/// # Weights loading mode
+/// Controls the behaviour of the weights loading when some model variables cannot be loaded
+/// from the weights file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WeightsLoadingMode {
+    /// All model variables must be found in the weights file with the expected shape (default).
+    #[default]
+    Strict,
+    /// Model variables missing from the weights file (or stored with a different shape) keep their
+    /// initial values. This allows for example loading a base model checkpoint into a model with a
+    /// newly initialized task-specific head.
+    Partial,
+}
+
+/// # Shape mismatch between a model variable and the matching tensor of a weights file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShapeMismatch {
+    /// Name of the model variable
+    pub name: String,
+    /// Shape of the model variable
+    pub expected: Vec<i64>,
+    /// Shape of the tensor found in the weights file
+    pub found: Vec<i64>,
+}
+
+/// # Report of the validation of a weights file against the variables of a model
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WeightsLoadingReport {
+    /// Model variables not found in the weights file
+    pub missing: Vec<String>,
+    /// Tensors of the weights file not matching any model variable
+    pub unexpected: Vec<String>,
+    /// Model variables found in the weights file with a different shape
+    pub shape_mismatches: Vec<ShapeMismatch>,
+}
+
+impl WeightsLoadingReport {
+    /// Returns `true` if all model variables were found in the weights file with the expected shape
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.shape_mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for WeightsLoadingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} missing, {} shape mismatched and {} unexpected tensors",
+            self.missing.len(),
+            self.shape_mismatches.len(),
+            self.unexpected.len()
+        )?;
+        if !self.missing.is_empty() {
+            write!(f, "\nMissing tensors: {}", self.missing.join(", "))?;
+        }
+        if !self.shape_mismatches.is_empty() {
+            let shape_mismatches = self
+                .shape_mismatches
+                .iter()
+                .map(|mismatch| {
+                    format!(
+                        "{} (expected {:?}, found {:?})",
+                        mismatch.name, mismatch.expected, mismatch.found
+                    )
+                })
+                .collect::<Vec<String>>();
+            write!(f, "\nShape mismatches: {}", shape_mismatches.join(", "))?;
+        }
+        if !self.unexpected.is_empty() {
+            write!(f, "\nUnexpected tensors: {}", self.unexpected.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Loads weights into a variable store from a weights file.
+///
+/// The format of the weights file is inferred from its extension: files with a `.safetensors`
+/// extension are read natively (renaming the tensors to match the variable store naming
+/// convention), files with a `.npz` extension are read as numpy archives and all other files are
+/// expected to be Torch weight files (e.g. `rust_model.ot`).
+///
+/// All variables of the variable store must be found in the weights file with the expected shape,
+/// otherwise a `RustBertError::WeightsLoadingError` listing the missing, unexpected and
+/// shape-mismatched tensors is returned. See `load_weights_with_mode` for a partial loading of
+/// the weights.
+///
+/// # Arguments
+///
+/// * `weights_path` - Path to the weights file to load
+/// * `var_store` - `VarStore` holding the (initialized) model variables to load the weights into
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::bert::{BertConfig, BertForMaskedLM};
+/// use rust_bert::weights::load_weights;
+/// use rust_bert::Config;
+/// use std::path::Path;
+/// use tch::{nn, Device};
+///
+/// let config = BertConfig::from_file(Path::new("path/to/config.json"));
+/// let mut var_store = nn::VarStore::new(Device::Cpu);
+/// let _model = BertForMaskedLM::new(var_store.root(), &config);
+/// load_weights(Path::new("path/to/model.safetensors"), &mut var_store)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_weights<P: AsRef<Path>>(
+    weights_path: P,
+    var_store: &mut VarStore,
+) -> Result<(), RustBertError> {
+    load_weights_with_mode(weights_path, var_store, WeightsLoadingMode::Strict)?;
+    Ok(())
+}
+
+/// Loads weights into a variable store from a weights file, with a given loading mode.
+///
+/// The weights file is first validated against the variables of the variable store. With a
+/// `WeightsLoadingMode::Strict` loading mode, an error is returned (and no variable is updated) if
+/// any of the variables is missing from the weights file or stored with a different shape. With a
+/// `WeightsLoadingMode::Partial` loading mode, these variables keep their initial values.
+///
+/// # Arguments
+///
+/// * `weights_path` - Path to the weights file to load
+/// * `var_store` - `VarStore` holding the (initialized) model variables to load the weights into
+/// * `loading_mode` - `WeightsLoadingMode` to use
+///
+/// # Returns
+///
+/// * `WeightsLoadingReport` listing the missing, unexpected and shape-mismatched tensors
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::bert::{BertConfig, BertForSequenceClassification};
+/// use rust_bert::weights::{load_weights_with_mode, WeightsLoadingMode};
+/// use rust_bert::Config;
+/// use std::path::Path;
+/// use tch::{nn, Device};
+///
+/// let config = BertConfig::from_file(Path::new("path/to/config.json"));
+/// let mut var_store = nn::VarStore::new(Device::Cpu);
+/// let _model = BertForSequenceClassification::new(var_store.root(), &config);
+/// let report = load_weights_with_mode(
+///     Path::new("path/to/rust_model.ot"),
+///     &mut var_store,
+///     WeightsLoadingMode::Partial,
+/// )?;
+/// println!("Newly initialized variables: {:?}", report.missing);
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_weights_with_mode<P: AsRef<Path>>(
+    weights_path: P,
+    var_store: &mut VarStore,
+    loading_mode: WeightsLoadingMode,
+) -> Result<WeightsLoadingReport, RustBertError> {
+    let weights_path = weights_path.as_ref();
+    let is_safetensors = is_safetensors_file(weights_path);
+    let named_tensors = if is_safetensors {
+        read_safetensors(weights_path)?
+            .into_iter()
+            .map(|(name, tensor)| (rename_weight(&name), tensor))
+            .collect::<HashMap<String, Tensor>>()
+    } else if weights_path.extension() == Some(OsStr::new(NPZ_EXTENSION)) {
+        Tensor::read_npz(weights_path)?.into_iter().collect()
+    } else {
+        Tensor::load_multi_with_device(weights_path, var_store.device())?
+            .into_iter()
+            .collect()
+    };
+
+    let variables = var_store.variables();
+    let (report, matched_tensors) = validate_weights(&variables, &named_tensors, is_safetensors);
+    if (loading_mode == WeightsLoadingMode::Strict) && !report.is_complete() {
+        return Err(RustBertError::WeightsLoadingError(report));
+    }
+    for (name, mut variable) in variables {
+        if let Some(source) = matched_tensors.get(&name) {
+            no_grad(|| variable.f_copy_(source))?;
+        }
+    }
+    Ok(report)
+}
+
+/// Checks if the file provided is a safetensors file (based on its extension)
+pub fn is_safetensors_file<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension() == Some(OsStr::new(SAFETENSORS_EXTENSION))
+}
+
+/// Reads all tensors stored in a safetensors file. The tensors are created on the CPU with the
+/// data type they were serialized with.
+///
+/// # Arguments
+///
+/// * `path` - Path to the safetensors file
+///
+/// # Returns
+///
+/// * `Vec<(String, Tensor)>` containing the name and value of the tensors stored in the file
+pub fn read_safetensors<P: AsRef<Path>>(path: P) -> Result<Vec<(String, Tensor)>, RustBertError> {
+    let buffer = std::fs::read(path.as_ref())?;
+    let safetensors = SafeTensors::deserialize(&buffer).map_err(|error| {
+        RustBertError::IOError(format!(
+            "Could not read safetensors file {}: {}",
+            path.as_ref().display(),
+            error
+        ))
+    })?;
+    let mut named_tensors = Vec::with_capacity(safetensors.len());
+    for (name, view) in safetensors.tensors() {
+        let kind = safetensors_dtype_to_kind(view.dtype())?;
+        let shape = view
+            .shape()
+            .iter()
+            .map(|&dim| dim as i64)
+            .collect::<Vec<i64>>();
+        let tensor = Tensor::of_data_size(view.data(), &shape, kind);
+        named_tensors.push((name, tensor));
+    }
+    Ok(named_tensors)
+}
+
+/// Renames a tensor from the Pytorch naming convention to the convention used in this library.
+/// Layer normalization parameters named `gamma` and `beta` in some checkpoints are renamed to
+/// `weight` and `bias` respectively.
+pub fn rename_weight(name: &str) -> String {
+    let (prefix, parameter) = match name.rsplit_once('.') {
+        Some((prefix, parameter)) => (Some(prefix), parameter),
+        None => (None, name),
+    };
+    let parameter = match parameter {
+        "gamma" => "weight",
+        "beta" => "bias",
+        _ => parameter,
+    };
+    match prefix {
+        Some(prefix) => format!("{}.{}", prefix, parameter),
+        None => parameter.to_string(),
+    }
+}
+
+fn safetensors_dtype_to_kind(dtype: Dtype) -> Result<Kind, RustBertError> {
+    Ok(match dtype {
+        Dtype::BOOL => Kind::Bool,
+        Dtype::U8 => Kind::Uint8,
+        Dtype::I8 => Kind::Int8,
+        Dtype::I16 => Kind::Int16,
+        Dtype::I32 => Kind::Int,
+        Dtype::I64 => Kind::Int64,
+        Dtype::F16 => Kind::Half,
+        Dtype::BF16 => Kind::BFloat16,
+        Dtype::F32 => Kind::Float,
+        Dtype::F64 => Kind::Double,
+        _ => {
+            return Err(RustBertError::ValueError(format!(
+                "Type not supported: cannot load safetensors data of type {:?}",
+                dtype
+            )));
+        }
+    })
+}
+
+/// Finds the tensor matching a variable name in a set of named tensors. An exact match is used
+/// if available. For Hugging Face checkpoints (`match_base_model_prefixes` set to `true`), the
+/// weights are otherwise allowed to differ from the variable name by a known base model prefix:
+/// weights files may contain an additional prefix (e.g. `model.` for encoder-decoder checkpoints)
+/// or be missing the base model prefix (e.g. `bert.` for checkpoints of a base model). The prefixed
+/// match is only used if it is unambiguous.
+pub(crate) fn find_tensor<'a>(
+    name: &str,
+    named_tensors: &'a HashMap<String, Tensor>,
+    match_base_model_prefixes: bool,
+) -> Option<(&'a String, &'a Tensor)> {
+    if let Some(named_tensor) = named_tensors.get_key_value(name) {
+        return Some(named_tensor);
+    }
+    if !match_base_model_prefixes {
+        return None;
+    }
+
+    let mut prefixed_candidates = HF_BASE_MODEL_PREFIXES
+        .iter()
+        .filter_map(|prefix| named_tensors.get_key_value(&format!("{}.{}", prefix, name)));
+    if let (Some(named_tensor), None) = (prefixed_candidates.next(), prefixed_candidates.next()) {
+        return Some(named_tensor);
+    }
+
+    name.split_once('.')
+        .filter(|(prefix, _)| HF_BASE_MODEL_PREFIXES.contains(prefix))
+        .and_then(|(_, unprefixed_name)| named_tensors.get_key_value(unprefixed_name))
+}
+
+/// Matches the variables of a variable store with a set of named tensors, returning a validation
+/// report and the tensors to load for each (valid) variable name.
+fn validate_weights<'a>(
+    variables: &HashMap<String, Tensor>,
+    named_tensors: &'a HashMap<String, Tensor>,
+    match_base_model_prefixes: bool,
+) -> (WeightsLoadingReport, HashMap<String, &'a Tensor>) {
+    let mut report = WeightsLoadingReport::default();
+    let mut matched_tensors = HashMap::with_capacity(variables.len());
+    let mut used_tensor_names = HashSet::with_capacity(variables.len());
+
+    for (name, variable) in variables {
+        match find_tensor(name, named_tensors, match_base_model_prefixes) {
+            Some((tensor_name, tensor)) => {
+                used_tensor_names.insert(tensor_name.as_str());
+                if variable.size() == tensor.size() {
+                    matched_tensors.insert(name.clone(), tensor);
+                } else {
+                    report.shape_mismatches.push(ShapeMismatch {
+                        name: name.clone(),
+                        expected: variable.size(),
+                        found: tensor.size(),
+                    });
+                }
+            }
+            None => report.missing.push(name.clone()),
+        }
+    }
+    report.unexpected = named_tensors
+        .keys()
+        .filter(|tensor_name| !used_tensor_names.contains(tensor_name.as_str()))
+        .cloned()
+        .collect();
+
+    report.missing.sort();
+    report.unexpected.sort();
+    report
+        .shape_mismatches
+        .sort_by(|mismatch_a, mismatch_b| mismatch_a.name.cmp(&mismatch_b.name));
+    (report, matched_tensors)
+}
@@ -10,23 +10,274 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Converts model weights to the Torch format (`.ot`) used by this library.
+//!
+//! Supported source formats are Pytorch checkpoints saved with `torch.save` (`pytorch_model.bin`,
+//! `.pt`, read natively from their zip archive without requiring Python), numpy archives (`.npz`),
+//! safetensors files (`.safetensors`) and Torch files (`.ot`). The tensor names are converted
+//! following the same conventions as `utils/convert_model.py` and an inventory of the converted
+//! tensors (name, shape and type) is printed to the standard output. The converted weights are
+//! validated against the model architecture when they are loaded: a
+//! `RustBertError::WeightsLoadingError` lists the missing, unexpected and shape-mismatched tensors.
+//!
+//! Floating point tensors are cast to the type given by `--dtype`. Contrary to
+//! `utils/convert_model.py`, which casts every tensor to `float32`, integer and boolean tensors
+//! (e.g. position ids buffers) keep their type.
+
 extern crate tch;
 
+use rust_bert::weights::{is_safetensors_file, read_pytorch_checkpoint, read_safetensors};
 use rust_bert::RustBertError;
+use std::path::Path;
+use tch::{Kind, Tensor};
+
+const USAGE: &str = "usage: convert-tensor [--skip_embeddings] [--prefix PREFIX] [--suffix] \
+[--dtype float32|float16|bfloat16|float64] source destination.ot
+
+  source               Pytorch checkpoint (.bin, .pt, .pth), numpy archive (.npz), safetensors
+                       (.safetensors) or Torch (.ot) weights file
+  destination.ot       Location of the converted weights file
+  --skip_embeddings    Skip shared embeddings / language model head
+  --prefix PREFIX      Add a prefix on weight names
+  --suffix             Split weight names on '.' and keep only last part
+  --dtype DTYPE        Type to cast floating point weights to (default: float32). Integer and
+                       boolean weights keep their type";
+
+/// Tensors skipped when the `--skip_embeddings` flag is provided (shared with the encoder-decoder
+/// `model.shared` embeddings)
+const SHARED_EMBEDDINGS: [&str; 3] = [
+    "lm_head.weight",
+    "model.encoder.embed_tokens.weight",
+    "model.decoder.embed_tokens.weight",
+];
+
+struct ConversionOptions {
+    source_file: String,
+    destination_file: String,
+    skip_embeddings: bool,
+    prefix: Option<String>,
+    suffix: bool,
+    kind: Kind,
+}
+
+impl ConversionOptions {
+    fn from_args(args: &[String]) -> Result<Self, RustBertError> {
+        let mut positional_args = Vec::new();
+        let mut skip_embeddings = false;
+        let mut prefix = None;
+        let mut suffix = false;
+        let mut kind = Kind::Float;
+
+        let mut args_iter = args.iter().skip(1);
+        while let Some(arg) = args_iter.next() {
+            match arg.as_str() {
+                "--skip_embeddings" => skip_embeddings = true,
+                "--suffix" => suffix = true,
+                "--prefix" => prefix = Some(Self::get_value(arg, args_iter.next())?.to_string()),
+                "--dtype" => kind = Self::parse_kind(Self::get_value(arg, args_iter.next())?)?,
+                "-h" | "--help" => return Err(RustBertError::ValueError(USAGE.to_string())),
+                _ if arg.starts_with("--") => {
+                    return Err(RustBertError::ValueError(format!(
+                        "Unknown option {}\n{}",
+                        arg, USAGE
+                    )));
+                }
+                _ => positional_args.push(arg.clone()),
+            }
+        }
+        if positional_args.len() != 2 {
+            return Err(RustBertError::ValueError(USAGE.to_string()));
+        }
+        let destination_file = positional_args.pop().unwrap();
+        let source_file = positional_args.pop().unwrap();
+        Ok(ConversionOptions {
+            source_file,
+            destination_file,
+            skip_embeddings,
+            prefix,
+            suffix,
+            kind,
+        })
+    }
+
+    fn get_value<'a>(option: &str, value: Option<&'a String>) -> Result<&'a str, RustBertError> {
+        value.map(|value| value.as_str()).ok_or_else(|| {
+            RustBertError::ValueError(format!("Missing value for option {}\n{}", option, USAGE))
+        })
+    }
+
+    fn parse_kind(dtype: &str) -> Result<Kind, RustBertError> {
+        match dtype {
+            "float32" | "float" => Ok(Kind::Float),
+            "float16" | "half" => Ok(Kind::Half),
+            "bfloat16" => Ok(Kind::BFloat16),
+            "float64" | "double" => Ok(Kind::Double),
+            _ => Err(RustBertError::ValueError(format!(
+                "Unsupported dtype {}\n{}",
+                dtype, USAGE
+            ))),
+        }
+    }
+
+    fn convert_name(&self, name: &str) -> Option<String> {
+        let name = name.replace("gamma", "weight").replace("beta", "bias");
+        if self.skip_embeddings && SHARED_EMBEDDINGS.contains(&name.as_str()) {
+            return None;
+        }
+        let name = match &self.prefix {
+            Some(prefix) => format!("{}{}", prefix, name),
+            None => name,
+        };
+        if self.suffix {
+            Some(name.rsplit('.').next().unwrap().to_string())
+        } else {
+            Some(name)
+        }
+    }
+}
+
+fn read_weights(path: &Path) -> Result<Vec<(String, Tensor)>, RustBertError> {
+    if is_safetensors_file(path) {
+        return read_safetensors(path);
+    }
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default();
+    Ok(match extension {
+        "npz" => Tensor::read_npz(path)?,
+        "ot" => Tensor::load_multi(path)?,
+        "bin" | "pt" | "pth" => read_pytorch_checkpoint(path)?,
+        _ => {
+            return Err(RustBertError::ValueError(format!(
+                "Unsupported weights file extension for {}\n{}",
+                path.display(),
+                USAGE
+            )));
+        }
+    })
+}
+
+fn is_floating_point(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Kind::Half | Kind::Float | Kind::Double | Kind::BFloat16
+    )
+}
 
 pub fn main() -> Result<(), RustBertError> {
     let args: Vec<_> = std::env::args().collect();
-    assert_eq!(
-        args.len(),
-        3,
-        "usage: {} source.npz destination.ot",
-        args[0].as_str()
-    );
+    let options = ConversionOptions::from_args(&args)?;
 
-    let source_file = &args[1];
-    let destination_file = &args[2];
-    let tensors = tch::Tensor::read_npz(source_file)?;
-    tch::Tensor::save_multi(&tensors, destination_file)?;
+    let mut tensors = Vec::new();
+    for (name, tensor) in read_weights(Path::new(&options.source_file))? {
+        if let Some(name) = options.convert_name(&name) {
+            let tensor = if is_floating_point(tensor.kind()) {
+                tensor.to_kind(options.kind)
+            } else {
+                tensor
+            };
+            tensors.push((name, tensor.contiguous()));
+        }
+    }
+    tensors.sort_by(|(name_a, _), (name_b, _)| name_a.cmp(name_b));
+
+    for (name, tensor) in tensors.iter() {
+        println!("{}\t{:?}\t{:?}", name, tensor.size(), tensor.kind());
+    }
+    println!(
+        "converted {} tensors to {}",
+        tensors.len(),
+        options.destination_file
+    );
+    tch::Tensor::save_multi(&tensors, &options.destination_file)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        std::iter::once("convert-tensor")
+            .chain(args.iter().copied())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn parse_default_options() {
+        let options = ConversionOptions::from_args(&args(&["model.npz", "rust_model.ot"])).unwrap();
+        assert_eq!(options.source_file, "model.npz");
+        assert_eq!(options.destination_file, "rust_model.ot");
+        assert!(!options.skip_embeddings);
+        assert!(options.prefix.is_none());
+        assert!(!options.suffix);
+        assert_eq!(options.kind, Kind::Float);
+    }
+
+    #[test]
+    fn parse_all_options() {
+        let options = ConversionOptions::from_args(&args(&[
+            "--skip_embeddings",
+            "model.safetensors",
+            "--prefix",
+            "bert.",
+            "--suffix",
+            "--dtype",
+            "float16",
+            "rust_model.ot",
+        ]))
+        .unwrap();
+        assert_eq!(options.source_file, "model.safetensors");
+        assert_eq!(options.destination_file, "rust_model.ot");
+        assert!(options.skip_embeddings);
+        assert_eq!(options.prefix.as_deref(), Some("bert."));
+        assert!(options.suffix);
+        assert_eq!(options.kind, Kind::Half);
+    }
+
+    #[test]
+    fn parse_invalid_options() {
+        for invalid_args in [
+            vec!["model.npz"],
+            vec!["model.npz", "rust_model.ot", "extra.ot"],
+            vec!["--prefix"],
+            vec!["model.npz", "rust_model.ot", "--dtype"],
+            vec!["model.npz", "rust_model.ot", "--dtype", "int8"],
+            vec!["model.npz", "rust_model.ot", "--unknown"],
+            vec!["--help"],
+        ] {
+            assert!(ConversionOptions::from_args(&args(&invalid_args)).is_err());
+        }
+    }
+
+    #[test]
+    fn convert_names() {
+        let options = ConversionOptions::from_args(&args(&["model.npz", "rust_model.ot"])).unwrap();
+        assert_eq!(
+            options.convert_name("bert.embeddings.LayerNorm.gamma"),
+            Some("bert.embeddings.LayerNorm.weight".to_string())
+        );
+        assert_eq!(
+            options.convert_name("encoder.gamma_layer.beta"),
+            Some("encoder.weight_layer.bias".to_string())
+        );
+
+        let options = ConversionOptions::from_args(&args(&[
+            "--skip_embeddings",
+            "--prefix",
+            "model.",
+            "--suffix",
+            "model.npz",
+            "rust_model.ot",
+        ]))
+        .unwrap();
+        assert_eq!(options.convert_name("lm_head.weight"), None);
+        assert_eq!(
+            options.convert_name("encoder.layer.0.LayerNorm.beta"),
+            Some("bias".to_string())
+        );
+    }
+}
@@ -11,7 +11,9 @@
 // limitations under the License.
 
 use crate::common::dropout::Dropout;
+use crate::common::quantization::{quantizable_linear, QuantizableLinear, QuantizationMode};
 use crate::distilbert::distilbert_model::DistilBertConfig;
+use crate::RustBertError;
 use std::borrow::Borrow;
 use tch::{nn, Tensor};
 
@@ -21,10 +23,10 @@ pub struct MultiHeadSelfAttention {
     dim_per_head: i64,
     dropout: Dropout,
     output_attentions: bool,
-    q_lin: nn::Linear,
-    k_lin: nn::Linear,
-    v_lin: nn::Linear,
-    out_lin: nn::Linear,
+    q_lin: QuantizableLinear,
+    k_lin: QuantizableLinear,
+    v_lin: QuantizableLinear,
+    out_lin: QuantizableLinear,
 }
 
 impl MultiHeadSelfAttention {
@@ -33,10 +35,10 @@ impl MultiHeadSelfAttention {
         P: Borrow<nn::Path<'p>>,
     {
         let p = p.borrow();
-        let q_lin = nn::linear(p / "q_lin", config.dim, config.dim, Default::default());
-        let k_lin = nn::linear(p / "k_lin", config.dim, config.dim, Default::default());
-        let v_lin = nn::linear(p / "v_lin", config.dim, config.dim, Default::default());
-        let out_lin = nn::linear(p / "out_lin", config.dim, config.dim, Default::default());
+        let q_lin = quantizable_linear(p / "q_lin", config.dim, config.dim, Default::default());
+        let k_lin = quantizable_linear(p / "k_lin", config.dim, config.dim, Default::default());
+        let v_lin = quantizable_linear(p / "v_lin", config.dim, config.dim, Default::default());
+        let out_lin = quantizable_linear(p / "out_lin", config.dim, config.dim, Default::default());
 
         let dropout = Dropout::new(config.attention_dropout);
         let output_attentions = config.output_attentions.unwrap_or(false);
@@ -53,6 +55,13 @@ impl MultiHeadSelfAttention {
         }
     }
 
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.q_lin.quantize(mode)?;
+        self.k_lin.quantize(mode)?;
+        self.v_lin.quantize(mode)?;
+        self.out_lin.quantize(mode)
+    }
+
     fn split_heads(&self, x: Tensor, bs: i64, dim_per_head: i64) -> Tensor {
         x.view((bs, -1, self.n_heads, dim_per_head)).transpose(1, 2)
     }
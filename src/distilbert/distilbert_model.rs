@@ -15,6 +15,7 @@ extern crate tch;
 use self::tch::{nn, Tensor};
 use crate::common::activations::Activation;
 use crate::common::dropout::Dropout;
+use crate::common::quantization::QuantizationMode;
 use crate::distilbert::embeddings::DistilBertEmbedding;
 use crate::distilbert::transformer::{DistilBertTransformerOutput, Transformer};
 use crate::{Config, RustBertError};
@@ -200,6 +201,35 @@ impl DistilBertModel {
         }
     }
 
+    /// Quantizes the linear layers of the transformer stack (the embeddings keep full precision
+    /// weights).
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::distilbert::{DistilBertConfig, DistilBertModel};
+    /// # use rust_bert::quantization::QuantizationMode;
+    /// # use rust_bert::weights::load_weights;
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::{nn, Device};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config = DistilBertConfig::from_file(Path::new("path/to/config.json"));
+    /// let mut vs = nn::VarStore::new(Device::Cpu);
+    /// let mut distilbert_model = DistilBertModel::new(&vs.root(), &config);
+    /// load_weights(Path::new("path/to/rust_model.ot"), &mut vs)?;
+    /// distilbert_model.quantize(QuantizationMode::DynamicInt8)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.transformer.quantize(mode)
+    }
+
     /// Forward pass through the model
     ///
     /// # Arguments
@@ -320,6 +350,16 @@ impl DistilBertModelClassifier {
         }
     }
 
+    /// Quantizes the linear layers of the encoder stack, the task-specific head keeping full
+    /// precision weights.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.distil_bert_model.quantize(mode)
+    }
+
     /// Forward pass through the model
     ///
     /// # Arguments
@@ -706,6 +746,16 @@ impl DistilBertForTokenClassification {
         }
     }
 
+    /// Quantizes the linear layers of the encoder stack, the task-specific head keeping full
+    /// precision weights.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.distil_bert_model.quantize(mode)
+    }
+
     /// Forward pass through the model
     ///
     /// # Arguments
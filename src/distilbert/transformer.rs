@@ -12,15 +12,17 @@
 
 use crate::common::activations::TensorFunction;
 use crate::common::dropout::Dropout;
+use crate::common::quantization::{quantizable_linear, QuantizableLinear, QuantizationMode};
 use crate::distilbert::attention::MultiHeadSelfAttention;
 use crate::distilbert::distilbert_model::DistilBertConfig;
+use crate::RustBertError;
 use std::borrow::{Borrow, BorrowMut};
 use tch::nn::LayerNorm;
 use tch::{nn, Tensor};
 
 pub struct FeedForwardNetwork {
-    lin1: nn::Linear,
-    lin2: nn::Linear,
+    lin1: QuantizableLinear,
+    lin2: QuantizableLinear,
     dropout: Dropout,
     activation: TensorFunction,
 }
@@ -31,13 +33,13 @@ impl FeedForwardNetwork {
         P: Borrow<nn::Path<'p>>,
     {
         let p = p.borrow();
-        let lin1 = nn::linear(
+        let lin1 = quantizable_linear(
             p / "lin1",
             config.dim,
             config.hidden_dim,
             Default::default(),
         );
-        let lin2 = nn::linear(
+        let lin2 = quantizable_linear(
             p / "lin2",
             config.hidden_dim,
             config.dim,
@@ -53,6 +55,11 @@ impl FeedForwardNetwork {
         }
     }
 
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.lin1.quantize(mode)?;
+        self.lin2.quantize(mode)
+    }
+
     pub fn forward_t(&self, input: &Tensor, train: bool) -> Tensor {
         (self.activation.get_fn())(&input.apply(&self.lin1))
             .apply(&self.lin2)
@@ -93,6 +100,11 @@ impl TransformerBlock {
         }
     }
 
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.attention.quantize(mode)?;
+        self.ffn.quantize(mode)
+    }
+
     pub fn forward_t(
         &self,
         input: &Tensor,
@@ -133,6 +145,13 @@ impl Transformer {
         }
     }
 
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        for layer in self.layers.iter_mut() {
+            layer.quantize(mode)?;
+        }
+        Ok(())
+    }
+
     pub fn forward_t(
         &self,
         input: &Tensor,
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use crate::common::dropout::Dropout;
+use crate::common::kv_cache::{static_attention_mask, write_static_states};
 use crate::gpt2::gpt2_model::Gpt2Config;
 use std::borrow::Borrow;
 use tch::kind::Kind::Float;
@@ -129,6 +130,7 @@ impl Attention {
         key: &Tensor,
         value: &Tensor,
         attention_mask: Option<&Tensor>,
+        query_offset: Option<i64>,
         train: bool,
     ) -> (Tensor, Option<Tensor>) {
         let mut w = query.matmul(key);
@@ -137,7 +139,10 @@ impl Attention {
         }
 
         let (nd, ns) = (w.size()[2], w.size()[3]);
-        let b = self.bias.narrow(2, ns - nd, nd).narrow(3, 0, ns);
+        let b = self
+            .bias
+            .narrow(2, query_offset.unwrap_or(ns - nd), nd)
+            .narrow(3, 0, ns);
         let mut w: Tensor = w * &b + 1e4 * (&b - 1);
         if let Some(mask) = attention_mask {
             w = w + mask;
@@ -157,6 +162,7 @@ impl Attention {
         &self,
         x: &Tensor,
         layer_past: Option<&Tensor>,
+        static_length: Option<i64>,
         attention_mask: Option<&Tensor>,
         train: bool,
     ) -> (Tensor, Tensor, Option<Tensor>) {
@@ -167,16 +173,52 @@ impl Attention {
             self.split_heads(&x[1], true),
             self.split_heads(&x[2], false),
         );
-        let (key, value) = match layer_past {
-            Some(past) => {
+        let (key, value, present, attention_mask) = match (layer_past, static_length) {
+            (Some(past), Some(static_length)) => {
+                write_static_states(&past.get(0), &key.transpose(-2, -1), 2, static_length);
+                write_static_states(&past.get(1), &value, 2, static_length);
+                let attention_mask = static_attention_mask(
+                    attention_mask,
+                    &query,
+                    static_length + query.size()[2],
+                    past.size()[3],
+                );
+                (
+                    past.get(0).transpose(-2, -1),
+                    past.get(1),
+                    past.shallow_clone(),
+                    Some(attention_mask),
+                )
+            }
+            (Some(past), None) => {
                 let key = Tensor::cat(&[past.get(0).transpose(-2, -1), key], -1);
                 let value = Tensor::cat(&[past.get(1), value], -2);
-                (key, value)
+                let present = Tensor::stack(&[key.transpose(-2, -1), value.copy()], 0);
+                (
+                    key,
+                    value,
+                    present,
+                    attention_mask.map(Tensor::shallow_clone),
+                )
+            }
+            (None, _) => {
+                let present = Tensor::stack(&[key.transpose(-2, -1), value.copy()], 0);
+                (
+                    key,
+                    value,
+                    present,
+                    attention_mask.map(Tensor::shallow_clone),
+                )
             }
-            None => (key, value),
         };
-        let present = Tensor::stack(&[key.transpose(-2, -1), value.copy()], 0);
-        let (a, attentions) = self.attention(&query, &key, &value, attention_mask, train);
+        let (a, attentions) = self.attention(
+            &query,
+            &key,
+            &value,
+            attention_mask.as_ref(),
+            static_length,
+            train,
+        );
 
         let a = self
             .flatten(a)
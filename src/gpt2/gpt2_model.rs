@@ -15,6 +15,7 @@
 use crate::common::activations::Activation;
 use crate::common::dropout::Dropout;
 use crate::common::embeddings::process_ids_embeddings_pair;
+use crate::common::weights::load_weights_with_mode;
 use crate::gpt2::transformer::Block;
 use crate::pipelines::common::{ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
@@ -435,6 +436,32 @@ impl Gpt2Model {
         position_ids: Option<&Tensor>,
         input_embeds: Option<&Tensor>,
         train: bool,
+    ) -> Result<Gpt2ModelOutput, RustBertError> {
+        self.forward_t_with_past(
+            input_ids,
+            layer_past,
+            None,
+            attention_mask,
+            token_type_ids,
+            position_ids,
+            input_embeds,
+            train,
+        )
+    }
+
+    /// Forward pass with optional preallocated key/value buffers: if `static_length` is provided,
+    /// `layer_past` contains buffers of shape (*2*, *batch size*, *number of heads*, *max_length*,
+    /// *hidden size per head*) filled up to `static_length`, updated in place.
+    fn forward_t_with_past(
+        &self,
+        input_ids: Option<&Tensor>,
+        layer_past: Option<&Vec<Tensor>>,
+        static_length: Option<i64>,
+        attention_mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
     ) -> Result<Gpt2ModelOutput, RustBertError> {
         let (calc_input_embeddings, input_size, _) =
             process_ids_embeddings_pair(input_ids, input_embeds, &self.wte)?;
@@ -453,9 +480,12 @@ impl Gpt2Model {
                 (
                     value
                         .iter()
-                        .map(|v| Some(v.copy()))
+                        .map(|v| match static_length {
+                            Some(_) => Some(v.shallow_clone()),
+                            None => Some(v.copy()),
+                        })
                         .collect::<Vec<Option<Tensor>>>(),
-                    value[0].size()[3],
+                    static_length.unwrap_or_else(|| value[0].size()[3]),
                 )
             }
             None => {
@@ -509,8 +539,13 @@ impl Gpt2Model {
         let layer_iter = self.h.iter().zip(layer_past);
         for layer_values in layer_iter {
             let (layer, past) = layer_values;
-            let temp =
-                layer.forward_t(&hidden_state, past.as_ref(), attention_mask.as_ref(), train);
+            let temp = layer.forward_t(
+                &hidden_state,
+                past.as_ref(),
+                static_length,
+                attention_mask.as_ref(),
+                train,
+            );
             hidden_state = temp.0;
             if let Some(presents) = all_presents.borrow_mut() {
                 presents.push(temp.1);
@@ -669,6 +704,34 @@ impl LMHeadModel for GPT2LMHeadModel {
                 input_embeds,
                 train,
             ),
+            Cache::GPT2StaticCache(layer_past, static_length) => {
+                let sequence_length = match (input_ids, input_embeds) {
+                    (Some(input_ids), _) => input_ids.size()[1],
+                    (None, Some(input_embeds)) => input_embeds.size()[1],
+                    (None, None) => 0,
+                };
+                let base_model_output = self.transformer.forward_t_with_past(
+                    input_ids,
+                    Some(&layer_past),
+                    Some(static_length),
+                    attention_mask,
+                    token_type_ids,
+                    position_ids,
+                    input_embeds,
+                    train,
+                )?;
+                let lm_logits = base_model_output
+                    .output
+                    .linear::<Tensor>(&self.transformer.wte.ws, None);
+                return Ok(LMModelOutput {
+                    lm_logits,
+                    hidden_state: Some(base_model_output.output),
+                    cache: Cache::GPT2StaticCache(
+                        base_model_output.cache.unwrap_or(layer_past),
+                        static_length + sequence_length,
+                    ),
+                });
+            }
             Cache::None => self.transformer.forward_t(
                 input_ids,
                 None,
@@ -690,6 +753,7 @@ impl LMHeadModel for GPT2LMHeadModel {
             .linear::<Tensor>(&self.transformer.wte.ws, None);
         Ok(LMModelOutput {
             lm_logits,
+            hidden_state: Some(base_model_output.output),
             cache: Cache::GPT2Cache(base_model_output.cache),
         })
     }
@@ -781,12 +845,16 @@ impl GPT2Generator {
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate()?;
         let mut var_store = nn::VarStore::new(device);
 
-        let config = Gpt2Config::from_file(config_path);
+        let config = Gpt2Config::try_from_file(config_path)?;
         let model = GPT2LMHeadModel::new(var_store.root(), &config);
-        var_store.load(weights_path)?;
+        load_weights_with_mode(
+            weights_path,
+            &mut var_store,
+            generate_config.weights_loading_mode,
+        )?;
 
         let bos_token_id = tokenizer.get_bos_id();
         let eos_token_ids = tokenizer.get_eos_id().map(|id| vec![id]);
@@ -882,6 +950,14 @@ impl PrivateLanguageGenerator<GPT2LMHeadModel, Gpt2Vocab, Gpt2Tokenizer> for GPT
                     }
                 }
             }
+            Cache::GPT2StaticCache(past, static_length) => PreparedInput {
+                prepared_input: Some(input_ids.select(1, -1).unsqueeze(-1)),
+                prepared_attention_mask: Some(attention_mask),
+                prepared_encoder_output: None,
+                prepared_decoder_input: None,
+                prepared_position_ids: Some(position_ids.select(1, -1).unsqueeze(-1)),
+                prepared_past: Cache::GPT2StaticCache(past, static_length),
+            },
             Cache::None => PreparedInput {
                 prepared_input: Some(input_ids),
                 prepared_attention_mask: Some(attention_mask),
@@ -910,6 +986,12 @@ impl PrivateLanguageGenerator<GPT2LMHeadModel, Gpt2Vocab, Gpt2Tokenizer> for GPT
                 }
                 None => None,
             },
+            Cache::GPT2StaticCache(cached_decoder_state, _) => {
+                for layer_past in cached_decoder_state.iter_mut() {
+                    *layer_past = layer_past.index_select(1, beam_indices);
+                }
+                None
+            }
             Cache::None => None,
             _ => {
                 panic!("Invalid cache for GPT2 model");
@@ -94,12 +94,17 @@ impl Block {
         &self,
         x: &Tensor,
         layer_past: Option<&Tensor>,
+        static_length: Option<i64>,
         attention_mask: Option<&Tensor>,
         train: bool,
     ) -> (Tensor, Tensor, Option<Tensor>) {
-        let (output, present, attentions) =
-            self.attn
-                .forward_t(&x.apply(&self.ln_1), layer_past, attention_mask, train);
+        let (output, present, attentions) = self.attn.forward_t(
+            &x.apply(&self.ln_1),
+            layer_past,
+            static_length,
+            attention_mask,
+            train,
+        );
         let x = x + output;
         let m = self.mlp.forward_t(&x.apply(&self.ln_2), train);
         let x = x + m;
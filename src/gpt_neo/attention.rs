@@ -11,6 +11,7 @@
 // limitations under the License.
 
 use crate::common::dropout::Dropout;
+use crate::common::kv_cache::{new_static_buffer, static_attention_mask, write_static_states};
 use crate::gpt_neo::gpt_neo_model::AttentionLayerType;
 use crate::gpt_neo::GptNeoConfig;
 use std::borrow::Borrow;
@@ -24,6 +25,9 @@ pub struct LayerState {
     pub prev_key: Tensor,
     /// Cached values
     pub prev_value: Option<Tensor>,
+    /// Number of filled positions if the keys and values are preallocated buffers updated in place
+    /// (static cache), `None` if they are extended at each step
+    pub static_length: Option<i64>,
 }
 
 impl Clone for LayerState {
@@ -31,6 +35,7 @@ impl Clone for LayerState {
         LayerState {
             prev_key: self.prev_key.copy(),
             prev_value: self.prev_value.as_ref().map(|value| value.copy()),
+            static_length: self.static_length,
         }
     }
 }
@@ -43,6 +48,18 @@ impl LayerState {
             .as_ref()
             .map(|value| value.index_select(0, new_indices));
     }
+
+    pub(crate) fn preallocate(&mut self, max_length: i64) {
+        let length = self.prev_key.size()[2];
+        if self.static_length.is_none() & (length < max_length) {
+            self.prev_key = new_static_buffer(&self.prev_key, 2, max_length);
+            self.prev_value = self
+                .prev_value
+                .as_ref()
+                .map(|value| new_static_buffer(value, 2, max_length));
+            self.static_length = Some(length);
+        }
+    }
 }
 
 pub struct GptNeoSelfAttention {
@@ -152,6 +169,7 @@ impl GptNeoSelfAttention {
         key: &Tensor,
         value: &Tensor,
         attention_mask: Option<&Tensor>,
+        query_offset: Option<i64>,
         train: bool,
     ) -> (Tensor, Tensor) {
         let query = query.to_kind(Kind::Float);
@@ -164,9 +182,10 @@ impl GptNeoSelfAttention {
         let query_length = query_dims[query_dims.len() - 2];
         let key_length = key_dims[key_dims.len() - 2];
 
+        let query_offset = query_offset.unwrap_or(key_length - query_length);
         let causal_mask = &self
             .bias
-            .slice(2, key_length - query_length, key_length, 1)
+            .slice(2, query_offset, query_offset + query_length, 1)
             .slice(3, 0, key_length, 1)
             .to_kind(Kind::Bool)
             .to_device(attention_weights.device());
@@ -202,21 +221,54 @@ impl GptNeoSelfAttention {
         let mut key = Self::split_heads(&key, self.num_heads, self.head_dim);
         let mut value = Self::split_heads(&value, self.num_heads, self.head_dim);
 
-        if let Some(layer_state_value) = &layer_state {
-            key = Tensor::cat(&[&layer_state_value.prev_key, &key], -2);
-            value = Tensor::cat(
-                &[layer_state_value.prev_value.as_ref().unwrap(), &value],
-                -2,
-            );
+        let (layer_state, attention_mask, query_offset) = match layer_state {
+            Some(LayerState {
+                prev_key,
+                prev_value: Some(prev_value),
+                static_length: Some(static_length),
+            }) => {
+                write_static_states(prev_key, &key, 2, *static_length);
+                write_static_states(prev_value, &value, 2, *static_length);
+                let attention_mask = static_attention_mask(
+                    attention_mask,
+                    &query,
+                    static_length + key.size()[2],
+                    prev_key.size()[2],
+                );
+                key = prev_key.shallow_clone();
+                value = prev_value.shallow_clone();
+                let layer_state = LayerState {
+                    prev_key: key.shallow_clone(),
+                    prev_value: Some(value.shallow_clone()),
+                    static_length: Some(static_length + query.size()[2]),
+                };
+                (layer_state, Some(attention_mask), Some(*static_length))
+            }
+            _ => {
+                if let Some(layer_state_value) = &layer_state {
+                    key = Tensor::cat(&[&layer_state_value.prev_key, &key], -2);
+                    value = Tensor::cat(
+                        &[layer_state_value.prev_value.as_ref().unwrap(), &value],
+                        -2,
+                    );
+                };
+                let layer_state = LayerState {
+                    prev_key: key.copy(),
+                    prev_value: Some(value.copy()),
+                    static_length: None,
+                };
+                (layer_state, attention_mask.map(Tensor::shallow_clone), None)
+            }
         };
 
-        let layer_state = Some(LayerState {
-            prev_key: key.copy(),
-            prev_value: Some(value.copy()),
-        });
-
-        let (attention_output, attention_weights) =
-            self.attend(&query, &key, &value, attention_mask, train);
+        let (attention_output, attention_weights) = self.attend(
+            &query,
+            &key,
+            &value,
+            attention_mask.as_ref(),
+            query_offset,
+            train,
+        );
 
         let attention_output = Self::merge_heads(&attention_output, self.num_heads, self.head_dim)
             .apply(&self.out_proj)
@@ -228,6 +280,6 @@ impl GptNeoSelfAttention {
             None
         };
 
-        (attention_output, attention_weights, layer_state)
+        (attention_output, attention_weights, Some(layer_state))
     }
 }
@@ -12,6 +12,7 @@
 
 use crate::common::dropout::Dropout;
 use crate::common::embeddings::process_ids_embeddings_pair;
+use crate::common::weights::load_weights_with_mode;
 use crate::gpt_neo::decoder::GptNeoBlock;
 use crate::gpt_neo::LayerState;
 use crate::pipelines::common::{ModelType, TokenizerOption};
@@ -341,9 +342,11 @@ impl GptNeoModel {
 
         let past_length = if let Some(past_state_value) = &layer_states {
             if let Some(first_layer_state) = &past_state_value[0] {
-                let mut size_iter = first_layer_state.prev_key.size().into_iter().rev();
-                size_iter.next();
-                size_iter.next().unwrap()
+                first_layer_state.static_length.unwrap_or_else(|| {
+                    let mut size_iter = first_layer_state.prev_key.size().into_iter().rev();
+                    size_iter.next();
+                    size_iter.next().unwrap()
+                })
             } else {
                 0
             }
@@ -563,6 +566,7 @@ impl GptNeoForCausalLM {
 
         Ok(GptNeoModelLMOutput {
             lm_logits,
+            hidden_state: base_model_output.hidden_states,
             next_cache: base_model_output.next_cache,
             all_hidden_states: base_model_output.all_hidden_states,
             all_attentions: base_model_output.all_attentions,
@@ -611,6 +615,7 @@ impl LMHeadModel for GptNeoForCausalLM {
 
         Ok(LMModelOutput {
             lm_logits: base_model_output.lm_logits,
+            hidden_state: Some(base_model_output.hidden_state),
             cache: Cache::GPTNeoCache(base_model_output.next_cache),
         })
     }
@@ -632,6 +637,8 @@ pub struct GptNeoModelOutput {
 pub struct GptNeoModelLMOutput {
     /// logits
     pub lm_logits: Tensor,
+    /// Last hidden state (before the language modeling head)
+    pub hidden_state: Tensor,
     /// Cached outputs of the model (attention layers keys and values) if the model is used for generation
     pub next_cache: Option<Vec<Option<LayerState>>>,
     /// Hidden states for all intermediate layers
@@ -713,11 +720,15 @@ impl GptNeoGenerator {
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate()?;
         let mut var_store = nn::VarStore::new(device);
-        let config = GptNeoConfig::from_file(config_path);
+        let config = GptNeoConfig::try_from_file(config_path)?;
         let model = GptNeoForCausalLM::new(var_store.root(), &config)?;
-        var_store.load(weights_path)?;
+        load_weights_with_mode(
+            weights_path,
+            &mut var_store,
+            generate_config.weights_loading_mode,
+        )?;
 
         let bos_token_id = tokenizer.get_bos_id();
         let eos_token_ids = tokenizer.get_eos_id().map(|id| vec![id]);
@@ -235,7 +235,7 @@
 //! telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more
 //! about exoplanets like K2-18b."];
 //!
-//! let output = model.summarize(&input);
+//! let output = model.summarize(&input)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -271,7 +271,7 @@
 //!
 //! let conversation_id =
 //!     conversation_manager.create("Going to the movies tonight - any suggestions?");
-//! let output = conversation_model.generate_responses(&mut conversation_manager);
+//! let output = conversation_model.generate_responses(&mut conversation_manager)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -302,7 +302,7 @@
 //!
 //! let prefix = None; // Optional prefix to append prompts with, will be excluded from the generated output
 //!
-//! let output = model.generate(&[input_context_1, input_context_2], prefix);
+//! let output = model.generate(&[input_context_1, input_context_2], prefix)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -645,6 +645,7 @@
 //! ]
 //! # ;
 //! ```
+//! `predict_with_options` with `FillMaskOptions` returns the `top_k` candidates of each masked span (restricted to a list of target words), and fills words split in several tokens by masking consecutive tokens (`"It was a [MASK] [MASK] day"`).
 //! </details>
 //!
 //! ## Benchmarks
@@ -653,17 +654,30 @@
 //!
 //! For text generation tasks (summarization, translation, conversation, free text generation), significant benefits can be expected (up to 2 to 4 times faster processing depending on the input and application). The article [Accelerating text generation with Rust](https://guillaume-be.github.io/2020-11-21/generation_benchmarks) focuses on these text generation applications and provides more details on the performance comparison to Python.
 //!
+//! For CPU inference, the sequence classification, token classification and sentence embeddings pipelines can be created with a `QuantizationMode::DynamicInt8` `quantization` setting (BERT, RoBERTa and DistilBERT models). The linear layers of the encoder are then converted to int8 weights after loading, with a dynamic quantization of the activations, reducing the inference latency at the cost of a small accuracy loss.
+//!
+//! The pipelines are created in full precision on the device set in their configuration. Their weights can be converted to half precision (`half`), bfloat16 (`bfloat16`, also supported on CPU) or back to full precision (`float`) and moved to another device (`set_device`) with the methods of the `DeviceAndPrecision` trait. The scores and sentence embeddings pooling are computed in full precision. Quantized pipelines only run on CPU in full precision and are left unchanged by these methods (their `try_set_device`, `try_half` and `try_bfloat16` variants return an error).
+//!
 //! ## Loading pretrained and custom model weights
 //!
 //! The base model and task-specific heads are also available for users looking to expose their own transformer based models.
 //! Examples on how to prepare the date using a native tokenizers Rust library are available in `./examples` for BERT, DistilBERT, RoBERTa, GPT, GPT2 and BART.
 //! Note that when importing models from Pytorch, the convention for parameters naming needs to be aligned with the Rust schema. Loading of the pre-trained weights will fail if any of the model parameters weights cannot be found in the weight files.
-//! If this quality check is to be skipped, an alternative method `load_partial` can be invoked from the variables store.
+//! In that case, a `RustBertError::WeightsLoadingError` is returned, listing the missing, unexpected and shape-mismatched tensors of the weights file.
+//! If this quality check is to be skipped, pipelines can be created with a `WeightsLoadingMode::Partial` `weights_loading_mode` (keeping the initial values of the variables that cannot be loaded, e.g. a newly initialized task-specific head),
+//! and the `rust_bert::weights::load_weights_with_mode` function can be used to load the weights of a variables store.
 //!
 //! Pretrained models are available on Hugging face's [model hub](https://huggingface.co/models?filter=rust) and can be loaded using `RemoteResources` defined in this library.
 //! A conversion utility script is included in `./utils` to convert Pytorch weights to a set of weights compatible with this library. This script requires Python and `torch` to be set-up, and can be used as follows:
 //! `python ./utils/convert_model.py path/to/pytorch_model.bin` where `path/to/pytorch_model.bin` is the location of the original Pytorch weights.
 //!
+//! Pytorch checkpoints (`pytorch_model.bin`), numpy archives (`.npz`) and safetensors files can also be converted without a Python environment using the native `convert-tensor` utility:
+//! `cargo run --bin=convert-tensor -- path/to/pytorch_model.bin path/to/rust_model.ot`. Pytorch checkpoints saved with Pytorch < 1.6 (legacy, non-zip format) still require the Python script above.
+//! The utility supports the `--skip_embeddings`, `--prefix` and `--suffix` options of the Python script, casts floating point weights to the type given by `--dtype` (default `float32`) and prints an inventory of the converted tensors (name, shape and type).
+//! Unlike the Python script, integer and boolean weights (e.g. position ids buffers) keep their original type.
+//!
+//! Weights published in the [safetensors](https://github.com/huggingface/safetensors) format can be used directly without conversion: model resources pointing to a file with a `.safetensors` extension are read natively by all pipelines, and tensor names are remapped to the Rust schema when loading (e.g. `LayerNorm.gamma` -> `LayerNorm.weight`).
+//!
 //!
 //! ## Async execution
 //!
@@ -721,5 +735,7 @@ pub mod t5;
 pub mod xlnet;
 
 pub use common::error::RustBertError;
+pub use common::quantization;
 pub use common::resources;
+pub use common::weights;
 pub use common::{Activation, Config};
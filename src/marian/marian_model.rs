@@ -12,6 +12,7 @@
 // limitations under the License.
 
 use crate::bart::{BartConfig, BartModel, BartModelOutput, LayerState};
+use crate::common::weights::load_weights_with_mode;
 use crate::pipelines::common::{ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
@@ -595,6 +596,7 @@ impl MarianForConditionalGeneration {
     ///   - `cache` - `(Option<Tensor>, Option<Vec<&LayerState, &LayerState>>)` of length *n_layer* containing the encoder padding mask and past keys and values for both the self attention and the encoder cross attention of each layer of the decoder.
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_decoder_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -776,6 +778,7 @@ impl LMHeadModel for MarianForConditionalGeneration {
             + &self.final_logits_bias;
         Ok(LMModelOutput {
             lm_logits,
+            hidden_state: Some(base_model_output.decoder_output),
             cache: Cache::BARTCache(base_model_output.cache),
         })
     }
@@ -868,12 +871,19 @@ impl MarianGenerator {
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate()?;
         let mut var_store = nn::VarStore::new(device);
 
-        let config = BartConfig::from_file(config_path);
+        let mut config = BartConfig::try_from_file(config_path)?;
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = MarianForConditionalGeneration::new(var_store.root(), &config);
-        var_store.load(weights_path)?;
+        load_weights_with_mode(
+            weights_path,
+            &mut var_store,
+            generate_config.weights_loading_mode,
+        )?;
 
         let bos_token_id = Some(config.bos_token_id.unwrap_or(0));
         let eos_token_ids = Some(match config.eos_token_id {
@@ -10,7 +10,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::bart::{BartDecoderOutput, _expand_mask, _prepare_decoder_attention_mask};
+use crate::bart::{_expand_mask, _prepare_decoder_attention_mask, BartDecoderOutput};
 use crate::common::activations::TensorFunction;
 use crate::common::dropout::Dropout;
 use crate::mbart::attention::MBartAttention;
@@ -20,6 +20,15 @@ use crate::Activation;
 use std::borrow::{Borrow, BorrowMut};
 use tch::{nn, Tensor};
 
+/// Output of a decoder layer: hidden state, self-attention weights, cross-attention weights and
+/// updated self-attention and cross-attention caches
+pub type MBartDecoderLayerOutput = (
+    Tensor,
+    Option<Tensor>,
+    Option<Tensor>,
+    (Option<LayerState>, Option<LayerState>),
+);
+
 pub struct MBartDecoderLayer {
     self_attention: MBartAttention,
     encoder_attention: MBartAttention,
@@ -119,11 +128,7 @@ impl MBartDecoderLayer {
         decoder_attention_mask: Option<&Tensor>,
         layer_states: (Option<LayerState>, Option<LayerState>),
         train: bool,
-    ) -> (
-        Tensor,
-        Option<Tensor>,
-        (Option<LayerState>, Option<LayerState>),
-    ) {
+    ) -> MBartDecoderLayerOutput {
         let output = x.apply(&self.self_attention_layer_norm);
 
         let (output, attention_weights, new_self_layer_states) = self.self_attention.forward_t(
@@ -136,13 +141,14 @@ impl MBartDecoderLayer {
         let output: Tensor = output.apply_t(&self.dropout, train) + x;
 
         let output1 = output.apply(&self.encoder_attention_layer_norm);
-        let (output1, _, new_encoder_layer_states) = self.encoder_attention.forward_t(
-            &output1,
-            Some(encoder_hidden_states),
-            encoder_attention_mask,
-            layer_states.1,
-            train,
-        );
+        let (output1, cross_attention_weights, new_encoder_layer_states) =
+            self.encoder_attention.forward_t(
+                &output1,
+                Some(encoder_hidden_states),
+                encoder_attention_mask,
+                layer_states.1,
+                train,
+            );
         let output1: Tensor = output1.apply_t(&self.dropout, train) + output;
 
         let output2 = output1.apply(&self.final_layer_norm);
@@ -155,6 +161,7 @@ impl MBartDecoderLayer {
         (
             output2,
             attention_weights,
+            cross_attention_weights,
             (new_self_layer_states, new_encoder_layer_states),
         )
     }
@@ -238,7 +245,9 @@ impl MBartDecoder {
     ) -> MBartDecoderOutput {
         let past_key_values_length = if let Some(old_layer_states_values) = &old_layer_states {
             if let Some(old_value_state) = &old_layer_states_values[0].0 {
-                old_value_state.prev_key.size()[2]
+                old_value_state
+                    .static_length
+                    .unwrap_or_else(|| old_value_state.prev_key.size()[2])
             } else {
                 0
             }
@@ -276,6 +285,11 @@ impl MBartDecoder {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
         let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.output_past {
                 if old_layer_states.is_some() {
@@ -288,10 +302,11 @@ impl MBartDecoder {
             };
 
         let mut attention_weights: Option<Tensor>;
+        let mut cross_attention_weights: Option<Tensor>;
 
         for (layer_idx, layer) in self.layers.iter().enumerate() {
-            let layer_state = match &next_decoder_cache {
-                Some(values) => values[layer_idx].to_owned(),
+            let layer_state = match &mut next_decoder_cache {
+                Some(values) => std::mem::take(&mut values[layer_idx]),
                 None => (None, None),
             };
             let temp = layer.forward_t(
@@ -304,14 +319,18 @@ impl MBartDecoder {
             );
             hidden_state = temp.0;
             attention_weights = temp.1;
+            cross_attention_weights = temp.2;
             if let Some(hidden_states) = all_hidden_states.borrow_mut() {
                 hidden_states.push(hidden_state.as_ref().copy());
             };
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(std::mem::take(&mut attention_weights.unwrap()));
             };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(std::mem::take(&mut cross_attention_weights.unwrap()));
+            };
             if let Some(value) = &mut next_decoder_cache {
-                value[layer_idx] = temp.2
+                value[layer_idx] = temp.3
             };
         }
 
@@ -321,6 +340,7 @@ impl MBartDecoder {
             next_decoder_cache,
             all_hidden_states,
             all_attentions,
+            all_cross_attentions,
         }
     }
 }
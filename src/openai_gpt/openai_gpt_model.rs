@@ -15,6 +15,7 @@
 use crate::common::dropout::Dropout;
 use crate::common::embeddings::process_ids_embeddings_pair;
 use crate::common::linear::{linear_no_bias, LinearNoBias};
+use crate::common::weights::load_weights_with_mode;
 use crate::gpt2::Gpt2Config;
 use crate::openai_gpt::transformer::Block;
 use crate::pipelines::common::{ModelType, TokenizerOption};
@@ -412,6 +413,7 @@ impl LMHeadModel for OpenAIGPTLMHeadModel {
         let lm_logits = base_model_output.hidden_state.apply(&self.lm_head);
         Ok(LMModelOutput {
             lm_logits,
+            hidden_state: Some(base_model_output.hidden_state),
             cache: Cache::None,
         })
     }
@@ -496,16 +498,20 @@ impl OpenAIGenerator {
         generate_config: GenerateConfig,
         tokenizer: TokenizerOption,
     ) -> Result<OpenAIGenerator, RustBertError> {
-        generate_config.validate();
+        generate_config.validate()?;
 
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         let mut var_store = nn::VarStore::new(device);
-        let config = Gpt2Config::from_file(config_path);
+        let config = Gpt2Config::try_from_file(config_path)?;
         let model = OpenAIGPTLMHeadModel::new(var_store.root(), &config);
-        var_store.load(weights_path)?;
+        load_weights_with_mode(
+            weights_path,
+            &mut var_store,
+            generate_config.weights_loading_mode,
+        )?;
 
         let bos_token_id = tokenizer.get_bos_id();
         let eos_token_ids = tokenizer.get_eos_id().map(|id| vec![id]);
@@ -55,7 +55,7 @@ impl Block {
         attention_mask: Option<&Tensor>,
         train: bool,
     ) -> (Tensor, Option<Tensor>) {
-        let (output, _, attentions) = self.attn.forward_t(x, None, attention_mask, train);
+        let (output, _, attentions) = self.attn.forward_t(x, None, None, attention_mask, train);
         let x = (x + output).apply(&self.ln_1);
         let m = self.mlp.forward_t(&x, train);
         let x = (x + m).apply(&self.ln_2);
@@ -56,6 +56,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::path::Path;
+use tch::nn::VarStore;
+use tch::Device;
 
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 /// # Identifies the type of model
@@ -180,33 +182,53 @@ pub enum TokenizerOption {
 
 impl ConfigOption {
     /// Interface method to load a configuration from file
+    ///
+    /// # Panics
+    ///
+    /// If the configuration file cannot be opened or parsed. See `try_from_file` for a fallible version of this method.
     pub fn from_file<P: AsRef<Path>>(model_type: ModelType, path: P) -> Self {
-        match model_type {
-            ModelType::Bart => ConfigOption::Bart(BartConfig::from_file(path)),
-            ModelType::Bert => ConfigOption::Bert(BertConfig::from_file(path)),
-            ModelType::Deberta => ConfigOption::Deberta(DebertaConfig::from_file(path)),
-            ModelType::DebertaV2 => ConfigOption::DebertaV2(DebertaV2Config::from_file(path)),
-            ModelType::DistilBert => ConfigOption::DistilBert(DistilBertConfig::from_file(path)),
-            ModelType::Electra => ConfigOption::Electra(ElectraConfig::from_file(path)),
-            ModelType::Marian => ConfigOption::Marian(MarianConfig::from_file(path)),
-            ModelType::MobileBert => ConfigOption::MobileBert(MobileBertConfig::from_file(path)),
-            ModelType::T5 => ConfigOption::T5(T5Config::from_file(path)),
-            ModelType::Albert => ConfigOption::Albert(AlbertConfig::from_file(path)),
-            ModelType::XLNet => ConfigOption::XLNet(XLNetConfig::from_file(path)),
-            ModelType::GPT2 => ConfigOption::GPT2(Gpt2Config::from_file(path)),
-            ModelType::GPTNeo => ConfigOption::GPTNeo(GptNeoConfig::from_file(path)),
-            ModelType::OpenAiGpt => ConfigOption::OpenAiGpt(OpenAiGptConfig::from_file(path)),
-            ModelType::Reformer => ConfigOption::Reformer(ReformerConfig::from_file(path)),
-            ModelType::ProphetNet => ConfigOption::ProphetNet(ProphetNetConfig::from_file(path)),
-            ModelType::Longformer => ConfigOption::Longformer(LongformerConfig::from_file(path)),
-            ModelType::Pegasus => ConfigOption::Pegasus(PegasusConfig::from_file(path)),
+        Self::try_from_file(model_type, path).unwrap()
+    }
+
+    /// Interface method to load a configuration from file, returning a `RustBertError::InvalidConfigurationError` if the file cannot be opened or parsed
+    pub fn try_from_file<P: AsRef<Path>>(
+        model_type: ModelType,
+        path: P,
+    ) -> Result<Self, RustBertError> {
+        Ok(match model_type {
+            ModelType::Bart => ConfigOption::Bart(BartConfig::try_from_file(path)?),
+            ModelType::Bert => ConfigOption::Bert(BertConfig::try_from_file(path)?),
+            ModelType::Deberta => ConfigOption::Deberta(DebertaConfig::try_from_file(path)?),
+            ModelType::DebertaV2 => ConfigOption::DebertaV2(DebertaV2Config::try_from_file(path)?),
+            ModelType::DistilBert => {
+                ConfigOption::DistilBert(DistilBertConfig::try_from_file(path)?)
+            }
+            ModelType::Electra => ConfigOption::Electra(ElectraConfig::try_from_file(path)?),
+            ModelType::Marian => ConfigOption::Marian(MarianConfig::try_from_file(path)?),
+            ModelType::MobileBert => {
+                ConfigOption::MobileBert(MobileBertConfig::try_from_file(path)?)
+            }
+            ModelType::T5 => ConfigOption::T5(T5Config::try_from_file(path)?),
+            ModelType::Albert => ConfigOption::Albert(AlbertConfig::try_from_file(path)?),
+            ModelType::XLNet => ConfigOption::XLNet(XLNetConfig::try_from_file(path)?),
+            ModelType::GPT2 => ConfigOption::GPT2(Gpt2Config::try_from_file(path)?),
+            ModelType::GPTNeo => ConfigOption::GPTNeo(GptNeoConfig::try_from_file(path)?),
+            ModelType::OpenAiGpt => ConfigOption::OpenAiGpt(OpenAiGptConfig::try_from_file(path)?),
+            ModelType::Reformer => ConfigOption::Reformer(ReformerConfig::try_from_file(path)?),
+            ModelType::ProphetNet => {
+                ConfigOption::ProphetNet(ProphetNetConfig::try_from_file(path)?)
+            }
+            ModelType::Longformer => {
+                ConfigOption::Longformer(LongformerConfig::try_from_file(path)?)
+            }
+            ModelType::Pegasus => ConfigOption::Pegasus(PegasusConfig::try_from_file(path)?),
             ModelType::Roberta | ModelType::XLMRoberta => {
-                ConfigOption::Roberta(RobertaConfig::from_file(path))
+                ConfigOption::Roberta(RobertaConfig::try_from_file(path)?)
             }
-            ModelType::MBart => ConfigOption::MBart(MBartConfig::from_file(path)),
-            ModelType::M2M100 => ConfigOption::M2M100(M2M100Config::from_file(path)),
-            ModelType::FNet => ConfigOption::FNet(FNetConfig::from_file(path)),
-        }
+            ModelType::MBart => ConfigOption::MBart(MBartConfig::try_from_file(path)?),
+            ModelType::M2M100 => ConfigOption::M2M100(M2M100Config::try_from_file(path)?),
+            ModelType::FNet => ConfigOption::FNet(FNetConfig::try_from_file(path)?),
+        })
     }
 
     pub fn get_label_mapping(&self) -> &HashMap<i64, String> {
@@ -1804,3 +1826,108 @@ impl TokenizerOption {
         }
     }
 }
+
+/// # Device and precision management for pipelines
+/// Pipelines are created in full precision (float32) on the device set in their configuration. The
+/// model weights can be converted to half precision (fp16) or bfloat16 and moved to another device
+/// after the pipeline creation. The post-processing operations sensitive to a reduced precision
+/// (e.g. the softmax of the output logits or the pooling of sentence embeddings) are computed in
+/// float32.
+///
+/// Quantized models (see `QuantizationMode`) hold their int8 weights outside of the variables store
+/// and only run on the CPU with float32 activations: they are left unchanged by `set_device`,
+/// `half` and `bfloat16`, and the `try_*` variants of these methods return an error.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::pipelines::common::DeviceAndPrecision;
+/// use rust_bert::pipelines::sequence_classification::SequenceClassificationModel;
+/// use tch::Device;
+///
+/// let mut model = SequenceClassificationModel::new(Default::default())?;
+/// model.set_device(Device::Cpu);
+/// model.bfloat16();
+/// # Ok(())
+/// # }
+/// ```
+pub trait DeviceAndPrecision {
+    /// Returns a reference to the variables store holding the model weights
+    fn get_var_store(&self) -> &VarStore;
+
+    /// Returns a mutable reference to the variables store holding the model weights
+    fn get_var_store_mut(&mut self) -> &mut VarStore;
+
+    /// Returns the device the model is placed on
+    fn device(&self) -> Device {
+        self.get_var_store().device()
+    }
+
+    /// Returns `true` if the linear layers of the model are quantized
+    fn is_quantized(&self) -> bool {
+        false
+    }
+
+    /// Converts the floating point weights of the model to half precision (fp16). Quantized models
+    /// are left unchanged.
+    fn half(&mut self) {
+        let _ = self.try_half();
+    }
+
+    /// Converts the floating point weights of the model to half precision (fp16). Returns an error
+    /// for quantized models.
+    fn try_half(&mut self) -> Result<(), RustBertError> {
+        check_not_quantized(self.is_quantized(), "converted to half precision")?;
+        self.get_var_store_mut().half();
+        Ok(())
+    }
+
+    /// Converts the floating point weights of the model to bfloat16 (supported on CPU and recent
+    /// GPUs). Quantized models are left unchanged.
+    fn bfloat16(&mut self) {
+        let _ = self.try_bfloat16();
+    }
+
+    /// Converts the floating point weights of the model to bfloat16. Returns an error for quantized
+    /// models.
+    fn try_bfloat16(&mut self) -> Result<(), RustBertError> {
+        check_not_quantized(self.is_quantized(), "converted to bfloat16")?;
+        self.get_var_store_mut().bfloat16();
+        Ok(())
+    }
+
+    /// Converts the floating point weights of the model to full precision (float32)
+    fn float(&mut self) {
+        self.get_var_store_mut().float();
+    }
+
+    /// Moves the model weights to the device provided. Quantized models are left on the CPU.
+    fn set_device(&mut self, device: Device) {
+        let _ = self.try_set_device(device);
+    }
+
+    /// Moves the model weights to the device provided. Returns an error when moving a quantized
+    /// model to a device other than the CPU.
+    fn try_set_device(&mut self, device: Device) -> Result<(), RustBertError> {
+        if device != Device::Cpu {
+            check_not_quantized(self.is_quantized(), &format!("moved to {:?}", device))?;
+        }
+        self.get_var_store_mut().set_device(device);
+        Ok(())
+    }
+}
+
+/// Returns an error for quantized models, the quantized weights not supporting the conversion provided
+pub(crate) fn check_not_quantized(
+    is_quantized: bool,
+    conversion: &str,
+) -> Result<(), RustBertError> {
+    if is_quantized {
+        return Err(RustBertError::ValueError(format!(
+            "Quantized models only run on CPU in float32 and cannot be {}",
+            conversion
+        )));
+    }
+    Ok(())
+}
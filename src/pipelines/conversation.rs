@@ -50,16 +50,25 @@
 //! # ];
 //! ```
 //!
+//! Conversations with `retain_cache` enabled keep the keys and values computed by the model in a
+//! `ConversationCache`, avoiding the re-encoding of their history at the next turn. The cache may be
+//! saved to a file and restored to resume a conversation evicted from memory.
+//!
 //! # Disclaimer
 //! The authors of this repository are not responsible for any generation
 //! from the 3rd party utilization of the pretrained system.
 use crate::common::error::RustBertError;
+use crate::common::weights::WeightsLoadingMode;
 use crate::gpt2::GPT2Generator;
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::common::{DeviceAndPrecision, ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
-use crate::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
+use crate::pipelines::generation_utils::{
+    Cache, GenerateConfig, GeneratedToken, LanguageGenerator,
+};
 use crate::resources::ResourceProvider;
 use std::collections::HashMap;
+use std::path::Path;
+use tch::nn::VarStore;
 use tch::{Device, Kind, Tensor};
 use uuid::Uuid;
 
@@ -113,8 +122,22 @@ pub struct ConversationConfig {
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Typical sampling mass, following [Meister et al.](https://arxiv.org/abs/2202.00666). If provided and lower than 1, keeps the most typical tokens until their cumulative probability reaches typical_p (sampling only)
+    pub typical_p: Option<f64>,
+    /// Epsilon sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than epsilon_cutoff (sampling only)
+    pub epsilon_cutoff: Option<f64>,
+    /// Eta sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than min(eta_cutoff, sqrt(eta_cutoff) * exp(-entropy)) (sampling only)
+    pub eta_cutoff: Option<f64>,
+    /// Min-p sampling. If provided and higher than 0, removes the tokens with a probability lower than min_p times the probability of the most likely token (sampling only)
+    pub min_p: Option<f64>,
+    /// Degeneration penalty for [contrastive search, Su et al.](https://arxiv.org/abs/2202.06417). If provided and higher than 0, greedy decoding with top_k higher than 1 uses contrastive search
+    pub penalty_alpha: Option<f64>,
+    /// Preallocated key/value cache. If true, the keys and values of the self-attention layers are stored in buffers sized to `max_length`, written in place at each decoding step instead of being concatenated to the cache (GPT2, GPT-Neo and BART-family models, requires a maximum length) (default: false)
+    pub static_cache: bool,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
 }
 
 #[cfg(feature = "remote")]
@@ -149,7 +172,14 @@ impl Default for ConversationConfig {
             num_return_sequences: 1,
             num_beam_groups: None,
             diversity_penalty: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            min_p: None,
+            penalty_alpha: None,
+            static_cache: false,
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
         }
     }
 }
@@ -175,7 +205,143 @@ impl From<ConversationConfig> for GenerateConfig {
             num_return_sequences: config.num_return_sequences,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            typical_p: config.typical_p,
+            epsilon_cutoff: config.epsilon_cutoff,
+            eta_cutoff: config.eta_cutoff,
+            min_p: config.min_p,
+            penalty_alpha: config.penalty_alpha,
+            static_cache: config.static_cache,
+            output_attentions: false,
             device: config.device,
+            weights_loading_mode: config.weights_loading_mode,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Keys and values computed by the model for the leading tokens of a conversation, allowing to
+/// resume the conversation at the next turn without re-encoding its history. The cache is only
+/// used if the tokens it holds are a prefix of the next model input (it is ignored if the history
+/// was modified or truncated). It can be saved to a file, for example to evict idle conversations
+/// from memory, and restored with `ConversationCache::load`.
+pub struct ConversationCache {
+    token_ids: Vec<i64>,
+    layer_states: Vec<Tensor>,
+}
+
+impl Clone for ConversationCache {
+    fn clone(&self) -> Self {
+        ConversationCache {
+            token_ids: self.token_ids.clone(),
+            layer_states: self
+                .layer_states
+                .iter()
+                .map(|layer_state| layer_state.copy())
+                .collect(),
+        }
+    }
+}
+
+impl ConversationCache {
+    /// Returns the token ids whose keys and values are held by the cache
+    pub fn token_ids(&self) -> &[i64] {
+        &self.token_ids
+    }
+
+    /// Saves the cache to a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to write
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::conversation::{ConversationManager, ConversationModel};
+    /// let model = ConversationModel::new(Default::default())?;
+    /// let mut conversation_manager = ConversationManager::new();
+    /// let conversation_id = conversation_manager.create("Hello, how are you?");
+    /// conversation_manager
+    ///     .get(&conversation_id)
+    ///     .unwrap()
+    ///     .retain_cache = true;
+    ///
+    /// let _ = model.generate_responses(&mut conversation_manager)?;
+    /// if let Some(cache) = &conversation_manager.get(&conversation_id).unwrap().cache {
+    ///     cache.save("conversation_cache.ot")?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RustBertError> {
+        let mut named_tensors = Vec::with_capacity(self.layer_states.len() + 1);
+        named_tensors.push(("token_ids".to_string(), Tensor::of_slice(&self.token_ids)));
+        for (layer_index, layer_state) in self.layer_states.iter().enumerate() {
+            named_tensors.push((
+                format!("layer_{}", layer_index),
+                layer_state.shallow_clone(),
+            ));
+        }
+        Tensor::save_multi(&named_tensors, path)?;
+        Ok(())
+    }
+
+    /// Loads a cache saved with `ConversationCache::save`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to read
+    /// * `device` - `Device` to place the cache on (the device of the conversation model)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::conversation::{Conversation, ConversationCache};
+    /// use tch::Device;
+    ///
+    /// let mut conversation = Conversation::new_empty();
+    /// // The conversation history is restored, e.g. with `load_from_history`
+    /// conversation.cache = Some(ConversationCache::load(
+    ///     "conversation_cache.ot",
+    ///     Device::cuda_if_available(),
+    /// )?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load<P: AsRef<Path>>(path: P, device: Device) -> Result<Self, RustBertError> {
+        let mut named_tensors = Tensor::load_multi_with_device(path, device)?
+            .into_iter()
+            .collect::<HashMap<String, Tensor>>();
+        let token_ids = named_tensors.remove("token_ids").ok_or_else(|| {
+            RustBertError::ValueError("Missing token ids in the conversation cache".to_string())
+        })?;
+        let layer_states = (0..named_tensors.len())
+            .map(|layer_index| {
+                named_tensors
+                    .remove(&format!("layer_{}", layer_index))
+                    .ok_or_else(|| {
+                        RustBertError::ValueError(format!(
+                            "Missing states of layer {} in the conversation cache",
+                            layer_index
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<Tensor>, RustBertError>>()?;
+        Ok(ConversationCache {
+            token_ids: Vec::<i64>::from(token_ids),
+            layer_states,
+        })
+    }
+
+    /// Number of leading positions of the conversation tokens `input` that can be read from the
+    /// cache (0 if the cache does not match the input)
+    fn cached_length(&self, input: &[i64]) -> usize {
+        if (self.token_ids.len() < input.len()) & input.starts_with(&self.token_ids) {
+            self.token_ids.len()
+        } else {
+            0
         }
     }
 }
@@ -193,6 +359,11 @@ pub struct Conversation {
     pub new_user_input: Option<String>,
     ///  History of the tokens passed as an input and generated so far used as context for next turn generation
     pub history: Vec<Vec<i64>>,
+    /// Flag indicating if the model keys and values should be kept in `cache` after each turn,
+    /// avoiding the re-encoding of the history at the next turn (greedy decoding and sampling only)
+    pub retain_cache: bool,
+    /// Cache of the keys and values computed for the conversation tokens, used for the next turn
+    pub cache: Option<ConversationCache>,
 }
 
 impl Conversation {
@@ -215,6 +386,8 @@ impl Conversation {
             generated_responses: vec![],
             new_user_input: Some(text.to_string()),
             history: vec![],
+            retain_cache: false,
+            cache: None,
         }
     }
 
@@ -233,6 +406,8 @@ impl Conversation {
             generated_responses: vec![],
             new_user_input: None,
             history: vec![],
+            retain_cache: false,
+            cache: None,
         }
     }
 
@@ -734,24 +909,109 @@ impl ConversationOption {
         &self,
         input_ids: Tensor,
         attention_mask: Option<Tensor>,
-    ) -> Vec<Vec<i64>> {
-        match *self {
+    ) -> Result<Vec<Vec<i64>>, RustBertError> {
+        Ok(match *self {
+            Self::GPT2(ref model) => model
+                .generate_from_ids_and_past(input_ids, attention_mask, None)?
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+        })
+    }
+
+    /// Interface method to generate_from_ids_and_past_stream() of the particular models.
+    pub fn generate_from_ids_and_past_stream<F>(
+        &self,
+        input_ids: Tensor,
+        attention_mask: Option<Tensor>,
+        token_callback: F,
+    ) -> Result<Vec<Vec<i64>>, RustBertError>
+    where
+        F: FnMut(&GeneratedToken),
+    {
+        Ok(match *self {
             Self::GPT2(ref model) => model
-                .generate_from_ids_and_past(input_ids, attention_mask, None)
+                .generate_from_ids_and_past_stream(input_ids, attention_mask, None, token_callback)?
                 .into_iter()
                 .map(|output| output.indices)
                 .collect(),
+        })
+    }
+
+    /// Interface method to generate_from_ids_and_cache() of the particular models.
+    pub fn generate_from_ids_and_cache(
+        &self,
+        input_ids: Tensor,
+        attention_mask: Option<Tensor>,
+        past: Cache,
+        cached_length: i64,
+    ) -> Result<(Vec<Vec<i64>>, Cache), RustBertError> {
+        let (output, cache) = match *self {
+            Self::GPT2(ref model) => model.generate_from_ids_and_cache(
+                input_ids,
+                attention_mask,
+                past,
+                cached_length,
+                None,
+            )?,
+        };
+        Ok((
+            output.into_iter().map(|output| output.indices).collect(),
+            cache,
+        ))
+    }
+
+    /// Interface method to generate_from_ids_and_cache_stream() of the particular models.
+    pub fn generate_from_ids_and_cache_stream<F>(
+        &self,
+        input_ids: Tensor,
+        attention_mask: Option<Tensor>,
+        past: Cache,
+        cached_length: i64,
+        token_callback: F,
+    ) -> Result<(Vec<Vec<i64>>, Cache), RustBertError>
+    where
+        F: FnMut(&GeneratedToken),
+    {
+        let (output, cache) = match *self {
+            Self::GPT2(ref model) => model.generate_from_ids_and_cache_stream(
+                input_ids,
+                attention_mask,
+                past,
+                cached_length,
+                None,
+                token_callback,
+            )?,
+        };
+        Ok((
+            output.into_iter().map(|output| output.indices).collect(),
+            cache,
+        ))
+    }
+
+    /// Returns a reference to the variables store of the underlying model
+    pub fn get_var_store(&self) -> &VarStore {
+        match self {
+            Self::GPT2(model_ref) => model_ref.get_var_store(),
+        }
+    }
+
+    /// Returns a mutable reference to the variables store of the underlying model
+    pub fn get_var_store_mut(&mut self) -> &mut VarStore {
+        match self {
+            Self::GPT2(model_ref) => model_ref.get_var_store_mut(),
         }
     }
 }
 
+type ConversationTokenCallback<'a> = &'a mut dyn FnMut(&Uuid, &GeneratedToken);
+
 /// # Conversation model
 /// Processes a ConversationManager and generate system responses for active conversations.
 pub struct ConversationModel {
     model: ConversationOption,
     eos_token_id: i64,
     max_allowed_context_length: Option<i64>,
-    device: Device,
 }
 
 impl ConversationModel {
@@ -777,14 +1037,12 @@ impl ConversationModel {
         let max_allowed_length = conversation_config
             .max_length
             .map(|max_length| max_length - conversation_config.min_length_for_response);
-        let device = conversation_config.device;
         let model = ConversationOption::new(conversation_config)?;
         let eos_token_id = model.get_eos_id()?;
         Ok(ConversationModel {
             model,
             eos_token_id,
             max_allowed_context_length: max_allowed_length,
-            device,
         })
     }
 
@@ -795,7 +1053,7 @@ impl ConversationModel {
     /// * `conversation_manager` - `&mut ConversationManager` Conversation manager keeping track of active conversations
     ///
     /// # Returns
-    /// * `HashMap<&Uuid, &str>` Responses from the model for each active conversation, referenced by Uuid
+    /// * `Result<HashMap<&Uuid, &str>, RustBertError>` Responses from the model for each active conversation, referenced by Uuid
     ///
     /// # Example
     ///
@@ -808,14 +1066,67 @@ impl ConversationModel {
     /// let mut conversation_manager = ConversationManager::new();
     /// conversation_manager.create("Hello, how are you?");
     ///
-    /// let output = model.generate_responses(&mut conversation_manager);
+    /// let output = model.generate_responses(&mut conversation_manager)?;
     /// # Ok(())
     /// # }
     /// ```
     pub fn generate_responses<'a>(
         &self,
         conversation_manager: &'a mut ConversationManager,
-    ) -> HashMap<&'a Uuid, &'a str> {
+    ) -> Result<HashMap<&'a Uuid, &'a str>, RustBertError> {
+        self.generate_responses_internal(conversation_manager, None)
+    }
+
+    /// Perform a multi-turn conversation based on user input, streaming the generated tokens to a callback as soon as
+    /// they are generated. Streaming is supported for greedy and sampling decoding (`num_beams` = 1).
+    ///
+    /// # Arguments
+    ///
+    /// * `conversation_manager` - `&mut ConversationManager` Conversation manager keeping track of active conversations
+    /// * `token_callback` - `FnMut(&Uuid, &GeneratedToken)` Callback called for every generated token with the Uuid of the conversation it belongs to and the text it adds to the response
+    ///
+    /// # Returns
+    /// * `Result<HashMap<&Uuid, &str>, RustBertError>` Responses from the model for each active conversation, referenced by Uuid
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::conversation::{
+    ///     ConversationConfig, ConversationManager, ConversationModel,
+    /// };
+    /// use std::io::Write;
+    /// let model = ConversationModel::new(ConversationConfig {
+    ///     num_beams: 1,
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// let mut conversation_manager = ConversationManager::new();
+    /// conversation_manager.create("Hello, how are you?");
+    ///
+    /// let output = model.generate_responses_stream(&mut conversation_manager, |_uuid, token| {
+    ///     print!("{}", token.text);
+    ///     std::io::stdout().flush().unwrap();
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn generate_responses_stream<'a, F>(
+        &self,
+        conversation_manager: &'a mut ConversationManager,
+        mut token_callback: F,
+    ) -> Result<HashMap<&'a Uuid, &'a str>, RustBertError>
+    where
+        F: FnMut(&Uuid, &GeneratedToken),
+    {
+        self.generate_responses_internal(conversation_manager, Some(&mut token_callback))
+    }
+
+    fn generate_responses_internal<'a>(
+        &self,
+        conversation_manager: &'a mut ConversationManager,
+        token_callback: Option<ConversationTokenCallback>,
+    ) -> Result<HashMap<&'a Uuid, &'a str>, RustBertError> {
         let (active_uuid, active_conversations) = conversation_manager.get_active_conversations();
         if !active_uuid.is_empty() {
             let texts = active_conversations
@@ -829,26 +1140,59 @@ impl ConversationModel {
                 .collect::<Vec<Vec<i64>>>();
 
             let prompt_ids = self.encode_prompts(texts.as_ref());
-            let (input_tensor, attention_mask) =
+            let (input_tensor, attention_mask, model_inputs) =
                 self.concat_input_history(prompt_ids.as_ref(), history);
             let input_length = *input_tensor.size().last().unwrap() as usize;
-            let mut generated = self
-                .model
-                .generate_from_ids_and_past(input_tensor, Some(attention_mask));
+            let (past, cached_length) =
+                self.get_batch_cache(&active_conversations, &model_inputs, input_length);
+            let (mut generated, past) = match token_callback {
+                Some(token_callback) => self.model.generate_from_ids_and_cache_stream(
+                    input_tensor,
+                    Some(attention_mask),
+                    past,
+                    cached_length,
+                    |token| token_callback(active_uuid[token.sequence_index], token),
+                )?,
+                None => self.model.generate_from_ids_and_cache(
+                    input_tensor,
+                    Some(attention_mask),
+                    past,
+                    cached_length,
+                )?,
+            };
             let removed_padding_quantities = self.clean_padding_indices(&mut generated);
+            let layer_states = self.get_output_layer_states(past, active_conversations.len());
 
             let mut output = HashMap::with_capacity(active_uuid.len());
 
             for (
-                ((conversation, (generated_sequence, conversation_promp_ids)), uuid),
-                removed_padding,
+                (
+                    ((conversation, (generated_sequence, conversation_promp_ids)), uuid),
+                    removed_padding,
+                ),
+                (row_index, model_input),
             ) in active_conversations
                 .into_iter()
                 .zip(generated.into_iter().zip(prompt_ids.into_iter()))
                 .zip(active_uuid.into_iter())
                 .zip(removed_padding_quantities.into_iter())
+                .zip(model_inputs.into_iter().enumerate())
             {
                 let generated_response = &generated_sequence[input_length - removed_padding.0..];
+                conversation.cache = match layer_states.as_ref() {
+                    Some(layer_states) if conversation.retain_cache => {
+                        let padding_length = (input_length - model_input.len()) as i64;
+                        let mut token_ids = model_input;
+                        token_ids.extend_from_slice(generated_response);
+                        Some(Self::get_conversation_cache(
+                            layer_states,
+                            row_index as i64,
+                            padding_length,
+                            token_ids,
+                        ))
+                    }
+                    _ => None,
+                };
                 conversation
                     .generated_responses
                     .push(
@@ -861,9 +1205,116 @@ impl ConversationModel {
                 conversation.mark_processed();
                 output.insert(uuid, conversation.get_last_response().unwrap());
             }
-            output
+            Ok(output)
         } else {
-            HashMap::new()
+            Ok(HashMap::new())
+        }
+    }
+
+    /// Builds the cache of the batch from the caches of the conversations. The cached positions
+    /// must be common to all (left-padded) sequences: the cache is truncated to the shortest cached
+    /// prefix, and is not used if a conversation padding extends past this prefix.
+    fn get_batch_cache(
+        &self,
+        conversations: &[&mut Conversation],
+        model_inputs: &[Vec<i64>],
+        input_length: usize,
+    ) -> (Cache, i64) {
+        let padding_lengths = model_inputs
+            .iter()
+            .map(|model_input| input_length - model_input.len())
+            .collect::<Vec<usize>>();
+        let cached_length = conversations
+            .iter()
+            .zip(model_inputs.iter().zip(padding_lengths.iter()))
+            .map(|(conversation, (model_input, padding_length))| {
+                padding_length
+                    + conversation
+                        .cache
+                        .as_ref()
+                        .map_or(0, |cache| cache.cached_length(model_input))
+            })
+            .min()
+            .unwrap_or(0);
+        let reference_cache = conversations.iter().find_map(|conversation| {
+            conversation
+                .cache
+                .as_ref()
+                .filter(|cache| !cache.layer_states.is_empty())
+        });
+        let reference_cache = match reference_cache {
+            Some(reference_cache)
+                if (cached_length > 0)
+                    & (cached_length >= *padding_lengths.iter().max().unwrap()) =>
+            {
+                reference_cache
+            }
+            _ => return (Cache::None, 0),
+        };
+
+        let caches = conversations
+            .iter()
+            .zip(padding_lengths)
+            .map(|(conversation, padding_length)| {
+                let num_positions = (cached_length - padding_length) as i64;
+                let layer_states = match &conversation.cache {
+                    Some(cache) if num_positions > 0 => &cache.layer_states,
+                    _ => &reference_cache.layer_states,
+                };
+                let mut cache = Cache::GPT2Cache(Some(
+                    layer_states
+                        .iter()
+                        .map(|layer_state| layer_state.to_device(self.device()))
+                        .collect(),
+                ));
+                cache.truncate(num_positions);
+                cache.left_pad(cached_length as i64);
+                cache
+            })
+            .collect();
+        (Cache::cat(caches), cached_length as i64)
+    }
+
+    /// Returns the keys and values of the generated sequences held by the cache if it can be split
+    /// into conversation caches
+    fn get_output_layer_states(&self, past: Cache, batch_size: usize) -> Option<Vec<Tensor>> {
+        let layer_states = match past {
+            Cache::GPT2Cache(Some(layer_states)) => layer_states,
+            Cache::GPT2StaticCache(layer_states, static_length) => layer_states
+                .iter()
+                .map(|layer_state| layer_state.narrow(3, 0, static_length))
+                .collect(),
+            _ => return None,
+        };
+        layer_states
+            .first()
+            .filter(|layer_state| layer_state.size()[1] == batch_size as i64)?;
+        Some(layer_states)
+    }
+
+    /// Extracts the cache of a conversation from the keys and values of the generated sequences,
+    /// skipping its `padding_length` leading padding positions
+    fn get_conversation_cache(
+        layer_states: &[Tensor],
+        row_index: i64,
+        padding_length: i64,
+        mut token_ids: Vec<i64>,
+    ) -> ConversationCache {
+        let num_positions = (layer_states[0].size()[3] - padding_length)
+            .min(token_ids.len() as i64)
+            .max(0);
+        token_ids.truncate(num_positions as usize);
+        ConversationCache {
+            token_ids,
+            layer_states: layer_states
+                .iter()
+                .map(|layer_state| {
+                    layer_state
+                        .narrow(1, row_index, 1)
+                        .narrow(3, padding_length, num_positions)
+                        .copy()
+                })
+                .collect(),
         }
     }
 
@@ -898,7 +1349,7 @@ impl ConversationModel {
         &self,
         inputs: &[Vec<i64>],
         history: Vec<Vec<i64>>,
-    ) -> (Tensor, Tensor) {
+    ) -> (Tensor, Tensor, Vec<Vec<i64>>) {
         // Concatenates the history token indices with new user input
         let pad_token = self
             .model
@@ -945,11 +1396,11 @@ impl ConversationModel {
 
         let attention_mask = Tensor::ones(
             &[inputs.len() as i64, max_len as i64],
-            (Kind::Int8, self.device),
+            (Kind::Int8, self.device()),
         );
 
         let concatenated_inputs = truncated_concatenated_inputs
-            .into_iter()
+            .iter()
             .enumerate()
             .map(|(input_idx, input)| {
                 let _ = attention_mask
@@ -957,13 +1408,20 @@ impl ConversationModel {
                     .slice(0, 0, (max_len - input.len()) as i64, 1)
                     .fill_(0);
                 let mut padded_input = vec![pad_token; max_len - input.len()];
-                padded_input.extend(input);
+                padded_input.extend_from_slice(input);
                 padded_input
             })
-            .map(|tokens| Tensor::of_slice(&tokens).to(self.device))
+            .map(|tokens| Tensor::of_slice(&tokens).to(self.device()))
             .collect::<Vec<Tensor>>();
 
-        (Tensor::stack(&concatenated_inputs, 0), attention_mask)
+        (
+            Tensor::stack(&concatenated_inputs, 0),
+            attention_mask,
+            truncated_concatenated_inputs
+                .into_iter()
+                .map(|input| input.to_vec())
+                .collect(),
+        )
     }
 
     fn get_truncated_input_index(
@@ -1030,6 +1488,16 @@ impl ConversationModel {
     }
 }
 
+impl DeviceAndPrecision for ConversationModel {
+    fn get_var_store(&self) -> &VarStore {
+        self.model.get_var_store()
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        self.model.get_var_store_mut()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
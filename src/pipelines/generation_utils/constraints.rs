@@ -0,0 +1,145 @@
+// Copyright 2022 The HuggingFace Inc. team.
+// Copyright 2019 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Constrained beam search
+//! Constraints forcing token sequences to appear in the generated output. A `Constraint` is either a phrase (a single
+//! token sequence) or a disjunctive set of alternative token sequences, one of which must be generated.
+//! Constraints are provided to the `generate` methods via the `constraints` field of `GenerateOptions` and require
+//! beam search (`num_beams` > 1) without sampling.
+//!
+//! At each step, the beam search candidates are extended with the tokens advancing the constraints of each beam.
+//! The candidates are then grouped by the number of constraint tokens they fulfill and the beams are filled from each
+//! group in turn, following [Hu et al.](https://aclanthology.org/N19-1090/) (dynamic beam allocation).
+//! A hypothesis is only finished (end of sequence token) once all constraints are fulfilled.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::bart::BartGenerator;
+//! use rust_bert::pipelines::generation_utils::constraints::Constraint;
+//! use rust_bert::pipelines::generation_utils::{GenerateOptions, LanguageGenerator};
+//!
+//! let bart_generator = BartGenerator::new(Default::default())?;
+//! let tokenizer = bart_generator.get_tokenizer();
+//! let constraints = [
+//!     Constraint::Phrase(tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(" water vapour"))),
+//!     Constraint::Disjunctive(vec![
+//!         tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(" planet")),
+//!         tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(" exoplanet")),
+//!     ]),
+//! ];
+//! let generate_options = GenerateOptions {
+//!     num_beams: Some(4),
+//!     do_sample: Some(false),
+//!     constraints: Some(&constraints),
+//!     ..Default::default()
+//! };
+//! let input = ["Scientists have found water vapour in the atmosphere of a planet circling a star in the constellation Leo."];
+//! let output = bart_generator.generate(Some(&input), Some(generate_options))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cmp::{max, min};
+use std::slice;
+
+/// # Generation constraint
+/// Token sequence(s) that must appear in the generated output
+#[derive(Debug, Clone)]
+pub enum Constraint {
+    /// Token sequence that must be generated
+    Phrase(Vec<i64>),
+    /// Alternative token sequences, one of which must be generated
+    Disjunctive(Vec<Vec<i64>>),
+}
+
+impl Constraint {
+    /// Returns the alternative token sequences fulfilling the constraint
+    pub fn alternatives(&self) -> &[Vec<i64>] {
+        match self {
+            Constraint::Phrase(token_ids) => slice::from_ref(token_ids),
+            Constraint::Disjunctive(alternatives) => alternatives.as_slice(),
+        }
+    }
+
+    /// Checks if the constraint is fulfilled by a sequence of generated token ids
+    pub fn is_fulfilled(&self, generated_ids: &[i64]) -> bool {
+        self.alternatives().iter().any(|alternative| {
+            generated_ids
+                .windows(alternative.len())
+                .any(|window| window == alternative.as_slice())
+        })
+    }
+
+    /// Returns, for each alternative, the number of its first tokens the generated ids end with (partial match)
+    fn partial_matches<'a>(
+        &'a self,
+        generated_ids: &'a [i64],
+    ) -> impl Iterator<Item = (&'a [i64], usize)> + 'a {
+        self.alternatives().iter().map(move |alternative| {
+            let max_match_length = min(alternative.len() - 1, generated_ids.len());
+            let match_length = (1..=max_match_length)
+                .rev()
+                .find(|match_length| generated_ids.ends_with(&alternative[..*match_length]))
+                .unwrap_or(0);
+            (alternative.as_slice(), match_length)
+        })
+    }
+}
+
+/// Checks if all constraints are fulfilled by a sequence of generated token ids
+pub(crate) fn constraints_fulfilled(constraints: &[Constraint], generated_ids: &[i64]) -> bool {
+    constraints
+        .iter()
+        .all(|constraint| constraint.is_fulfilled(generated_ids))
+}
+
+/// Returns the tokens advancing the unfulfilled constraints for a sequence of generated token ids
+pub(crate) fn get_advance_tokens(constraints: &[Constraint], generated_ids: &[i64]) -> Vec<i64> {
+    let mut advance_tokens = constraints
+        .iter()
+        .filter(|constraint| !constraint.is_fulfilled(generated_ids))
+        .flat_map(|constraint| {
+            constraint
+                .partial_matches(generated_ids)
+                .map(|(alternative, match_length)| alternative[match_length])
+        })
+        .collect::<Vec<i64>>();
+    advance_tokens.sort_unstable();
+    advance_tokens.dedup();
+    advance_tokens
+}
+
+/// Returns the constraints progress (bank) of a sequence of generated token ids: fulfilled constraints count for the
+/// length of the longest alternative, partially matched constraints for this length minus the remaining tokens.
+pub(crate) fn get_constraints_bank(constraints: &[Constraint], generated_ids: &[i64]) -> usize {
+    let max_length = constraints
+        .iter()
+        .flat_map(|constraint| constraint.alternatives().iter().map(Vec::len))
+        .max()
+        .unwrap_or(0);
+    constraints
+        .iter()
+        .map(|constraint| {
+            if constraint.is_fulfilled(generated_ids) {
+                max_length
+            } else {
+                constraint
+                    .partial_matches(generated_ids)
+                    .filter(|(_, match_length)| *match_length > 0)
+                    .map(|(alternative, match_length)| {
+                        max_length - (alternative.len() - match_length)
+                    })
+                    .fold(0, max)
+            }
+        })
+        .sum()
+}
@@ -0,0 +1,702 @@
+// Copyright 2020 The HuggingFace Inc. team.
+// Copyright 2019 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Logits processors and warpers
+//! Modifications of the next token scores applied at every generation step. `LogitsProcessor`s are applied for all
+//! decoding strategies (greedy decoding, sampling and beam search) and constrain the generation (e.g. repetition
+//! penalty, banned words, minimum length, logit bias). `LogitsWarper`s are only applied when sampling and reshape the
+//! distribution the next tokens are sampled from (temperature, top-k and nucleus filtering).
+//!
+//! The built-in processors and warpers are created from the `GenerateConfig` and `GenerateOptions` settings.
+//! Custom processors and warpers can be provided to the `generate` methods via the `logits_processors` and
+//! `logits_warpers` fields of `GenerateOptions` and are applied in order after the built-in ones.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::gpt2::GPT2Generator;
+//! use rust_bert::pipelines::generation_utils::logits_processor::{
+//!     LogitsProcessor, LogitsProcessorContext,
+//! };
+//! use rust_bert::pipelines::generation_utils::{GenerateOptions, LanguageGenerator};
+//! use tch::Tensor;
+//!
+//! /// Forbids the generation of a single token
+//! struct BanTokenProcessor {
+//!     token_id: i64,
+//! }
+//!
+//! impl LogitsProcessor for BanTokenProcessor {
+//!     fn process(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+//!         let _ = scores.select(1, self.token_id).fill_(f64::NEG_INFINITY);
+//!     }
+//! }
+//!
+//! let gpt2_generator = GPT2Generator::new(Default::default())?;
+//! let ban_token_processor = BanTokenProcessor { token_id: 13 };
+//! let logits_processors: [&dyn LogitsProcessor; 1] = [&ban_token_processor];
+//! let generate_options = GenerateOptions {
+//!     logits_processors: Some(&logits_processors),
+//!     ..Default::default()
+//! };
+//! let output = gpt2_generator.generate(Some(&["The dog"]), Some(generate_options))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cmp::{max, min};
+use std::collections::HashMap;
+
+use tch::{Device, Kind, Tensor};
+
+use crate::common::kind::get_positive_infinity;
+use crate::pipelines::generation_utils::PrefixAllowedFunction;
+
+/// # Generation state passed to the logits processors and warpers
+pub struct LogitsProcessorContext<'a> {
+    /// Current length of the sequences (including the prompt for decoder-only models)
+    pub current_length: i64,
+    /// Number of beams per input for the rows of the scores (1 for greedy decoding and sampling, group size for
+    /// diverse beam search)
+    pub num_beams: i64,
+    /// Beam group information (only set for diverse beam search)
+    pub beam_group: Option<BeamGroupContext<'a>>,
+}
+
+/// # Beam group state for diverse beam search
+pub struct BeamGroupContext<'a> {
+    /// Tokens selected at the current step for all beams (set for the groups preceding the current group)
+    pub current_tokens: &'a Tensor,
+    /// Index of the first beam of the current group
+    pub group_start_index: i64,
+    /// Number of beams in the current group
+    pub group_size: i64,
+    /// Total number of beams per input
+    pub num_beams: i64,
+}
+
+/// # Logits processor
+/// Modifies the next token scores (of shape (*batch size x number of beams*, *vocabulary size*)) in place, given the
+/// previous token ids (of shape (*batch size x number of beams*, *sequence length*)). The scores are the logits for
+/// greedy decoding and sampling and the log-probabilities for beam search.
+pub trait LogitsProcessor {
+    fn process(&self, input_ids: &Tensor, scores: &mut Tensor, context: &LogitsProcessorContext);
+}
+
+impl<T: LogitsProcessor + ?Sized> LogitsProcessor for &T {
+    fn process(&self, input_ids: &Tensor, scores: &mut Tensor, context: &LogitsProcessorContext) {
+        (**self).process(input_ids, scores, context)
+    }
+}
+
+/// # Logits warper
+/// Modifies the next token scores (of shape (*batch size x number of beams*, *vocabulary size*)) in place before
+/// sampling, given the previous token ids (of shape (*batch size x number of beams*, *sequence length*)).
+/// Warpers are only applied when sampling is enabled.
+pub trait LogitsWarper {
+    fn warp(&self, input_ids: &Tensor, scores: &mut Tensor, context: &LogitsProcessorContext);
+}
+
+impl<T: LogitsWarper + ?Sized> LogitsWarper for &T {
+    fn warp(&self, input_ids: &Tensor, scores: &mut Tensor, context: &LogitsProcessorContext) {
+        (**self).warp(input_ids, scores, context)
+    }
+}
+
+/// # Repetition penalty processor
+/// Penalizes the tokens already present in the sequence (mostly useful for CTRL decoders). Values higher than 1 will
+/// penalize tokens that have been already generated.
+pub struct RepetitionPenaltyLogitsProcessor {
+    pub penalty: f64,
+}
+
+impl LogitsProcessor for RepetitionPenaltyLogitsProcessor {
+    fn process(&self, input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        for i in 0..*scores.size().first().unwrap() {
+            for token_position in 0..input_ids.get(i).size()[0] {
+                let token = input_ids.get(i).int64_value(&[token_position]);
+                let updated_value = &scores.double_value(&[i, token]);
+                if updated_value < &0f64 {
+                    let _ = scores.get(i).index_fill_(
+                        0,
+                        &Tensor::of_slice(&[token])
+                            .to_kind(Kind::Int64)
+                            .to_device(scores.device()),
+                        updated_value * self.penalty,
+                    );
+                } else {
+                    let _ = scores.get(i).index_fill_(
+                        0,
+                        &Tensor::of_slice(&[token])
+                            .to_kind(Kind::Int64)
+                            .to_device(scores.device()),
+                        updated_value / self.penalty,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// # N-gram repetition processor
+/// Bans the tokens that would repeat an n-gram of size `ngram_size` already present in the sequence.
+pub struct NoRepeatNGramLogitsProcessor {
+    pub ngram_size: i64,
+}
+
+impl NoRepeatNGramLogitsProcessor {
+    fn get_banned_tokens(&self, input_ids: &Tensor, cur_len: i64) -> Vec<Vec<i64>> {
+        //        Ported from hugging face's transformers and fairseq (https://github.com/pytorch/fairseq/blob/master/fairseq/sequence_generator.py)
+        if cur_len + 1 < self.ngram_size {
+            vec![vec![]]
+        } else {
+            let input_ids = input_ids.to(Device::Cpu);
+            let num_hypothesis = *input_ids.size().first().unwrap();
+            let mut banned_tokens: Vec<Vec<i64>> = Vec::with_capacity(num_hypothesis as usize);
+            for hypothesis_index in 0..num_hypothesis {
+                let hypothesis_input_ids = input_ids.get(hypothesis_index);
+                let mut generated_ngram: HashMap<Vec<i64>, Vec<i64>> = HashMap::new();
+                let input: Vec<i64> = (0..hypothesis_input_ids.size1().unwrap()).collect();
+                let hypothesis_input_ids = hypothesis_input_ids
+                    .iter::<i64>()
+                    .unwrap()
+                    .collect::<Vec<i64>>();
+                let query = &hypothesis_input_ids
+                    [cur_len as usize + 1 - self.ngram_size as usize..]
+                    .to_vec();
+                for ngram in input
+                    .windows(self.ngram_size as usize)
+                    .map(|win| (*win.first().unwrap(), *win.last().unwrap()))
+                {
+                    let ngram = &hypothesis_input_ids[ngram.0 as usize..ngram.1 as usize + 1];
+                    let key = ngram[..self.ngram_size as usize - 1].to_vec();
+                    let value = *ngram.last().unwrap();
+                    generated_ngram
+                        .entry(key)
+                        .or_insert_with(|| vec![value])
+                        .push(value);
+                }
+                let hypothesis_banned_tokens = match generated_ngram.get(query) {
+                    Some(banned_tokens) => banned_tokens.clone(),
+                    None => vec![],
+                };
+                banned_tokens.push(hypothesis_banned_tokens);
+            }
+            banned_tokens
+        }
+    }
+}
+
+impl LogitsProcessor for NoRepeatNGramLogitsProcessor {
+    fn process(&self, input_ids: &Tensor, scores: &mut Tensor, context: &LogitsProcessorContext) {
+        let banned_tokens = self.get_banned_tokens(input_ids, context.current_length);
+        for (batch_index, index_banned_token) in (0..banned_tokens.len() as i64).zip(banned_tokens)
+        {
+            let _ = scores.get(batch_index).index_fill_(
+                0,
+                &Tensor::of_slice(&index_banned_token).to_device(scores.device()),
+                f64::NEG_INFINITY,
+            );
+        }
+    }
+}
+
+/// # Bad words processor
+/// Bans the generation of a list of words (may be sequences of token ids). Words made of a single token are always
+/// banned, the last token of longer words is banned if the sequence ends with the preceding tokens of the word.
+pub struct NoBadWordsLogitsProcessor<'a> {
+    static_bad_word_ids: Vec<i64>,
+    dynamic_bad_word_ids: Vec<&'a Vec<i64>>,
+}
+
+impl<'a> NoBadWordsLogitsProcessor<'a> {
+    pub fn new(bad_word_ids: &'a [Vec<i64>]) -> Self {
+        let mut static_bad_word_ids = vec![];
+        let mut dynamic_bad_word_ids = vec![];
+        for bad_word in bad_word_ids {
+            if bad_word.len() == 1 {
+                static_bad_word_ids.push(bad_word[0]);
+            } else if bad_word.len() > 1 {
+                dynamic_bad_word_ids.push(bad_word);
+            }
+        }
+        NoBadWordsLogitsProcessor {
+            static_bad_word_ids,
+            dynamic_bad_word_ids,
+        }
+    }
+
+    fn tokens_match(prev_tokens: &[i64], tokens: &[i64]) -> bool {
+        if tokens.is_empty() {
+            true
+        } else if tokens.len() > prev_tokens.len() {
+            false
+        } else {
+            &prev_tokens[prev_tokens.len() - tokens.len()..] == tokens
+        }
+    }
+
+    fn calc_static_bad_word_mask(&self, scores: &Tensor) -> Tensor {
+        let mut static_bad_words_mask =
+            Tensor::zeros(&[scores.size()[1]], (Kind::Int8, scores.device()));
+        let _ = static_bad_words_mask.index_fill_(
+            0,
+            &Tensor::of_slice(&self.static_bad_word_ids).to_device(scores.device()),
+            1,
+        );
+        static_bad_words_mask.unsqueeze(0).totype(Kind::Bool)
+    }
+
+    fn calc_dynamic_bad_word_mask(&self, input_ids: &Tensor, scores: &Tensor) -> Tensor {
+        let longest_bad_word = self
+            .dynamic_bad_word_ids
+            .iter()
+            .map(|bad_word| bad_word.len())
+            .max()
+            .unwrap() as i64;
+        let last_token_ids =
+            input_ids.slice(1, -min(longest_bad_word, input_ids.size()[1]), None, 1);
+        let dynamic_banned_mask =
+            Tensor::zeros(scores.size().as_slice(), (Kind::Int, scores.device()));
+        for sequence_index in 0..input_ids.size()[0] {
+            let prev_tokens = last_token_ids
+                .get(sequence_index)
+                .iter::<i64>()
+                .unwrap()
+                .collect::<Vec<i64>>();
+            let sequence_ban_tokens = self
+                .dynamic_bad_word_ids
+                .iter()
+                .filter(|bad_word| {
+                    Self::tokens_match(&prev_tokens, &bad_word[..bad_word.len() - 1])
+                })
+                .map(|bad_word| *bad_word.last().unwrap())
+                .collect::<Vec<i64>>();
+            if !sequence_ban_tokens.is_empty() {
+                let _ = dynamic_banned_mask.get(sequence_index).index_fill_(
+                    0,
+                    &Tensor::of_slice(&sequence_ban_tokens).to_device(scores.device()),
+                    1,
+                );
+            }
+        }
+        dynamic_banned_mask.to_kind(Kind::Bool)
+    }
+}
+
+impl LogitsProcessor for NoBadWordsLogitsProcessor<'_> {
+    fn process(&self, input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        let static_bad_words_mask =
+            (!self.static_bad_word_ids.is_empty()).then(|| self.calc_static_bad_word_mask(scores));
+        let dynamic_bad_words_mask = (!self.dynamic_bad_word_ids.is_empty())
+            .then(|| self.calc_dynamic_bad_word_mask(input_ids, scores));
+
+        let bad_word_mask = match (static_bad_words_mask, dynamic_bad_words_mask) {
+            (Some(static_mask), Some(dynamic_mask)) => {
+                Some(static_mask.bitwise_or_tensor(&dynamic_mask))
+            }
+            (Some(static_mask), None) => Some(static_mask),
+            (None, Some(dynamic_mask)) => Some(dynamic_mask),
+            (None, None) => None,
+        };
+
+        if let Some(bad_word_mask) = bad_word_mask {
+            let _ = scores.masked_fill_(&bad_word_mask, f64::NEG_INFINITY);
+        }
+    }
+}
+
+/// # Prefix allowed tokens processor
+/// Restricts the generation to the tokens returned by a function taking the batch index and the tokens generated
+/// so far.
+pub struct PrefixAllowedTokensLogitsProcessor<'a> {
+    pub prefix_allowed_tokens_fn: PrefixAllowedFunction<'a>,
+}
+
+impl LogitsProcessor for PrefixAllowedTokensLogitsProcessor<'_> {
+    fn process(&self, input_ids: &Tensor, scores: &mut Tensor, context: &LogitsProcessorContext) {
+        let mask = scores.new_full(
+            scores.size().as_slice(),
+            get_positive_infinity(scores.kind()).unwrap(),
+            (scores.kind(), scores.device()),
+        );
+        for idx in 0..scores.size()[0] {
+            let batch_id = idx / context.num_beams;
+            let allowed_tokens: Vec<i64> =
+                (self.prefix_allowed_tokens_fn)(batch_id, &input_ids.get(idx));
+            let _ = mask.get(idx).index_fill_(
+                0,
+                &Tensor::of_slice(allowed_tokens.as_slice()).to(scores.device()),
+                0,
+            );
+        }
+        let _ = scores.subtract_(&mask);
+    }
+}
+
+/// # Minimum length processor
+/// Bans the end of sequence tokens until the sequences reach a minimum length.
+pub struct MinLengthLogitsProcessor<'a> {
+    pub min_length: i64,
+    pub eos_token_ids: &'a [i64],
+}
+
+impl LogitsProcessor for MinLengthLogitsProcessor<'_> {
+    fn process(&self, _input_ids: &Tensor, scores: &mut Tensor, context: &LogitsProcessorContext) {
+        if context.current_length < self.min_length {
+            let _ = scores.index_fill_(
+                1,
+                &Tensor::of_slice(self.eos_token_ids).to(scores.device()),
+                f64::NEG_INFINITY,
+            );
+        }
+    }
+}
+
+/// # Hamming diversity processor
+/// Penalizes the tokens selected at the current step by the previous beam groups (diverse beam search).
+/// High values will enforce more difference between beam groups.
+pub struct HammingDiversityLogitsProcessor {
+    pub diversity_penalty: f64,
+}
+
+impl LogitsProcessor for HammingDiversityLogitsProcessor {
+    fn process(&self, _input_ids: &Tensor, scores: &mut Tensor, context: &LogitsProcessorContext) {
+        if let Some(beam_group) = &context.beam_group {
+            if beam_group.group_start_index > 0 {
+                let vocab_size = *scores.size().last().unwrap();
+                let batch_size = scores.size()[0] / beam_group.group_size;
+                for batch_index in 0..batch_size {
+                    let previous_group_tokens = beam_group.current_tokens.slice(
+                        0,
+                        batch_index * beam_group.num_beams,
+                        batch_index * beam_group.num_beams + beam_group.group_start_index,
+                        1,
+                    );
+                    let diversity_penalty = previous_group_tokens
+                        .bincount::<Tensor>(None, vocab_size)
+                        * self.diversity_penalty;
+                    let _ = scores
+                        .slice(
+                            0,
+                            batch_index * beam_group.group_size,
+                            (batch_index + 1) * beam_group.group_size,
+                            1,
+                        )
+                        .subtract_(&diversity_penalty);
+                }
+            }
+        }
+    }
+}
+
+/// # Logit bias processor
+/// Adds a bias to the scores of the given token ids. Positive values make the tokens more likely, negative values
+/// less likely (a bias of `f64::NEG_INFINITY` bans a token).
+pub struct LogitBiasLogitsProcessor<'a> {
+    pub logit_bias: &'a HashMap<i64, f64>,
+}
+
+impl LogitsProcessor for LogitBiasLogitsProcessor<'_> {
+    fn process(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        for (token_id, bias) in self.logit_bias.iter() {
+            let _ = scores.select(1, *token_id).g_add_scalar_(*bias);
+        }
+    }
+}
+
+/// # Temperature warper
+/// Divides the scores by the temperature. Values higher than 1 will improve originality at the risk of reducing
+/// relevance.
+pub struct TemperatureLogitsWarper {
+    pub temperature: f64,
+}
+
+impl LogitsWarper for TemperatureLogitsWarper {
+    fn warp(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        *scores /= self.temperature;
+    }
+}
+
+/// # Top-k warper
+/// Keeps the `top_k` tokens with the highest scores (and at least `min_tokens_to_keep` tokens).
+pub struct TopKLogitsWarper {
+    pub top_k: i64,
+    pub min_tokens_to_keep: i64,
+}
+
+impl LogitsWarper for TopKLogitsWarper {
+    fn warp(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        let vocab_size = *scores.size().last().unwrap();
+        let top_k = vocab_size - min(max(self.top_k, self.min_tokens_to_keep), vocab_size);
+        let (_, indices_to_remove) = scores.topk(top_k, -1, false, false);
+        for index in 0..*scores.size().first().unwrap() {
+            let _ =
+                scores
+                    .get(index)
+                    .index_fill_(0, &indices_to_remove.get(index), f64::NEG_INFINITY);
+        }
+    }
+}
+
+/// # Nucleus (top-p) warper
+/// Keeps the top tokens until their cumulative probability reaches `top_p` (and at least `min_tokens_to_keep`
+/// tokens), following [Holtzman et al.](http://arxiv.org/abs/1904.09751).
+pub struct TopPLogitsWarper {
+    pub top_p: f64,
+    pub min_tokens_to_keep: i64,
+}
+
+impl LogitsWarper for TopPLogitsWarper {
+    fn warp(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        //        Ported from https://gist.github.com/thomwolf/1a5a29f6962089e871b94cbd09daf317
+        let vocab_size = *scores.size().last().unwrap();
+        let (sorted_logits, sorted_indices) = scores.sort(-1, true);
+        let cumulative_probabilities = sorted_logits
+            .softmax(-1, sorted_logits.kind())
+            .cumsum(-1, sorted_logits.kind());
+        let mut sorted_indices_to_remove =
+            cumulative_probabilities.ge(self.top_p).to_kind(Kind::Int64);
+        if self.min_tokens_to_keep > 1 {
+            let _ = sorted_indices_to_remove.index_fill_(
+                1,
+                &Tensor::arange_start(
+                    0,
+                    self.min_tokens_to_keep + 1,
+                    (Kind::Int64, scores.device()),
+                ),
+                0,
+            );
+        }
+        let _ = sorted_indices_to_remove.index_copy_(
+            1,
+            &Tensor::arange_start(1, vocab_size, (Kind::Int64, scores.device())),
+            &sorted_indices_to_remove
+                .slice(1, 0, vocab_size - 1, 1)
+                .copy(),
+        );
+        let _ = sorted_indices_to_remove.index_fill_(
+            1,
+            &Tensor::of_slice(&[0])
+                .to_kind(Kind::Int64)
+                .to_device(sorted_indices_to_remove.device()),
+            0,
+        );
+        let indices_to_remove = sorted_indices_to_remove
+            .scatter(1, &sorted_indices, &sorted_indices_to_remove)
+            .to_kind(Kind::Bool);
+        let _ = scores.masked_fill_(&indices_to_remove, f64::NEG_INFINITY);
+    }
+}
+
+/// # Typical warper
+/// Keeps the tokens whose information content is closest to the expected information content (entropy) of the
+/// distribution until their cumulative probability reaches `mass` (and at least `min_tokens_to_keep` tokens),
+/// following [Meister et al.](https://arxiv.org/abs/2202.00666).
+pub struct TypicalLogitsWarper {
+    pub mass: f64,
+    pub min_tokens_to_keep: i64,
+}
+
+impl LogitsWarper for TypicalLogitsWarper {
+    fn warp(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        let vocab_size = *scores.size().last().unwrap();
+        let log_probabilities = scores.log_softmax(-1, scores.kind());
+        let entropy = get_entropy(&log_probabilities);
+        let shifted_scores = (-&log_probabilities - entropy).abs();
+        let (sorted_scores, sorted_indices) = shifted_scores.sort(-1, false);
+        let cumulative_probabilities = scores
+            .gather(-1, &sorted_indices, false)
+            .softmax(-1, scores.kind())
+            .cumsum(-1, scores.kind());
+        let last_index = cumulative_probabilities
+            .lt(self.mass)
+            .sum_dim_intlist([-1].as_slice(), true, Kind::Int64)
+            .clamp_max(vocab_size - 1);
+        let sorted_indices_to_remove =
+            sorted_scores.gt_tensor(&sorted_scores.gather(-1, &last_index, false));
+        let _ = sorted_indices_to_remove
+            .slice(1, 0, min(self.min_tokens_to_keep, vocab_size), 1)
+            .fill_(0);
+        let indices_to_remove =
+            sorted_indices_to_remove.scatter(1, &sorted_indices, &sorted_indices_to_remove);
+        let _ = scores.masked_fill_(&indices_to_remove, f64::NEG_INFINITY);
+    }
+}
+
+/// # Epsilon warper
+/// Removes the tokens with a probability lower than `epsilon` (keeping at least `min_tokens_to_keep` tokens),
+/// following [Hewitt et al.](https://arxiv.org/abs/2210.15191).
+pub struct EpsilonLogitsWarper {
+    pub epsilon: f64,
+    pub min_tokens_to_keep: i64,
+}
+
+impl LogitsWarper for EpsilonLogitsWarper {
+    fn warp(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        let indices_to_remove = scores.softmax(-1, scores.kind()).lt(self.epsilon);
+        remove_tokens(scores, &indices_to_remove, self.min_tokens_to_keep);
+    }
+}
+
+/// # Eta warper
+/// Removes the tokens with a probability lower than `min(epsilon, sqrt(epsilon) * exp(-entropy))` (keeping at least
+/// `min_tokens_to_keep` tokens), following [Hewitt et al.](https://arxiv.org/abs/2210.15191).
+pub struct EtaLogitsWarper {
+    pub epsilon: f64,
+    pub min_tokens_to_keep: i64,
+}
+
+impl LogitsWarper for EtaLogitsWarper {
+    fn warp(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        let log_probabilities = scores.log_softmax(-1, scores.kind());
+        let eta = ((-get_entropy(&log_probabilities)).exp() * self.epsilon.sqrt())
+            .clamp_max(self.epsilon);
+        let indices_to_remove = log_probabilities.exp().lt_tensor(&eta);
+        remove_tokens(scores, &indices_to_remove, self.min_tokens_to_keep);
+    }
+}
+
+/// # Min-p warper
+/// Removes the tokens with a probability lower than `min_p` times the probability of the most likely token (keeping
+/// at least `min_tokens_to_keep` tokens).
+pub struct MinPLogitsWarper {
+    pub min_p: f64,
+    pub min_tokens_to_keep: i64,
+}
+
+impl LogitsWarper for MinPLogitsWarper {
+    fn warp(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        let probabilities = scores.softmax(-1, scores.kind());
+        let (top_probabilities, _) = probabilities.max_dim(-1, true);
+        let indices_to_remove = probabilities.lt_tensor(&(top_probabilities * self.min_p));
+        remove_tokens(scores, &indices_to_remove, self.min_tokens_to_keep);
+    }
+}
+
+/// # Per-row bad words processor
+/// Bans a different list of words for each row of the scores (rows without a list are not modified), see
+/// `NoBadWordsLogitsProcessor`.
+pub struct RowNoBadWordsLogitsProcessor<'a> {
+    row_processors: Vec<Option<NoBadWordsLogitsProcessor<'a>>>,
+}
+
+impl<'a> RowNoBadWordsLogitsProcessor<'a> {
+    pub fn new(row_bad_word_ids: &[Option<&'a Vec<Vec<i64>>>]) -> Self {
+        RowNoBadWordsLogitsProcessor {
+            row_processors: row_bad_word_ids
+                .iter()
+                .map(|bad_word_ids| bad_word_ids.map(|ids| NoBadWordsLogitsProcessor::new(ids)))
+                .collect(),
+        }
+    }
+}
+
+impl LogitsProcessor for RowNoBadWordsLogitsProcessor<'_> {
+    fn process(&self, input_ids: &Tensor, scores: &mut Tensor, context: &LogitsProcessorContext) {
+        for (row_index, row_processor) in self.row_processors.iter().enumerate() {
+            if let Some(row_processor) = row_processor {
+                let row_index = row_index as i64;
+                let mut row_scores = scores.narrow(0, row_index, 1);
+                row_processor.process(&input_ids.narrow(0, row_index, 1), &mut row_scores, context);
+            }
+        }
+    }
+}
+
+/// # Per-row temperature warper
+/// Divides the scores of each row by its temperature, given as a tensor of shape (*batch size*, 1).
+pub struct RowTemperatureLogitsWarper {
+    pub temperatures: Tensor,
+}
+
+impl LogitsWarper for RowTemperatureLogitsWarper {
+    fn warp(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        *scores /= &self.temperatures;
+    }
+}
+
+/// # Per-row top-k warper
+/// Keeps the `top_k` tokens with the highest scores of each row (and at least `min_tokens_to_keep` tokens), with
+/// `top_k` given as an integer tensor of shape (*batch size*, 1). Rows with a `top_k` of 0 are not filtered.
+pub struct RowTopKLogitsWarper {
+    pub top_k: Tensor,
+    pub min_tokens_to_keep: i64,
+}
+
+impl LogitsWarper for RowTopKLogitsWarper {
+    fn warp(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        let vocab_size = *scores.size().last().unwrap();
+        let top_k = self
+            .top_k
+            .where_scalarother(&self.top_k.gt(0), vocab_size)
+            .clamp(max(self.min_tokens_to_keep, 1), vocab_size);
+        let (sorted_scores, _) = scores.sort(-1, true);
+        let threshold = sorted_scores.gather(-1, &(top_k - 1), false);
+        let indices_to_remove = scores.lt_tensor(&threshold);
+        let _ = scores.masked_fill_(&indices_to_remove, f64::NEG_INFINITY);
+    }
+}
+
+/// # Per-row nucleus (top-p) warper
+/// Keeps the top tokens of each row until their cumulative probability reaches `top_p` (and at least
+/// `min_tokens_to_keep` tokens), with `top_p` given as a tensor of shape (*batch size*, 1). Rows with a `top_p` of 1
+/// are not filtered.
+pub struct RowTopPLogitsWarper {
+    pub top_p: Tensor,
+    pub min_tokens_to_keep: i64,
+}
+
+impl LogitsWarper for RowTopPLogitsWarper {
+    fn warp(&self, _input_ids: &Tensor, scores: &mut Tensor, _context: &LogitsProcessorContext) {
+        let (sorted_scores, sorted_indices) = scores.sort(-1, true);
+        let sorted_probabilities = sorted_scores.softmax(-1, sorted_scores.kind());
+        // A token is removed if the cumulative probability of the tokens ranked before it reaches top_p
+        let preceding_probabilities =
+            sorted_probabilities.cumsum(-1, sorted_scores.kind()) - &sorted_probabilities;
+        let top_p = self
+            .top_p
+            .where_scalarother(&self.top_p.lt(1), f64::INFINITY);
+        let sorted_indices_to_remove = preceding_probabilities.ge_tensor(&top_p);
+        let _ = sorted_indices_to_remove
+            .slice(
+                1,
+                0,
+                min(
+                    max(self.min_tokens_to_keep, 1),
+                    *scores.size().last().unwrap(),
+                ),
+                1,
+            )
+            .fill_(0);
+        let indices_to_remove =
+            sorted_indices_to_remove.scatter(1, &sorted_indices, &sorted_indices_to_remove);
+        let _ = scores.masked_fill_(&indices_to_remove, f64::NEG_INFINITY);
+    }
+}
+
+/// Entropy of the distributions given as log-probabilities, of shape (*batch size*, 1)
+fn get_entropy(log_probabilities: &Tensor) -> Tensor {
+    -(log_probabilities.exp() * log_probabilities.nan_to_num(0.0, None, 0.0)).sum_dim_intlist(
+        [-1].as_slice(),
+        true,
+        log_probabilities.kind(),
+    )
+}
+
+/// Masks the `indices_to_remove` scores, except for the `min_tokens_to_keep` highest scores of each row
+fn remove_tokens(scores: &mut Tensor, indices_to_remove: &Tensor, min_tokens_to_keep: i64) {
+    let vocab_size = *scores.size().last().unwrap();
+    let (top_scores, _) = scores.topk(min(max(min_tokens_to_keep, 1), vocab_size), -1, true, true);
+    let threshold = top_scores.slice(1, -1, None, 1);
+    let indices_to_remove = indices_to_remove.logical_and(&scores.lt_tensor(&threshold));
+    let _ = scores.masked_fill_(&indices_to_remove, f64::NEG_INFINITY);
+}
@@ -0,0 +1,4802 @@
+// Copyright 2018 The Google AI Language Team Authors, Facebook AI Research authors.
+// Copyright 2018 Google AI, Google Brain and Carnegie Mellon University Authors and the HuggingFace Inc. team.
+// Copyright (c) 2018, NVIDIA CORPORATION.  All rights reserved.
+// Copyright 2019 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Natural Language Generation utilities
+//! Set of text generation utilities, serving as a basis for TextGenerationModel, SummarizationModels and TranslationModels.
+//! Include techniques such as beam search, top-k and nucleus sampling, temperature setting and repetition penalty.
+//! Supports batch generation of sentences from several prompts. Sequences will be left-padded with the model's padding token if present, the unknown token otherwise.
+//! This may impact the results and it is recommended to submit prompts of similar length for best results.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::gpt2::GPT2Generator;
+//! use rust_bert::pipelines::generation_utils::{
+//!     GenerateConfig, GenerateOptions, LanguageGenerator,
+//! };
+//!
+//! let generate_config = GenerateConfig {
+//!     do_sample: true,
+//!     num_beams: 5,
+//!     temperature: 1.1,
+//!     num_return_sequences: 3,
+//!     ..Default::default()
+//! };
+//! let mut gpt2_generator = GPT2Generator::new(generate_config)?;
+//!
+//! let input_context = "The dog";
+//! let second_input_context = "The cat was";
+//!
+//! let generate_options = GenerateOptions {
+//!     min_length: Some(32),
+//!     max_length: Some(128),
+//!     output_scores: true,
+//!     ..Default::default()
+//! };
+//!
+//! let output = gpt2_generator.generate(
+//!     Some(&[input_context, second_input_context]),
+//!     Some(generate_options),
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Example output: \
+//! ```no_run
+//! # let output =
+//! [
+//!     "The dog's owners, however, did not want to be named. According to the lawsuit, the animal's owner, a 29-year",
+//!     "The dog has always been part of the family. \"He was always going to be my dog and he was always looking out for me",
+//!     "The dog has been able to stay in the home for more than three months now. \"It's a very good dog. She's",
+//!     "The cat was discovered earlier this month in the home of a relative of the deceased. The cat\'s owner, who wished to remain anonymous,",
+//!     "The cat was pulled from the street by two-year-old Jazmine.\"I didn't know what to do,\" she said",
+//!     "The cat was attacked by two stray dogs and was taken to a hospital. Two other cats were also injured in the attack and are being treated."
+//! ]
+//! # ;
+//! ```
+
+use rust_tokenizers::tokenizer::{Tokenizer, TruncationStrategy};
+use rust_tokenizers::vocab::Vocab;
+use std::any::Any;
+use std::fmt::Debug;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+use crate::bart::LayerState as BartLayerState;
+use crate::common::error::RustBertError;
+use crate::common::kv_cache::new_static_buffer;
+use crate::common::resources::ResourceProvider;
+use crate::common::weights::WeightsLoadingMode;
+use crate::gpt_neo::LayerState as GPTNeoLayerState;
+pub use crate::pipelines::generation_utils::private_generation_utils::PreparedInput;
+use crate::pipelines::generation_utils::private_generation_utils::{
+    InternalGenerateOptions, PrivateLanguageGenerator,
+};
+use crate::prophetnet::LayerState as ProphetNetLayerState;
+use crate::reformer::LayerState as ReformerLayerState;
+use crate::t5::LayerState as T5LayerState;
+use crate::xlnet::LayerState as XLNetLayerState;
+
+use self::ordered_float::OrderedFloat;
+use crate::pipelines::common::TokenizerOption;
+use crate::pipelines::scoring::{ScoringOptions, TextScore};
+
+#[cfg(feature = "remote")]
+use crate::{
+    gpt2::{Gpt2ConfigResources, Gpt2MergesResources, Gpt2ModelResources, Gpt2VocabResources},
+    resources::RemoteResource,
+};
+
+extern crate ordered_float;
+
+pub mod constraints;
+pub mod logits_processor;
+mod sampler;
+pub mod scheduler;
+pub mod speculative;
+
+use constraints::Constraint;
+use logits_processor::{LogitsProcessor, LogitsWarper};
+use sampler::Sampler;
+use speculative::DraftModel;
+use std::collections::HashMap;
+
+/// # Configuration for text generation
+pub struct GenerateConfig {
+    /// Model weights resource (default: pretrained GPT2 model)
+    pub model_resource: Box<dyn ResourceProvider + Send>,
+    /// Config resource (default: pretrained GPT2 model)
+    pub config_resource: Box<dyn ResourceProvider + Send>,
+    /// Vocab resource (default: pretrained GPT2 model)
+    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    /// Merges resource (default: pretrained GPT2 model)
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    /// Minimum sequence length (default: 0)
+    pub min_length: i64,
+    /// Maximum sequence length (default: 20)
+    pub max_length: Option<i64>,
+    /// Sampling flag. If true, will perform top-k and/or nucleus sampling on generated tokens, otherwise greedy (deterministic) decoding (default: true)
+    pub do_sample: bool,
+    /// Early stopping flag indicating if the beam search should stop as soon as `num_beam` hypotheses have been generated (default: false)
+    pub early_stopping: bool,
+    /// Number of beams for beam search (default: 5)
+    pub num_beams: i64,
+    /// Temperature setting. Values higher than 1 will improve originality at the risk of reducing relevance (default: 1.0)
+    pub temperature: f64,
+    /// Top_k values for sampling tokens. Value higher than 0 will enable the feature (default: 0)
+    pub top_k: i64,
+    /// Top_p value for [Nucleus sampling, Holtzman et al.](http://arxiv.org/abs/1904.09751). Keep top tokens until cumulative probability reaches top_p (default: 0.9)
+    pub top_p: f64,
+    /// Repetition penalty (mostly useful for CTRL decoders). Values higher than 1 will penalize tokens that have been already generated. (default: 1.0)
+    pub repetition_penalty: f64,
+    /// Exponential penalty based on the length of the hypotheses generated (default: 1.0)
+    pub length_penalty: f64,
+    /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature (default: 3)
+    pub no_repeat_ngram_size: i64,
+    /// Number of sequences to return for each prompt text (default: 1)
+    pub num_return_sequences: i64,
+    /// Number of beam groups for diverse beam generation. If provided and higher than 1, will split the beams into beam subgroups leading to more diverse generation.
+    pub num_beam_groups: Option<i64>,
+    /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
+    pub diversity_penalty: Option<f64>,
+    /// Typical sampling mass, following [Meister et al.](https://arxiv.org/abs/2202.00666). If provided and lower than 1, keeps the most typical tokens until their cumulative probability reaches typical_p (sampling only)
+    pub typical_p: Option<f64>,
+    /// Epsilon sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than epsilon_cutoff (sampling only)
+    pub epsilon_cutoff: Option<f64>,
+    /// Eta sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than min(eta_cutoff, sqrt(eta_cutoff) * exp(-entropy)) (sampling only)
+    pub eta_cutoff: Option<f64>,
+    /// Min-p sampling. If provided and higher than 0, removes the tokens with a probability lower than min_p times the probability of the most likely token (sampling only)
+    pub min_p: Option<f64>,
+    /// Degeneration penalty for [contrastive search, Su et al.](https://arxiv.org/abs/2202.06417). If provided and higher than 0, greedy decoding with top_k higher than 1 uses contrastive search
+    pub penalty_alpha: Option<f64>,
+    /// Preallocated key/value cache. If true, the keys and values of the self-attention layers are stored in buffers sized to `max_length`, written in place at each decoding step instead of being concatenated to the cache (GPT2, GPT-Neo and BART-family models, requires a maximum length) (default: false)
+    pub static_cache: bool,
+    /// Flag indicating if the model should output its attention weights, including the decoder cross-attention weights (overrides the model configuration, BART-family and T5 encoder-decoder models) (default: false)
+    pub output_attentions: bool,
+    /// Device to place the model on (default: CUDA/GPU when available)
+    pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
+}
+
+#[cfg(feature = "remote")]
+impl Default for GenerateConfig {
+    fn default() -> GenerateConfig {
+        GenerateConfig {
+            model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+            config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+            vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+            merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+                Gpt2MergesResources::GPT2,
+            ))),
+            min_length: 0,
+            max_length: Some(56),
+            do_sample: true,
+            early_stopping: true,
+            num_beams: 5,
+            temperature: 1.0,
+            top_k: 0,
+            top_p: 0.9,
+            repetition_penalty: 1.0,
+            length_penalty: 1.0,
+            no_repeat_ngram_size: 3,
+            num_return_sequences: 1,
+            num_beam_groups: None,
+            diversity_penalty: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            min_p: None,
+            penalty_alpha: None,
+            static_cache: false,
+            output_attentions: false,
+            device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
+        }
+    }
+}
+
+impl GenerateConfig {
+    pub(crate) fn validate(&self) -> Result<(), RustBertError> {
+        self.internal_generate_options().validate()
+    }
+
+    /// Generation options set by the configuration, without special token ids
+    pub(crate) fn internal_generate_options(&self) -> InternalGenerateOptions<'static> {
+        InternalGenerateOptions {
+            min_length: self.min_length,
+            max_length: self.max_length,
+            do_sample: self.do_sample,
+            temperature: self.temperature,
+            top_k: self.top_k,
+            top_p: self.top_p,
+            repetition_penalty: self.repetition_penalty,
+            no_repeat_ngram_size: self.no_repeat_ngram_size,
+            pad_token_id: None,
+            eos_token_ids: None,
+            num_return_sequences: self.num_return_sequences,
+            early_stopping: self.early_stopping,
+            num_beams: self.num_beams,
+            length_penalty: self.length_penalty,
+            num_beam_groups: self.num_beam_groups,
+            diversity_penalty: self.diversity_penalty,
+            typical_p: self.typical_p,
+            epsilon_cutoff: self.epsilon_cutoff,
+            eta_cutoff: self.eta_cutoff,
+            min_p: self.min_p,
+            penalty_alpha: self.penalty_alpha,
+            static_cache: self.static_cache,
+            forced_bos_token_id: None,
+            bad_word_ids: None,
+            stop_token_ids: None,
+            stop_strings: None,
+            stopping_criteria: None,
+            logits_processors: None,
+            logits_warpers: None,
+            logit_bias: None,
+            constraints: None,
+            draft_model: None,
+            sampler: Sampler::new(None),
+            row_options: None,
+            row_max_new_tokens: None,
+        }
+    }
+}
+
+/// # Cache for models defined outside of this crate
+/// Cached states of a custom architecture, wrapped in `Cache::Custom`. The generation methods
+/// (sampling, beam search, `generate_from_ids_and_past`) manipulate the cache through these
+/// operations: beam search and the continuous batching scheduler reorder the cached states,
+/// speculative decoding and the scheduler select positions, and the scheduler concatenates the
+/// caches of sequences admitted in a running batch. Positions selection and concatenation are
+/// optional: if not supported, the states are recomputed from the full sequence.
+///
+/// ```no_run
+/// use rust_bert::pipelines::generation_utils::CustomCache;
+/// use std::any::Any;
+/// use tch::Tensor;
+///
+/// #[derive(Debug)]
+/// struct KeyValueCache {
+///     // Cached keys and values of shape (batch size, sequence length, hidden size)
+///     keys: Vec<Tensor>,
+///     values: Vec<Tensor>,
+/// }
+///
+/// impl CustomCache for KeyValueCache {
+///     fn reorder(&mut self, batch_indices: &Tensor) {
+///         for states in self.keys.iter_mut().chain(self.values.iter_mut()) {
+///             *states = states.index_select(0, batch_indices);
+///         }
+///     }
+///
+///     fn map_positions(&mut self, f: &dyn Fn(&Tensor, i64) -> Tensor) -> bool {
+///         for states in self.keys.iter_mut().chain(self.values.iter_mut()) {
+///             *states = f(states, 1);
+///         }
+///         true
+///     }
+///
+///     fn as_any(&self) -> &dyn Any {
+///         self
+///     }
+/// }
+/// ```
+pub trait CustomCache: Debug + Send {
+    /// Reorders the cached states along the batch dimension, keeping the given batch indices (in
+    /// this order, possibly repeated)
+    fn reorder(&mut self, batch_indices: &Tensor);
+
+    /// Applies a function to each cached tensor with positional states, given with the index of
+    /// its sequence dimension (used to truncate, trim or left-pad the cached positions). Returns
+    /// `false` if not supported (default).
+    fn map_positions(&mut self, _f: &dyn Fn(&Tensor, i64) -> Tensor) -> bool {
+        false
+    }
+
+    /// Concatenates the cached states of another cache of the same type and number of positions
+    /// along the batch dimension. Returns `false` if not supported (default).
+    fn concat(&mut self, _other: &dyn CustomCache) -> bool {
+        false
+    }
+
+    /// Returns the cache as `Any`, allowing to downcast the other cache in `concat`
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// # Model-specific methods of generators defined outside of this crate
+/// Implementing `CustomLanguageGenerator` and `LanguageGenerator` for a generator wrapping a model
+/// implementing `LMHeadModel` gives access to all generation methods. Models using a cache return
+/// it as a `Cache::Custom` and only process the positions that are not cached yet in
+/// `prepare_inputs_for_generation`.
+pub trait CustomLanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>> {
+    /// Returns the language model
+    fn get_model(&self) -> &T;
+    /// Returns the tokenizer used to encode the prompts and decode the generated sequences (also
+    /// returned by `LanguageGenerator::get_tokenizer`)
+    fn get_tokenizer(&self) -> &TokenizerOption;
+    /// Returns the variable store holding the model weights
+    fn get_var_store(&self) -> &nn::VarStore;
+    /// Returns a mutable reference to the variable store holding the model weights
+    fn get_var_store_mut(&mut self) -> &mut nn::VarStore;
+    /// Returns the default generation configuration
+    fn get_config(&self) -> &GenerateConfig;
+    /// Returns the beginning of sequence token id, if any
+    fn get_bos_id(&self) -> Option<i64>;
+    /// Returns the end of sequence token ids, if any
+    fn get_eos_ids(&self) -> Option<&Vec<i64>>;
+    /// Returns the padding token id, if any
+    fn get_pad_id(&self) -> Option<i64>;
+    /// Returns `true` for encoder-decoder models (which must then implement `encode`)
+    fn is_encoder_decoder(&self) -> bool;
+    /// Returns the size of the model vocabulary
+    fn get_vocab_size(&self) -> i64;
+    /// Returns the decoder start token id of encoder-decoder models
+    fn get_decoder_start_id(&self) -> Option<i64>;
+    /// Returns the maximum number of positions processed by the model
+    fn get_max_positions_embeddings(&self) -> i64;
+
+    /// Encodes the input of encoder-decoder models (default: `None`)
+    fn encode(&self, _input_ids: &Tensor, _attention_mask: Option<&Tensor>) -> Option<Tensor> {
+        None
+    }
+
+    /// Prepares the inputs of the model forward pass at each generation step. The default
+    /// implementation processes the full sequence and passes the cache unchanged.
+    fn prepare_inputs_for_generation<'a>(
+        &self,
+        input_ids: Tensor,
+        _encoder_outputs: Option<&'a Tensor>,
+        past: Cache,
+        attention_mask: Tensor,
+    ) -> PreparedInput<'a> {
+        PreparedInput {
+            prepared_input: Some(input_ids),
+            prepared_attention_mask: Some(attention_mask),
+            prepared_encoder_output: None,
+            prepared_decoder_input: None,
+            prepared_position_ids: None,
+            prepared_past: past,
+        }
+    }
+}
+
+impl<G, T, V, U> PrivateLanguageGenerator<T, V, U> for G
+where
+    G: CustomLanguageGenerator<T, V, U>,
+    T: LMHeadModel,
+    V: Vocab,
+    U: Tokenizer<V>,
+{
+    fn get_model(&self) -> &T {
+        CustomLanguageGenerator::get_model(self)
+    }
+    fn _get_tokenizer(&self) -> &TokenizerOption {
+        CustomLanguageGenerator::get_tokenizer(self)
+    }
+    fn get_var_store(&self) -> &nn::VarStore {
+        CustomLanguageGenerator::get_var_store(self)
+    }
+    fn get_var_store_mut(&mut self) -> &mut nn::VarStore {
+        CustomLanguageGenerator::get_var_store_mut(self)
+    }
+    fn get_config(&self) -> &GenerateConfig {
+        CustomLanguageGenerator::get_config(self)
+    }
+    fn get_bos_id(&self) -> Option<i64> {
+        CustomLanguageGenerator::get_bos_id(self)
+    }
+    fn get_eos_ids(&self) -> Option<&Vec<i64>> {
+        CustomLanguageGenerator::get_eos_ids(self)
+    }
+    fn get_pad_id(&self) -> Option<i64> {
+        CustomLanguageGenerator::get_pad_id(self)
+    }
+    fn is_encoder_decoder(&self) -> bool {
+        CustomLanguageGenerator::is_encoder_decoder(self)
+    }
+    fn get_vocab_size(&self) -> i64 {
+        CustomLanguageGenerator::get_vocab_size(self)
+    }
+    fn get_decoder_start_id(&self) -> Option<i64> {
+        CustomLanguageGenerator::get_decoder_start_id(self)
+    }
+    fn get_max_positions_embeddings(&self) -> i64 {
+        CustomLanguageGenerator::get_max_positions_embeddings(self)
+    }
+    fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Option<Tensor> {
+        CustomLanguageGenerator::encode(self, input_ids, attention_mask)
+    }
+    fn prepare_inputs_for_generation<'a>(
+        &self,
+        input_ids: Tensor,
+        encoder_outputs: Option<&'a Tensor>,
+        past: Cache,
+        attention_mask: Tensor,
+    ) -> PreparedInput<'a> {
+        CustomLanguageGenerator::prepare_inputs_for_generation(
+            self,
+            input_ids,
+            encoder_outputs,
+            past,
+            attention_mask,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum Cache {
+    GPT2Cache(Option<Vec<Tensor>>),
+    /// Preallocated GPT2 keys and values (see `GenerateConfig::static_cache`), with the number of
+    /// filled positions
+    GPT2StaticCache(Vec<Tensor>, i64),
+    BARTCache(Option<Vec<(Option<BartLayerState>, Option<BartLayerState>)>>),
+    T5Cache(Option<Vec<(Option<T5LayerState>, Option<T5LayerState>)>>),
+    XLNetCache(Option<Vec<Option<XLNetLayerState>>>),
+    ReformerCache(Option<Vec<Option<ReformerLayerState>>>),
+    ProphetNetCache(Option<Vec<(Option<ProphetNetLayerState>, Option<ProphetNetLayerState>)>>),
+    GPTNeoCache(Option<Vec<Option<GPTNeoLayerState>>>),
+    Custom(Box<dyn CustomCache>),
+    None,
+}
+
+impl Cache {
+    /// Applies a function to the cached keys and values of the self-attention layers, given with
+    /// the index of their sequence dimension. Returns `false` if the cache does not support this
+    /// operation: the cache is then cleared and the states have to be recomputed from the full
+    /// sequence.
+    fn map_positions<F>(&mut self, f: F) -> bool
+    where
+        F: Fn(&Tensor, i64) -> Tensor,
+    {
+        match self {
+            Cache::GPT2Cache(Some(layer_states)) => {
+                for layer_state in layer_states.iter_mut() {
+                    *layer_state = f(layer_state, 3);
+                }
+                true
+            }
+            Cache::GPTNeoCache(Some(layer_states))
+                if layer_states
+                    .iter()
+                    .flatten()
+                    .all(|layer_state| layer_state.static_length.is_none()) =>
+            {
+                for layer_state in layer_states.iter_mut().flatten() {
+                    layer_state.prev_key = f(&layer_state.prev_key, 2);
+                    layer_state.prev_value = layer_state
+                        .prev_value
+                        .as_ref()
+                        .map(|prev_value| f(prev_value, 2));
+                }
+                true
+            }
+            Cache::Custom(cache) => {
+                let supported = cache.map_positions(&f);
+                if !supported {
+                    *self = Cache::None;
+                }
+                supported
+            }
+            _ => {
+                *self = Cache::None;
+                false
+            }
+        }
+    }
+
+    /// Moves the cached keys and values of the self-attention layers to buffers preallocated for
+    /// `max_length` positions, updated in place at the next decoding steps (GPT2, GPT-Neo and
+    /// BART-family caches). Other caches are returned unchanged.
+    pub(crate) fn into_static(self, max_length: i64) -> Cache {
+        match self {
+            Cache::GPT2Cache(Some(layer_states))
+                if !layer_states.is_empty() && (layer_states[0].size()[3] < max_length) =>
+            {
+                let length = layer_states[0].size()[3];
+                Cache::GPT2StaticCache(
+                    layer_states
+                        .iter()
+                        .map(|layer_state| new_static_buffer(layer_state, 3, max_length))
+                        .collect(),
+                    length,
+                )
+            }
+            Cache::GPTNeoCache(Some(mut layer_states)) => {
+                for layer_state in layer_states.iter_mut().flatten() {
+                    layer_state.preallocate(max_length);
+                }
+                Cache::GPTNeoCache(Some(layer_states))
+            }
+            Cache::BARTCache(Some(mut layer_states)) => {
+                for (self_attention_state, _) in layer_states.iter_mut() {
+                    if let Some(self_attention_state) = self_attention_state {
+                        self_attention_state.preallocate(max_length);
+                    }
+                }
+                Cache::BARTCache(Some(layer_states))
+            }
+            cache => cache,
+        }
+    }
+
+    /// Truncates the cached states to their first `length` positions (e.g. when speculative draft
+    /// tokens are rejected). Returns `false` (clearing the cache) if truncation is not supported.
+    pub(crate) fn truncate(&mut self, length: i64) -> bool {
+        self.map_positions(|states, dim| states.slice(dim, 0, length, 1))
+    }
+
+    /// Removes the first `num_positions` cached positions. Returns `false` (clearing the cache) if
+    /// this is not supported.
+    pub(crate) fn trim_left(&mut self, num_positions: i64) -> bool {
+        self.map_positions(|states, dim| states.slice(dim, num_positions, None, 1))
+    }
+
+    /// Left-pads the cached states with zeros up to `length` positions (the padding positions
+    /// must be masked by the attention mask). Returns `false` (clearing the cache) if padding is
+    /// not supported.
+    pub(crate) fn left_pad(&mut self, length: i64) -> bool {
+        self.map_positions(|states, dim| {
+            let mut padding_shape = states.size();
+            padding_shape[dim as usize] = length - padding_shape[dim as usize];
+            Tensor::cat(
+                &[
+                    &Tensor::zeros(&padding_shape, (states.kind(), states.device())),
+                    states,
+                ],
+                dim,
+            )
+        })
+    }
+
+    /// Concatenates caches with the same number of positions along the batch dimension. Returns
+    /// `Cache::None` if the caches do not support concatenation.
+    pub(crate) fn cat(caches: Vec<Cache>) -> Cache {
+        let mut layer_states: Vec<Vec<Tensor>> = vec![];
+        let mut neo_layer_states: Vec<Vec<GPTNeoLayerState>> = vec![];
+        let mut custom_cache: Option<Box<dyn CustomCache>> = None;
+        for cache in caches {
+            match cache {
+                Cache::Custom(cache) if layer_states.is_empty() & neo_layer_states.is_empty() => {
+                    match custom_cache.as_mut() {
+                        Some(custom_cache) => {
+                            if !custom_cache.concat(cache.as_ref()) {
+                                return Cache::None;
+                            }
+                        }
+                        None => custom_cache = Some(cache),
+                    }
+                }
+                Cache::GPT2Cache(Some(cache_layer_states))
+                    if neo_layer_states.is_empty() & custom_cache.is_none() =>
+                {
+                    layer_states.resize_with(cache_layer_states.len(), Vec::new);
+                    for (layer, layer_state) in layer_states.iter_mut().zip(cache_layer_states) {
+                        layer.push(layer_state);
+                    }
+                }
+                Cache::GPTNeoCache(Some(cache_layer_states))
+                    if layer_states.is_empty() & custom_cache.is_none() =>
+                {
+                    neo_layer_states.resize_with(cache_layer_states.len(), Vec::new);
+                    for (layer, layer_state) in neo_layer_states.iter_mut().zip(cache_layer_states)
+                    {
+                        match layer_state {
+                            Some(layer_state)
+                                if layer_state.prev_value.is_some()
+                                    & layer_state.static_length.is_none() =>
+                            {
+                                layer.push(layer_state)
+                            }
+                            _ => return Cache::None,
+                        }
+                    }
+                }
+                _ => return Cache::None,
+            }
+        }
+        if let Some(custom_cache) = custom_cache {
+            Cache::Custom(custom_cache)
+        } else if !layer_states.is_empty() {
+            Cache::GPT2Cache(Some(
+                layer_states
+                    .iter()
+                    .map(|layer| Tensor::cat(layer, 1))
+                    .collect(),
+            ))
+        } else if !neo_layer_states.is_empty() {
+            Cache::GPTNeoCache(Some(
+                neo_layer_states
+                    .iter()
+                    .map(|layer| {
+                        Some(GPTNeoLayerState {
+                            prev_key: Tensor::cat(
+                                &layer
+                                    .iter()
+                                    .map(|layer_state| &layer_state.prev_key)
+                                    .collect::<Vec<&Tensor>>(),
+                                0,
+                            ),
+                            prev_value: Some(Tensor::cat(
+                                &layer
+                                    .iter()
+                                    .map(|layer_state| layer_state.prev_value.as_ref().unwrap())
+                                    .collect::<Vec<&Tensor>>(),
+                                0,
+                            )),
+                            static_length: None,
+                        })
+                    })
+                    .collect(),
+            ))
+        } else {
+            Cache::None
+        }
+    }
+}
+
+macro_rules! unpack_config {
+    ($field_name:ident, $generate_options: ident, $generate_config: ident) => {
+        $generate_options.map_or($generate_config.$field_name, |opts| {
+            opts.$field_name.unwrap_or($generate_config.$field_name)
+        })
+    };
+}
+
+pub(crate) mod private_generation_utils {
+    use std::cmp::{min, Reverse};
+    use std::collections::{HashMap, HashSet, VecDeque};
+    use std::mem;
+
+    use rust_tokenizers::tokenizer::{truncate_sequences, Tokenizer, TruncationStrategy};
+    use rust_tokenizers::vocab::Vocab;
+    use rust_tokenizers::TokenIdsWithOffsets;
+    use tch::kind::Kind::Int64;
+    use tch::{nn, no_grad, Kind, Tensor};
+
+    use crate::common::error::RustBertError;
+    use crate::pipelines::common::TokenizerOption;
+    use crate::pipelines::generation_utils::{
+        BeamHypotheses, Cache, GenerateConfig, GenerateOptions, GeneratedIndicesOutput,
+        LMHeadModel, PrefixAllowedFunction, RowGenerateOptions, StoppingCriteria,
+    };
+
+    use super::constraints::{
+        constraints_fulfilled, get_advance_tokens, get_constraints_bank, Constraint,
+    };
+    use super::logits_processor::{
+        BeamGroupContext, EpsilonLogitsWarper, EtaLogitsWarper, HammingDiversityLogitsProcessor,
+        LogitBiasLogitsProcessor, LogitsProcessor, LogitsProcessorContext, LogitsWarper,
+        MinLengthLogitsProcessor, MinPLogitsWarper, NoBadWordsLogitsProcessor,
+        NoRepeatNGramLogitsProcessor, PrefixAllowedTokensLogitsProcessor,
+        RepetitionPenaltyLogitsProcessor, RowNoBadWordsLogitsProcessor, RowTemperatureLogitsWarper,
+        RowTopKLogitsWarper, RowTopPLogitsWarper, TemperatureLogitsWarper, TopKLogitsWarper,
+        TopPLogitsWarper, TypicalLogitsWarper,
+    };
+    use super::ordered_float::OrderedFloat;
+    use super::sampler::Sampler;
+    use super::speculative::DraftModel;
+
+    /// Callback receiving the tokens generated at each decoding step for each sequence of the
+    /// batch (`None` for sequences that have already finished)
+    pub type TokenCallback<'a> = &'a mut dyn FnMut(&[Option<i64>]);
+
+    pub struct InternalGenerateOptions<'a> {
+        pub min_length: i64,
+        pub max_length: Option<i64>,
+        pub do_sample: bool,
+        pub temperature: f64,
+        pub top_k: i64,
+        pub top_p: f64,
+        pub repetition_penalty: f64,
+        pub no_repeat_ngram_size: i64,
+        pub pad_token_id: Option<i64>,
+        pub eos_token_ids: Option<Vec<i64>>,
+        pub num_return_sequences: i64,
+        pub early_stopping: bool,
+        pub num_beams: i64,
+        pub length_penalty: f64,
+        pub num_beam_groups: Option<i64>,
+        pub diversity_penalty: Option<f64>,
+        pub typical_p: Option<f64>,
+        pub epsilon_cutoff: Option<f64>,
+        pub eta_cutoff: Option<f64>,
+        pub min_p: Option<f64>,
+        pub penalty_alpha: Option<f64>,
+        pub static_cache: bool,
+        pub forced_bos_token_id: Option<i64>,
+        pub bad_word_ids: Option<&'a Vec<Vec<i64>>>,
+        pub stop_token_ids: Option<&'a Vec<Vec<i64>>>,
+        pub stop_strings: Option<&'a [&'a str]>,
+        pub stopping_criteria: Option<&'a dyn StoppingCriteria>,
+        pub logits_processors: Option<&'a [&'a dyn LogitsProcessor]>,
+        pub logits_warpers: Option<&'a [&'a dyn LogitsWarper]>,
+        pub logit_bias: Option<&'a HashMap<i64, f64>>,
+        pub constraints: Option<&'a [Constraint]>,
+        pub draft_model: Option<&'a DraftModel<'a>>,
+        pub sampler: Sampler,
+        pub row_options: Option<&'a [RowGenerateOptions<'a>]>,
+        /// Maximum number of new tokens of each generated sequence, resolved from the per-row
+        /// options and the maximum length (`None` if unlimited)
+        pub row_max_new_tokens: Option<Vec<Option<i64>>>,
+    }
+
+    impl<'a> InternalGenerateOptions<'a> {
+        pub fn validate_token_ids(&self, vocab_size: i64) -> Result<(), RustBertError> {
+            let logit_bias_token_ids = self.logit_bias.into_iter().flat_map(HashMap::keys);
+            let constraint_token_ids = self
+                .constraints
+                .into_iter()
+                .flatten()
+                .flat_map(|constraint| constraint.alternatives().iter().flatten());
+            if let Some(token_id) = logit_bias_token_ids
+                .chain(constraint_token_ids)
+                .find(|token_id| !(0..vocab_size).contains(*token_id))
+            {
+                return Err(RustBertError::ValueError(format!(
+                    "token id {} is out of the vocabulary range (vocabulary size: {})",
+                    token_id, vocab_size
+                )));
+            }
+            Ok(())
+        }
+
+        /// Moves the cache to preallocated buffers if a static cache is enabled
+        pub fn static_cache_from(&self, cache: Cache) -> Cache {
+            match (self.static_cache, self.max_length) {
+                (true, Some(max_length)) => cache.into_static(max_length),
+                _ => cache,
+            }
+        }
+
+        pub fn has_stop_conditions(&self) -> bool {
+            self.stop_token_ids.is_some()
+                | self.stop_strings.is_some()
+                | self.stopping_criteria.is_some()
+                | self.row_max_new_tokens.is_some()
+        }
+
+        /// Values of a per-row option for each sequence generated (*number of prompts* x
+        /// *num_return_sequences*), defaulting to `default` for the rows not setting it. Returns
+        /// `None` without per-row options.
+        pub fn row_values<T, F>(&self, value: F, default: T) -> Option<Vec<T>>
+        where
+            T: Copy,
+            F: Fn(&RowGenerateOptions<'a>) -> Option<T>,
+        {
+            let rows_per_prompt = if self.do_sample {
+                self.num_return_sequences as usize
+            } else {
+                1
+            };
+            self.row_options.map(|row_options| {
+                row_options
+                    .iter()
+                    .flat_map(|row_option| {
+                        let row_value = value(row_option).unwrap_or(default);
+                        (0..rows_per_prompt).map(move |_| row_value)
+                    })
+                    .collect()
+            })
+        }
+
+        pub fn validate(&self) -> Result<(), RustBertError> {
+            if self.temperature <= 0f64 {
+                return Err(RustBertError::ValueError(format!(
+                    "temperature must be strictly positive, got {}",
+                    self.temperature
+                )));
+            }
+            if !((self.top_p >= 0f64) & (self.top_p <= 1f64)) {
+                return Err(RustBertError::ValueError(format!(
+                    "top_p must be between 0 and 1, got {}",
+                    self.top_p
+                )));
+            }
+            if self.repetition_penalty < 1f64 {
+                return Err(RustBertError::ValueError(format!(
+                    "repetition_penalty must be greater than 1, got {}",
+                    self.repetition_penalty
+                )));
+            }
+            if self.length_penalty <= 0f64 {
+                return Err(RustBertError::ValueError(format!(
+                    "length_penalty must be strictly greater than 0, got {}",
+                    self.length_penalty
+                )));
+            }
+            if self.num_return_sequences <= 0i64 {
+                return Err(RustBertError::ValueError(format!(
+                    "num_return_sequences must be strictly greater than 0, got {}",
+                    self.num_return_sequences
+                )));
+            }
+            if self.num_beams <= 0i64 {
+                return Err(RustBertError::ValueError(format!(
+                    "num_beams must be strictly greater than 0, got {}",
+                    self.num_beams
+                )));
+            }
+            if !self.do_sample {
+                if (self.num_beams == 1) & (self.num_return_sequences != 1) {
+                    return Err(RustBertError::ValueError(format!(
+                        "num_return_sequences must be set to 1 for greedy decoding, got {}",
+                        self.num_return_sequences
+                    )));
+                }
+                if self.num_beams < self.num_return_sequences {
+                    return Err(RustBertError::ValueError(format!(
+                        "num_return_sequences ({}) must be lower than the number of beams ({})",
+                        self.num_return_sequences, self.num_beams
+                    )));
+                }
+            }
+            if let Some(num_beam_groups) = self.num_beam_groups {
+                if (num_beam_groups > 1) & (self.num_beams % num_beam_groups != 0) {
+                    return Err(RustBertError::ValueError(format!(
+                        "num_beams ({}) must be a multiple of num_beam_groups ({})",
+                        self.num_beams, num_beam_groups
+                    )));
+                }
+            }
+            for (name, value, lower_bound_inclusive, upper_bound_inclusive) in [
+                ("typical_p", self.typical_p, false, true),
+                ("epsilon_cutoff", self.epsilon_cutoff, true, false),
+                ("eta_cutoff", self.eta_cutoff, true, false),
+                ("min_p", self.min_p, true, true),
+                ("penalty_alpha", self.penalty_alpha, true, true),
+            ] {
+                if let Some(value) = value {
+                    let above_lower_bound = if lower_bound_inclusive {
+                        value >= 0f64
+                    } else {
+                        value > 0f64
+                    };
+                    let below_upper_bound = if upper_bound_inclusive {
+                        value <= 1f64
+                    } else {
+                        value < 1f64
+                    };
+                    if !(above_lower_bound & below_upper_bound) {
+                        return Err(RustBertError::ValueError(format!(
+                            "{} must be in the range {}0, 1{}, got {}",
+                            name,
+                            if lower_bound_inclusive { "[" } else { "(" },
+                            if upper_bound_inclusive { "]" } else { ")" },
+                            value
+                        )));
+                    }
+                }
+            }
+            if let Some(constraints) = self.constraints {
+                if (self.num_beams <= 1) | self.do_sample | (self.num_beam_groups.unwrap_or(1) > 1)
+                {
+                    return Err(RustBertError::ValueError(
+                        "Constraints require beam search (num_beams > 1) without sampling or beam groups"
+                            .to_string(),
+                    ));
+                }
+                if constraints.iter().any(|constraint| {
+                    constraint.alternatives().is_empty()
+                        | constraint
+                            .alternatives()
+                            .iter()
+                            .any(|alternative| alternative.is_empty())
+                }) {
+                    return Err(RustBertError::ValueError(
+                        "constraints must not contain empty token sequences".to_string(),
+                    ));
+                }
+            }
+            if self.draft_model.is_some()
+                & ((self.num_beams > 1)
+                    | (!self.do_sample
+                        & (self.penalty_alpha.unwrap_or(0f64) > 0f64)
+                        & (self.top_k > 1)))
+            {
+                return Err(RustBertError::ValueError(
+                    "Speculative decoding requires greedy decoding or sampling (num_beams = 1) without contrastive search"
+                        .to_string(),
+                ));
+            }
+            if let Some(row_options) = self.row_options {
+                if (self.num_beams > 1)
+                    | self.draft_model.is_some()
+                    | (!self.do_sample
+                        & (self.penalty_alpha.unwrap_or(0f64) > 0f64)
+                        & (self.top_k > 1))
+                {
+                    return Err(RustBertError::ValueError(
+                        "Per-row generation options require greedy decoding or sampling (num_beams = 1) without speculative decoding or contrastive search"
+                            .to_string(),
+                    ));
+                }
+                for row_option in row_options {
+                    if let Some(temperature) = row_option.temperature {
+                        if temperature <= 0f64 {
+                            return Err(RustBertError::ValueError(format!(
+                                "temperature must be strictly positive, got {}",
+                                temperature
+                            )));
+                        }
+                    }
+                    if let Some(top_p) = row_option.top_p {
+                        if !(0f64..=1f64).contains(&top_p) {
+                            return Err(RustBertError::ValueError(format!(
+                                "top_p must be between 0 and 1, got {}",
+                                top_p
+                            )));
+                        }
+                    }
+                    if let Some(max_new_tokens) = row_option.max_new_tokens {
+                        if max_new_tokens <= 0 {
+                            return Err(RustBertError::ValueError(format!(
+                                "max_new_tokens must be strictly greater than 0, got {}",
+                                max_new_tokens
+                            )));
+                        }
+                    }
+                }
+            }
+            if let Some(stop_strings) = self.stop_strings {
+                if stop_strings
+                    .iter()
+                    .any(|stop_string| stop_string.is_empty())
+                {
+                    return Err(RustBertError::ValueError(
+                        "stop_strings must not contain empty strings".to_string(),
+                    ));
+                }
+            }
+            if let Some(stop_token_ids) = self.stop_token_ids {
+                if stop_token_ids
+                    .iter()
+                    .any(|stop_sequence| stop_sequence.is_empty())
+                {
+                    return Err(RustBertError::ValueError(
+                        "stop_token_ids must not contain empty token sequences".to_string(),
+                    ));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    pub struct PreparedInput<'a> {
+        pub prepared_input: Option<Tensor>,
+        pub prepared_attention_mask: Option<Tensor>,
+        pub prepared_encoder_output: Option<&'a Tensor>,
+        pub prepared_decoder_input: Option<Tensor>,
+        pub prepared_position_ids: Option<Tensor>,
+        pub prepared_past: Cache,
+    }
+
+    pub struct GeneratedOutputWithScores {
+        pub indices: Tensor,
+        pub scores: Option<Vec<f64>>,
+        pub token_scores: Option<Vec<Vec<f64>>>,
+        /// Cache of the leading positions of the generated sequences (`Cache::None` for beam search)
+        pub past: Cache,
+    }
+
+    pub struct ContrastiveSearchOutput {
+        pub next_token: Tensor,
+        pub next_token_logits: Tensor,
+        pub hidden_state: Tensor,
+        pub past: Cache,
+    }
+
+    /// Speculative decoding state: caches of the main and draft models (with the number of
+    /// positions they hold) and tokens (with their processed logits) accepted but not yet added
+    /// to the output
+    pub struct SpeculativeDecodingState<'a> {
+        pub draft_model: &'a DraftModel<'a>,
+        pub past: Cache,
+        pub cached_length: i64,
+        pub draft_past: Cache,
+        pub draft_cached_length: i64,
+        pub pending_tokens: VecDeque<(Tensor, Tensor)>,
+    }
+
+    /// Selects the beam search candidates for constrained generation. The top-scoring candidates
+    /// are extended with the best token and the tokens advancing the constraints for each beam.
+    /// The candidates are then ranked by score within groups (banks) of equal constraints progress,
+    /// taking the best candidate of each bank (from the highest progress) in turn.
+    fn get_constrained_beam_candidates(
+        constraints: &[Constraint],
+        input_ids: &Tensor,
+        cur_len: i64,
+        next_scores: &Tensor,
+        batch_size: i64,
+        num_beams: i64,
+    ) -> (Tensor, Tensor) {
+        let vocab_size = *next_scores.size().last().unwrap();
+        let num_candidates = 2 * num_beams;
+        let (_, top_candidates) = next_scores
+            .contiguous()
+            .view((batch_size, num_beams * vocab_size))
+            .topk(num_candidates, 1, true, true);
+        let (_, beam_top_tokens) = next_scores.max_dim(-1, false);
+
+        let mut candidate_scores = Vec::with_capacity((batch_size * num_candidates) as usize);
+        let mut candidate_ids = Vec::with_capacity((batch_size * num_candidates) as usize);
+        for batch_index in 0..batch_size {
+            let generated_ids = (0..num_beams)
+                .map(|beam_index| {
+                    input_ids
+                        .get(batch_index * num_beams + beam_index)
+                        .slice(0, cur_len, None, 1)
+                        .iter::<i64>()
+                        .unwrap()
+                        .collect::<Vec<i64>>()
+                })
+                .collect::<Vec<Vec<i64>>>();
+            let mut candidates = top_candidates
+                .get(batch_index)
+                .iter::<i64>()
+                .unwrap()
+                .collect::<Vec<i64>>();
+            for (beam_index, beam_generated_ids) in generated_ids.iter().enumerate() {
+                let beam_index = beam_index as i64;
+                candidates.push(
+                    beam_index * vocab_size
+                        + beam_top_tokens.int64_value(&[batch_index * num_beams + beam_index]),
+                );
+                candidates.extend(
+                    get_advance_tokens(constraints, beam_generated_ids)
+                        .into_iter()
+                        .map(|token_id| beam_index * vocab_size + token_id),
+                );
+            }
+
+            let mut candidates = candidates
+                .into_iter()
+                .map(|candidate| {
+                    let beam_index = candidate / vocab_size;
+                    let token_id = candidate % vocab_size;
+                    let mut sequence = generated_ids[beam_index as usize].clone();
+                    sequence.push(token_id);
+                    let score =
+                        next_scores.double_value(&[batch_index * num_beams + beam_index, token_id]);
+                    (candidate, score, sequence)
+                })
+                .collect::<Vec<(i64, f64, Vec<i64>)>>();
+            candidates.sort_by_key(|(_, score, _)| Reverse(OrderedFloat(*score)));
+            // Beams may hold identical sequences (e.g. at the first step)
+            let mut unique_sequences = HashSet::new();
+            candidates.retain(|(_, _, sequence)| unique_sequences.insert(sequence.clone()));
+
+            let mut bank_sizes: HashMap<usize, usize> = HashMap::new();
+            let mut ranked_candidates = candidates
+                .into_iter()
+                .map(|(candidate, score, sequence)| {
+                    let bank = get_constraints_bank(constraints, &sequence);
+                    let rank_in_bank = if score > f64::NEG_INFINITY {
+                        let bank_size = bank_sizes.entry(bank).or_insert(0);
+                        *bank_size += 1;
+                        *bank_size
+                    } else {
+                        usize::MAX
+                    };
+                    ((rank_in_bank, Reverse(bank)), candidate, score)
+                })
+                .collect::<Vec<((usize, Reverse<usize>), i64, f64)>>();
+            ranked_candidates.sort_by_key(|(rank, _, _)| *rank);
+            for (_, candidate, score) in ranked_candidates.into_iter().take(num_candidates as usize)
+            {
+                candidate_ids.push(candidate);
+                candidate_scores.push(score);
+            }
+        }
+        (
+            Tensor::of_slice(&candidate_scores)
+                .view((batch_size, num_candidates))
+                .to_kind(next_scores.kind())
+                .to_device(next_scores.device()),
+            Tensor::of_slice(&candidate_ids)
+                .view((batch_size, num_candidates))
+                .to_device(next_scores.device()),
+        )
+    }
+
+    pub trait PrivateLanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>> {
+        fn get_model(&self) -> &T;
+        fn _get_tokenizer(&self) -> &TokenizerOption;
+        fn get_var_store(&self) -> &nn::VarStore;
+        fn get_var_store_mut(&mut self) -> &mut nn::VarStore;
+        fn get_config(&self) -> &GenerateConfig;
+        fn get_bos_id(&self) -> Option<i64>;
+        fn get_eos_ids(&self) -> Option<&Vec<i64>>;
+        fn get_pad_id(&self) -> Option<i64>;
+        fn is_encoder_decoder(&self) -> bool;
+        fn get_vocab_size(&self) -> i64;
+        fn get_decoder_start_id(&self) -> Option<i64>;
+        fn get_max_positions_embeddings(&self) -> i64;
+
+        fn prepare_scores_for_generation(
+            &self,
+            _scores: &mut Tensor,
+            _current_length: i64,
+            _max_length: Option<i64>,
+            _forced_bos_token_id: Option<i64>,
+        ) {
+        }
+
+        fn encode(&self, _input_ids: &Tensor, _attention_mask: Option<&Tensor>) -> Option<Tensor> {
+            None
+        }
+
+        fn prepare_inputs_for_generation<'a>(
+            &self,
+            input_ids: Tensor,
+            _encoder_outputs: Option<&'a Tensor>,
+            past: Cache,
+            attention_mask: Tensor,
+        ) -> PreparedInput<'a> {
+            PreparedInput {
+                prepared_input: Some(input_ids),
+                prepared_attention_mask: Some(attention_mask),
+                prepared_encoder_output: None,
+                prepared_decoder_input: None,
+                prepared_position_ids: None,
+                prepared_past: past,
+            }
+        }
+
+        fn encode_prompt_text<S>(
+            &self,
+            prompt_text: &[S],
+            max_len: Option<i64>,
+            pad_token_id: Option<i64>,
+        ) -> Tensor
+        where
+            S: AsRef<str> + Sync,
+        {
+            let tokens = self._get_tokenizer().tokenize_list(prompt_text);
+            let token_ids = tokens
+                .into_iter()
+                .map(|prompt_tokens| self._get_tokenizer().convert_tokens_to_ids(&prompt_tokens))
+                .collect::<Vec<Vec<i64>>>();
+
+            let num_truncated_tokens = token_ids
+                .iter()
+                .map(|token_ids| {
+                    max_len
+                        .map(|max_len| {
+                            if token_ids.len() > max_len as usize {
+                                token_ids.len() - max_len as usize
+                            } else {
+                                0
+                            }
+                        })
+                        .unwrap_or(0)
+                })
+                .collect::<Vec<usize>>();
+
+            let token_ids = token_ids
+                .into_iter()
+                .zip(num_truncated_tokens)
+                .map(|(tokens, num_truncated_tokens)| {
+                    truncate_sequences(
+                        TokenIdsWithOffsets {
+                            ids: tokens,
+                            offsets: vec![],
+                            reference_offsets: vec![],
+                            masks: vec![],
+                        },
+                        None,
+                        num_truncated_tokens,
+                        &TruncationStrategy::LongestFirst,
+                        0,
+                    )
+                    .unwrap()
+                    .0
+                    .ids
+                })
+                .collect::<Vec<Vec<i64>>>();
+
+            let max_len = token_ids.iter().map(|input| input.len()).max().unwrap();
+
+            let pad_token = match pad_token_id {
+                Some(value) => value,
+                None => self._get_tokenizer().get_unk_id(),
+            };
+
+            let token_ids = token_ids
+                .into_iter()
+                .map(|input| {
+                    let mut temp = vec![pad_token; max_len - input.len()];
+                    temp.extend(input);
+                    temp
+                })
+                .map(|tokens| Tensor::of_slice(&tokens).to(self.get_var_store().device()))
+                .collect::<Vec<Tensor>>();
+            Tensor::stack(&token_ids, 0)
+        }
+
+        fn get_logits_processors<'a>(
+            &self,
+            gen_opt: &'a InternalGenerateOptions,
+            prefix_allowed_tokens_fn: Option<PrefixAllowedFunction<'a>>,
+        ) -> Vec<Box<dyn LogitsProcessor + 'a>> {
+            let mut logits_processors: Vec<Box<dyn LogitsProcessor + 'a>> = vec![];
+            if gen_opt.repetition_penalty > 1f64 {
+                logits_processors.push(Box::new(RepetitionPenaltyLogitsProcessor {
+                    penalty: gen_opt.repetition_penalty,
+                }));
+            }
+            if let Some(bad_word_ids) = gen_opt.bad_word_ids {
+                logits_processors.push(Box::new(NoBadWordsLogitsProcessor::new(bad_word_ids)));
+            }
+            if let Some(row_bad_word_ids) =
+                gen_opt.row_values(|row_option| row_option.bad_word_ids.map(Some), None)
+            {
+                logits_processors.push(Box::new(RowNoBadWordsLogitsProcessor::new(
+                    &row_bad_word_ids,
+                )));
+            }
+            if gen_opt.no_repeat_ngram_size > 0 {
+                logits_processors.push(Box::new(NoRepeatNGramLogitsProcessor {
+                    ngram_size: gen_opt.no_repeat_ngram_size,
+                }));
+            }
+            if let Some(prefix_allowed_tokens_fn) = prefix_allowed_tokens_fn {
+                logits_processors.push(Box::new(PrefixAllowedTokensLogitsProcessor {
+                    prefix_allowed_tokens_fn,
+                }));
+            }
+            if let Some(eos_token_ids) = &gen_opt.eos_token_ids {
+                logits_processors.push(Box::new(MinLengthLogitsProcessor {
+                    min_length: gen_opt.min_length,
+                    eos_token_ids,
+                }));
+            }
+            if let Some(logit_bias) = gen_opt.logit_bias {
+                logits_processors.push(Box::new(LogitBiasLogitsProcessor { logit_bias }));
+            }
+            if let Some(custom_logits_processors) = gen_opt.logits_processors {
+                logits_processors.extend(
+                    custom_logits_processors
+                        .iter()
+                        .map(|logits_processor| Box::new(*logits_processor) as Box<_>),
+                );
+            }
+            logits_processors
+        }
+
+        fn get_beam_logits_processors<'a>(
+            &self,
+            gen_opt: &'a InternalGenerateOptions,
+            diversity_processor: Option<HammingDiversityLogitsProcessor>,
+            prefix_allowed_tokens_fn: Option<PrefixAllowedFunction<'a>>,
+        ) -> Vec<Box<dyn LogitsProcessor + 'a>> {
+            let mut logits_processors: Vec<Box<dyn LogitsProcessor + 'a>> = vec![];
+            if let Some(eos_token_ids) = &gen_opt.eos_token_ids {
+                logits_processors.push(Box::new(MinLengthLogitsProcessor {
+                    min_length: gen_opt.min_length,
+                    eos_token_ids,
+                }));
+            }
+            if let Some(bad_word_ids) = gen_opt.bad_word_ids {
+                logits_processors.push(Box::new(NoBadWordsLogitsProcessor::new(bad_word_ids)));
+            }
+            if gen_opt.no_repeat_ngram_size > 0 {
+                logits_processors.push(Box::new(NoRepeatNGramLogitsProcessor {
+                    ngram_size: gen_opt.no_repeat_ngram_size,
+                }));
+            }
+            if let Some(diversity_processor) = diversity_processor {
+                logits_processors.push(Box::new(diversity_processor));
+            }
+            if let Some(prefix_allowed_tokens_fn) = prefix_allowed_tokens_fn {
+                logits_processors.push(Box::new(PrefixAllowedTokensLogitsProcessor {
+                    prefix_allowed_tokens_fn,
+                }));
+            }
+            if let Some(logit_bias) = gen_opt.logit_bias {
+                logits_processors.push(Box::new(LogitBiasLogitsProcessor { logit_bias }));
+            }
+            if let Some(custom_logits_processors) = gen_opt.logits_processors {
+                logits_processors.extend(
+                    custom_logits_processors
+                        .iter()
+                        .map(|logits_processor| Box::new(*logits_processor) as Box<_>),
+                );
+            }
+            logits_processors
+        }
+
+        fn get_logits_warpers<'a>(
+            &self,
+            gen_opt: &'a InternalGenerateOptions,
+            min_tokens_to_keep: i64,
+        ) -> Vec<Box<dyn LogitsWarper + 'a>> {
+            let mut logits_warpers: Vec<Box<dyn LogitsWarper + 'a>> = vec![];
+            if let Some(row_top_k) =
+                gen_opt.row_values(|row_option| row_option.top_k, gen_opt.top_k)
+            {
+                logits_warpers.push(Box::new(RowTopKLogitsWarper {
+                    top_k: self.get_row_tensor(&row_top_k),
+                    min_tokens_to_keep,
+                }));
+            } else if gen_opt.top_k > 0 {
+                logits_warpers.push(Box::new(TopKLogitsWarper {
+                    top_k: gen_opt.top_k,
+                    min_tokens_to_keep,
+                }));
+            }
+            if let Some(row_top_p) =
+                gen_opt.row_values(|row_option| row_option.top_p, gen_opt.top_p)
+            {
+                logits_warpers.push(Box::new(RowTopPLogitsWarper {
+                    top_p: self.get_row_tensor(&row_top_p),
+                    min_tokens_to_keep,
+                }));
+            } else if gen_opt.top_p < 1f64 {
+                logits_warpers.push(Box::new(TopPLogitsWarper {
+                    top_p: gen_opt.top_p,
+                    min_tokens_to_keep,
+                }));
+            }
+            if let Some(typical_p) = gen_opt.typical_p {
+                if typical_p < 1f64 {
+                    logits_warpers.push(Box::new(TypicalLogitsWarper {
+                        mass: typical_p,
+                        min_tokens_to_keep,
+                    }));
+                }
+            }
+            if let Some(epsilon_cutoff) = gen_opt.epsilon_cutoff {
+                if epsilon_cutoff > 0f64 {
+                    logits_warpers.push(Box::new(EpsilonLogitsWarper {
+                        epsilon: epsilon_cutoff,
+                        min_tokens_to_keep,
+                    }));
+                }
+            }
+            if let Some(eta_cutoff) = gen_opt.eta_cutoff {
+                if eta_cutoff > 0f64 {
+                    logits_warpers.push(Box::new(EtaLogitsWarper {
+                        epsilon: eta_cutoff,
+                        min_tokens_to_keep,
+                    }));
+                }
+            }
+            if let Some(min_p) = gen_opt.min_p {
+                if min_p > 0f64 {
+                    logits_warpers.push(Box::new(MinPLogitsWarper {
+                        min_p,
+                        min_tokens_to_keep,
+                    }));
+                }
+            }
+            if let Some(custom_logits_warpers) = gen_opt.logits_warpers {
+                logits_warpers.extend(
+                    custom_logits_warpers
+                        .iter()
+                        .map(|logits_warper| Box::new(*logits_warper) as Box<_>),
+                );
+            }
+            logits_warpers
+        }
+
+        /// Logits warpers used for sampling without beam search: temperature followed by the
+        /// built-in and custom warpers
+        fn get_sampling_logits_warpers<'a>(
+            &self,
+            gen_opt: &'a InternalGenerateOptions,
+        ) -> Vec<Box<dyn LogitsWarper + 'a>> {
+            let mut logits_warpers: Vec<Box<dyn LogitsWarper + 'a>> = vec![];
+            if let Some(row_temperatures) =
+                gen_opt.row_values(|row_option| row_option.temperature, gen_opt.temperature)
+            {
+                // Temperatures are only applied if higher than 1, as for a single temperature
+                let row_temperatures = row_temperatures
+                    .into_iter()
+                    .map(|temperature| temperature.max(1f64))
+                    .collect::<Vec<f64>>();
+                logits_warpers.push(Box::new(RowTemperatureLogitsWarper {
+                    temperatures: self.get_row_tensor(&row_temperatures),
+                }));
+            } else if gen_opt.temperature > 1f64 {
+                logits_warpers.push(Box::new(TemperatureLogitsWarper {
+                    temperature: gen_opt.temperature,
+                }));
+            }
+            logits_warpers.extend(self.get_logits_warpers(gen_opt, 1));
+            logits_warpers
+        }
+
+        /// Column tensor of shape (*number of sequences*, 1) holding the values of a per-row option
+        fn get_row_tensor<E: tch::kind::Element>(&self, row_values: &[E]) -> Tensor {
+            Tensor::of_slice(row_values)
+                .unsqueeze(-1)
+                .to(self.get_var_store().device())
+        }
+
+        fn is_stopped(
+            &self,
+            gen_opt: &InternalGenerateOptions,
+            sequence_index: usize,
+            generated_ids: &[i64],
+        ) -> bool {
+            if let Some(row_max_new_tokens) = &gen_opt.row_max_new_tokens {
+                if let Some(Some(max_new_tokens)) = row_max_new_tokens.get(sequence_index) {
+                    if generated_ids.len() as i64 >= *max_new_tokens {
+                        return true;
+                    }
+                }
+            }
+            if let Some(stop_token_ids) = gen_opt.stop_token_ids {
+                if stop_token_ids
+                    .iter()
+                    .any(|stop_sequence| generated_ids.ends_with(stop_sequence))
+                {
+                    return true;
+                }
+            }
+            if let Some(stop_strings) = gen_opt.stop_strings {
+                if self.ends_with_stop_string(stop_strings, generated_ids) {
+                    return true;
+                }
+            }
+            if let Some(stopping_criteria) = gen_opt.stopping_criteria {
+                return stopping_criteria.should_stop(
+                    sequence_index,
+                    generated_ids,
+                    self._get_tokenizer(),
+                );
+            }
+            false
+        }
+
+        /// Checks whether a stop string ends in the text added by the last generated token (the
+        /// previous tokens were checked at the previous steps). The window decoded is extended
+        /// backwards until the text preceding the last token holds the longest stop string: a token
+        /// may decode to part of a character (byte-level BPE splits multi-byte characters over
+        /// several tokens) or to nothing (special tokens).
+        fn ends_with_stop_string(&self, stop_strings: &[&str], generated_ids: &[i64]) -> bool {
+            let tokenizer = self._get_tokenizer();
+            let max_stop_string_length = stop_strings
+                .iter()
+                .map(|stop_string| stop_string.chars().count())
+                .max()
+                .unwrap_or(0);
+            let checked_length = generated_ids.len().saturating_sub(1);
+            let mut window_start = checked_length;
+            while window_start > 0 {
+                window_start -= 1;
+                // The first token of the window may start within a character and is not counted
+                let context_text = tokenizer.decode(
+                    &generated_ids[window_start + 1..checked_length],
+                    true,
+                    false,
+                );
+                let context_length = context_text
+                    .chars()
+                    .filter(|character| *character != '\u{FFFD}')
+                    .count();
+                if context_length >= max_stop_string_length {
+                    break;
+                }
+            }
+            let generated_text = tokenizer.decode(&generated_ids[window_start..], true, false);
+            stop_strings
+                .iter()
+                .any(|stop_string| generated_text.contains(stop_string))
+        }
+
+        fn get_stop_string(&self, stop_strings: &[&str], generated_ids: &[i64]) -> Option<String> {
+            let generated_text = self._get_tokenizer().decode(generated_ids, true, false);
+            stop_strings
+                .iter()
+                .filter_map(|stop_string| {
+                    generated_text
+                        .find(stop_string)
+                        .map(|position| (position, stop_string))
+                })
+                .min_by_key(|(position, _)| *position)
+                .map(|(_, stop_string)| stop_string.to_string())
+        }
+
+        /// Selects the next token among the `top_k` most likely candidates, trading off the model
+        /// confidence and a degeneration penalty (maximum cosine similarity between the candidate and
+        /// context hidden states), following [Su et al.](https://arxiv.org/abs/2202.06417).
+        /// The candidates are scored in a single forward pass, returning the logits and cache for the
+        /// next step.
+        fn contrastive_search_step(
+            &self,
+            input_ids: &Tensor,
+            encoder_outputs: Option<&Tensor>,
+            mut past: Cache,
+            attention_mask: &Tensor,
+            next_token_logits: &Tensor,
+            context_hidden_states: &Tensor,
+            context_mask: &Tensor,
+            penalty_alpha: f64,
+            top_k: i64,
+        ) -> Result<ContrastiveSearchOutput, RustBertError> {
+            let batch_size = next_token_logits.size()[0];
+            let (top_k_logits, top_k_ids) = next_token_logits.topk(top_k, -1, true, true);
+            let top_k_probabilities = next_token_logits
+                .softmax(-1, next_token_logits.kind())
+                .gather(1, &top_k_ids, false);
+
+            let batch_indices = Tensor::arange(batch_size, (Kind::Int64, input_ids.device()));
+            let candidate_indices = batch_indices.repeat_interleave_self_int(top_k, 0, None);
+            let candidate_encoder_outputs = self.reorder_cache(
+                &mut past,
+                encoder_outputs.map(|value| value.shallow_clone()),
+                &candidate_indices,
+            );
+            let candidate_input_ids = Tensor::cat(
+                &[
+                    input_ids.index_select(0, &candidate_indices),
+                    top_k_ids.view((-1, 1)),
+                ],
+                -1,
+            );
+            let candidate_attention_mask = attention_mask.index_select(0, &candidate_indices);
+            let candidate_attention_mask = if self.is_encoder_decoder() {
+                candidate_attention_mask
+            } else {
+                Tensor::cat(
+                    &[
+                        candidate_attention_mask.as_ref(),
+                        Tensor::ones(
+                            &[batch_size * top_k, 1],
+                            (Kind::Int64, candidate_attention_mask.device()),
+                        )
+                        .as_ref(),
+                    ],
+                    -1,
+                )
+            };
+            let prepared_input = self.prepare_inputs_for_generation(
+                candidate_input_ids,
+                candidate_encoder_outputs.as_ref(),
+                past,
+                candidate_attention_mask,
+            );
+            let candidate_output = self.get_model().forward_t(
+                prepared_input.prepared_input.as_ref(),
+                prepared_input.prepared_past,
+                prepared_input.prepared_attention_mask.as_ref(),
+                None,
+                prepared_input.prepared_position_ids.as_ref(),
+                None,
+                prepared_input.prepared_encoder_output,
+                prepared_input.prepared_decoder_input.as_ref(),
+                false,
+            )?;
+            let candidate_hidden_states = candidate_output
+                .hidden_state
+                .ok_or_else(|| {
+                    RustBertError::ValueError(
+                        "Contrastive search requires the model to return its last hidden state"
+                            .to_string(),
+                    )
+                })?
+                .select(1, -1);
+
+            let (degeneration_penalty, _) = Tensor::cosine_similarity(
+                &context_hidden_states.index_select(0, &candidate_indices),
+                &candidate_hidden_states.unsqueeze(1),
+                -1,
+                1e-8,
+            )
+            .masked_fill(
+                &context_mask.index_select(0, &candidate_indices).eq(0),
+                f64::NEG_INFINITY,
+            )
+            .max_dim(-1, false);
+            let contrastive_scores = (top_k_probabilities.view(-1) * (1f64 - penalty_alpha)
+                - degeneration_penalty * penalty_alpha)
+                .view((batch_size, top_k))
+                .masked_fill(&top_k_logits.eq(f64::NEG_INFINITY), f64::NEG_INFINITY);
+            let selected_candidates = contrastive_scores.argmax(-1, false);
+            let selected_indices = batch_indices * top_k + &selected_candidates;
+
+            let mut past = candidate_output.cache;
+            self.reorder_cache(&mut past, None, &selected_indices);
+            Ok(ContrastiveSearchOutput {
+                next_token: top_k_ids
+                    .gather(1, &selected_candidates.unsqueeze(1), false)
+                    .squeeze_dim(1),
+                next_token_logits: candidate_output
+                    .lm_logits
+                    .select(1, -1)
+                    .index_select(0, &selected_indices),
+                hidden_state: candidate_hidden_states.index_select(0, &selected_indices),
+                past,
+            })
+        }
+
+        /// Applies the logits processors and, when sampling, the logits warpers to the scores of
+        /// the next token
+        fn process_next_token_logits(
+            &self,
+            input_ids: &Tensor,
+            next_token_logits: &mut Tensor,
+            current_length: i64,
+            gen_opt: &InternalGenerateOptions,
+            logits_processors: &[Box<dyn LogitsProcessor + '_>],
+            logits_warpers: &[Box<dyn LogitsWarper + '_>],
+        ) {
+            let logits_processor_context = LogitsProcessorContext {
+                current_length,
+                num_beams: 1,
+                beam_group: None,
+            };
+            // Repetition penalty, banned words and n-grams, prefix constraints, minimum length
+            // and custom logits processors
+            for logits_processor in logits_processors.iter() {
+                logits_processor.process(input_ids, next_token_logits, &logits_processor_context);
+            }
+
+            self.prepare_scores_for_generation(
+                next_token_logits,
+                current_length,
+                gen_opt.max_length,
+                gen_opt.forced_bos_token_id,
+            );
+
+            // Temperature, top-k, top-p and custom logits warpers for sampling
+            if gen_opt.do_sample {
+                for logits_warper in logits_warpers.iter() {
+                    logits_warper.warp(input_ids, next_token_logits, &logits_processor_context);
+                }
+            }
+        }
+
+        /// Computes the logits of the input positions following the `cached_length` positions held
+        /// by the cache (all positions if `cached_length` is 0), returning the logits and the
+        /// updated cache. Used for speculative decoding and generation from a cache (decoder-only
+        /// models).
+        fn forward_uncached_positions(
+            &self,
+            input_ids: &Tensor,
+            attention_mask: &Tensor,
+            past: Cache,
+            cached_length: i64,
+        ) -> Result<(Tensor, Cache), RustBertError> {
+            let prepared_input = self.prepare_inputs_for_generation(
+                input_ids.copy(),
+                None,
+                Cache::None,
+                attention_mask.copy(),
+            );
+            let past = if cached_length > 0 {
+                past
+            } else {
+                prepared_input.prepared_past
+            };
+            let uncached_input = prepared_input
+                .prepared_input
+                .map(|input| input.slice(1, cached_length, None, 1));
+            let uncached_position_ids = prepared_input
+                .prepared_position_ids
+                .map(|position_ids| position_ids.slice(1, cached_length, None, 1));
+            let output = self.get_model().forward_t(
+                uncached_input.as_ref(),
+                past,
+                prepared_input.prepared_attention_mask.as_ref(),
+                None,
+                uncached_position_ids.as_ref(),
+                None,
+                None,
+                None,
+                false,
+            )?;
+            if output.lm_logits.size()[1] != input_ids.size()[1] - cached_length {
+                return Err(RustBertError::ValueError(
+                    "Speculative decoding requires the model to return the logits of all input positions"
+                        .to_string(),
+                ));
+            }
+            Ok((output.lm_logits, output.cache))
+        }
+
+        /// Computes the log-probabilities of the tokens of right-padded input sequences, each token
+        /// being conditioned on the previous tokens of its sequence. Returns a tensor of shape
+        /// (*batch size*, *sequence_length* - 1) with the log-probabilities of the tokens following the
+        /// first position. Used for text scoring (decoder-only models).
+        fn token_log_probabilities(
+            &self,
+            input_ids: &Tensor,
+            attention_mask: &Tensor,
+        ) -> Result<Tensor, RustBertError> {
+            let output = self.get_model().forward_t(
+                Some(input_ids),
+                Cache::None,
+                Some(attention_mask),
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )?;
+            let sequence_length = input_ids.size()[1];
+            if output.lm_logits.size()[1] != sequence_length {
+                return Err(RustBertError::ValueError(
+                    "Text scoring requires the model to return the logits of all input positions"
+                        .to_string(),
+                ));
+            }
+            let logits = output
+                .lm_logits
+                .slice(1, 0, sequence_length - 1, 1)
+                .to_kind(Kind::Float);
+            let next_token_logits = logits
+                .gather(2, &input_ids.slice(1, 1, None, 1).unsqueeze(-1), false)
+                .squeeze_dim(-1);
+            Ok(next_token_logits - logits.logsumexp([-1].as_slice(), false))
+        }
+
+        /// Runs a speculative decoding step: the draft model proposes tokens that are verified by
+        /// the model in a single forward pass. The accepted tokens and the token generated by the
+        /// model at the first rejected position (or after the last draft token) are added to the
+        /// pending tokens of the speculative decoding state, and the caches of both models are
+        /// truncated to the accepted sequence.
+        fn speculative_decoding_step(
+            &self,
+            speculative_state: &mut SpeculativeDecodingState,
+            input_ids: &Tensor,
+            attention_mask: &Tensor,
+            unfinished_sentences: &Tensor,
+            current_length: i64,
+            gen_opt: &InternalGenerateOptions,
+            logits_processors: &[Box<dyn LogitsProcessor + '_>],
+            logits_warpers: &[Box<dyn LogitsWarper + '_>],
+        ) -> Result<(), RustBertError> {
+            let draft_model = speculative_state.draft_model;
+            let draft_device = draft_model.device();
+            let sequence_length = input_ids.size()[1];
+            // Leave room for the token generated by the model
+            let num_draft_tokens =
+                gen_opt
+                    .max_length
+                    .map_or(draft_model.num_draft_tokens(), |max_length| {
+                        min(
+                            draft_model.num_draft_tokens(),
+                            max_length - current_length - 1,
+                        )
+                    });
+
+            let mut candidate_ids = input_ids.copy();
+            let mut candidate_attention_mask = attention_mask.copy();
+            let mut draft_tokens = Vec::with_capacity(num_draft_tokens.max(0) as usize);
+            let mut draft_probabilities = Vec::with_capacity(num_draft_tokens.max(0) as usize);
+            for draft_position in 0..num_draft_tokens {
+                let (draft_logits, mut draft_past) = draft_model.forward_uncached_positions(
+                    &candidate_ids.to_device(draft_device),
+                    &candidate_attention_mask.to_device(draft_device),
+                    mem::replace(&mut speculative_state.draft_past, Cache::None),
+                    speculative_state.draft_cached_length,
+                )?;
+                let candidate_length = sequence_length + draft_position;
+                speculative_state.draft_cached_length = if draft_past.truncate(candidate_length) {
+                    candidate_length
+                } else {
+                    0
+                };
+                speculative_state.draft_past = draft_past;
+
+                let mut next_token_logits =
+                    draft_logits.select(1, -1).to_device(input_ids.device());
+                self.process_next_token_logits(
+                    &candidate_ids,
+                    &mut next_token_logits,
+                    current_length + draft_position,
+                    gen_opt,
+                    logits_processors,
+                    logits_warpers,
+                );
+                let draft_token = if gen_opt.do_sample {
+                    let probabilities = next_token_logits.softmax(-1, Kind::Float);
+                    let draft_token = gen_opt
+                        .sampler
+                        .multinomial(&probabilities, 1)
+                        .squeeze_dim(1);
+                    draft_probabilities.push(probabilities);
+                    draft_token
+                } else {
+                    next_token_logits.argmax(-1, false)
+                };
+                candidate_ids = Tensor::cat(&[candidate_ids, draft_token.unsqueeze(-1)], -1);
+                candidate_attention_mask = Tensor::cat(
+                    &[
+                        candidate_attention_mask.as_ref(),
+                        Tensor::ones(
+                            &[candidate_attention_mask.size()[0], 1],
+                            (Kind::Int64, candidate_attention_mask.device()),
+                        )
+                        .as_ref(),
+                    ],
+                    -1,
+                );
+                draft_tokens.push(draft_token);
+            }
+
+            // Scores all draft tokens (and the token following them) in a single forward pass
+            let (logits, mut past) = self.forward_uncached_positions(
+                &candidate_ids,
+                &candidate_attention_mask,
+                mem::replace(&mut speculative_state.past, Cache::None),
+                speculative_state.cached_length,
+            )?;
+            let logits = logits.slice(1, logits.size()[1] - num_draft_tokens - 1, None, 1);
+            let finished_sentences = unfinished_sentences.eq(0);
+            let mut num_accepted_tokens = num_draft_tokens;
+            for position in 0..=num_draft_tokens {
+                let mut next_token_logits = logits.select(1, position);
+                self.process_next_token_logits(
+                    &candidate_ids.slice(1, 0, sequence_length + position, 1),
+                    &mut next_token_logits,
+                    current_length + position,
+                    gen_opt,
+                    logits_processors,
+                    logits_warpers,
+                );
+                let (next_token, accepted) = if position == num_draft_tokens {
+                    let next_token = if gen_opt.do_sample {
+                        gen_opt
+                            .sampler
+                            .multinomial(&next_token_logits.softmax(-1, Kind::Float), 1)
+                            .squeeze_dim(1)
+                    } else {
+                        next_token_logits.argmax(-1, false)
+                    };
+                    (next_token, None)
+                } else if gen_opt.do_sample {
+                    // Accept the draft token with probability min(1, p / q), or resample from the
+                    // residual distribution max(0, p - q)
+                    let draft_token = &draft_tokens[position as usize];
+                    let draft_probabilities = &draft_probabilities[position as usize];
+                    let probabilities = next_token_logits.softmax(-1, Kind::Float);
+                    let acceptance_ratio = probabilities
+                        .gather(1, &draft_token.unsqueeze(1), false)
+                        .squeeze_dim(1)
+                        / draft_probabilities
+                            .gather(1, &draft_token.unsqueeze(1), false)
+                            .squeeze_dim(1);
+                    let accepted = gen_opt
+                        .sampler
+                        .rand_like(&acceptance_ratio)
+                        .lt_tensor(&acceptance_ratio);
+                    let residual_probabilities =
+                        (&probabilities - draft_probabilities).clamp_min(0);
+                    // Identical distributions leave no residual mass (the draft token is always
+                    // accepted)
+                    let residual_probabilities = residual_probabilities.where_self(
+                        &residual_probabilities
+                            .sum_dim_intlist([-1].as_slice(), true, Kind::Float)
+                            .gt(0),
+                        &probabilities,
+                    );
+                    let resampled_token = gen_opt
+                        .sampler
+                        .multinomial(&residual_probabilities, 1)
+                        .squeeze_dim(1);
+                    (
+                        draft_token.where_self(&accepted, &resampled_token),
+                        Some(accepted),
+                    )
+                } else {
+                    let next_token = next_token_logits.argmax(-1, false);
+                    let accepted = next_token.eq_tensor(&draft_tokens[position as usize]);
+                    (next_token, Some(accepted))
+                };
+                speculative_state
+                    .pending_tokens
+                    .push_back((next_token, next_token_logits));
+                if let Some(accepted) = accepted {
+                    if accepted
+                        .logical_or(&finished_sentences)
+                        .all()
+                        .int64_value(&[])
+                        == 0
+                    {
+                        num_accepted_tokens = position;
+                        break;
+                    }
+                }
+            }
+
+            // Truncate the caches to the accepted sequence (excluding the last generated token)
+            let accepted_length = sequence_length + num_accepted_tokens;
+            speculative_state.cached_length = if past.truncate(accepted_length) {
+                accepted_length
+            } else {
+                0
+            };
+            speculative_state.past = past;
+            let draft_length = min(speculative_state.draft_cached_length, accepted_length);
+            speculative_state.draft_cached_length =
+                if speculative_state.draft_past.truncate(draft_length) {
+                    draft_length
+                } else {
+                    0
+                };
+            Ok(())
+        }
+
+        fn generate_no_beam_search(
+            &self,
+            input_ids: Tensor,
+            encoder_outputs: Option<Tensor>,
+            cur_len: i64,
+            batch_size: i64,
+            attention_mask: Tensor,
+            past: Cache,
+            cached_length: i64,
+            gen_opt: InternalGenerateOptions,
+            prefix_allowed_tokens_fn: Option<PrefixAllowedFunction>,
+            output_scores: bool,
+            mut token_callback: Option<TokenCallback>,
+        ) -> Result<GeneratedOutputWithScores, RustBertError> {
+            let mut unfinished_sentences =
+                Tensor::ones(&[batch_size], (Kind::Int64, self.get_var_store().device()));
+            let mut sentence_lengths: Tensor =
+                Tensor::ones(&[batch_size], (Kind::Int64, self.get_var_store().device()));
+            let logits_processors = self.get_logits_processors(&gen_opt, prefix_allowed_tokens_fn);
+            let logits_warpers = self.get_sampling_logits_warpers(&gen_opt);
+            let contrastive_penalty_alpha = gen_opt.penalty_alpha.filter(|penalty_alpha| {
+                (*penalty_alpha > 0f64) & !gen_opt.do_sample & (gen_opt.top_k > 1)
+            });
+            let mut attention_mask = attention_mask.copy();
+            let mut input_ids = input_ids.copy();
+            // Contrastive search requires the hidden states of all context positions: the cached
+            // positions are then recomputed
+            let (mut past, mut cached_length) = if contrastive_penalty_alpha.is_some() {
+                (Cache::None, 0)
+            } else {
+                (past, cached_length)
+            };
+            let mut current_length = cur_len;
+            let mut token_scores_output: Option<Vec<Tensor>> =
+                if output_scores { Some(vec![]) } else { None };
+            // Contrastive search state: hidden states (and padding mask) of the context and logits
+            // already computed for the next step when scoring the candidate tokens
+            let mut context_hidden_states: Option<(Tensor, Tensor)> = None;
+            let mut next_step_logits: Option<Tensor> = None;
+            let mut speculative_state =
+                gen_opt
+                    .draft_model
+                    .map(|draft_model| SpeculativeDecodingState {
+                        draft_model,
+                        past: std::mem::replace(&mut past, Cache::None),
+                        cached_length: std::mem::take(&mut cached_length),
+                        draft_past: Cache::None,
+                        draft_cached_length: 0,
+                        pending_tokens: VecDeque::new(),
+                    });
+
+            loop {
+                let (next_token, next_token_logits) = if let Some(speculative_state) =
+                    speculative_state.as_mut()
+                {
+                    if speculative_state.pending_tokens.is_empty() {
+                        self.speculative_decoding_step(
+                            speculative_state,
+                            &input_ids,
+                            &attention_mask,
+                            &unfinished_sentences,
+                            current_length,
+                            &gen_opt,
+                            &logits_processors,
+                            &logits_warpers,
+                        )?;
+                    }
+                    speculative_state
+                        .pending_tokens
+                        .pop_front()
+                        .ok_or_else(|| {
+                            RustBertError::ValueError(
+                                "Speculative decoding step did not generate any token".to_string(),
+                            )
+                        })?
+                } else {
+                    let mut next_token_logits = if let Some(next_step_logits) =
+                        next_step_logits.take()
+                    {
+                        next_step_logits
+                    } else if cached_length > 0 {
+                        let (lm_logits, cache) = self.forward_uncached_positions(
+                            &input_ids,
+                            &attention_mask,
+                            std::mem::replace(&mut past, Cache::None),
+                            cached_length,
+                        )?;
+                        past = gen_opt.static_cache_from(cache);
+                        cached_length = 0;
+                        lm_logits.select(1, -1)
+                    } else {
+                        let prepared_input = self.prepare_inputs_for_generation(
+                            input_ids.copy(),
+                            encoder_outputs.as_ref(),
+                            past,
+                            attention_mask.copy(),
+                        );
+                        let temp = self.get_model().forward_t(
+                            prepared_input.prepared_input.as_ref(),
+                            prepared_input.prepared_past,
+                            prepared_input.prepared_attention_mask.as_ref(),
+                            None,
+                            prepared_input.prepared_position_ids.as_ref(),
+                            None,
+                            prepared_input.prepared_encoder_output,
+                            prepared_input.prepared_decoder_input.as_ref(),
+                            false,
+                        )?;
+                        past = gen_opt.static_cache_from(temp.cache);
+                        if contrastive_penalty_alpha.is_some() {
+                            let hidden_state = temp.hidden_state.ok_or_else(|| {
+                            RustBertError::ValueError(
+                                "Contrastive search requires the model to return its last hidden state"
+                                    .to_string(),
+                            )
+                        })?;
+                            let context_mask = if !self.is_encoder_decoder()
+                                & (hidden_state.size()[1] == attention_mask.size()[1])
+                            {
+                                attention_mask.copy()
+                            } else {
+                                Tensor::ones(
+                                    &hidden_state.size()[..2],
+                                    (Kind::Int64, hidden_state.device()),
+                                )
+                            };
+                            context_hidden_states = Some((hidden_state, context_mask));
+                        }
+                        temp.lm_logits.select(1, -1)
+                    };
+                    self.process_next_token_logits(
+                        &input_ids,
+                        &mut next_token_logits,
+                        current_length,
+                        &gen_opt,
+                        &logits_processors,
+                        &logits_warpers,
+                    );
+
+                    let next_token = if gen_opt.do_sample {
+                        let probabilities = next_token_logits.softmax(-1, next_token_logits.kind());
+                        gen_opt
+                            .sampler
+                            .multinomial(&probabilities, 1)
+                            .squeeze_dim(1)
+                    } else if let Some(penalty_alpha) = contrastive_penalty_alpha {
+                        let (hidden_states, context_mask) =
+                            context_hidden_states.take().ok_or_else(|| {
+                                RustBertError::ValueError(
+                                    "Missing context hidden states for contrastive search"
+                                        .to_string(),
+                                )
+                            })?;
+                        let contrastive_output = self.contrastive_search_step(
+                            &input_ids,
+                            encoder_outputs.as_ref(),
+                            std::mem::replace(&mut past, Cache::None),
+                            &attention_mask,
+                            &next_token_logits,
+                            &hidden_states,
+                            &context_mask,
+                            penalty_alpha,
+                            gen_opt.top_k,
+                        )?;
+                        past = contrastive_output.past;
+                        next_step_logits = Some(contrastive_output.next_token_logits);
+                        context_hidden_states = Some((
+                            Tensor::cat(
+                                &[hidden_states, contrastive_output.hidden_state.unsqueeze(1)],
+                                1,
+                            ),
+                            Tensor::cat(
+                                &[
+                                    context_mask.as_ref(),
+                                    Tensor::ones(
+                                        &[batch_size, 1],
+                                        (Kind::Int64, context_mask.device()),
+                                    )
+                                    .as_ref(),
+                                ],
+                                1,
+                            ),
+                        ));
+                        contrastive_output.next_token
+                    } else {
+                        next_token_logits.argmax(-1, false)
+                    };
+                    (next_token, next_token_logits)
+                };
+
+                if let Some(prev_scores) = token_scores_output.as_mut() {
+                    let finished_mask = unfinished_sentences.eq(0);
+                    prev_scores.push(
+                        next_token_logits
+                            .log_softmax(-1, next_token_logits.kind())
+                            .gather(1, &next_token.reshape(&[-1, 1]), true)
+                            .squeeze()
+                            .masked_fill(&finished_mask, 0),
+                    );
+                };
+
+                // Add tokens to unfinished sentences
+                let tokens_to_add = match gen_opt.pad_token_id {
+                    Some(pad_token_id)
+                        if gen_opt.eos_token_ids.is_some() | gen_opt.has_stop_conditions() =>
+                    {
+                        next_token * &unfinished_sentences
+                            - pad_token_id * (&unfinished_sentences - 1)
+                    }
+                    _ => next_token,
+                };
+
+                // Stream the tokens generated for unfinished sentences
+                if let Some(token_callback) = token_callback.as_mut() {
+                    let step_tokens = tokens_to_add
+                        .iter::<i64>()?
+                        .zip(unfinished_sentences.iter::<i64>()?)
+                        .map(|(token, unfinished)| (unfinished > 0).then_some(token))
+                        .collect::<Vec<Option<i64>>>();
+                    token_callback(&step_tokens);
+                }
+
+                input_ids = Tensor::cat(&[input_ids, tokens_to_add.unsqueeze(-1)], -1);
+                if let Some(eos_token_ids) = gen_opt.eos_token_ids.as_ref() {
+                    for eos_token_id in eos_token_ids {
+                        let sentence_with_eos =
+                            tokens_to_add.eq(*eos_token_id).to_kind(Kind::Int64);
+                        let sentence_with_eos: Tensor = sentence_with_eos * &unfinished_sentences;
+                        let _ = sentence_lengths.masked_fill_(
+                            &sentence_with_eos
+                                .to_kind(Kind::Bool)
+                                .to_device(sentence_lengths.device()),
+                            current_length + 1,
+                        );
+                        unfinished_sentences = -unfinished_sentences * (sentence_with_eos - 1);
+                    }
+                }
+                if gen_opt.has_stop_conditions() {
+                    let mut stopped_sentences = Vec::with_capacity(batch_size as usize);
+                    for (sequence_index, unfinished) in
+                        unfinished_sentences.iter::<i64>()?.enumerate()
+                    {
+                        let is_stopped = (unfinished > 0)
+                            && self.is_stopped(
+                                &gen_opt,
+                                sequence_index,
+                                &input_ids
+                                    .get(sequence_index as i64)
+                                    .slice(0, cur_len, None, 1)
+                                    .iter::<i64>()?
+                                    .collect::<Vec<i64>>(),
+                            );
+                        stopped_sentences.push(is_stopped as i64);
+                    }
+                    let stopped_sentences =
+                        Tensor::of_slice(&stopped_sentences).to(unfinished_sentences.device());
+                    let _ = sentence_lengths.masked_fill_(
+                        &stopped_sentences
+                            .to_kind(Kind::Bool)
+                            .to_device(sentence_lengths.device()),
+                        current_length + 1,
+                    );
+                    unfinished_sentences = unfinished_sentences * (1 - stopped_sentences);
+                }
+                if (gen_opt.eos_token_ids.is_some() | gen_opt.has_stop_conditions())
+                    && (i64::from(unfinished_sentences.max()) == 0)
+                {
+                    break;
+                }
+                if !self.is_encoder_decoder() {
+                    attention_mask = Tensor::cat(
+                        &[
+                            attention_mask.as_ref(),
+                            Tensor::ones(
+                                &[*attention_mask.size().first().unwrap(), 1],
+                                (Kind::Int64, attention_mask.device()),
+                            )
+                            .as_ref(),
+                        ],
+                        -1,
+                    );
+                }
+                current_length += 1;
+                if let Some(max_length) = gen_opt.max_length {
+                    if current_length >= max_length {
+                        let _ = sentence_lengths.masked_fill_(
+                            &unfinished_sentences
+                                .to_kind(Kind::Bool)
+                                .to_device(sentence_lengths.device()),
+                            current_length,
+                        );
+                        break;
+                    }
+                }
+            }
+            let scores_output = token_scores_output
+                .as_ref()
+                .map(|scores_tensor| -> Result<Vec<f64>, RustBertError> {
+                    Ok((Tensor::stack(scores_tensor, 1).sum_dim_intlist(
+                        [1].as_slice(),
+                        false,
+                        Kind::Float,
+                    ) / sentence_lengths.pow_tensor_scalar(gen_opt.length_penalty))
+                    .iter::<f64>()?
+                    .collect::<Vec<f64>>())
+                })
+                .transpose()?;
+            let token_scores_output = token_scores_output
+                .map(|score_tensors| -> Result<Vec<Vec<f64>>, RustBertError> {
+                    Tensor::stack(&score_tensors, 1)
+                        .split(1, 0)
+                        .iter()
+                        .map(|sequence_scores| {
+                            Ok(sequence_scores
+                                .squeeze_dim(0)
+                                .iter::<f64>()?
+                                .collect::<Vec<f64>>())
+                        })
+                        .collect()
+                })
+                .transpose()?;
+            let past = match speculative_state {
+                Some(speculative_state) => speculative_state.past,
+                None => past,
+            };
+            Ok(GeneratedOutputWithScores {
+                indices: input_ids,
+                scores: scores_output,
+                token_scores: token_scores_output,
+                past,
+            })
+        }
+
+        fn generate_beam_search(
+            &self,
+            mut input_ids: Tensor,
+            encoder_outputs: Option<Tensor>,
+            cur_len: i64,
+            batch_size: i64,
+            mut attention_mask: Tensor,
+            gen_opt: InternalGenerateOptions,
+            prefix_allowed_tokens_fn: Option<PrefixAllowedFunction>,
+            output_scores: bool,
+        ) -> GeneratedOutputWithScores {
+            let num_beam_groups = gen_opt.num_beam_groups.unwrap_or(1);
+            let num_sub_beams = gen_opt.num_beams / num_beam_groups;
+            let diversity_penalty = gen_opt.diversity_penalty.unwrap_or(5.5);
+            let repetition_penalty_processor =
+                (gen_opt.repetition_penalty > 1f64).then_some(RepetitionPenaltyLogitsProcessor {
+                    penalty: gen_opt.repetition_penalty,
+                });
+            let temperature_warper =
+                (gen_opt.temperature > 1f64).then_some(TemperatureLogitsWarper {
+                    temperature: gen_opt.temperature,
+                });
+            let logits_processors = self.get_beam_logits_processors(
+                &gen_opt,
+                (num_beam_groups > 1)
+                    .then_some(HammingDiversityLogitsProcessor { diversity_penalty }),
+                prefix_allowed_tokens_fn,
+            );
+            let logits_warpers = self.get_logits_warpers(&gen_opt, 2);
+
+            let mut hypotheses = (0..batch_size)
+                .map(|_| {
+                    BeamHypotheses::new(
+                        gen_opt.num_beams,
+                        gen_opt.max_length,
+                        gen_opt.length_penalty,
+                        gen_opt.early_stopping,
+                    )
+                })
+                .collect::<Vec<BeamHypotheses>>();
+
+            let vocab_size = self.get_vocab_size();
+            let beam_scores = Tensor::ones(
+                &[batch_size, gen_opt.num_beams],
+                (Kind::Float, self.get_var_store().device()),
+            ) * -1e9;
+            let _ = beam_scores
+                .slice(1, 0, *beam_scores.size().last().unwrap(), num_sub_beams)
+                .fill_(0);
+
+            let mut beam_scores = beam_scores.view_(&[-1]);
+            let mut beam_tokens = Tensor::zeros(
+                &[batch_size * gen_opt.num_beams],
+                (Kind::Int64, self.get_var_store().device()),
+            );
+            let mut beam_indices = Tensor::zeros(
+                &[batch_size * gen_opt.num_beams],
+                (Kind::Int64, self.get_var_store().device()),
+            );
+            let mut saved_beam_scores: Option<Vec<Tensor>> =
+                if output_scores { Some(vec![]) } else { None };
+            let mut current_tokens = Tensor::new();
+
+            let mut past: Cache = Cache::None;
+            let mut done = vec![false; batch_size as usize];
+
+            let mut outputs: Tensor;
+            let mut encoder_outputs = encoder_outputs;
+            let mut current_length = cur_len;
+
+            loop {
+                if num_beam_groups > 1 {
+                    current_tokens = Tensor::zeros(
+                        &[batch_size * gen_opt.num_beams],
+                        (input_ids.kind(), input_ids.device()),
+                    );
+                }
+                let prepared_input = self.prepare_inputs_for_generation(
+                    input_ids.copy(),
+                    encoder_outputs.as_ref(),
+                    past,
+                    attention_mask.copy(),
+                );
+                let temp = self
+                    .get_model()
+                    .forward_t(
+                        prepared_input.prepared_input.as_ref(),
+                        prepared_input.prepared_past,
+                        prepared_input.prepared_attention_mask.as_ref(),
+                        None,
+                        prepared_input.prepared_position_ids.as_ref(),
+                        None,
+                        prepared_input.prepared_encoder_output,
+                        prepared_input.prepared_decoder_input.as_ref(),
+                        false,
+                    )
+                    .unwrap();
+                outputs = temp.lm_logits;
+                past = gen_opt.static_cache_from(temp.cache);
+
+                for beam_group_index in 0..num_beam_groups {
+                    let group_start_index = beam_group_index * num_sub_beams;
+                    let group_end_index = min(group_start_index + num_sub_beams, gen_opt.num_beams);
+                    let group_size = group_end_index - group_start_index;
+
+                    let (group_input_ids, batch_group_indices) = if num_beam_groups > 1 {
+                        let mut batch_group_indices: Vec<i64> =
+                            Vec::with_capacity((batch_size * group_size) as usize);
+                        for batch_index in 0..batch_size {
+                            batch_group_indices.extend(
+                                (group_start_index..group_end_index)
+                                    .map(|value| value + batch_index * gen_opt.num_beams),
+                            )
+                        }
+                        let batch_group_indices =
+                            Tensor::of_slice(batch_group_indices.as_slice()).to(input_ids.device());
+                        (
+                            Some(input_ids.index_select(0, &batch_group_indices)),
+                            Some(batch_group_indices),
+                        )
+                    } else {
+                        (None, None)
+                    };
+
+                    let mut next_token_logits = if num_beam_groups <= 1 {
+                        outputs.select(1, -1)
+                    } else {
+                        outputs
+                            .select(1, -1)
+                            .index_select(0, batch_group_indices.as_ref().unwrap())
+                    };
+                    let group_input_ids = group_input_ids.as_ref().unwrap_or(&input_ids);
+                    let logits_processor_context = LogitsProcessorContext {
+                        current_length,
+                        num_beams: num_sub_beams,
+                        beam_group: (num_beam_groups > 1).then_some(BeamGroupContext {
+                            current_tokens: &current_tokens,
+                            group_start_index,
+                            group_size,
+                            num_beams: gen_opt.num_beams,
+                        }),
+                    };
+                    // Reduce probability for repeated inputs
+                    if let Some(repetition_penalty_processor) = &repetition_penalty_processor {
+                        repetition_penalty_processor.process(
+                            group_input_ids,
+                            &mut next_token_logits,
+                            &logits_processor_context,
+                        );
+                    }
+
+                    if let Some(temperature_warper) = &temperature_warper {
+                        temperature_warper.warp(
+                            group_input_ids,
+                            &mut next_token_logits,
+                            &logits_processor_context,
+                        );
+                    }
+                    self.prepare_scores_for_generation(
+                        &mut next_token_logits,
+                        current_length,
+                        gen_opt.max_length,
+                        gen_opt.forced_bos_token_id,
+                    );
+
+                    let mut scores = next_token_logits.log_softmax(-1, next_token_logits.kind());
+
+                    // Minimum length, banned words and n-grams, diversity penalty, prefix constraints
+                    // and custom logits processors
+                    for logits_processor in logits_processors.iter() {
+                        logits_processor.process(
+                            group_input_ids,
+                            &mut scores,
+                            &logits_processor_context,
+                        );
+                    }
+
+                    let mut next_scores: Tensor = &scores
+                        + (if num_beam_groups > 1 {
+                            beam_scores
+                                .index_select(0, batch_group_indices.as_ref().unwrap())
+                                .unsqueeze(-1)
+                                .expand_as(&scores)
+                        } else {
+                            beam_scores.unsqueeze(-1).expand_as(&scores)
+                        });
+
+                    let (next_scores, next_tokens) = if gen_opt.do_sample {
+                        for logits_warper in logits_warpers.iter() {
+                            logits_warper.warp(
+                                group_input_ids,
+                                &mut next_scores,
+                                &logits_processor_context,
+                            );
+                        }
+                        let _scores = next_scores
+                            .contiguous()
+                            .view((batch_size, group_size * vocab_size));
+
+                        let probabilities = _scores.softmax(-1, _scores.kind());
+                        let next_tokens =
+                            gen_opt.sampler.multinomial(&probabilities, 2 * group_size);
+                        let _scores = _scores.gather(-1, &next_tokens, false);
+                        let (_scores, next_scores_indices) = _scores.sort(1, true);
+                        let next_tokens = next_tokens.gather(-1, &next_scores_indices, false);
+                        (_scores, next_tokens)
+                    } else if let Some(constraints) = gen_opt.constraints {
+                        get_constrained_beam_candidates(
+                            constraints,
+                            &input_ids,
+                            cur_len,
+                            &next_scores,
+                            batch_size,
+                            group_size,
+                        )
+                    } else {
+                        let _scores = next_scores
+                            .contiguous()
+                            .view((batch_size, group_size * vocab_size));
+                        _scores.topk(2 * group_size, 1, true, true)
+                    };
+
+                    let eos_token_ids = gen_opt.eos_token_ids.as_ref();
+                    let beam_ids_tensor = &next_tokens.divide_scalar_mode(vocab_size, "floor");
+                    let effective_beam_ids_tensor =
+                        (&next_tokens.ones_like().cumsum(0, Kind::Int64) - 1) * group_size
+                            + beam_ids_tensor;
+                    let token_id_tensor = &next_tokens - beam_ids_tensor * vocab_size;
+                    let (max_scores, _) = next_scores.max_dim(1, false);
+                    let mut eos_mask = token_id_tensor.ones_like();
+                    if let Some(eos_token_id) = eos_token_ids {
+                        eos_mask -= token_id_tensor.eq(eos_token_id[0]).to_kind(Kind::Int64);
+                    }
+                    // Candidates meeting a stop condition are finished like the ones ending with an eos token
+                    let stop_mask = if gen_opt.has_stop_conditions() {
+                        let mut stop_mask = vec![0i64; (batch_size * 2 * group_size) as usize];
+                        for batch_index in 0..batch_size {
+                            if done[batch_index as usize] {
+                                continue;
+                            }
+                            for beam_index_pos in 0..2 * group_size {
+                                let token_id =
+                                    token_id_tensor.int64_value(&[batch_index, beam_index_pos]);
+                                if let Some(eos_token_ids) = eos_token_ids {
+                                    if eos_token_ids[0] == token_id {
+                                        continue;
+                                    }
+                                }
+                                let effective_beam_id = effective_beam_ids_tensor
+                                    .int64_value(&[batch_index, beam_index_pos]);
+                                let mut generated_ids = input_ids
+                                    .get(effective_beam_id)
+                                    .slice(0, cur_len, None, 1)
+                                    .iter::<i64>()
+                                    .unwrap()
+                                    .collect::<Vec<i64>>();
+                                generated_ids.push(token_id);
+                                if self.is_stopped(&gen_opt, batch_index as usize, &generated_ids) {
+                                    stop_mask[(batch_index * 2 * group_size + beam_index_pos)
+                                        as usize] = 1;
+                                }
+                            }
+                        }
+                        let stop_mask = Tensor::of_slice(&stop_mask)
+                            .view_as(&eos_mask)
+                            .to_device(eos_mask.device());
+                        eos_mask -= &stop_mask;
+                        Some(stop_mask)
+                    } else {
+                        None
+                    };
+                    let eos_mask2 = eos_mask
+                        .cumsum(1, Kind::Int64)
+                        .le(group_size)
+                        .to_kind(Kind::Bool)
+                        .logical_and(&eos_mask);
+
+                    let group_beam_scores = next_scores.masked_select(&eos_mask2);
+                    let group_beam_tokens = token_id_tensor.masked_select(&eos_mask2);
+                    let group_beam_indices = effective_beam_ids_tensor.masked_select(&eos_mask2);
+                    let eos_pos = (eos_mask.ones_like() - eos_mask).nonzero();
+
+                    for eos_idx in 0..eos_pos.size()[0] {
+                        let eos_data = eos_pos.get(eos_idx);
+                        let batch_index = eos_data.int64_value(&[0]);
+                        if !done[batch_index as usize] {
+                            let beam_index_pos = eos_data.int64_value(&[1]);
+                            let is_beam_token_worse_than_top_num_beams =
+                                beam_index_pos >= gen_opt.num_beams;
+                            if is_beam_token_worse_than_top_num_beams {
+                                continue;
+                            }
+                            let effective_beam_id = effective_beam_ids_tensor
+                                .int64_value(&[batch_index, beam_index_pos]);
+                            let beam_token_score =
+                                next_scores.double_value(&[batch_index, beam_index_pos]);
+                            let saved_beam_scores =
+                                saved_beam_scores.as_ref().map(|step_wise_scores| {
+                                    Tensor::stack(step_wise_scores, 1)
+                                        .get(effective_beam_id)
+                                        .copy()
+                                });
+                            let hypothesis_ids = match &stop_mask {
+                                Some(stop_mask)
+                                    if stop_mask.int64_value(&[batch_index, beam_index_pos])
+                                        > 0 =>
+                                {
+                                    Tensor::cat(
+                                        &[
+                                            input_ids.get(effective_beam_id),
+                                            token_id_tensor.get(batch_index).narrow(
+                                                0,
+                                                beam_index_pos,
+                                                1,
+                                            ),
+                                        ],
+                                        0,
+                                    )
+                                }
+                                _ => input_ids.get(effective_beam_id).copy(),
+                            };
+                            // Hypotheses are only finished once all constraints are fulfilled
+                            if let Some(constraints) = gen_opt.constraints {
+                                let generated_ids = hypothesis_ids
+                                    .slice(0, cur_len, None, 1)
+                                    .iter::<i64>()
+                                    .unwrap()
+                                    .collect::<Vec<i64>>();
+                                if !constraints_fulfilled(constraints, &generated_ids) {
+                                    continue;
+                                }
+                            }
+                            hypotheses[batch_index as usize].add(
+                                hypothesis_ids,
+                                beam_token_score,
+                                saved_beam_scores,
+                            );
+                        }
+                    }
+
+                    for batch_index in 0..batch_size {
+                        if done[batch_index as usize] {
+                            let _ = group_beam_scores
+                                .narrow(0, batch_index * gen_opt.num_beams, gen_opt.num_beams)
+                                .fill_(0f64);
+                            let _ = group_beam_tokens
+                                .narrow(0, batch_index * gen_opt.num_beams, gen_opt.num_beams)
+                                .fill_(gen_opt.pad_token_id.unwrap());
+                            let _ = group_beam_indices
+                                .narrow(0, batch_index * gen_opt.num_beams, gen_opt.num_beams)
+                                .fill_(0);
+                            continue;
+                        } else {
+                            done[batch_index as usize] |= hypotheses[batch_index as usize]
+                                .is_done(max_scores.double_value(&[batch_index]), current_length);
+                        }
+                    }
+
+                    if num_beam_groups <= 1 {
+                        beam_scores = group_beam_scores.view(-1);
+                        beam_tokens = group_beam_tokens.view(-1);
+                        beam_indices = group_beam_indices.view(-1);
+                    } else {
+                        let _ = beam_scores.index_copy_(
+                            0,
+                            batch_group_indices.as_ref().unwrap(),
+                            &group_beam_scores,
+                        );
+                        let _ = beam_tokens.index_copy_(
+                            0,
+                            batch_group_indices.as_ref().unwrap(),
+                            &group_beam_tokens,
+                        );
+                        let new_indices = gen_opt.num_beams
+                            * group_beam_indices.divide_scalar_mode(group_size, "floor")
+                            + group_start_index
+                            + group_beam_indices.remainder(group_size);
+                        let _ = beam_indices.index_copy_(
+                            0,
+                            batch_group_indices.as_ref().unwrap(),
+                            &new_indices,
+                        );
+                        let _ = current_tokens.index_copy_(
+                            0,
+                            batch_group_indices.as_ref().unwrap(),
+                            &group_beam_tokens,
+                        );
+                    }
+                }
+
+                if let Some(scores_output) = saved_beam_scores.as_mut() {
+                    scores_output.push(beam_scores.copy());
+                }
+                if done.iter().all(|&x| x) {
+                    break;
+                }
+
+                input_ids = Tensor::cat(
+                    &[
+                        input_ids.index_select(0, &beam_indices),
+                        beam_tokens.unsqueeze(1),
+                    ],
+                    -1,
+                );
+
+                current_length += 1;
+                if let Some(max_length) = gen_opt.max_length {
+                    if current_length >= max_length {
+                        break;
+                    }
+                }
+                encoder_outputs = self.reorder_cache(&mut past, encoder_outputs, &beam_indices);
+
+                if !self.is_encoder_decoder() {
+                    attention_mask = Tensor::cat(
+                        &[
+                            attention_mask.as_ref(),
+                            Tensor::ones(
+                                &[*attention_mask.size().first().unwrap(), 1],
+                                (Kind::Int64, attention_mask.device()),
+                            )
+                            .as_ref(),
+                        ],
+                        -1,
+                    );
+                }
+            }
+
+            let mut batch_index = 0i64;
+
+            let mut saved_beam_scores = saved_beam_scores
+                .map(|step_wise_scores| Tensor::stack(&step_wise_scores, 1).split(1, 0));
+            loop {
+                if batch_index == batch_size {
+                    break;
+                }
+                if done[batch_index as usize] {
+                    batch_index += 1;
+                    continue;
+                }
+                let mut effective_beam_ids = (0..gen_opt.num_beams)
+                    .map(|beam_index| (batch_index * gen_opt.num_beams + beam_index, true))
+                    .collect::<Vec<(i64, bool)>>();
+                if let Some(constraints) = gen_opt.constraints {
+                    for (effective_beam_id, fulfilled) in effective_beam_ids.iter_mut() {
+                        let generated_ids = input_ids
+                            .get(*effective_beam_id)
+                            .slice(0, cur_len, None, 1)
+                            .iter::<i64>()
+                            .unwrap()
+                            .collect::<Vec<i64>>();
+                        *fulfilled = constraints_fulfilled(constraints, &generated_ids);
+                    }
+                    effective_beam_ids.sort_by_key(|(_, fulfilled)| !fulfilled);
+                }
+                for (effective_beam_id, fulfilled) in effective_beam_ids {
+                    // Hypotheses not fulfilling the constraints are only used if there are not
+                    // enough hypotheses fulfilling them
+                    if !fulfilled
+                        & (hypotheses[batch_index as usize].len() >= gen_opt.num_return_sequences)
+                    {
+                        break;
+                    }
+                    let beam_saved_token_scores = saved_beam_scores.as_mut().map(|saved_tokens| {
+                        mem::replace(&mut saved_tokens[effective_beam_id as usize], Tensor::new())
+                    });
+                    let final_score = f64::from(beam_scores.get(effective_beam_id));
+                    let final_tokens = input_ids.get(effective_beam_id);
+                    hypotheses[batch_index as usize].add(
+                        final_tokens,
+                        final_score,
+                        beam_saved_token_scores,
+                    );
+                }
+                batch_index += 1;
+            }
+            let (output_batch_size, output_num_return_sequences_per_batch) = if gen_opt.do_sample {
+                (batch_size, 1)
+            } else {
+                (
+                    batch_size * gen_opt.num_return_sequences,
+                    gen_opt.num_return_sequences,
+                )
+            };
+
+            let mut sentence_lengths =
+                Tensor::zeros(&[output_batch_size], (Kind::Int64, input_ids.device()));
+            let mut best_ids = vec![];
+
+            let mut scores_output = if output_scores {
+                Some(Vec::with_capacity(best_ids.len()))
+            } else {
+                None
+            };
+            let mut token_scores_output = if output_scores {
+                Some(Vec::with_capacity(best_ids.len()))
+            } else {
+                None
+            };
+            for (hypothesis_index, hypothesis) in hypotheses.iter().enumerate() {
+                let mut sorted_hypotheses = hypothesis.clone();
+                sorted_hypotheses
+                    .beams
+                    .sort_by_key(|(score, _, _)| OrderedFloat(*score));
+                for j in 0..output_num_return_sequences_per_batch {
+                    let effective_batch_index =
+                        output_num_return_sequences_per_batch * hypothesis_index as i64 + j;
+
+                    let (best_score, best_hyp, best_token_scores) =
+                        sorted_hypotheses.beams.pop().unwrap();
+                    let _ = sentence_lengths.index_fill_(
+                        0,
+                        &Tensor::of_slice(&[effective_batch_index]).to(sentence_lengths.device()),
+                        *best_hyp.size().first().unwrap(),
+                    );
+                    best_ids.push(best_hyp);
+                    if let Some(current_best_scores) = &mut scores_output {
+                        current_best_scores.push(best_score);
+                    }
+                    if let Some(current_best_token_scores) = &mut token_scores_output {
+                        current_best_token_scores.push(
+                            best_token_scores
+                                .unwrap()
+                                .iter::<f64>()
+                                .unwrap()
+                                .collect::<Vec<f64>>(),
+                        );
+                    }
+                }
+            }
+            let sentence_max_length = gen_opt
+                .max_length
+                .map(|max_length| min(i64::from(sentence_lengths.max()) + 1, max_length))
+                .unwrap_or(i64::from(sentence_lengths.max()) + 1);
+
+            let mut decoded = input_ids.new_empty(
+                &[output_batch_size, sentence_max_length],
+                (Kind::Int64, input_ids.device()),
+            );
+            if i64::from(sentence_lengths.max()) != i64::from(sentence_lengths.min()) {
+                let _ = decoded.fill_(
+                    gen_opt
+                        .pad_token_id
+                        .unwrap_or_else(|| gen_opt.eos_token_ids.as_ref().unwrap()[0]),
+                );
+            }
+            for (hypothesis_index, best_id) in best_ids.iter().enumerate() {
+                let _ = decoded.get(hypothesis_index as i64).index_copy_(
+                    0,
+                    &Tensor::arange_start(
+                        0,
+                        i64::from(sentence_lengths.get(hypothesis_index as i64)),
+                        (Kind::Int64, input_ids.device()),
+                    ),
+                    best_id,
+                );
+                let sentence_length = i64::from(sentence_lengths.get(hypothesis_index as i64));
+                let sentence_length_max = gen_opt
+                    .max_length
+                    .unwrap_or_else(|| i64::from(sentence_lengths.max()));
+                if sentence_length < sentence_length_max {
+                    let _ = decoded.get(hypothesis_index as i64).index_fill_(
+                        0,
+                        &Tensor::of_slice(&[sentence_length]).to_device(input_ids.device()),
+                        gen_opt.eos_token_ids.as_ref().unwrap()[0],
+                    );
+                }
+            }
+            GeneratedOutputWithScores {
+                indices: decoded,
+                scores: scores_output,
+                token_scores: token_scores_output,
+                past: Cache::None,
+            }
+        }
+
+        fn reorder_cache(
+            &self,
+            past: &mut Cache,
+            encoder_outputs: Option<Tensor>,
+            beam_indices: &Tensor,
+        ) -> Option<Tensor> {
+            match past {
+                Cache::Custom(cache) => cache.reorder(beam_indices),
+                Cache::None => {}
+                _ => {
+                    panic!("Not implemented");
+                }
+            }
+            encoder_outputs.map(|encoder_outputs| encoder_outputs.index_select(0, beam_indices))
+        }
+
+        fn prepare_prompt_input_ids<S>(
+            &self,
+            prompt_texts: Option<&[S]>,
+            generate_options: Option<GenerateOptions>,
+        ) -> Result<Option<Tensor>, RustBertError>
+        where
+            S: AsRef<str> + Sync,
+        {
+            let eos_token_ids = self.get_eos_ids();
+
+            let config = self.get_config();
+
+            let max_length = generate_options.map_or(config.max_length, |generate_options| {
+                generate_options.max_length
+            });
+            let encoding_max_len = if self.is_encoder_decoder() {
+                Some(self.get_max_positions_embeddings())
+            } else {
+                max_length
+            };
+            let pad_token_id = match self.get_pad_id() {
+                Some(value) => Some(value),
+                None => eos_token_ids.as_ref().map(|eos_ids| eos_ids[0]),
+            };
+
+            let input_ids = match prompt_texts {
+                Some(prompts) if !prompts.is_empty() => {
+                    self.encode_prompt_text(prompts, encoding_max_len, pad_token_id)
+                }
+                None => match self.get_bos_id() {
+                    Some(bos_id) => {
+                        Tensor::ones(&[1, 1], (Int64, self.get_var_store().device())) * bos_id
+                    }
+                    None => {
+                        return Err(RustBertError::ValueError(
+                            "A model with a BOS token must be used to start generation with an empty input"
+                                .to_string(),
+                        ));
+                    }
+                },
+                _ => return Ok(None),
+            };
+            Ok(Some(input_ids))
+        }
+
+        /// Number of leading tokens of the generated sequences that are not generated: the prompt
+        /// (or the beginning of sequence token replacing an empty prompt) for decoder-only models,
+        /// the decoder start token for encoder-decoder models
+        fn get_prompt_length(&self, input_ids: &Tensor) -> usize {
+            if self.is_encoder_decoder() {
+                1
+            } else {
+                (*input_ids.size().last().unwrap()).max(1) as usize
+            }
+        }
+
+        fn generate_from_ids_and_past_internal(
+            &self,
+            input_ids: Tensor,
+            attention_mask: Option<Tensor>,
+            generate_options: Option<GenerateOptions>,
+            token_callback: Option<TokenCallback>,
+        ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError> {
+            Ok(self
+                .generate_from_ids_and_cache_internal(
+                    input_ids,
+                    attention_mask,
+                    Cache::None,
+                    0,
+                    generate_options,
+                    token_callback,
+                )?
+                .0)
+        }
+
+        /// Generates from `input_ids` with a cache holding the states of their first
+        /// `cached_length` positions, returning the generated indices and the cache of the
+        /// generated sequences. The cache is only used for greedy decoding and sampling of
+        /// decoder-only models: beam search recomputes the states of all positions and returns
+        /// `Cache::None`.
+        fn generate_from_ids_and_cache_internal(
+            &self,
+            mut input_ids: Tensor,
+            mut attention_mask: Option<Tensor>,
+            mut past: Cache,
+            cached_length: i64,
+            generate_options: Option<GenerateOptions>,
+            token_callback: Option<TokenCallback>,
+        ) -> Result<(Vec<GeneratedIndicesOutput>, Cache), RustBertError> {
+            let eos_token_ids = self.get_eos_ids().cloned();
+
+            let config = self.get_config();
+
+            // Set generation options. Priority goes to options provided to the `generate` method, then
+            // model configuration, then default values.
+            let do_sample = unpack_config!(do_sample, generate_options, config);
+            let num_return_sequences =
+                unpack_config!(num_return_sequences, generate_options, config);
+            let num_beams = unpack_config!(num_beams, generate_options, config);
+            let min_length = unpack_config!(min_length, generate_options, config);
+            let early_stopping = unpack_config!(early_stopping, generate_options, config);
+            let temperature = unpack_config!(temperature, generate_options, config);
+            let top_k = unpack_config!(top_k, generate_options, config);
+            let top_p = unpack_config!(top_p, generate_options, config);
+            let repetition_penalty = unpack_config!(repetition_penalty, generate_options, config);
+            let length_penalty = unpack_config!(length_penalty, generate_options, config);
+            let no_repeat_ngram_size =
+                unpack_config!(no_repeat_ngram_size, generate_options, config);
+            let num_beam_groups = generate_options.map_or(config.num_beam_groups, |opts| {
+                opts.num_beam_groups.or(config.num_beam_groups)
+            });
+            let diversity_penalty = generate_options.map_or(config.diversity_penalty, |opts| {
+                opts.diversity_penalty.or(config.diversity_penalty)
+            });
+            let typical_p = generate_options
+                .map_or(config.typical_p, |opts| opts.typical_p.or(config.typical_p));
+            let epsilon_cutoff = generate_options.map_or(config.epsilon_cutoff, |opts| {
+                opts.epsilon_cutoff.or(config.epsilon_cutoff)
+            });
+            let eta_cutoff = generate_options.map_or(config.eta_cutoff, |opts| {
+                opts.eta_cutoff.or(config.eta_cutoff)
+            });
+            let min_p = generate_options.map_or(config.min_p, |opts| opts.min_p.or(config.min_p));
+            let penalty_alpha = generate_options.map_or(config.penalty_alpha, |opts| {
+                opts.penalty_alpha.or(config.penalty_alpha)
+            });
+            let decoder_start_token_id =
+                generate_options.and_then(|opts| opts.decoder_start_token_id);
+            let forced_bos_token_id = generate_options.and_then(|opts| opts.forced_bos_token_id);
+            let bad_word_ids = generate_options.and_then(|opts| opts.bad_word_ids);
+            let stop_token_ids = generate_options.and_then(|opts| opts.stop_token_ids);
+            let stop_strings = generate_options.and_then(|opts| opts.stop_strings);
+            let stopping_criteria = generate_options.and_then(|opts| opts.stopping_criteria);
+            let logits_processors = generate_options.and_then(|opts| opts.logits_processors);
+            let logits_warpers = generate_options.and_then(|opts| opts.logits_warpers);
+            let logit_bias = generate_options.and_then(|opts| opts.logit_bias);
+            let constraints = generate_options.and_then(|opts| opts.constraints);
+            let draft_model = generate_options.and_then(|opts| opts.draft_model);
+            let seed = generate_options.and_then(|opts| opts.seed);
+            let row_options = generate_options.and_then(|opts| opts.row_options);
+            let prefix_allowed_tokens_fn =
+                generate_options.and_then(|opts| opts.prefix_allowed_tokens_fn);
+            let output_scores = generate_options.map_or(false, |opts| opts.output_scores);
+
+            let pad_token_id = match self.get_pad_id() {
+                Some(value) => Some(value),
+                None => eos_token_ids.as_ref().map(|eos_ids| eos_ids[0]),
+            };
+
+            let input_id_size = input_ids.size();
+            let mut input_ids_len = *input_id_size.last().unwrap();
+            if input_ids_len == 0 {
+                input_ids = Tensor::ones(
+                    &[*input_id_size.first().unwrap(), 1],
+                    (Int64, input_ids.device()),
+                ) * self.get_bos_id().ok_or_else(|| {
+                    RustBertError::ValueError(
+                        "`bos_token_id` has to be defined when no `input_ids` are provided."
+                            .to_string(),
+                    )
+                })?;
+                attention_mask = Some(Tensor::ones(
+                    &[*input_id_size.first().unwrap(), 1],
+                    (Int64, input_ids.device()),
+                ));
+                input_ids_len += 1;
+            }
+
+            let cur_len = if !self.is_encoder_decoder() {
+                *input_ids.size().last().unwrap()
+            } else {
+                1
+            };
+            let max_length = if let Some(generate_options) = generate_options {
+                match (generate_options.max_length, generate_options.max_new_tokens) {
+                    (Some(max_length), _) => Some(max_length),
+                    (None, Some(max_new_tokens)) => Some(max_new_tokens + cur_len),
+                    (None, None) => config.max_length,
+                }
+            } else {
+                config.max_length
+            };
+
+            // Per-row maximum numbers of new tokens: the generation loop runs up to the longest row,
+            // shorter rows being stopped once their number of new tokens is reached
+            let (max_length, row_max_new_tokens) = match row_options {
+                Some(row_options) => {
+                    let batch_size = *input_ids.size().first().unwrap();
+                    if row_options.len() as i64 != batch_size {
+                        return Err(RustBertError::ValueError(format!(
+                            "The number of per-row generation options ({}) must match the number of inputs ({})",
+                            row_options.len(),
+                            batch_size
+                        )));
+                    }
+                    let rows_per_prompt = if do_sample { num_return_sequences } else { 1 };
+                    let row_max_new_tokens = row_options
+                        .iter()
+                        .flat_map(|row_option| {
+                            let max_new_tokens = row_option
+                                .max_new_tokens
+                                .or_else(|| max_length.map(|max_length| max_length - cur_len));
+                            (0..rows_per_prompt.max(1)).map(move |_| max_new_tokens)
+                        })
+                        .collect::<Vec<Option<i64>>>();
+                    let max_length = row_max_new_tokens
+                        .iter()
+                        .copied()
+                        .collect::<Option<Vec<i64>>>()
+                        .and_then(|max_new_tokens| max_new_tokens.into_iter().max())
+                        .map(|max_new_tokens| max_new_tokens + cur_len);
+                    (max_length, Some(row_max_new_tokens))
+                }
+                None => (max_length, None),
+            };
+
+            if max_length.is_none() & eos_token_ids.is_none() {
+                return Err(RustBertError::ValueError(
+                    "No maximum length given for a model without an EOS token. \
+                This would lead to an infinite generation loop. Please provide a `max_length` or `max_new_tokens`"
+                        .to_string(),
+                ));
+            }
+            // Per-row seeds: the returned sequences of each input are sampled with a generator
+            // seeded with the row seed (or the seed of the call), shared by these sequences as in
+            // a separate call
+            let sampler = match row_options {
+                Some(row_options)
+                    if row_options
+                        .iter()
+                        .any(|row_option| row_option.seed.is_some()) =>
+                {
+                    let row_seeds = row_options
+                        .iter()
+                        .map(|row_option| {
+                            row_option.seed.or(seed).ok_or_else(|| {
+                                RustBertError::ValueError(
+                                    "A seed must be provided for all rows (or for the call) when using per-row seeds"
+                                        .to_string(),
+                                )
+                            })
+                        })
+                        .collect::<Result<Vec<u64>, RustBertError>>()?;
+                    Sampler::with_row_seeds(&row_seeds)
+                }
+                _ => Sampler::new(seed),
+            };
+
+            if config.static_cache & max_length.is_none() {
+                return Err(RustBertError::ValueError(
+                    "A static cache requires a maximum length. Please provide a `max_length` or `max_new_tokens`"
+                        .to_string(),
+                ));
+            }
+
+            let gen_opt = InternalGenerateOptions {
+                min_length,
+                max_length,
+                do_sample,
+                temperature,
+                top_k,
+                top_p,
+                repetition_penalty,
+                no_repeat_ngram_size,
+                pad_token_id,
+                eos_token_ids,
+                num_return_sequences,
+                early_stopping,
+                num_beams,
+                length_penalty,
+                num_beam_groups,
+                diversity_penalty,
+                typical_p,
+                epsilon_cutoff,
+                eta_cutoff,
+                min_p,
+                penalty_alpha,
+                static_cache: config.static_cache,
+                forced_bos_token_id,
+                bad_word_ids,
+                stop_token_ids,
+                stop_strings,
+                stopping_criteria,
+                logits_processors,
+                logits_warpers,
+                logit_bias,
+                constraints,
+                draft_model,
+                sampler,
+                row_options,
+                row_max_new_tokens,
+            };
+            gen_opt.validate()?;
+            gen_opt.validate_token_ids(self.get_vocab_size())?;
+            if let Some(draft_model) = draft_model {
+                if self.is_encoder_decoder() {
+                    return Err(RustBertError::ValueError(
+                        "Speculative decoding is only supported for decoder-only models"
+                            .to_string(),
+                    ));
+                }
+                if draft_model.vocab_size() != self.get_vocab_size() {
+                    return Err(RustBertError::ValueError(format!(
+                        "The draft model vocabulary size ({}) must match the model vocabulary size ({})",
+                        draft_model.vocab_size(),
+                        self.get_vocab_size()
+                    )));
+                }
+            }
+
+            if (cached_length > 0) & self.is_encoder_decoder() {
+                return Err(RustBertError::ValueError(
+                    "Generation from a cache is only supported for decoder-only models".to_string(),
+                ));
+            }
+            if (cached_length < 0) | (cached_length >= input_ids_len) {
+                return Err(RustBertError::ValueError(format!(
+                    "The cached length ({}) must be non-negative and lower than the input length ({})",
+                    cached_length, input_ids_len
+                )));
+            }
+
+            let batch_size = *input_ids.size().first().unwrap();
+
+            let (effective_batch_size, effective_batch_mult) = match do_sample {
+                true => (batch_size * num_return_sequences, num_return_sequences),
+                false => (batch_size, 1),
+            };
+
+            let attention_mask = match attention_mask {
+                Some(value) => value,
+                None => match pad_token_id {
+                    Some(pad_id) => input_ids.ne(pad_id).to_kind(Int64),
+                    None => input_ids.ones_like().to_kind(Int64),
+                },
+            };
+
+            let encoder_outputs = if self.is_encoder_decoder() {
+                let encoder_outputs =
+                    self.encode(&input_ids, Some(&attention_mask))
+                        .ok_or_else(|| {
+                            RustBertError::ValueError(
+                                "Encoder-decoder models must return encoder outputs".to_string(),
+                            )
+                        })?;
+                let expanded_batch_indices =
+                    Tensor::arange(batch_size, (Int64, input_ids.device()))
+                        .view((-1, 1))
+                        .repeat(&[1, num_beams * effective_batch_mult])
+                        .view(-1);
+                Some(encoder_outputs.index_select(0, &expanded_batch_indices))
+            } else {
+                None
+            };
+
+            let cached_length = if (num_beams == 1) & (cached_length > 0) {
+                if effective_batch_mult > 1 {
+                    let expanded_batch_indices =
+                        Tensor::arange(batch_size, (Int64, input_ids.device()))
+                            .view((-1, 1))
+                            .repeat(&[1, effective_batch_mult])
+                            .view(-1);
+                    self.reorder_cache(&mut past, None, &expanded_batch_indices);
+                }
+                cached_length
+            } else {
+                0
+            };
+
+            let (input_ids, attention_mask) = if !self.is_encoder_decoder() {
+                if (num_return_sequences > 1) | (num_beams > 1) {
+                    (
+                        input_ids
+                            .unsqueeze(1)
+                            .expand(
+                                &[batch_size, effective_batch_mult * num_beams, cur_len],
+                                true,
+                            )
+                            .contiguous()
+                            .view((effective_batch_size * num_beams, cur_len)),
+                        attention_mask
+                            .unsqueeze(1)
+                            .expand(
+                                &[batch_size, effective_batch_mult * num_beams, cur_len],
+                                true,
+                            )
+                            .contiguous()
+                            .view((effective_batch_size * num_beams, cur_len)),
+                    )
+                } else {
+                    (input_ids, attention_mask)
+                }
+            } else {
+                let decoder_start_token_id = decoder_start_token_id
+                    .or_else(|| self.get_decoder_start_id())
+                    .ok_or_else(|| {
+                        RustBertError::ValueError(
+                            "decoder start id must be specified for encoder decoders".to_string(),
+                        )
+                    })?;
+                let input_ids = Tensor::full(
+                    &[effective_batch_size * num_beams, 1],
+                    decoder_start_token_id,
+                    (Int64, input_ids.device()),
+                );
+                let attention_mask = if (num_return_sequences > 1) | (num_beams > 1) {
+                    attention_mask
+                        .unsqueeze(1)
+                        .expand(
+                            &[batch_size, effective_batch_mult * num_beams, input_ids_len],
+                            true,
+                        )
+                        .contiguous()
+                        .view((effective_batch_size * num_beams, input_ids_len))
+                } else {
+                    attention_mask
+                };
+                (input_ids, attention_mask)
+            };
+
+            if token_callback.is_some() & (num_beams > 1) {
+                return Err(RustBertError::ValueError(
+                    "Token streaming is only supported for greedy and sampling decoding (num_beams = 1)"
+                        .to_string(),
+                ));
+            }
+
+            let generated_output_with_scores = no_grad(|| {
+                if num_beams > 1 {
+                    Ok(self.generate_beam_search(
+                        input_ids,
+                        encoder_outputs,
+                        cur_len,
+                        effective_batch_size,
+                        attention_mask,
+                        gen_opt,
+                        prefix_allowed_tokens_fn,
+                        output_scores,
+                    ))
+                } else {
+                    self.generate_no_beam_search(
+                        input_ids,
+                        encoder_outputs,
+                        cur_len,
+                        effective_batch_size,
+                        attention_mask,
+                        past,
+                        cached_length,
+                        gen_opt,
+                        prefix_allowed_tokens_fn,
+                        output_scores,
+                        token_callback,
+                    )
+                }
+            })?;
+            let (decoded, scores, mut token_scores, past) = (
+                generated_output_with_scores.indices,
+                generated_output_with_scores.scores,
+                generated_output_with_scores.token_scores,
+                generated_output_with_scores.past,
+            );
+            let num_sequences = *decoded.size().first().unwrap();
+            let mut output = Vec::with_capacity(num_sequences as usize);
+            for sequence_index in 0..num_sequences {
+                let indices = decoded
+                    .as_ref()
+                    .get(sequence_index)
+                    .iter::<i64>()
+                    .unwrap()
+                    .collect::<Vec<i64>>();
+                let score = scores
+                    .as_ref()
+                    .map(|scores_value| scores_value[sequence_index as usize]);
+
+                let token_scores = token_scores
+                    .as_mut()
+                    .map(|token_scores| std::mem::take(&mut token_scores[sequence_index as usize]));
+
+                let stop_string = stop_strings.and_then(|stop_strings| {
+                    self.get_stop_string(stop_strings, &indices[cur_len as usize..])
+                });
+
+                output.push(GeneratedIndicesOutput {
+                    indices,
+                    score,
+                    token_scores,
+                    stop_string,
+                });
+            }
+            Ok((output, past))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// # Generated text output
+/// Contains generated text and an optional log-likelihood score for the generated sequence
+pub struct GeneratedTextOutput {
+    pub text: String,
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+/// # Generated indices output
+/// Contains generated indices and an optional log-likelihood score for the generated sequence and individual tokens
+pub struct GeneratedIndicesOutput {
+    pub indices: Vec<i64>,
+    pub score: Option<f64>,
+    pub token_scores: Option<Vec<f64>>,
+    /// First stop string found in the generated text, if any (trimmed from the decoded text output)
+    pub stop_string: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+/// # Generated token
+/// Token streamed to the callback of the `*_stream` generation methods as soon as it is generated
+pub struct GeneratedToken {
+    /// Index of the sequence in the generation output (*number_of_prompts* x *num_return_sequences*)
+    pub sequence_index: usize,
+    /// Generated token id
+    pub token_id: i64,
+    /// Text added to the sequence by this token. This may be empty if the token does not complete a valid
+    /// UTF-8 character (the pending text is then included in a following token), is a special token or may be the
+    /// beginning of a stop string (the text is then held back until the stop string is ruled out). The text held back
+    /// when the generation ends is streamed with the last token of the sequence, and the text from a stop string on is
+    /// not streamed: the streamed texts add up to the generated part of the text output.
+    pub text: String,
+}
+
+/// Generated tokens and decoding state of a streamed sequence
+#[derive(Clone, Default)]
+struct StreamedSequence {
+    tokens: Vec<i64>,
+    /// Start of the window of tokens decoded to get the text of the new tokens (the decoding of a
+    /// token may depend on the previous tokens)
+    prefix_offset: usize,
+    /// Number of tokens whose text has been streamed (or held back)
+    read_offset: usize,
+    /// Text held back as it may be the beginning of a stop string
+    held_text: String,
+    stopped: bool,
+}
+
+impl StreamedSequence {
+    /// Decodes the text added by the last tokens from a sliding window, returning an empty text if
+    /// the tokens do not complete a valid UTF-8 character yet
+    fn next_text(&mut self, tokenizer: &TokenizerOption) -> String {
+        let prefix_text = tokenizer.decode(
+            &self.tokens[self.prefix_offset..self.read_offset],
+            true,
+            true,
+        );
+        let text = tokenizer.decode(&self.tokens[self.prefix_offset..], true, true);
+        if (text.len() > prefix_text.len())
+            && !text.ends_with('\u{FFFD}')
+            && text.is_char_boundary(prefix_text.len())
+        {
+            self.prefix_offset = self.read_offset;
+            self.read_offset = self.tokens.len();
+            text[prefix_text.len()..].to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Returns the new text that can be streamed, holding back its end if it may be the beginning
+    /// of a stop string and dropping the text from the first stop string found on
+    fn release_text(&mut self, new_text: &str, stop_strings: &[&str]) -> String {
+        if self.stopped {
+            return String::new();
+        }
+        self.held_text.push_str(new_text);
+        if let Some(stop_position) = stop_strings
+            .iter()
+            .filter_map(|stop_string| self.held_text.find(stop_string))
+            .min()
+        {
+            self.stopped = true;
+            self.held_text.truncate(stop_position);
+            return std::mem::take(&mut self.held_text);
+        }
+        let held_start = self
+            .held_text
+            .char_indices()
+            .map(|(position, _)| position)
+            .find(|&position| {
+                stop_strings
+                    .iter()
+                    .any(|stop_string| stop_string.starts_with(&self.held_text[position..]))
+            })
+            .unwrap_or(self.held_text.len());
+        let held_text = self.held_text.split_off(held_start);
+        std::mem::replace(&mut self.held_text, held_text)
+    }
+}
+
+/// Accumulates the tokens generated for each sequence and streams the text added by each new token
+struct TokenStreamer<'a, F: FnMut(&GeneratedToken)> {
+    tokenizer: &'a TokenizerOption,
+    stop_strings: &'a [&'a str],
+    sequences: Vec<StreamedSequence>,
+    token_callback: F,
+}
+
+impl<'a, F: FnMut(&GeneratedToken)> TokenStreamer<'a, F> {
+    fn new(
+        tokenizer: &'a TokenizerOption,
+        stop_strings: Option<&'a [&'a str]>,
+        token_callback: F,
+    ) -> Self {
+        TokenStreamer {
+            tokenizer,
+            stop_strings: stop_strings.unwrap_or_default(),
+            sequences: Vec::new(),
+            token_callback,
+        }
+    }
+
+    fn push(&mut self, step_tokens: &[Option<i64>]) {
+        if self.sequences.len() < step_tokens.len() {
+            self.sequences
+                .resize(step_tokens.len(), StreamedSequence::default());
+        }
+        for (sequence_index, token_id) in step_tokens.iter().enumerate() {
+            if let Some(token_id) = *token_id {
+                let sequence = &mut self.sequences[sequence_index];
+                sequence.tokens.push(token_id);
+                let new_text = sequence.next_text(self.tokenizer);
+                let text = sequence.release_text(&new_text, self.stop_strings);
+                (self.token_callback)(&GeneratedToken {
+                    sequence_index,
+                    token_id,
+                    text,
+                });
+            }
+        }
+    }
+
+    /// Streams the text held back at the end of the generation with the last token of each sequence
+    fn finish(&mut self) {
+        for (sequence_index, sequence) in self.sequences.iter_mut().enumerate() {
+            if let (Some(token_id), false) = (sequence.tokens.last(), sequence.held_text.is_empty())
+            {
+                (self.token_callback)(&GeneratedToken {
+                    sequence_index,
+                    token_id: *token_id,
+                    text: std::mem::take(&mut sequence.held_text),
+                });
+            }
+        }
+    }
+}
+
+/// # Stopping criteria for text generation
+/// Custom condition ending the generation of a sequence, evaluated after every generation step in addition to the
+/// `eos` tokens, maximum length, stop strings and stop token ids. It is implemented for closures with the same
+/// signature as `should_stop`.
+///
+/// ```no_run
+/// use rust_bert::pipelines::common::TokenizerOption;
+/// use rust_bert::pipelines::generation_utils::GenerateOptions;
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let time_budget =
+///     |_sequence_index: usize, _generated_ids: &[i64], _tokenizer: &TokenizerOption| {
+///         start.elapsed() > Duration::from_secs(2)
+///     };
+/// let generate_options = GenerateOptions {
+///     stopping_criteria: Some(&time_budget),
+///     ..Default::default()
+/// };
+/// ```
+pub trait StoppingCriteria {
+    /// Returns `true` if the generation of a sequence should stop
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence_index` - `usize` Index of the sequence in the batch (index of the input for beam search)
+    /// * `generated_ids` - `&[i64]` Tokens generated so far for the sequence (excluding the prompt)
+    /// * `tokenizer` - `&TokenizerOption` Tokenizer of the generator, allowing to decode the generated tokens
+    fn should_stop(
+        &self,
+        sequence_index: usize,
+        generated_ids: &[i64],
+        tokenizer: &TokenizerOption,
+    ) -> bool;
+}
+
+impl<F> StoppingCriteria for F
+where
+    F: Fn(usize, &[i64], &TokenizerOption) -> bool,
+{
+    fn should_stop(
+        &self,
+        sequence_index: usize,
+        generated_ids: &[i64],
+        tokenizer: &TokenizerOption,
+    ) -> bool {
+        self(sequence_index, generated_ids, tokenizer)
+    }
+}
+
+pub type PrefixAllowedFunction<'a> = &'a dyn Fn(i64, &Tensor) -> Vec<i64>;
+/// Type alias for a function defining allowed tokens based on current tokens generated.
+/// This function should take a `batch_id` and associated tensor of already generated tokens and
+/// should return a vector of allowed tokens. This is useful for controlled generation, i.e.
+/// deterministic generation of a token continuation if a sequence of token occurs.
+
+#[derive(Clone, Copy, Default)]
+/// # Generation options for text generation.
+/// When provided to a `generate` method, these options will take priority over the `GenerateConfig` used to create the
+/// `LanguageGenerator`. Some of these options may be left as `None`, options without a value will individually default
+/// to the `GenerateConfig`.
+pub struct GenerateOptions<'a> {
+    /// Minimum sequence length
+    pub min_length: Option<i64>,
+    /// Maximum sequence length
+    pub max_length: Option<i64>,
+    /// Maximum number of new tokens to generate (useful for causal generation models).
+    /// Only one of `max_length` and `max_new_tokens` should be provided.
+    /// When both are given, `max_new_tokens` is ignored and the `max_length` setting is used.
+    pub max_new_tokens: Option<i64>,
+    /// Early stopping flag indicating if the beam search should stop as soon as `num_beam` hypotheses have been generated
+    pub early_stopping: Option<bool>,
+    /// Number of sequences to return for each prompt text
+    pub num_return_sequences: Option<i64>,
+    /// Number of beams for beam search
+    pub num_beams: Option<i64>,
+    pub num_beam_groups: Option<i64>,
+    /// Sampling flag. If true, will perform top-k and/or nucleus sampling on generated tokens, otherwise greedy (deterministic) decoding
+    pub do_sample: Option<bool>,
+    /// Temperature setting. Values higher than 1 will improve originality at the risk of reducing relevance
+    pub temperature: Option<f64>,
+    /// Top_k values for sampling tokens. Value higher than 0 will enable the feature
+    pub top_k: Option<i64>,
+    /// Top_p value for [Nucleus sampling, Holtzman et al.](http://arxiv.org/abs/1904.09751). Keep top tokens until cumulative probability reaches top_p
+    pub top_p: Option<f64>,
+    /// Repetition penalty (mostly useful for CTRL decoders). Values higher than 1 will penalize tokens that have been already generated.
+    pub repetition_penalty: Option<f64>,
+    /// Exponential penalty based on the length of the hypotheses generated
+    pub length_penalty: Option<f64>,
+    /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature
+    pub no_repeat_ngram_size: Option<i64>,
+    /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups
+    pub diversity_penalty: Option<f64>,
+    /// Typical sampling mass, following [Meister et al.](https://arxiv.org/abs/2202.00666). If provided and lower than 1, keeps the most typical tokens until their cumulative probability reaches typical_p (sampling only)
+    pub typical_p: Option<f64>,
+    /// Epsilon sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than epsilon_cutoff (sampling only)
+    pub epsilon_cutoff: Option<f64>,
+    /// Eta sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than min(eta_cutoff, sqrt(eta_cutoff) * exp(-entropy)) (sampling only)
+    pub eta_cutoff: Option<f64>,
+    /// Min-p sampling. If provided and higher than 0, removes the tokens with a probability lower than min_p times the probability of the most likely token (sampling only)
+    pub min_p: Option<f64>,
+    /// Degeneration penalty for [contrastive search, Su et al.](https://arxiv.org/abs/2202.06417). If provided and higher than 0, greedy decoding with top_k higher than 1 uses contrastive search
+    pub penalty_alpha: Option<f64>,
+    /// Decoder start token id
+    pub decoder_start_token_id: Option<i64>,
+    /// Forced first token generated
+    pub forced_bos_token_id: Option<i64>,
+    /// Function to control the generation process. The function should take a `batch_id` (i64) and a tensor of token_ids already generated and returns a `Vec<i64>` of allowed tokens.
+    pub prefix_allowed_tokens_fn: Option<PrefixAllowedFunction<'a>>,
+    /// List of bad word ids (may be a sequence of word ids) that will be banned during the generation
+    pub bad_word_ids: Option<&'a Vec<Vec<i64>>>,
+    /// List of token id sequences ending the generation of a sequence when generated
+    pub stop_token_ids: Option<&'a Vec<Vec<i64>>>,
+    /// List of strings ending the generation of a sequence when found in the generated text. The stop string and the
+    /// text following it are trimmed from the text output.
+    pub stop_strings: Option<&'a [&'a str]>,
+    /// Custom criteria ending the generation of a sequence
+    pub stopping_criteria: Option<&'a dyn StoppingCriteria>,
+    /// Custom logits processors, applied in order after the built-in processors (repetition penalty, banned words and
+    /// n-grams, prefix constraints, minimum length, logit bias)
+    pub logits_processors: Option<&'a [&'a dyn LogitsProcessor]>,
+    /// Custom logits warpers, applied in order after the built-in warpers (temperature, top-k, top-p, typical, epsilon,
+    /// eta and min-p) when sampling
+    pub logits_warpers: Option<&'a [&'a dyn LogitsWarper]>,
+    /// Bias added to the scores of token ids. Positive values make the tokens more likely, negative values less likely
+    pub logit_bias: Option<&'a HashMap<i64, f64>>,
+    /// Constraints (phrases or disjunctive sets of phrases) that must appear in the generated output. Requires beam
+    /// search (`num_beams` > 1) without sampling or beam groups.
+    pub constraints: Option<&'a [Constraint]>,
+    /// Draft model for speculative decoding, proposing tokens verified by the model in a single forward pass. Requires
+    /// a decoder-only model with greedy decoding or sampling (`num_beams` = 1).
+    pub draft_model: Option<&'a DraftModel<'a>>,
+    /// Seed of the random number generator used for sampling. If provided, the tokens are sampled with a generator
+    /// owned by the generation call: the same seed and inputs give the same output, independently of the global `tch`
+    /// random number generator and of concurrent generations. The random numbers are computed with integer tensor
+    /// operations on the model device (about a dozen element-wise operations over the vocabulary for each sampling
+    /// draw), which makes a seeded sampling step slightly slower than an unseeded one
+    pub seed: Option<u64>,
+    /// Per-row generation options, one per input. Overrides the sampling temperature, top-k, top-p, maximum number of
+    /// new tokens and banned token sequences of each input, allowing requests with different settings to share the
+    /// same forward passes. Requires greedy decoding or sampling (`num_beams` = 1).
+    pub row_options: Option<&'a [RowGenerateOptions<'a>]>,
+    /// Flag indicating if text generation scores should be returned
+    pub output_scores: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+/// # Per-row generation options
+/// Generation options of a single input within a batch, set with `GenerateOptions::row_options`. Options left as `None`
+/// default to the `GenerateOptions` of the call, and then to the `GenerateConfig`. The banned token sequences are
+/// applied in addition to the `bad_word_ids` of the call.
+pub struct RowGenerateOptions<'a> {
+    /// Sampling temperature (strictly positive). As for `GenerateConfig::temperature`, only values higher than 1 are applied
+    pub temperature: Option<f64>,
+    /// Top_k values for sampling tokens. Value higher than 0 will enable the feature
+    pub top_k: Option<i64>,
+    /// Top_p value for [Nucleus sampling, Holtzman et al.](http://arxiv.org/abs/1904.09751). Keep top tokens until cumulative probability reaches top_p
+    pub top_p: Option<f64>,
+    /// Maximum number of new tokens generated for this input
+    pub max_new_tokens: Option<i64>,
+    /// List of token sequences banned from the generation of this input
+    pub bad_word_ids: Option<&'a Vec<Vec<i64>>>,
+    /// Seed of the random number generator used to sample this input. The input (and its return sequences, sharing the
+    /// generator) is then sampled as in a separate call with this seed. If set for any row, rows without a seed use the
+    /// seed of the call (which must then be provided)
+    pub seed: Option<u64>,
+}
+
+/// Decodes the generated sequences, trimming the text from the first occurrence of the stop string
+/// the generation stopped on. The stop string is searched after the prompt (`prompt_length` tokens).
+fn decode_text_outputs(
+    tokenizer: &TokenizerOption,
+    indices_outputs: Vec<GeneratedIndicesOutput>,
+    prompt_length: usize,
+) -> Vec<GeneratedTextOutput> {
+    let mut output = Vec::with_capacity(indices_outputs.len());
+    for generated_sequence in indices_outputs {
+        let mut text = tokenizer.decode(&generated_sequence.indices, true, true);
+        if let Some(stop_string) = &generated_sequence.stop_string {
+            let prompt_ids =
+                &generated_sequence.indices[..prompt_length.min(generated_sequence.indices.len())];
+            let prompt_text = tokenizer.decode(prompt_ids, true, true);
+            let generated_start = if text.starts_with(&prompt_text) {
+                prompt_text.len()
+            } else {
+                0
+            };
+            if let Some(position) = text[generated_start..].find(stop_string.as_str()) {
+                text.truncate(generated_start + position);
+            }
+        }
+        output.push(GeneratedTextOutput {
+            text,
+            score: generated_sequence.score,
+        });
+    }
+    output
+}
+
+/// # Common trait for text generation models.
+/// Main API for text generation
+pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
+    PrivateLanguageGenerator<T, V, U>
+{
+    /// Generate text based on a vector of promp texts.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. An empty prompt to the model may be passed if the model implement a `bos_id`.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    ///
+    /// # Returns
+    /// * `Result<Vec<TextOutput>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing TextOutput with the generated texts and the generation score if `output_scores` is true.
+    ///   A `RustBertError::ValueError` is returned if the generation options (merged with the generator configuration) are invalid.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tch::Device;
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::{
+    ///     GenerateConfig, GenerateOptions, LanguageGenerator,
+    /// };
+    /// use tch::Tensor;
+    /// # let mut home: PathBuf = dirs::home_dir().unwrap();
+    /// # home.push("rustbert");
+    /// # home.push("gpt2");
+    /// # let config_path = &home.as_path().join("config.json");
+    /// # let vocab_path = &home.as_path().join("vocab.txt");
+    /// # let merges_path = &home.as_path().join("merges.txt");
+    /// # let weights_path = &home.as_path().join("model.ot");
+    /// let device = Device::cuda_if_available();
+    /// let generate_config = GenerateConfig {
+    ///     max_length: Some(30),
+    ///     do_sample: true,
+    ///     num_beams: 5,
+    ///     temperature: 1.1,
+    ///     num_return_sequences: 3,
+    ///     ..Default::default()
+    /// };
+    /// let gpt2_generator = GPT2Generator::new(generate_config)?;
+    /// let input_context = "The dog";
+    /// let second_input_context = "The cat was";
+    ///
+    /// //Example custom function for fine-grained generation control
+    /// fn force_one_paragraph(_batch_id: i64, previous_token_ids: &Tensor) -> Vec<i64> {
+    ///     let paragraph_tokens = [198, 628];
+    ///
+    ///     for paragraph_token in paragraph_tokens.iter() {
+    ///         if previous_token_ids
+    ///             .iter::<i64>()
+    ///             .unwrap()
+    ///             .collect::<Vec<i64>>()
+    ///             .contains(paragraph_token)
+    ///         {
+    ///             return vec![50256];
+    ///         }
+    ///     }
+    ///     (0..50255).collect()
+    /// }
+    ///
+    /// let generate_options = GenerateOptions {
+    ///     min_length: Some(32),
+    ///     max_length: Some(128),
+    ///     output_scores: true,
+    ///     prefix_allowed_tokens_fn: Some(&force_one_paragraph),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let output = gpt2_generator.generate(
+    ///     Some(&[input_context, second_input_context]),
+    ///     Some(generate_options),
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// Example output: \
+    /// ```no_run
+    /// # let output =
+    /// [
+    ///     "The dog's owners, however, did not want to be named. According to the lawsuit, the animal's owner, a 29-year",
+    ///     "The dog has always been part of the family. \"He was always going to be my dog and he was always looking out for me",
+    ///     "The dog has been able to stay in the home for more than three months now. \"It's a very good dog. She's",
+    ///     "The cat was discovered earlier this month in the home of a relative of the deceased. The cat\'s owner, who wished to remain anonymous,",
+    ///     "The cat was pulled from the street by two-year-old Jazmine.\"I didn't know what to do,\" she said",
+    ///     "The cat was attacked by two stray dogs and was taken to a hospital. Two other cats were also injured in the attack and are being treated."
+    /// ]
+    /// # ;
+    /// ```
+    fn generate<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<GeneratedTextOutput>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        match self.prepare_prompt_input_ids(prompt_texts, generate_options)? {
+            Some(input_ids) => {
+                let prompt_length = self.get_prompt_length(&input_ids);
+                let indices_outputs = self.generate_from_ids_and_past_internal(
+                    input_ids,
+                    None,
+                    generate_options,
+                    None,
+                )?;
+                Ok(decode_text_outputs(
+                    self._get_tokenizer(),
+                    indices_outputs,
+                    prompt_length,
+                ))
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Generate token indices without decoding (useful for token-level operations before returning final text or as validation step during training).
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. An empty prompt to the model may be passed if the model implement a `bos_id`.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    ///
+    /// # Returns
+    /// * `Result<Vec<IndicesOutput>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing IndicesOutput with the generated indices and the generation score if `output_scores` is true.
+    ///   A `RustBertError::ValueError` is returned if the generation options (merged with the generator configuration) are invalid.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tch::Device;
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::{
+    ///     GenerateConfig, GenerateOptions, LanguageGenerator,
+    /// };
+    /// use tch::Tensor;
+    /// # let mut home: PathBuf = dirs::home_dir().unwrap();
+    /// # home.push("rustbert");
+    /// # home.push("gpt2");
+    /// # let config_path = &home.as_path().join("config.json");
+    /// # let vocab_path = &home.as_path().join("vocab.txt");
+    /// # let merges_path = &home.as_path().join("merges.txt");
+    /// # let weights_path = &home.as_path().join("model.ot");
+    /// let device = Device::cuda_if_available();
+    /// let generate_config = GenerateConfig {
+    ///     max_length: Some(30),
+    ///     do_sample: true,
+    ///     num_beams: 5,
+    ///     temperature: 1.1,
+    ///     num_return_sequences: 3,
+    ///     ..Default::default()
+    /// };
+    /// let gpt2_generator = GPT2Generator::new(generate_config)?;
+    /// let input_context = "The dog";
+    /// let second_input_context = "The cat was";
+    ///
+    /// //Example custom function for fine-grained generation control
+    /// fn force_one_paragraph(_batch_id: i64, previous_token_ids: &Tensor) -> Vec<i64> {
+    ///     let paragraph_tokens = [198, 628];
+    ///
+    ///     for paragraph_token in paragraph_tokens.iter() {
+    ///         if previous_token_ids
+    ///             .iter::<i64>()
+    ///             .unwrap()
+    ///             .collect::<Vec<i64>>()
+    ///             .contains(paragraph_token)
+    ///         {
+    ///             return vec![50256];
+    ///         }
+    ///     }
+    ///     (0..50255).collect()
+    /// }
+    ///
+    /// let generate_options = GenerateOptions {
+    ///     min_length: Some(32),
+    ///     max_length: Some(128),
+    ///     output_scores: true,
+    ///     prefix_allowed_tokens_fn: Some(&force_one_paragraph),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let output = gpt2_generator.generate_indices(
+    ///     Some(&[input_context, second_input_context]),
+    ///     Some(generate_options),
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_indices<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        match self.prepare_prompt_input_ids(prompt_texts, generate_options)? {
+            Some(input_ids) => {
+                self.generate_from_ids_and_past_internal(input_ids, None, generate_options, None)
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Generate token indices given a list of indices (useful when the input has been pre-tokenized).
+    /// Returns a list of output tokens that need to be decoded using a tokenizer.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - `Tensor` pre-tokenized and encoded input for generation.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    ///
+    /// # Returns
+    /// * `Result<Vec<IndicesOutput>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing IndicesOutput with the generated indices and the generation score if `output_scores` is true.
+    ///   A `RustBertError::ValueError` is returned if the generation options (merged with the generator configuration) are invalid.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tch::Device;
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::{
+    ///     GenerateConfig, GenerateOptions, LanguageGenerator,
+    /// };
+    /// use tch::{Kind, Tensor};
+    /// # let mut home: PathBuf = dirs::home_dir().unwrap();
+    /// # home.push("rustbert");
+    /// # home.push("gpt2");
+    /// # let config_path = &home.as_path().join("config.json");
+    /// # let vocab_path = &home.as_path().join("vocab.txt");
+    /// # let merges_path = &home.as_path().join("merges.txt");
+    /// # let weights_path = &home.as_path().join("model.ot");
+    /// let device = Device::cuda_if_available();
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let input_tensor = Tensor::randn(&[32, 128], (Kind::Int64, Device::Cpu));
+    /// let input_mask = Tensor::ones(&[32, 128], (Kind::Int64, Device::Cpu));
+    ///
+    /// let generate_options = GenerateOptions {
+    ///     min_length: Some(32),
+    ///     max_length: Some(128),
+    ///     output_scores: true,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let output = gpt2_generator.generate_from_ids_and_past(
+    ///     input_tensor,
+    ///     Some(input_mask),
+    ///     Some(generate_options),
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_from_ids_and_past(
+        &self,
+        input_ids: Tensor,
+        attention_mask: Option<Tensor>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError> {
+        self.generate_from_ids_and_past_internal(input_ids, attention_mask, generate_options, None)
+    }
+
+    /// Generate text based on a vector of prompt texts, streaming the generated tokens to a callback as soon as they
+    /// are generated. Streaming is supported for greedy and sampling decoding (`num_beams` = 1).
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. An empty prompt to the model may be passed if the model implement a `bos_id`.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    /// * `token_callback` - `FnMut(&GeneratedToken)` Callback called for every generated token with the index of the sequence it belongs to and the text it adds to the sequence
+    ///
+    /// # Returns
+    /// * `Result<Vec<TextOutput>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing TextOutput with the generated texts and the generation score if `output_scores` is true.
+    ///   A `RustBertError::ValueError` is returned if the generation options are invalid or if beam search is requested.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
+    /// use std::io::Write;
+    ///
+    /// let generate_config = GenerateConfig {
+    ///     max_length: Some(30),
+    ///     num_beams: 1,
+    ///     ..Default::default()
+    /// };
+    /// let gpt2_generator = GPT2Generator::new(generate_config)?;
+    ///
+    /// let output = gpt2_generator.generate_stream(Some(&["The dog"]), None, |token| {
+    ///     print!("{}", token.text);
+    ///     std::io::stdout().flush().unwrap();
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_stream<S, F>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+        token_callback: F,
+    ) -> Result<Vec<GeneratedTextOutput>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+        F: FnMut(&GeneratedToken),
+    {
+        match self.prepare_prompt_input_ids(prompt_texts, generate_options)? {
+            Some(input_ids) => {
+                let prompt_length = self.get_prompt_length(&input_ids);
+                let indices_outputs = self.generate_from_ids_and_past_stream(
+                    input_ids,
+                    None,
+                    generate_options,
+                    token_callback,
+                )?;
+                Ok(decode_text_outputs(
+                    self._get_tokenizer(),
+                    indices_outputs,
+                    prompt_length,
+                ))
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Generate token indices based on a vector of prompt texts, streaming the generated tokens to a callback as soon
+    /// as they are generated. Streaming is supported for greedy and sampling decoding (`num_beams` = 1).
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. An empty prompt to the model may be passed if the model implement a `bos_id`.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    /// * `token_callback` - `FnMut(&GeneratedToken)` Callback called for every generated token with the index of the sequence it belongs to and the text it adds to the sequence
+    ///
+    /// # Returns
+    /// * `Result<Vec<IndicesOutput>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing IndicesOutput with the generated indices and the generation score if `output_scores` is true.
+    ///   A `RustBertError::ValueError` is returned if the generation options are invalid or if beam search is requested.
+    fn generate_indices_stream<S, F>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+        token_callback: F,
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+        F: FnMut(&GeneratedToken),
+    {
+        match self.prepare_prompt_input_ids(prompt_texts, generate_options)? {
+            Some(input_ids) => self.generate_from_ids_and_past_stream(
+                input_ids,
+                None,
+                generate_options,
+                token_callback,
+            ),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Generate token indices given a list of indices, streaming the generated tokens to a callback as soon as they
+    /// are generated. Streaming is supported for greedy and sampling decoding (`num_beams` = 1).
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - `Tensor` pre-tokenized and encoded input for generation.
+    /// * `attention_mask` - `Option<Tensor>` Optional attention mask for the input.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    /// * `token_callback` - `FnMut(&GeneratedToken)` Callback called for every generated token with the index of the sequence it belongs to and the text it adds to the sequence
+    ///
+    /// # Returns
+    /// * `Result<Vec<IndicesOutput>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing IndicesOutput with the generated indices and the generation score if `output_scores` is true.
+    ///   A `RustBertError::ValueError` is returned if the generation options are invalid or if beam search is requested.
+    fn generate_from_ids_and_past_stream<F>(
+        &self,
+        input_ids: Tensor,
+        attention_mask: Option<Tensor>,
+        generate_options: Option<GenerateOptions>,
+        token_callback: F,
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError>
+    where
+        F: FnMut(&GeneratedToken),
+    {
+        let mut token_streamer = TokenStreamer::new(
+            self._get_tokenizer(),
+            generate_options.and_then(|options| options.stop_strings),
+            token_callback,
+        );
+        let output = {
+            let mut step_callback = |step_tokens: &[Option<i64>]| token_streamer.push(step_tokens);
+            self.generate_from_ids_and_past_internal(
+                input_ids,
+                attention_mask,
+                generate_options,
+                Some(&mut step_callback),
+            )?
+        };
+        token_streamer.finish();
+        Ok(output)
+    }
+
+    /// Generate token indices given a list of indices and a cache holding the model states of their leading positions
+    /// (e.g. returned by a previous generation), returning the cache of the generated sequences along with the
+    /// generated indices. This allows resuming the generation of a sequence without recomputing the states of its
+    /// prefix. The cache is used for greedy decoding and sampling (`num_beams` = 1) of decoder-only models, beam search
+    /// and contrastive search recompute the states of all positions.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - `Tensor` pre-tokenized and encoded input for generation.
+    /// * `attention_mask` - `Option<Tensor>` Optional attention mask for the input.
+    /// * `past` - `Cache` Cache of the model states for the first `cached_length` positions of `input_ids`
+    /// * `cached_length` - `i64` Number of positions of `input_ids` held by the cache (0 if `past` is `Cache::None`)
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    ///
+    /// # Returns
+    /// * `Result<(Vec<IndicesOutput>, Cache), RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing IndicesOutput with the generated indices and the generation score if `output_scores` is true,
+    ///   and the cache of the leading positions of the generated sequences (`Cache::None` for beam search). The last generated token of each sequence may not be part of the cache.
+    ///   A `RustBertError::ValueError` is returned if the generation options are invalid or if a cache is provided for an encoder-decoder model.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::{Cache, GenerateConfig, LanguageGenerator};
+    /// use tch::Tensor;
+    ///
+    /// let generate_config = GenerateConfig {
+    ///     max_length: None,
+    ///     do_sample: false,
+    ///     num_beams: 1,
+    ///     ..Default::default()
+    /// };
+    /// let gpt2_generator = GPT2Generator::new(generate_config)?;
+    /// let input_ids = Tensor::of_slice(&[464i64, 3290]).unsqueeze(0);
+    ///
+    /// let (output, cache) =
+    ///     gpt2_generator.generate_from_ids_and_cache(input_ids, None, Cache::None, 0, None)?;
+    /// // The generation may be resumed from the cache of the generated sequence
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_from_ids_and_cache(
+        &self,
+        input_ids: Tensor,
+        attention_mask: Option<Tensor>,
+        past: Cache,
+        cached_length: i64,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<(Vec<GeneratedIndicesOutput>, Cache), RustBertError> {
+        self.generate_from_ids_and_cache_internal(
+            input_ids,
+            attention_mask,
+            past,
+            cached_length,
+            generate_options,
+            None,
+        )
+    }
+
+    /// Generate token indices given a list of indices and a cache holding the model states of their leading positions,
+    /// streaming the generated tokens to a callback as soon as they are generated (see `generate_from_ids_and_cache`).
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - `Tensor` pre-tokenized and encoded input for generation.
+    /// * `attention_mask` - `Option<Tensor>` Optional attention mask for the input.
+    /// * `past` - `Cache` Cache of the model states for the first `cached_length` positions of `input_ids`
+    /// * `cached_length` - `i64` Number of positions of `input_ids` held by the cache (0 if `past` is `Cache::None`)
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    /// * `token_callback` - `FnMut(&GeneratedToken)` Callback called for every generated token with the index of the sequence it belongs to and the text it adds to the sequence
+    ///
+    /// # Returns
+    /// * `Result<(Vec<IndicesOutput>, Cache), RustBertError>` Generated indices and cache of the leading positions of the generated sequences.
+    ///   A `RustBertError::ValueError` is returned if the generation options are invalid or if beam search is requested.
+    fn generate_from_ids_and_cache_stream<F>(
+        &self,
+        input_ids: Tensor,
+        attention_mask: Option<Tensor>,
+        past: Cache,
+        cached_length: i64,
+        generate_options: Option<GenerateOptions>,
+        token_callback: F,
+    ) -> Result<(Vec<GeneratedIndicesOutput>, Cache), RustBertError>
+    where
+        F: FnMut(&GeneratedToken),
+    {
+        let mut token_streamer = TokenStreamer::new(
+            self._get_tokenizer(),
+            generate_options.and_then(|options| options.stop_strings),
+            token_callback,
+        );
+        let output = {
+            let mut step_callback = |step_tokens: &[Option<i64>]| token_streamer.push(step_tokens);
+            self.generate_from_ids_and_cache_internal(
+                input_ids,
+                attention_mask,
+                past,
+                cached_length,
+                generate_options,
+                Some(&mut step_callback),
+            )?
+        };
+        token_streamer.finish();
+        Ok(output)
+    }
+
+    /// Score texts with a decoder-only language model
+    ///
+    /// Computes the log-probability of each token of the texts given the previous tokens, and the
+    /// perplexity of the texts. The beginning of sequence token of the model (if any) is prepended to
+    /// the texts so that their first token is scored as well. Texts longer than the maximum length are
+    /// scored with a sliding window: the windows are batched together and each token is scored once.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - `&[S]` Texts to score
+    /// * `options` - `&ScoringOptions` Batch size and sliding window settings
+    ///
+    /// # Returns
+    /// * `Result<Vec<TextScore>, RustBertError>` Vector of length *number_of_texts* containing the token log-probabilities, log-likelihood and perplexity of each text.
+    ///   A `RustBertError::ValueError` is returned for encoder-decoder models, Reformer models (not supported) or invalid options.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::LanguageGenerator;
+    /// use rust_bert::pipelines::scoring::ScoringOptions;
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let scoring_options = ScoringOptions {
+    ///     batch_size: 4,
+    ///     max_length: Some(512),
+    ///     stride: Some(256),
+    /// };
+    /// let output = gpt2_generator.score(&["The cat sat on the mat."], &scoring_options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn score<S>(
+        &self,
+        texts: &[S],
+        options: &ScoringOptions,
+    ) -> Result<Vec<TextScore>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if self.is_encoder_decoder() {
+            return Err(RustBertError::ValueError(
+                "Text scoring requires a decoder-only model".to_string(),
+            ));
+        }
+        if options.batch_size == 0 {
+            return Err(RustBertError::ValueError(
+                "The scoring batch size must be greater than 0".to_string(),
+            ));
+        }
+        let max_length = options
+            .max_length
+            .unwrap_or(self.get_max_positions_embeddings().max(0) as usize);
+        if max_length < 2 {
+            return Err(RustBertError::ValueError(format!(
+                "The scoring max_length must be at least 2, got {}",
+                max_length
+            )));
+        }
+        let stride = options.stride.unwrap_or(max_length / 2);
+        if stride == 0 || stride >= max_length {
+            return Err(RustBertError::ValueError(format!(
+                "The scoring stride must be between 1 and max_length - 1 ({}), got {}",
+                max_length - 1,
+                stride
+            )));
+        }
+
+        let tokenizer = self._get_tokenizer();
+        let sequences = tokenizer
+            .tokenize_list(texts)
+            .into_iter()
+            .map(|tokens| {
+                let mut token_ids = Vec::with_capacity(tokens.len() + 1);
+                if let Some(bos_id) = self.get_bos_id() {
+                    token_ids.push(bos_id);
+                }
+                token_ids.extend(tokenizer.convert_tokens_to_ids(&tokens));
+                token_ids
+            })
+            .collect::<Vec<Vec<i64>>>();
+
+        // Sliding windows (sequence index, start, end, first scored position). The positions of a
+        // window scored by the previous window are used as context only.
+        let mut windows = vec![];
+        for (sequence_index, token_ids) in sequences.iter().enumerate() {
+            let mut start = 0usize;
+            let mut scored_end = 1;
+            while scored_end < token_ids.len() {
+                let end = start.saturating_add(max_length).min(token_ids.len());
+                windows.push((sequence_index, start, end, scored_end));
+                scored_end = end;
+                start += stride;
+            }
+        }
+
+        let device = self.get_var_store().device();
+        let pad_id = self.get_pad_id().unwrap_or(0);
+        let mut token_log_probabilities = sequences
+            .iter()
+            .map(|token_ids| vec![None; token_ids.len()])
+            .collect::<Vec<Vec<Option<f64>>>>();
+        for batch in windows.chunks(options.batch_size) {
+            let batch_length = batch
+                .iter()
+                .map(|(_, start, end, _)| end - start)
+                .max()
+                .unwrap();
+            let mut input_ids = Vec::with_capacity(batch.len() * batch_length);
+            let mut attention_mask = Vec::with_capacity(batch.len() * batch_length);
+            for (sequence_index, start, end, _) in batch {
+                let padding_length = batch_length - (end - start);
+                input_ids.extend_from_slice(&sequences[*sequence_index][*start..*end]);
+                input_ids.extend(vec![pad_id; padding_length]);
+                attention_mask.extend(vec![1i64; end - start]);
+                attention_mask.extend(vec![0i64; padding_length]);
+            }
+            let shape = [batch.len() as i64, batch_length as i64];
+            let input_ids = Tensor::of_slice(&input_ids).view(shape).to(device);
+            let attention_mask = Tensor::of_slice(&attention_mask).view(shape).to(device);
+            let batch_log_probabilities =
+                no_grad(|| self.token_log_probabilities(&input_ids, &attention_mask))?
+                    .to(Device::Cpu);
+            for (row, (sequence_index, start, end, scored_start)) in batch.iter().enumerate() {
+                let row_log_probabilities = batch_log_probabilities
+                    .get(row as i64)
+                    .iter::<f64>()?
+                    .collect::<Vec<f64>>();
+                // The log-probability of the token at `position` is stored at `position - 1`
+                for position in *scored_start..*end {
+                    token_log_probabilities[*sequence_index][position] =
+                        Some(row_log_probabilities[position - start - 1]);
+                }
+            }
+        }
+
+        Ok(sequences
+            .into_iter()
+            .zip(token_log_probabilities)
+            .map(|(token_ids, token_log_probabilities)| {
+                TextScore::new(token_ids, token_log_probabilities)
+            })
+            .collect())
+    }
+
+    /// Score target texts conditioned on source texts with an encoder-decoder model
+    ///
+    /// Computes the log-probability of each token of the targets given the source and the previous
+    /// target tokens (teacher forcing), for example for translation quality estimation or for the
+    /// ranking of candidate outputs. The sources and targets are encoded with the special tokens of
+    /// the model (e.g. the end of sequence token), and the special tokens of the targets are scored
+    /// as well. The sources and targets are right-padded within a batch: the padding of the sources
+    /// is masked and the padding positions of the targets are not scored, so that the scores do not
+    /// depend on the batch size.
+    ///
+    /// # Arguments
+    ///
+    /// * `sources` - `&[S]` Source texts
+    /// * `targets` - `&[S]` Target texts, of the same length as `sources`
+    /// * `batch_size` - Number of (source, target) pairs processed in a single forward pass
+    ///
+    /// # Returns
+    /// * `Result<Vec<TextScore>, RustBertError>` Vector of length *number_of_targets* containing the token log-probabilities, log-likelihood and perplexity of each target.
+    ///   A `RustBertError::ValueError` is returned for decoder-only models or invalid arguments.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::bart::BartGenerator;
+    /// use rust_bert::pipelines::generation_utils::LanguageGenerator;
+    ///
+    /// let bart_generator = BartGenerator::new(Default::default())?;
+    /// let article = "The tower is 324 metres (1,063 ft) tall, about the same height as an 81-storey building.";
+    /// let sources = [article, article];
+    /// let targets = ["The tower is 324 metres tall.", "The tower is 81 metres tall."];
+    ///
+    /// let output = bart_generator.score_conditional(&sources, &targets, 8)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn score_conditional<S>(
+        &self,
+        sources: &[S],
+        targets: &[S],
+        batch_size: usize,
+    ) -> Result<Vec<TextScore>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if !self.is_encoder_decoder() {
+            return Err(RustBertError::ValueError(
+                "Conditional scoring requires an encoder-decoder model".to_string(),
+            ));
+        }
+        if sources.len() != targets.len() {
+            return Err(RustBertError::ValueError(format!(
+                "The number of sources ({}) and targets ({}) must be equal",
+                sources.len(),
+                targets.len()
+            )));
+        }
+        if batch_size == 0 {
+            return Err(RustBertError::ValueError(
+                "The scoring batch size must be greater than 0".to_string(),
+            ));
+        }
+        if sources.is_empty() {
+            return Ok(vec![]);
+        }
+        let decoder_start_id = self.get_decoder_start_id().ok_or_else(|| {
+            RustBertError::ValueError(
+                "Conditional scoring requires a model with a decoder start token".to_string(),
+            )
+        })?;
+        let pad_id = match self.get_pad_id() {
+            Some(value) => value,
+            None => self.get_eos_ids().map_or(0, |eos_ids| eos_ids[0]),
+        };
+        let max_length = self.get_max_positions_embeddings();
+        let encode = |texts: &[S]| {
+            self._get_tokenizer()
+                .encode_list(
+                    texts,
+                    max_length as usize,
+                    &TruncationStrategy::LongestFirst,
+                    0,
+                )
+                .into_iter()
+                .map(|tokenized_input| tokenized_input.token_ids)
+                .collect::<Vec<Vec<i64>>>()
+        };
+        let source_ids = encode(sources);
+        let target_ids = encode(targets);
+
+        let device = self.get_var_store().device();
+        let mut output = Vec::with_capacity(targets.len());
+        for (batch_sources, batch_targets) in source_ids
+            .chunks(batch_size)
+            .zip(target_ids.chunks(batch_size))
+        {
+            let target_length = batch_targets.iter().map(Vec::len).max().unwrap();
+            if target_length == 0 {
+                output.extend(batch_targets.iter().map(|_| TextScore::new(vec![], vec![])));
+                continue;
+            }
+            // The sources are right-padded so that the positions of their tokens do not depend on
+            // the other sources of the batch, and the attention mask is built from their lengths
+            let source_length = batch_sources.iter().map(Vec::len).max().unwrap();
+            let mut input_ids = Vec::with_capacity(batch_sources.len() * source_length);
+            let mut attention_mask = Vec::with_capacity(batch_sources.len() * source_length);
+            for source in batch_sources {
+                let padding_length = source_length - source.len();
+                input_ids.extend_from_slice(source);
+                input_ids.extend(vec![pad_id; padding_length]);
+                attention_mask.extend(vec![1i64; source.len()]);
+                attention_mask.extend(vec![0i64; padding_length]);
+            }
+            let shape = [batch_sources.len() as i64, source_length as i64];
+            let input_ids = Tensor::of_slice(&input_ids).view(shape).to(device);
+            let attention_mask = Tensor::of_slice(&attention_mask).view(shape).to(device);
+
+            // The decoder inputs are the targets shifted right, starting with the decoder start token
+            let mut decoder_input_ids = Vec::with_capacity(batch_targets.len() * target_length);
+            let mut labels = Vec::with_capacity(batch_targets.len() * target_length);
+            for target in batch_targets {
+                let padding_length = target_length - target.len();
+                if !target.is_empty() {
+                    decoder_input_ids.push(decoder_start_id);
+                    decoder_input_ids.extend_from_slice(&target[..target.len() - 1]);
+                }
+                decoder_input_ids.extend(vec![pad_id; padding_length]);
+                labels.extend_from_slice(target);
+                labels.extend(vec![pad_id; padding_length]);
+            }
+            let shape = [batch_targets.len() as i64, target_length as i64];
+            let decoder_input_ids = Tensor::of_slice(&decoder_input_ids).view(shape).to(device);
+            let labels = Tensor::of_slice(&labels).view(shape).to(device);
+
+            let batch_log_probabilities = no_grad(|| {
+                let logits = self
+                    .get_model()
+                    .forward_t(
+                        Some(&input_ids),
+                        Cache::None,
+                        Some(&attention_mask),
+                        None,
+                        None,
+                        None,
+                        None,
+                        Some(&decoder_input_ids),
+                        false,
+                    )?
+                    .lm_logits
+                    .to_kind(Kind::Float);
+                let label_logits = logits
+                    .gather(2, &labels.unsqueeze(-1), false)
+                    .squeeze_dim(-1);
+                Ok::<Tensor, RustBertError>(
+                    (label_logits - logits.logsumexp([-1].as_slice(), false)).to(Device::Cpu),
+                )
+            })?;
+            for (row, target) in batch_targets.iter().enumerate() {
+                let token_log_probabilities = batch_log_probabilities
+                    .get(row as i64)
+                    .iter::<f64>()?
+                    .take(target.len())
+                    .map(Some)
+                    .collect::<Vec<Option<f64>>>();
+                output.push(TextScore::new(target.clone(), token_log_probabilities));
+            }
+        }
+        Ok(output)
+    }
+
+    /// Returns a reference to the text generator's tokenizer
+    ///
+    /// # Returns
+    /// * `&TokenizerOption` Reference to the generator's tokenizer.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tch::Device;
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
+    /// use tch::Tensor;
+    /// # let mut home: PathBuf = dirs::home_dir().unwrap();
+    /// # home.push("rustbert");
+    /// # home.push("gpt2");
+    /// # let config_path = &home.as_path().join("config.json");
+    /// # let vocab_path = &home.as_path().join("vocab.txt");
+    /// # let merges_path = &home.as_path().join("merges.txt");
+    /// # let weights_path = &home.as_path().join("model.ot");
+    /// let device = Device::cuda_if_available();
+    /// let generate_config = GenerateConfig {
+    ///     max_length: Some(30),
+    ///     do_sample: true,
+    ///     num_beams: 5,
+    ///     temperature: 1.1,
+    ///     num_return_sequences: 3,
+    ///     ..Default::default()
+    /// };
+    /// let gpt2_generator = GPT2Generator::new(generate_config)?;
+    /// let tokenizer = gpt2_generator.get_tokenizer();
+    /// tokenizer.tokenize("Hello, world!");
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn get_tokenizer(&self) -> &TokenizerOption {
+        self._get_tokenizer()
+    }
+
+    fn half(&mut self) {
+        self.get_var_store_mut().half();
+    }
+
+    fn bfloat16(&mut self) {
+        self.get_var_store_mut().bfloat16();
+    }
+
+    fn float(&mut self) {
+        self.get_var_store_mut().float();
+    }
+
+    fn set_device(&mut self, device: Device) {
+        self.get_var_store_mut().set_device(device);
+    }
+}
+
+#[derive(Debug)]
+struct BeamHypotheses {
+    max_length: Option<i64>,
+    length_penalty: f64,
+    early_stopping: bool,
+    num_beams: i64,
+    beams: Vec<(f64, Tensor, Option<Tensor>)>,
+    worst_score: f64,
+}
+
+impl Clone for BeamHypotheses {
+    fn clone(&self) -> Self {
+        BeamHypotheses {
+            max_length: self.max_length,
+            length_penalty: self.length_penalty,
+            early_stopping: self.early_stopping,
+            num_beams: self.num_beams,
+            beams: self
+                .beams
+                .iter()
+                .map(|(score, tensor, scores_tensor)| {
+                    (
+                        *score,
+                        tensor.copy(),
+                        scores_tensor
+                            .as_ref()
+                            .map(|scores_tensor| scores_tensor.copy()),
+                    )
+                })
+                .collect::<Vec<(f64, Tensor, Option<Tensor>)>>(),
+            worst_score: self.worst_score,
+        }
+    }
+}
+
+impl BeamHypotheses {
+    fn new(
+        num_beams: i64,
+        max_length: Option<i64>,
+        length_penalty: f64,
+        early_stopping: bool,
+    ) -> BeamHypotheses {
+        BeamHypotheses {
+            max_length: max_length.map(|max_length| max_length - 1),
+            length_penalty,
+            early_stopping,
+            num_beams,
+            beams: Vec::with_capacity(num_beams as usize + 1),
+            worst_score: 1e9f64,
+        }
+    }
+
+    fn len(&self) -> i64 {
+        self.beams.len() as i64
+    }
+
+    fn add(
+        &mut self,
+        hypothesis: Tensor,
+        sum_log_probabilities: f64,
+        token_scores: Option<Tensor>,
+    ) {
+        let score =
+            sum_log_probabilities / ((hypothesis.size()[0] as f64).powf(self.length_penalty));
+        if (self.len() < self.num_beams) | (score > self.worst_score) {
+            let token_scores = token_scores.map(|scores_tensor| {
+                scores_tensor.squeeze_dim(0).diff::<Tensor>(
+                    1,
+                    0,
+                    Some(Tensor::zeros(
+                        &[1],
+                        (scores_tensor.kind(), scores_tensor.device()),
+                    )),
+                    None,
+                )
+            });
+            self.beams.push((score, hypothesis, token_scores));
+            if self.len() > self.num_beams {
+                let (worst_score_position, _) = self
+                    .beams
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, (score, _, _))| OrderedFloat(*score))
+                    .unwrap();
+                let _ = self.beams.remove(worst_score_position);
+            }
+            self.worst_score = self
+                .beams
+                .iter()
+                .min_by_key(|(score, _, _)| OrderedFloat(*score))
+                .unwrap()
+                .0;
+        }
+    }
+
+    fn is_done(&self, best_sum_log_probabilities: f64, current_length: i64) -> bool {
+        if self.len() < self.num_beams {
+            false
+        } else if self.early_stopping {
+            true
+        } else {
+            self.worst_score
+                >= best_sum_log_probabilities / (current_length as f64).powf(self.length_penalty)
+        }
+    }
+}
+
+/// # Language Model trait
+/// Shared trait between language generation models (e.g. GPT2, GPT, BART) used in language generation pipelines.
+pub trait LMHeadModel {
+    /// Forward pass through the model. Example provided for GPT2.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). If None, pre-computed embeddings must be provided (see `input_embeds`)
+    /// * `layer_past` - Optional vector of size *n_layer* containing the past keys and values of each layer of shape (*2*, *batch size*, *number of heads*, *past_sequence_length*, *hidden size per head*). When provided, these are concatenated with the current input keys and values.
+    /// * `attention_mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `input_embeds` - Optional pre-computed input embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see `input_ids`)
+    /// * `token_type_ids` - Optional token type ids used to indicate the portion of the input the token belongs to. If not None, token type embeddings will be added to the token and position embeddings.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented starting from the length of the past input.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `output` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
+    /// * `past` - `Option<Vec<Tensor>>` of length *n_layer* containing the past keys and values of each layer of shape (*2*, *batch size*, *number of heads*, *past_sequence_length*, *hidden size per head*)
+    /// * `hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    /// * `attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::{Int64, Double};
+    /// use rust_bert::gpt2::{GPT2LMHeadModel, Gpt2Config};
+    /// use rust_bert::pipelines::generation_utils::{Cache, LMHeadModel};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let vocab_path = Path::new("path/to/vocab.txt");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = Gpt2Config::from_file(config_path);
+    /// # let mut gpt2_model: GPT2LMHeadModel = GPT2LMHeadModel::new(&vs.root(), &config);
+    /// let (batch_size, sequence_length, past_sequence_length) = (64, 128, 56);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Int64, device));
+    /// let mut past: Vec<Tensor> = Vec::with_capacity(config.n_layer as usize);
+    /// for _ in 0..config.n_layer as usize {
+    ///     past.push(Tensor::rand(
+    ///         &[
+    ///             2,
+    ///             batch_size,
+    ///             config.n_head,
+    ///             past_sequence_length,
+    ///             config.n_embd / config.n_head,
+    ///         ],
+    ///         (Double, device),
+    ///     ))
+    /// }
+    /// let attention_mask = Tensor::zeros(&[batch_size, sequence_length], (Int64, device));
+    /// let token_type_ids = Tensor::ones(&[batch_size, sequence_length], (Int64, device));
+    /// let position_ids = Tensor::arange(sequence_length, (Int64, device))
+    ///     .expand(&[batch_size, sequence_length], true);
+    ///
+    /// let model_output = no_grad(|| {
+    ///     gpt2_model
+    ///         .forward_t(
+    ///             Some(&input_tensor),
+    ///             Cache::GPT2Cache(Some(past)),
+    ///             Some(&attention_mask),
+    ///             Some(&token_type_ids),
+    ///             Some(&position_ids),
+    ///             None,
+    ///             None,
+    ///             None,
+    ///             false,
+    ///         )
+    ///         .unwrap()
+    /// });
+    /// ```
+    fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        layer_past: Cache,
+        attention_mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        encoder_outputs: Option<&Tensor>,
+        decoder_input_ids: Option<&Tensor>,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError>;
+}
+
+/// Container holding a language model output for generation tasks
+pub struct LMModelOutput {
+    /// Logits for each vocab item and position
+    pub lm_logits: Tensor,
+    /// Last hidden state (before the language modeling head) for each position
+    pub hidden_state: Option<Tensor>,
+    /// cached state for improved efficiency during decoding
+    pub cache: Cache,
+}
@@ -0,0 +1,127 @@
+// Copyright 2019 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Random draws of the sampling strategies. Without a seed, the tokens are drawn with the global
+//! `tch` random number generator. A seeded sampler owns a generator for a single generation call,
+//! making the sampled tokens reproducible independently of the other uses of the global generator
+//! (e.g. concurrent generations). A sampler seeded per input draws the random numbers of the rows
+//! of each input of the batch (its return sequences) from its own generator, in the order a
+//! sampler seeded for this input only draws them: each input is then sampled as in a separate call
+//! with its seed.
+
+use std::cell::Cell;
+use tch::{Kind, Tensor};
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+const MIX_1: u64 = 0xBF58_476D_1CE4_E5B9;
+const MIX_2: u64 = 0x94D0_49BB_1331_11EB;
+
+/// Source of randomness of a generation call
+pub struct Sampler {
+    states: Vec<Cell<u64>>,
+}
+
+impl Sampler {
+    /// Creates a sampler seeded with `seed`, or using the global `tch` generator if no seed is
+    /// provided
+    pub fn new(seed: Option<u64>) -> Sampler {
+        Sampler {
+            states: seed.into_iter().map(Cell::new).collect(),
+        }
+    }
+
+    /// Creates a sampler with a generator per input of the batch, seeded with `row_seeds`. The
+    /// rows of the batch are split in groups of consecutive rows (the sequences of an input), one
+    /// for each seed.
+    pub fn with_row_seeds(row_seeds: &[u64]) -> Sampler {
+        Sampler {
+            states: row_seeds.iter().copied().map(Cell::new).collect(),
+        }
+    }
+
+    /// Returns the current state of the generator of each input, allowing to resume the sampling
+    /// of the inputs with `with_row_seeds`
+    pub fn row_states(&self) -> Vec<u64> {
+        self.states.iter().map(Cell::get).collect()
+    }
+
+    /// Draws `num_samples` indices without replacement from the categorical distributions given
+    /// by the last dimension of `probabilities`, in the order they are drawn
+    pub fn multinomial(&self, probabilities: &Tensor, num_samples: i64) -> Tensor {
+        match self.states.first() {
+            None => probabilities.multinomial(num_samples, false),
+            Some(_) => {
+                // Exponential race: the indices with the smallest -log(u) / p values are distributed as
+                // successive draws without replacement
+                let keys = -self.rand_like(probabilities).log() / probabilities;
+                keys.topk(num_samples, -1, false, true).1
+            }
+        }
+    }
+
+    /// Returns a tensor with the shape of `tensor` filled with random numbers uniformly
+    /// distributed in (0, 1). For a sampler seeded per input, the values of each group of rows
+    /// (first dimension of `tensor`) are drawn from the generator of the input. The SplitMix64
+    /// steps of a draw are computed at once with integer tensor operations on the device of
+    /// `tensor`: only the generator states are copied from the host.
+    pub fn rand_like(&self, tensor: &Tensor) -> Tensor {
+        if self.states.is_empty() {
+            return Tensor::rand_like(tensor);
+        }
+        let size = tensor.size();
+        let num_values = size.iter().product::<i64>();
+        let num_groups = self.states.len() as i64;
+        let states = if (num_groups > 1) && (size[0] % num_groups == 0) {
+            &self.states[..]
+        } else {
+            &self.states[..1]
+        };
+        let values_per_state = num_values / states.len() as i64;
+        let device = tensor.device();
+
+        let initial_states = Tensor::of_slice(
+            &states
+                .iter()
+                .map(|state| state.get() as i64)
+                .collect::<Vec<i64>>(),
+        )
+        .to_device(device)
+        .unsqueeze(1);
+        let steps =
+            Tensor::arange_start(1, values_per_state + 1, (Kind::Int64, device)).unsqueeze(0);
+        // SplitMix64 steps: the integer tensor operations wrap around on overflow as `u64` operations
+        let mut value = initial_states + steps * (GOLDEN_GAMMA as i64);
+        value = value.bitwise_xor_tensor(&Self::logical_right_shift(&value, 30)) * (MIX_1 as i64);
+        value = value.bitwise_xor_tensor(&Self::logical_right_shift(&value, 27)) * (MIX_2 as i64);
+        value = value.bitwise_xor_tensor(&Self::logical_right_shift(&value, 31));
+        for state in states {
+            state.set(
+                state
+                    .get()
+                    .wrapping_add(GOLDEN_GAMMA.wrapping_mul(values_per_state as u64)),
+            );
+        }
+
+        let values = (Self::logical_right_shift(&value, 11).to_kind(Kind::Double) + 0.5)
+            / (1u64 << 53) as f64;
+        values.view(size.as_slice()).to_kind(match tensor.kind() {
+            Kind::Double => Kind::Double,
+            _ => Kind::Float,
+        })
+    }
+
+    /// Right shift of the bits of an `Int64` tensor filling the high bits with zeros, as for `u64`
+    fn logical_right_shift(value: &Tensor, shift: i64) -> Tensor {
+        value
+            .bitwise_right_shift_tensor_scalar(shift)
+            .bitwise_and((1i64 << (64 - shift)) - 1)
+    }
+}
@@ -0,0 +1,630 @@
+// Copyright 2019 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Continuous batching scheduler
+//! Serves text generation requests with continuous batching: instead of running a fixed batch until all its
+//! sequences are complete, the `GenerationScheduler` admits queued prompts into the running batch and evicts finished
+//! sequences at every decoding step. The cached states of the admitted prompts are left-padded and concatenated to the
+//! cache of the running batch, the cache of the remaining sequences is reordered when sequences are evicted.
+//!
+//! Requests are submitted with `GenerationScheduler::submit` and their status retrieved with
+//! `GenerationScheduler::poll`. Both methods can be called concurrently from multiple threads while a serving loop
+//! drives the generation by calling `GenerationScheduler::step`.
+//! The generation settings (greedy decoding or sampling, temperature, top-k, top-p, repetition penalty...) are read
+//! from the `GenerateConfig` of the generator and applied to the whole running batch at once. Each request is sampled
+//! with its own random number generator: a request submitted with a seed gives the same output as a `generate` call
+//! with this seed. Beam search is not supported and the generator must be a decoder-only
+//! model. Models whose cache cannot be padded (GPT2 and GPT-Neo caches can) recompute their states when prompts are
+//! admitted.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::gpt2::GPT2Generator;
+//! use rust_bert::pipelines::generation_utils::scheduler::{GenerationScheduler, RequestStatus};
+//! use std::thread;
+//! use std::time::Duration;
+//!
+//! let scheduler = GenerationScheduler::new(GPT2Generator::new(Default::default())?, 8)?;
+//!
+//! thread::scope(|scope| -> anyhow::Result<()> {
+//!     let clients = ["The dog", "The cat was"].map(|prompt| {
+//!         let scheduler = &scheduler;
+//!         scope.spawn(move || -> anyhow::Result<String> {
+//!             let request_id = scheduler.submit(prompt, Some(32), None)?;
+//!             loop {
+//!                 if let Some(RequestStatus::Finished(output)) = scheduler.poll(request_id) {
+//!                     return Ok(output.text);
+//!                 }
+//!                 thread::sleep(Duration::from_millis(10));
+//!             }
+//!         })
+//!     });
+//!     //    Serving loop
+//!     while clients.iter().any(|client| !client.is_finished()) {
+//!         if scheduler.step()? == 0 {
+//!             thread::sleep(Duration::from_millis(10));
+//!         }
+//!     }
+//!     Ok(())
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use rust_tokenizers::tokenizer::Tokenizer;
+use rust_tokenizers::vocab::Vocab;
+use tch::{no_grad, Device, Kind, Tensor};
+
+use crate::common::error::RustBertError;
+use crate::pipelines::generation_utils::sampler::Sampler;
+use crate::pipelines::generation_utils::{Cache, LMHeadModel, LanguageGenerator};
+
+/// # Identifier of a generation request submitted to a `GenerationScheduler`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestId(u64);
+
+/// # Output of a completed generation request
+#[derive(Debug, Clone)]
+pub struct ScheduledGenerationOutput {
+    /// Text generated after the prompt
+    pub text: String,
+    /// Token ids generated after the prompt
+    pub token_ids: Vec<i64>,
+}
+
+/// # Status of a generation request
+#[derive(Debug, Clone)]
+pub enum RequestStatus {
+    /// The request is waiting to be admitted in the running batch
+    Queued,
+    /// The request is part of the running batch
+    Running,
+    /// The generation is complete
+    Finished(ScheduledGenerationOutput),
+}
+
+struct QueuedRequest {
+    request_id: RequestId,
+    token_ids: Vec<i64>,
+    max_new_tokens: Option<i64>,
+    seed: u64,
+}
+
+#[derive(Default)]
+struct RequestQueue {
+    next_request_id: u64,
+    queued_requests: VecDeque<QueuedRequest>,
+    statuses: HashMap<RequestId, RequestStatus>,
+}
+
+struct RunningRequest {
+    request_id: RequestId,
+    sequence_length: i64,
+    max_length: Option<i64>,
+    generated_ids: Vec<i64>,
+    sampler_state: u64,
+}
+
+/// Left-padded sequences of the running batch, with the cache of all their positions and the
+/// logits of their next token
+struct BatchState {
+    input_ids: Tensor,
+    attention_mask: Tensor,
+    past: Cache,
+    next_token_logits: Tensor,
+}
+
+impl BatchState {
+    fn left_pad(mut self, length: i64, pad_token_id: i64) -> BatchState {
+        let (batch_size, sequence_length) = self.input_ids.size2().unwrap();
+        if sequence_length < length {
+            let padding_shape = [batch_size, length - sequence_length];
+            let device = self.input_ids.device();
+            self.input_ids = Tensor::cat(
+                &[
+                    &Tensor::full(&padding_shape, pad_token_id, (Kind::Int64, device)),
+                    &self.input_ids,
+                ],
+                1,
+            );
+            self.attention_mask = Tensor::cat(
+                &[
+                    &Tensor::zeros(&padding_shape, (Kind::Int64, device)),
+                    &self.attention_mask,
+                ],
+                1,
+            );
+            self.past.left_pad(length);
+        }
+        self
+    }
+}
+
+struct RunningBatch<T, V, U, G> {
+    generator: G,
+    requests: Vec<RunningRequest>,
+    state: Option<BatchState>,
+    _phantom: PhantomData<(T, V, U)>,
+}
+
+/// # Continuous batching scheduler for text generation
+/// Runs the generation of the submitted requests, admitting new prompts into the running batch and evicting finished
+/// sequences at each step. The scheduler can be shared across threads (it is `Sync` for generators that are `Send`).
+pub struct GenerationScheduler<T, V, U, G>
+where
+    T: LMHeadModel,
+    V: Vocab,
+    U: Tokenizer<V>,
+    G: LanguageGenerator<T, V, U>,
+{
+    queue: Mutex<RequestQueue>,
+    batch: Mutex<RunningBatch<T, V, U, G>>,
+    max_batch_size: usize,
+    max_length: Option<i64>,
+    eos_token_ids: Option<Vec<i64>>,
+    pad_token_id: i64,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<T, V, U, G> GenerationScheduler<T, V, U, G>
+where
+    T: LMHeadModel,
+    V: Vocab,
+    U: Tokenizer<V>,
+    G: LanguageGenerator<T, V, U>,
+{
+    /// Build a new `GenerationScheduler`
+    ///
+    /// # Arguments
+    ///
+    /// * `generator` - Decoder-only language generator. The generation settings are read from its `GenerateConfig`.
+    /// * `max_batch_size` - Maximum number of sequences generated concurrently
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::scheduler::GenerationScheduler;
+    ///
+    /// let scheduler = GenerationScheduler::new(GPT2Generator::new(Default::default())?, 8)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(generator: G, max_batch_size: usize) -> Result<Self, RustBertError> {
+        if max_batch_size == 0 {
+            return Err(RustBertError::ValueError(
+                "max_batch_size must be strictly greater than 0".to_string(),
+            ));
+        }
+        if generator.is_encoder_decoder() {
+            return Err(RustBertError::ValueError(
+                "The generation scheduler only supports decoder-only models".to_string(),
+            ));
+        }
+        let config = generator.get_config();
+        if config.num_beams > 1 {
+            return Err(RustBertError::ValueError(
+                "The generation scheduler does not support beam search (num_beams > 1)".to_string(),
+            ));
+        }
+        let eos_token_ids = generator.get_eos_ids().cloned();
+        let pad_token_id = generator
+            .get_pad_id()
+            .or_else(|| eos_token_ids.as_ref().map(|eos_token_ids| eos_token_ids[0]))
+            .ok_or_else(|| {
+                RustBertError::ValueError(
+                    "The generation scheduler requires a padding or end of sequence token"
+                        .to_string(),
+                )
+            })?;
+        Ok(GenerationScheduler {
+            queue: Mutex::new(RequestQueue::default()),
+            max_length: config.max_length,
+            batch: Mutex::new(RunningBatch {
+                generator,
+                requests: vec![],
+                state: None,
+                _phantom: PhantomData,
+            }),
+            max_batch_size,
+            eos_token_ids,
+            pad_token_id,
+        })
+    }
+
+    /// Submits a prompt for generation. The request is queued until it is admitted in the running batch. The prompt
+    /// is tokenized with the tokenizer of the generator (waiting for a running step to complete).
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - Prompt text to continue
+    /// * `max_new_tokens` - Maximum number of tokens to generate. If `None`, the generation is limited by the
+    ///   `max_length` of the generator configuration.
+    /// * `seed` - Seed of the random number generator used to sample this request. If `None`, a seed is drawn from
+    ///   the global `tch` random number generator.
+    ///
+    /// # Returns
+    /// * `RequestId` identifying the request, used to poll its status
+    pub fn submit(
+        &self,
+        prompt: &str,
+        max_new_tokens: Option<i64>,
+        seed: Option<u64>,
+    ) -> Result<RequestId, RustBertError> {
+        if let Some(max_new_tokens) = max_new_tokens {
+            if max_new_tokens <= 0 {
+                return Err(RustBertError::ValueError(format!(
+                    "max_new_tokens must be strictly greater than 0, got {}",
+                    max_new_tokens
+                )));
+            }
+        } else if self.max_length.is_none() & self.eos_token_ids.is_none() {
+            return Err(RustBertError::ValueError(
+                "No maximum length given for a model without an EOS token. \
+                Please provide a `max_new_tokens`"
+                    .to_string(),
+            ));
+        }
+        let token_ids = {
+            let batch = lock(&self.batch);
+            let max_positions = batch.generator.get_max_positions_embeddings();
+            let tokenizer = batch.generator.get_tokenizer();
+            let mut token_ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(prompt));
+            if token_ids.len() as i64 >= max_positions {
+                token_ids.drain(..token_ids.len() + 1 - max_positions as usize);
+            }
+            token_ids
+        };
+        if token_ids.is_empty() {
+            return Err(RustBertError::ValueError(
+                "The prompt must contain at least one token".to_string(),
+            ));
+        }
+        let seed = seed.unwrap_or_else(|| {
+            Tensor::randint(i64::MAX, &[1], (Kind::Int64, Device::Cpu)).int64_value(&[0]) as u64
+        });
+        let mut queue = lock(&self.queue);
+        let request_id = RequestId(queue.next_request_id);
+        queue.next_request_id += 1;
+        queue.queued_requests.push_back(QueuedRequest {
+            request_id,
+            token_ids,
+            max_new_tokens,
+            seed,
+        });
+        queue.statuses.insert(request_id, RequestStatus::Queued);
+        Ok(request_id)
+    }
+
+    /// Returns the status of a request, or `None` for unknown request ids. The output of a finished request is
+    /// returned once: the request is then removed from the scheduler.
+    pub fn poll(&self, request_id: RequestId) -> Option<RequestStatus> {
+        let mut queue = lock(&self.queue);
+        match queue.statuses.get(&request_id) {
+            Some(RequestStatus::Finished(_)) => queue.statuses.remove(&request_id),
+            status => status.cloned(),
+        }
+    }
+
+    /// Runs a decoding step: admits queued requests in the running batch (up to the maximum batch size), generates
+    /// the next token of each running sequence and evicts the finished sequences.
+    ///
+    /// # Returns
+    /// * Number of requests queued or running after the step
+    pub fn step(&self) -> Result<usize, RustBertError> {
+        let mut batch = lock(&self.batch);
+        let admitted_requests = {
+            let mut queue = lock(&self.queue);
+            let num_admitted =
+                (self.max_batch_size - batch.requests.len()).min(queue.queued_requests.len());
+            let admitted_requests = queue
+                .queued_requests
+                .drain(..num_admitted)
+                .collect::<Vec<QueuedRequest>>();
+            for request in admitted_requests.iter() {
+                queue
+                    .statuses
+                    .insert(request.request_id, RequestStatus::Running);
+            }
+            admitted_requests
+        };
+
+        let finished_requests = no_grad(|| -> Result<_, RustBertError> {
+            if !admitted_requests.is_empty() {
+                self.admit(&mut batch, admitted_requests)?;
+            }
+            if batch.requests.is_empty() {
+                return Ok(vec![]);
+            }
+            let next_tokens = self.next_tokens(&mut batch)?;
+            self.advance(&mut batch, next_tokens)
+        })?;
+
+        let mut queue = lock(&self.queue);
+        let tokenizer = batch.generator.get_tokenizer();
+        for request in finished_requests {
+            let text = tokenizer.decode(&request.generated_ids, true, true);
+            queue.statuses.insert(
+                request.request_id,
+                RequestStatus::Finished(ScheduledGenerationOutput {
+                    text,
+                    token_ids: request.generated_ids,
+                }),
+            );
+        }
+        Ok(batch.requests.len() + queue.queued_requests.len())
+    }
+
+    /// Encodes the admitted prompts, computes their cache and next token logits and merges them
+    /// into the running batch
+    fn admit(
+        &self,
+        batch: &mut RunningBatch<T, V, U, G>,
+        requests: Vec<QueuedRequest>,
+    ) -> Result<(), RustBertError> {
+        let generator = &batch.generator;
+        let device = generator.get_var_store().device();
+        let sequence_length = requests
+            .iter()
+            .map(|request| request.token_ids.len())
+            .max()
+            .unwrap();
+        let (input_ids, attention_mask): (Vec<Tensor>, Vec<Tensor>) = requests
+            .iter()
+            .map(|request| {
+                let token_ids = &request.token_ids;
+                let padding_length = sequence_length - token_ids.len();
+                let mut padded_token_ids = vec![self.pad_token_id; padding_length];
+                padded_token_ids.extend(token_ids);
+                let mut attention_mask = vec![0; padding_length];
+                attention_mask.extend(vec![1; token_ids.len()]);
+                (
+                    Tensor::of_slice(&padded_token_ids),
+                    Tensor::of_slice(&attention_mask),
+                )
+            })
+            .unzip();
+        let input_ids = Tensor::stack(&input_ids, 0).to(device);
+        let attention_mask = Tensor::stack(&attention_mask, 0).to(device);
+
+        let prepared_input = generator.prepare_inputs_for_generation(
+            input_ids.copy(),
+            None,
+            Cache::None,
+            attention_mask.copy(),
+        );
+        let output = generator.get_model().forward_t(
+            prepared_input.prepared_input.as_ref(),
+            prepared_input.prepared_past,
+            prepared_input.prepared_attention_mask.as_ref(),
+            None,
+            prepared_input.prepared_position_ids.as_ref(),
+            None,
+            None,
+            None,
+            false,
+        )?;
+        let admitted_state = BatchState {
+            input_ids,
+            attention_mask,
+            past: output.cache,
+            next_token_logits: output.lm_logits.select(1, -1),
+        };
+
+        batch.requests.extend(requests.into_iter().map(|request| {
+            RunningRequest {
+                request_id: request.request_id,
+                sequence_length: request.token_ids.len() as i64,
+                max_length: request
+                    .max_new_tokens
+                    .map(|max_new_tokens| max_new_tokens + request.token_ids.len() as i64)
+                    .or(self.max_length),
+                generated_ids: vec![],
+                sampler_state: request.seed,
+            }
+        }));
+        batch.state = Some(match batch.state.take() {
+            Some(running_state) => {
+                let length = running_state.input_ids.size()[1].max(sequence_length as i64);
+                let running_state = running_state.left_pad(length, self.pad_token_id);
+                let admitted_state = admitted_state.left_pad(length, self.pad_token_id);
+                BatchState {
+                    input_ids: Tensor::cat(&[running_state.input_ids, admitted_state.input_ids], 0),
+                    attention_mask: Tensor::cat(
+                        &[running_state.attention_mask, admitted_state.attention_mask],
+                        0,
+                    ),
+                    past: Cache::cat(vec![running_state.past, admitted_state.past]),
+                    next_token_logits: Tensor::cat(
+                        &[
+                            running_state.next_token_logits,
+                            admitted_state.next_token_logits,
+                        ],
+                        0,
+                    ),
+                }
+            }
+            None => admitted_state,
+        });
+        Ok(())
+    }
+
+    /// Selects the next token of each running sequence. The logits of the (left-padded) running
+    /// batch are processed at once, each sequence being sampled with its own generator.
+    fn next_tokens(&self, batch: &mut RunningBatch<T, V, U, G>) -> Result<Vec<i64>, RustBertError> {
+        let generator = &batch.generator;
+        let state = batch.state.as_ref().unwrap();
+        let mut gen_opt = generator.get_config().internal_generate_options();
+        gen_opt.eos_token_ids = self.eos_token_ids.clone();
+        gen_opt.pad_token_id = Some(self.pad_token_id);
+        gen_opt.sampler = Sampler::with_row_seeds(
+            &batch
+                .requests
+                .iter()
+                .map(|request| request.sampler_state)
+                .collect::<Vec<u64>>(),
+        );
+        let logits_processors = generator.get_logits_processors(&gen_opt, None);
+        let logits_warpers = generator.get_sampling_logits_warpers(&gen_opt);
+
+        let mut next_token_logits = state.next_token_logits.copy();
+        generator.process_next_token_logits(
+            &state.input_ids,
+            &mut next_token_logits,
+            state.input_ids.size()[1],
+            &gen_opt,
+            &logits_processors,
+            &logits_warpers,
+        );
+        let next_tokens = if gen_opt.do_sample {
+            let probabilities = next_token_logits.softmax(-1, next_token_logits.kind());
+            gen_opt
+                .sampler
+                .multinomial(&probabilities, 1)
+                .squeeze_dim(1)
+        } else {
+            next_token_logits.argmax(-1, false)
+        };
+        let next_tokens = next_tokens.iter::<i64>()?.collect::<Vec<i64>>();
+        for (request, sampler_state) in batch.requests.iter_mut().zip(gen_opt.sampler.row_states())
+        {
+            request.sampler_state = sampler_state;
+        }
+        Ok(next_tokens)
+    }
+
+    /// Appends the next tokens to the running sequences, evicts the finished sequences (returned)
+    /// and computes the next token logits of the remaining sequences
+    fn advance(
+        &self,
+        batch: &mut RunningBatch<T, V, U, G>,
+        next_tokens: Vec<i64>,
+    ) -> Result<Vec<RunningRequest>, RustBertError> {
+        let max_positions = batch.generator.get_max_positions_embeddings();
+        let mut finished_requests = vec![];
+        let mut remaining_indices = vec![];
+        for (row, (mut request, next_token)) in mem::take(&mut batch.requests)
+            .into_iter()
+            .zip(next_tokens.iter())
+            .enumerate()
+        {
+            request.generated_ids.push(*next_token);
+            request.sequence_length += 1;
+            let is_finished = self
+                .eos_token_ids
+                .iter()
+                .flatten()
+                .any(|eos_token_id| eos_token_id == next_token)
+                | matches!(request.max_length, Some(max_length) if request.sequence_length >= max_length)
+                | (request.sequence_length >= max_positions);
+            if is_finished {
+                finished_requests.push(request);
+            } else {
+                remaining_indices.push(row as i64);
+                batch.requests.push(request);
+            }
+        }
+
+        let mut state = batch.state.take().unwrap();
+        if batch.requests.is_empty() {
+            return Ok(finished_requests);
+        }
+        let device = state.input_ids.device();
+        state.input_ids = Tensor::cat(
+            &[
+                state.input_ids,
+                Tensor::of_slice(&next_tokens).to(device).unsqueeze(1),
+            ],
+            1,
+        );
+        state.attention_mask = Tensor::cat(
+            &[
+                state.attention_mask.as_ref(),
+                Tensor::ones(&[state.attention_mask.size()[0], 1], (Kind::Int64, device)).as_ref(),
+            ],
+            1,
+        );
+        if !finished_requests.is_empty() {
+            // Evict the finished sequences and remove the positions padded for all remaining
+            // sequences
+            let remaining_indices = Tensor::of_slice(&remaining_indices).to(device);
+            state.input_ids = state.input_ids.index_select(0, &remaining_indices);
+            state.attention_mask = state.attention_mask.index_select(0, &remaining_indices);
+            batch
+                .generator
+                .reorder_cache(&mut state.past, None, &remaining_indices);
+            let num_padding_positions = state.input_ids.size()[1]
+                - batch
+                    .requests
+                    .iter()
+                    .map(|request| request.sequence_length)
+                    .max()
+                    .unwrap();
+            if num_padding_positions > 0 {
+                state.input_ids = state.input_ids.slice(1, num_padding_positions, None, 1);
+                state.attention_mask =
+                    state
+                        .attention_mask
+                        .slice(1, num_padding_positions, None, 1);
+                state.past.trim_left(num_padding_positions);
+            }
+        }
+
+        // Without cache (e.g. after merging caches not supporting padding), the states of all
+        // positions are recomputed
+        let past = mem::replace(&mut state.past, Cache::None);
+        let prepared_input = batch.generator.prepare_inputs_for_generation(
+            state.input_ids.copy(),
+            None,
+            past,
+            state.attention_mask.copy(),
+        );
+        let output = batch.generator.get_model().forward_t(
+            prepared_input.prepared_input.as_ref(),
+            prepared_input.prepared_past,
+            prepared_input.prepared_attention_mask.as_ref(),
+            None,
+            prepared_input.prepared_position_ids.as_ref(),
+            None,
+            None,
+            None,
+            false,
+        )?;
+        state.past = output.cache;
+        state.next_token_logits = output.lm_logits.select(1, -1);
+        batch.state = Some(state);
+        Ok(finished_requests)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gpt2::GPT2Generator;
+
+    use super::GenerationScheduler;
+
+    #[test]
+    #[ignore] // compilation is enough, no need to run
+    fn generation_scheduler_sync() {
+        let scheduler =
+            GenerationScheduler::new(GPT2Generator::new(Default::default()).unwrap(), 4);
+        let _: Box<dyn Sync> = Box::new(scheduler.unwrap());
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright 2023 The HuggingFace Inc. team.
+// Copyright 2019 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Speculative decoding
+//! Speculative (assisted) decoding with a smaller draft model sharing the tokenizer of the main model, following
+//! [Leviathan et al.](https://arxiv.org/abs/2211.17192) and [Chen et al.](https://arxiv.org/abs/2302.01318).
+//! At each step, the draft model proposes `num_draft_tokens` tokens which are verified by the main model in a
+//! single forward pass:
+//! - for greedy decoding, the draft tokens matching the main model prediction are accepted, followed by the main model
+//!   prediction at the first mismatch. The output is identical to greedy decoding with the main model only.
+//! - for sampling, each draft token is accepted with probability min(1, p/q) (with p and q the main and draft model
+//!   probabilities) and the first rejected token is resampled from the normalized residual max(0, p - q). The
+//!   generated sequences follow the distribution of the main model.
+//!
+//! The cached states of both models are truncated to the accepted tokens. Models whose cache cannot be truncated
+//! recompute their states from the full sequence at the next step.
+//! Speculative decoding is available for decoder-only models with greedy decoding or sampling (`num_beams` = 1) and
+//! is enabled by providing a `DraftModel` via the `draft_model` field of `GenerateOptions`.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::gpt2::{
+//!     GPT2Generator, Gpt2ConfigResources, Gpt2MergesResources, Gpt2ModelResources, Gpt2VocabResources,
+//! };
+//! use rust_bert::pipelines::generation_utils::speculative::DraftModel;
+//! use rust_bert::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+//! use rust_bert::resources::RemoteResource;
+//!
+//! let generate_config = GenerateConfig {
+//!     model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2_LARGE)),
+//!     config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2_LARGE)),
+//!     vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2_LARGE)),
+//!     merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+//!         Gpt2MergesResources::GPT2_LARGE,
+//!     ))),
+//!     do_sample: false,
+//!     ..Default::default()
+//! };
+//! let gpt2_generator = GPT2Generator::new(generate_config)?;
+//!
+//! let draft_config = GenerateConfig {
+//!     model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::DISTIL_GPT2)),
+//!     config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::DISTIL_GPT2)),
+//!     vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::DISTIL_GPT2)),
+//!     merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+//!         Gpt2MergesResources::DISTIL_GPT2,
+//!     ))),
+//!     ..Default::default()
+//! };
+//! let draft_generator = GPT2Generator::new(draft_config)?;
+//! let draft_model = DraftModel::new(&draft_generator, 4)?;
+//!
+//! let generate_options = GenerateOptions {
+//!     max_new_tokens: Some(32),
+//!     draft_model: Some(&draft_model),
+//!     ..Default::default()
+//! };
+//! let output = gpt2_generator.generate(Some(&["The dog"]), Some(generate_options))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::marker::PhantomData;
+
+use rust_tokenizers::tokenizer::Tokenizer;
+use rust_tokenizers::vocab::Vocab;
+use tch::{Device, Tensor};
+
+use crate::common::error::RustBertError;
+use crate::pipelines::generation_utils::{Cache, LMHeadModel, LanguageGenerator};
+
+/// # Draft model for speculative decoding
+/// Wraps a (smaller) language generator proposing tokens to be verified by the main model. The draft generator must
+/// be a decoder-only model sharing the tokenizer (and vocabulary) of the main model.
+pub struct DraftModel<'a> {
+    generator: Box<dyn DraftGenerator + 'a>,
+    num_draft_tokens: i64,
+}
+
+impl<'a> DraftModel<'a> {
+    /// Build a new `DraftModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `generator` - Language generator used as a draft model
+    /// * `num_draft_tokens` - Number of tokens proposed by the draft model at each step
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::speculative::DraftModel;
+    ///
+    /// let draft_generator = GPT2Generator::new(Default::default())?;
+    /// let draft_model = DraftModel::new(&draft_generator, 4)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new<T, V, U, G>(
+        generator: &'a G,
+        num_draft_tokens: i64,
+    ) -> Result<DraftModel<'a>, RustBertError>
+    where
+        T: LMHeadModel + 'a,
+        V: Vocab + 'a,
+        U: Tokenizer<V> + 'a,
+        G: LanguageGenerator<T, V, U>,
+    {
+        if num_draft_tokens <= 0 {
+            return Err(RustBertError::ValueError(format!(
+                "num_draft_tokens must be strictly greater than 0, got {}",
+                num_draft_tokens
+            )));
+        }
+        if generator.is_encoder_decoder() {
+            return Err(RustBertError::ValueError(
+                "Speculative decoding requires a decoder-only draft model".to_string(),
+            ));
+        }
+        Ok(DraftModel {
+            generator: Box::new(DraftLanguageGenerator {
+                generator,
+                _phantom: PhantomData,
+            }),
+            num_draft_tokens,
+        })
+    }
+
+    /// Returns the number of tokens proposed by the draft model at each step
+    pub fn num_draft_tokens(&self) -> i64 {
+        self.num_draft_tokens
+    }
+
+    pub(crate) fn device(&self) -> Device {
+        self.generator.device()
+    }
+
+    pub(crate) fn vocab_size(&self) -> i64 {
+        self.generator.vocab_size()
+    }
+
+    pub(crate) fn forward_uncached_positions(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        past: Cache,
+        cached_length: i64,
+    ) -> Result<(Tensor, Cache), RustBertError> {
+        self.generator
+            .forward_uncached_positions(input_ids, attention_mask, past, cached_length)
+    }
+}
+
+/// Object-safe view of a language generator used as a draft model
+trait DraftGenerator {
+    fn device(&self) -> Device;
+
+    fn vocab_size(&self) -> i64;
+
+    fn forward_uncached_positions(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        past: Cache,
+        cached_length: i64,
+    ) -> Result<(Tensor, Cache), RustBertError>;
+}
+
+struct DraftLanguageGenerator<'a, T, V, U, G> {
+    generator: &'a G,
+    _phantom: PhantomData<(T, V, U)>,
+}
+
+impl<T, V, U, G> DraftGenerator for DraftLanguageGenerator<'_, T, V, U, G>
+where
+    T: LMHeadModel,
+    V: Vocab,
+    U: Tokenizer<V>,
+    G: LanguageGenerator<T, V, U>,
+{
+    fn device(&self) -> Device {
+        self.generator.get_var_store().device()
+    }
+
+    fn vocab_size(&self) -> i64 {
+        self.generator.get_vocab_size()
+    }
+
+    fn forward_uncached_positions(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        past: Cache,
+        cached_length: i64,
+    ) -> Result<(Tensor, Cache), RustBertError> {
+        self.generator
+            .forward_uncached_positions(input_ids, attention_mask, past, cached_length)
+    }
+}
@@ -20,6 +20,7 @@
 /// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
 /// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 /// SOFTWARE.
+use crate::pipelines::common::DeviceAndPrecision;
 use crate::pipelines::keywords_extraction::tokenizer::StopWordsTokenizer;
 #[cfg(feature = "remote")]
 use crate::pipelines::sentence_embeddings::SentenceEmbeddingsModelType;
@@ -33,6 +34,8 @@ use rust_tokenizers::Offset;
 use std::borrow::Cow;
 use std::cmp::min;
 use std::collections::{HashMap, HashSet};
+use tch::nn::VarStore;
+use tch::Device;
 
 /// # Keyword generated by a `KeywordExtractionModel`
 #[derive(Debug, Clone)]
@@ -143,18 +146,18 @@ impl<'a> KeywordExtractionModel<'a> {
     pub fn new(
         config: KeywordExtractionConfig<'a>,
     ) -> Result<KeywordExtractionModel<'a>, RustBertError> {
-        let tokenizer_config = SentenceEmbeddingsTokenizerConfig::from_file(
+        let tokenizer_config = SentenceEmbeddingsTokenizerConfig::try_from_file(
             config
                 .sentence_embeddings_config
                 .tokenizer_config_resource
                 .get_local_path()?,
-        );
-        let sentence_bert_config = SentenceEmbeddingsSentenceBertConfig::from_file(
+        )?;
+        let sentence_bert_config = SentenceEmbeddingsSentenceBertConfig::try_from_file(
             config
                 .sentence_embeddings_config
                 .sentence_bert_config_resource
                 .get_local_path()?,
-        );
+        )?;
         let sentence_embeddings_model =
             SentenceEmbeddingsModel::new(config.sentence_embeddings_config)?;
 
@@ -268,3 +271,21 @@ impl<'a> KeywordExtractionModel<'a> {
         (flat_word_list, doc_boundaries)
     }
 }
+
+impl DeviceAndPrecision for KeywordExtractionModel<'_> {
+    fn get_var_store(&self) -> &VarStore {
+        self.sentence_embeddings_model.get_var_store()
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        self.sentence_embeddings_model.get_var_store_mut()
+    }
+
+    fn is_quantized(&self) -> bool {
+        self.sentence_embeddings_model.is_quantized()
+    }
+
+    fn try_set_device(&mut self, device: Device) -> Result<(), RustBertError> {
+        self.sentence_embeddings_model.try_set_device(device)
+    }
+}
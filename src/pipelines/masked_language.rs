@@ -45,12 +45,19 @@
 //! }
 //! ```
 //!
+use crate::albert::AlbertForMaskedLM;
 use crate::bert::BertForMaskedLM;
 use crate::common::error::RustBertError;
+use crate::common::weights::{load_weights_with_mode, WeightsLoadingMode};
 use crate::deberta::DebertaForMaskedLM;
 use crate::deberta_v2::DebertaV2ForMaskedLM;
+use crate::distilbert::DistilBertModelMaskedLM;
+use crate::electra::ElectraForMaskedLM;
 use crate::fnet::FNetForMaskedLM;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::longformer::LongformerForMaskedLM;
+use crate::mobilebert::MobileBertForMaskedLM;
+use crate::pipelines::common::{ConfigOption, DeviceAndPrecision, ModelType, TokenizerOption};
+use crate::pipelines::scoring::TextScore;
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForMaskedLM;
 #[cfg(feature = "remote")]
@@ -59,10 +66,12 @@ use crate::{
     resources::RemoteResource,
 };
 use rust_tokenizers::tokenizer::TruncationStrategy;
-use rust_tokenizers::TokenizedInput;
+use rust_tokenizers::{Offset, TokenizedInput};
 use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use tch::nn::VarStore;
-use tch::{nn, no_grad, Device, Tensor};
+use tch::{nn, no_grad, Device, Kind, Tensor};
 
 #[derive(Debug, Clone)]
 /// Output container for masked language model pipeline.
@@ -75,6 +84,74 @@ pub struct MaskedToken {
     pub score: f64,
 }
 
+/// # Decoding strategy for consecutive masked tokens
+/// Controls how spans of consecutive masked tokens (e.g. a word split in several word pieces)
+/// are filled by `MaskedLanguageModel::predict_with_options`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaskDecoding {
+    /// Each masked token of the span is predicted independently from a single forward pass (default)
+    #[default]
+    Independent,
+    /// The most confident masked token is filled first and the model is run again on the updated
+    /// sequence until all masked tokens of the span are filled
+    Iterative,
+    /// The masked tokens of the span are filled from left to right, conditioning each prediction
+    /// on the tokens already filled and keeping the `num_beams` best hypotheses at each step
+    Beam {
+        /// Number of hypotheses kept at each decoding step
+        num_beams: usize,
+    },
+}
+
+/// # Options for the fill-mask prediction
+/// Used by `MaskedLanguageModel::predict_with_options`.
+#[derive(Debug, Clone)]
+pub struct FillMaskOptions {
+    /// Number of candidates returned for each masked span (default: 1)
+    pub top_k: usize,
+    /// Optional list of words the candidates are restricted to. Only targets tokenized in as many
+    /// tokens as the masked span are considered for this span (default: None)
+    pub targets: Option<Vec<String>>,
+    /// Decoding strategy for spans of consecutive masked tokens (default: `MaskDecoding::Independent`)
+    pub decoding: MaskDecoding,
+    /// Number of sequences processed in a single forward pass when decoding consecutive masked
+    /// tokens jointly or scoring multi-token targets (default: 32)
+    pub batch_size: usize,
+}
+
+impl Default for FillMaskOptions {
+    fn default() -> Self {
+        FillMaskOptions {
+            top_k: 1,
+            targets: None,
+            decoding: MaskDecoding::default(),
+            batch_size: 32,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Candidate filling a masked span
+pub struct FillMaskCandidate {
+    /// String representation of the candidate
+    pub text: String,
+    /// Vocabulary indices of the candidate tokens
+    pub ids: Vec<i64>,
+    /// Probability of the candidate (product of the probabilities of its tokens)
+    pub score: f64,
+}
+
+#[derive(Debug, Clone)]
+/// Output container for the fill-mask prediction with options. A span groups consecutive masked tokens.
+pub struct MaskedSpan {
+    /// Position of the masked span in the original input text
+    pub offset: Option<Offset>,
+    /// Number of masked tokens in the span
+    pub num_masks: usize,
+    /// Candidates for the span, sorted by decreasing score
+    pub candidates: Vec<FillMaskCandidate>,
+}
+
 /// # Configuration for MaskedLanguageModel
 /// Contains information regarding the model to load and device to place the model on.
 pub struct MaskedLanguageConfig {
@@ -98,6 +175,8 @@ pub struct MaskedLanguageConfig {
     pub mask_token: Option<String>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
 }
 
 impl MaskedLanguageConfig {
@@ -139,6 +218,7 @@ impl MaskedLanguageConfig {
             add_prefix_space: add_prefix_space.into(),
             mask_token: mask_token.into(),
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
         }
     }
 }
@@ -175,6 +255,16 @@ pub enum MaskedLanguageOption {
     XLMRoberta(RobertaForMaskedLM),
     /// FNet for Masked Language
     FNet(FNetForMaskedLM),
+    /// Albert for Masked Language
+    Albert(AlbertForMaskedLM),
+    /// DistilBert for Masked Language
+    DistilBert(DistilBertModelMaskedLM),
+    /// Electra for Masked Language
+    Electra(ElectraForMaskedLM),
+    /// MobileBert for Masked Language
+    MobileBert(MobileBertForMaskedLM),
+    /// Longformer for Masked Language
+    Longformer(LongformerForMaskedLM),
 }
 impl MaskedLanguageOption {
     /// Instantiate a new masked language model of the supplied type.
@@ -256,6 +346,61 @@ impl MaskedLanguageOption {
                     ))
                 }
             }
+            ModelType::Albert => {
+                if let ConfigOption::Albert(config) = config {
+                    Ok(MaskedLanguageOption::Albert(AlbertForMaskedLM::new(
+                        p, config,
+                    )))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply an AlbertConfig for Albert!".to_string(),
+                    ))
+                }
+            }
+            ModelType::DistilBert => {
+                if let ConfigOption::DistilBert(config) = config {
+                    Ok(MaskedLanguageOption::DistilBert(
+                        DistilBertModelMaskedLM::new(p, config),
+                    ))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a DistilBertConfig for DistilBert!".to_string(),
+                    ))
+                }
+            }
+            ModelType::Electra => {
+                if let ConfigOption::Electra(config) = config {
+                    Ok(MaskedLanguageOption::Electra(ElectraForMaskedLM::new(
+                        p, config,
+                    )))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply an ElectraConfig for Electra!".to_string(),
+                    ))
+                }
+            }
+            ModelType::MobileBert => {
+                if let ConfigOption::MobileBert(config) = config {
+                    Ok(MaskedLanguageOption::MobileBert(
+                        MobileBertForMaskedLM::new(p, config),
+                    ))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a MobileBertConfig for MobileBert!".to_string(),
+                    ))
+                }
+            }
+            ModelType::Longformer => {
+                if let ConfigOption::Longformer(config) = config {
+                    Ok(MaskedLanguageOption::Longformer(
+                        LongformerForMaskedLM::new(p, config),
+                    ))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a LongformerConfig for Longformer!".to_string(),
+                    ))
+                }
+            }
             _ => Err(RustBertError::InvalidConfigurationError(format!(
                 "Masked Language is not implemented for {:?}!",
                 model_type
@@ -272,6 +417,11 @@ impl MaskedLanguageOption {
             Self::Roberta(_) => ModelType::Roberta,
             Self::XLMRoberta(_) => ModelType::Roberta,
             Self::FNet(_) => ModelType::FNet,
+            Self::Albert(_) => ModelType::Albert,
+            Self::DistilBert(_) => ModelType::DistilBert,
+            Self::Electra(_) => ModelType::Electra,
+            Self::MobileBert(_) => ModelType::MobileBert,
+            Self::Longformer(_) => ModelType::Longformer,
         }
     }
 
@@ -350,6 +500,63 @@ impl MaskedLanguageOption {
                     .expect("Error in FNet forward pass.")
                     .prediction_scores
             }
+            Self::Albert(ref model) => {
+                model
+                    .forward_t(
+                        input_ids,
+                        mask,
+                        token_type_ids,
+                        position_ids,
+                        input_embeds,
+                        train,
+                    )
+                    .prediction_scores
+            }
+            Self::DistilBert(ref model) => {
+                model
+                    .forward_t(input_ids, mask, input_embeds, train)
+                    .expect("Error in DistilBert forward pass.")
+                    .prediction_scores
+            }
+            Self::Electra(ref model) => {
+                model
+                    .forward_t(
+                        input_ids,
+                        mask,
+                        token_type_ids,
+                        position_ids,
+                        input_embeds,
+                        train,
+                    )
+                    .prediction_scores
+            }
+            Self::MobileBert(ref model) => {
+                model
+                    .forward_t(
+                        input_ids,
+                        token_type_ids,
+                        position_ids,
+                        input_embeds,
+                        mask,
+                        train,
+                    )
+                    .expect("Error in MobileBert forward pass.")
+                    .logits
+            }
+            Self::Longformer(ref model) => {
+                model
+                    .forward_t(
+                        input_ids,
+                        mask,
+                        None,
+                        token_type_ids,
+                        position_ids,
+                        input_embeds,
+                        train,
+                    )
+                    .expect("Error in Longformer forward pass.")
+                    .prediction_scores
+            }
         }
     }
 }
@@ -400,7 +607,7 @@ impl MaskedLanguageModel {
             config.add_prefix_space,
         )?;
         let mut var_store = VarStore::new(device);
-        let model_config = ConfigOption::from_file(config.model_type, config_path);
+        let model_config = ConfigOption::try_from_file(config.model_type, config_path)?;
         let max_length = model_config
             .get_max_len()
             .map(|v| v as usize)
@@ -408,7 +615,7 @@ impl MaskedLanguageModel {
 
         let language_encode =
             MaskedLanguageOption::new(config.model_type, var_store.root(), &model_config)?;
-        var_store.load(weights_path)?;
+        load_weights_with_mode(weights_path, &mut var_store, config.weights_loading_mode)?;
         let mask_token = config.mask_token;
         Ok(MaskedLanguageModel {
             tokenizer,
@@ -552,7 +759,715 @@ impl MaskedLanguageModel {
         }
         Ok(output_tokens)
     }
+
+    /// Log-probabilities over the vocabulary at the masked positions of a batch of tokenized inputs,
+    /// processed in a single forward pass (right-padded to the longest input). Returns a tensor of
+    /// shape (*number of masked positions*, *vocab_size*) for each input.
+    fn masked_log_probs(
+        &self,
+        tokenized_input: &[TokenizedInput],
+        masked_positions: &[Vec<usize>],
+    ) -> Vec<Tensor> {
+        let device = self.var_store.device();
+        let pad_id = self.tokenizer.get_pad_id().unwrap_or(0);
+        let max_len = tokenized_input
+            .iter()
+            .map(|input| input.token_ids.len())
+            .max()
+            .unwrap();
+        let mut input_ids = Vec::with_capacity(tokenized_input.len() * max_len);
+        let mut attention_mask = Vec::with_capacity(tokenized_input.len() * max_len);
+        for input in tokenized_input {
+            let padding_length = max_len - input.token_ids.len();
+            input_ids.extend_from_slice(&input.token_ids);
+            input_ids.extend(vec![pad_id; padding_length]);
+            attention_mask.extend(vec![1i64; input.token_ids.len()]);
+            attention_mask.extend(vec![0i64; padding_length]);
+        }
+        let shape = [tokenized_input.len() as i64, max_len as i64];
+        let input_ids = Tensor::of_slice(&input_ids).view(shape).to(device);
+        let attention_mask = Tensor::of_slice(&attention_mask).view(shape).to(device);
+        no_grad(|| {
+            let logits = self.language_encode.forward_t(
+                Some(&input_ids),
+                Some(&attention_mask),
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            );
+            masked_positions
+                .iter()
+                .enumerate()
+                .map(|(input_index, positions)| {
+                    let positions = Tensor::of_slice(
+                        &positions
+                            .iter()
+                            .map(|&position| position as i64)
+                            .collect::<Vec<i64>>(),
+                    )
+                    .to(device);
+                    logits
+                        .get(input_index as i64)
+                        .index_select(0, &positions)
+                        .log_softmax(-1, Kind::Float)
+                })
+                .collect()
+        })
+    }
+
+    /// Fill the masked spans of texts with whole-word candidates
+    ///
+    /// Consecutive masked tokens are grouped in a span filled by a single candidate, allowing the
+    /// prediction of words split in several tokens. The spans of an input are filled independently
+    /// of each other, keeping the other spans masked.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to mask.
+    /// * `options` - `FillMaskOptions` controlling the number of candidates, the target words and the decoding of consecutive masked tokens.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<MaskedSpan>>` containing the candidates for each masked span of the input texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::masked_language::{
+    ///     FillMaskOptions, MaskDecoding, MaskedLanguageModel,
+    /// };
+    /// //    Set-up model
+    /// let mask_language_model = MaskedLanguageModel::new(Default::default())?;
+    ///
+    /// //    Define input
+    /// let input = ["Paris is the [MASK] of France.", "It was a [MASK] [MASK] day"];
+    /// let options = FillMaskOptions {
+    ///     top_k: 3,
+    ///     decoding: MaskDecoding::Beam { num_beams: 5 },
+    ///     ..Default::default()
+    /// };
+    ///
+    /// //    Run model
+    /// let output = mask_language_model.predict_with_options(&input, &options)?;
+    /// for spans in output {
+    ///     println!("{:?}", spans);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_with_options<'a, S>(
+        &self,
+        input: S,
+        options: &FillMaskOptions,
+    ) -> Result<Vec<Vec<MaskedSpan>>, RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+    {
+        if options.top_k == 0 {
+            return Err(RustBertError::ValueError(
+                "The number of candidates `top_k` must be greater than 0".into(),
+            ));
+        }
+        if options.batch_size == 0 {
+            return Err(RustBertError::ValueError(
+                "The fill-mask batch size must be greater than 0".into(),
+            ));
+        }
+        if let MaskDecoding::Beam { num_beams: 0 } = options.decoding {
+            return Err(RustBertError::ValueError(
+                "The number of beams must be greater than 0".into(),
+            ));
+        }
+        let mask_token_id =
+            self.tokenizer
+                .get_mask_id()
+                .ok_or_else(|| RustBertError::InvalidConfigurationError(
+                    "Tokenizer does not have a mask token id, Please use a tokenizer/model with a mask token.".into(),
+                ))?;
+        let targets = match &options.targets {
+            Some(targets) => Some(self.tokenize_targets(targets)?),
+            None => None,
+        };
+
+        let mut texts = Vec::with_capacity(input.as_ref().len());
+        let mut mask_starts = Vec::with_capacity(input.as_ref().len());
+        let mut length_difference = 0;
+        for &text in input.as_ref() {
+            match &self.mask_token {
+                Some(mask_token) => {
+                    let (text, text_mask_starts, text_length_difference) =
+                        self.replace_mask_token_with_positions(text, mask_token)?;
+                    texts.push(text);
+                    mask_starts.push(text_mask_starts);
+                    length_difference = text_length_difference;
+                }
+                None => {
+                    texts.push(text.to_string());
+                    mask_starts.push(vec![]);
+                }
+            }
+        }
+        let tokenized_input = self.tokenizer.encode_list(
+            &texts
+                .iter()
+                .map(|text| text.as_str())
+                .collect::<Vec<&str>>(),
+            self.max_length,
+            &TruncationStrategy::LongestFirst,
+            0,
+        );
+
+        // Group consecutive masked tokens in spans
+        let spans = tokenized_input
+            .iter()
+            .map(|tokenized| {
+                let mut spans: Vec<Vec<usize>> = vec![];
+                for (position, &token_id) in tokenized.token_ids.iter().enumerate() {
+                    if token_id == mask_token_id {
+                        match spans.last_mut() {
+                            Some(span) if *span.last().unwrap() + 1 == position => {
+                                span.push(position)
+                            }
+                            _ => spans.push(vec![position]),
+                        }
+                    }
+                }
+                spans
+            })
+            .collect::<Vec<Vec<Vec<usize>>>>();
+        if spans.iter().all(|input_spans| input_spans.is_empty()) {
+            return Ok(vec![vec![]; tokenized_input.len()]);
+        }
+
+        let masked_positions = spans
+            .iter()
+            .map(|input_spans| input_spans.concat())
+            .collect::<Vec<Vec<usize>>>();
+        let log_probs = self.masked_log_probs(&tokenized_input, &masked_positions);
+        let mut output = Vec::with_capacity(tokenized_input.len());
+        for (input_index, (tokenized, input_spans)) in tokenized_input.iter().zip(spans).enumerate()
+        {
+            let token_ids = &tokenized.token_ids;
+            let mut masked_spans = Vec::with_capacity(input_spans.len());
+            let mut span_start = 0;
+            for positions in input_spans {
+                let input_log_probs =
+                    log_probs[input_index].narrow(0, span_start, positions.len() as i64);
+                span_start += positions.len() as i64;
+                let mut hypotheses = match (&targets, options.decoding) {
+                    (None, MaskDecoding::Independent) => self.beam_search(
+                        token_ids,
+                        &positions,
+                        &input_log_probs,
+                        options.top_k,
+                        false,
+                        options.batch_size,
+                    )?,
+                    (None, MaskDecoding::Beam { num_beams }) => self.beam_search(
+                        token_ids,
+                        &positions,
+                        &input_log_probs,
+                        num_beams.max(options.top_k),
+                        true,
+                        options.batch_size,
+                    )?,
+                    (None, MaskDecoding::Iterative) => self.iterative_search(
+                        token_ids,
+                        &positions,
+                        &input_log_probs,
+                        options.top_k,
+                        options.batch_size,
+                    )?,
+                    (Some(targets), decoding) => self.score_targets(
+                        token_ids,
+                        &positions,
+                        &input_log_probs,
+                        targets,
+                        decoding != MaskDecoding::Independent,
+                        options.batch_size,
+                    ),
+                };
+                hypotheses.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+                hypotheses.truncate(options.top_k);
+
+                let candidates = hypotheses
+                    .into_iter()
+                    .map(|(ids, score)| FillMaskCandidate {
+                        text: self.tokenizer.decode(&ids, true, true).trim().to_string(),
+                        ids,
+                        score: score.exp(),
+                    })
+                    .collect();
+                let offset = match (
+                    tokenized.token_offsets[positions[0]],
+                    tokenized.token_offsets[*positions.last().unwrap()],
+                ) {
+                    (Some(start), Some(end)) => {
+                        let to_original = |position: u32| {
+                            let num_masks_before = mask_starts[input_index]
+                                .iter()
+                                .filter(|&&start| start < position)
+                                .count() as i64;
+                            (position as i64 - num_masks_before * length_difference) as u32
+                        };
+                        Some(Offset {
+                            begin: to_original(start.begin),
+                            end: to_original(end.end),
+                        })
+                    }
+                    _ => None,
+                };
+                masked_spans.push(MaskedSpan {
+                    offset,
+                    num_masks: positions.len(),
+                    candidates,
+                });
+            }
+            output.push(masked_spans);
+        }
+        Ok(output)
+    }
+
+    /// Compute the pseudo-log-likelihood of texts
+    ///
+    /// Each token of the texts is masked in turn and scored by the model given all other tokens. The
+    /// pseudo-log-likelihood of a text is the sum of the log-probabilities of its tokens. Special tokens
+    /// are not scored, and texts longer than the maximum length of the model are truncated.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to score.
+    /// * `batch_size` - Number of masked copies of the texts processed in a single forward pass.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<TextScore>` containing the token log-probabilities, pseudo-log-likelihood and pseudo-perplexity of the input texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::masked_language::MaskedLanguageModel;
+    /// //    Set-up model
+    /// let mask_language_model = MaskedLanguageModel::new(Default::default())?;
+    ///
+    /// //    Define input
+    /// let input = ["The cat sat on the mat.", "Mat the on sat cat the."];
+    ///
+    /// //    Run model
+    /// let output = mask_language_model.pseudo_log_likelihood(&input, 16)?;
+    /// for text_score in output {
+    ///     println!("{}", text_score.log_likelihood);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pseudo_log_likelihood<'a, S>(
+        &self,
+        input: S,
+        batch_size: usize,
+    ) -> Result<Vec<TextScore>, RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+    {
+        if batch_size == 0 {
+            return Err(RustBertError::ValueError(
+                "The scoring batch size must be greater than 0".into(),
+            ));
+        }
+        let mask_token_id =
+            self.tokenizer
+                .get_mask_id()
+                .ok_or_else(|| RustBertError::InvalidConfigurationError(
+                    "Tokenizer does not have a mask token id, Please use a tokenizer/model with a mask token.".into(),
+                ))?;
+        let tokenized_input = self.tokenizer.encode_list(
+            input.as_ref(),
+            self.max_length,
+            &TruncationStrategy::LongestFirst,
+            0,
+        );
+        // (input index, masked position) for each masked copy of the inputs
+        let masked_positions = tokenized_input
+            .iter()
+            .enumerate()
+            .flat_map(|(input_index, tokenized)| {
+                tokenized
+                    .special_tokens_mask
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &is_special)| is_special == 0)
+                    .map(move |(position, _)| (input_index, position))
+            })
+            .collect::<Vec<(usize, usize)>>();
+
+        let device = self.var_store.device();
+        let pad_id = self.tokenizer.get_pad_id().unwrap_or(0);
+        let mut token_log_probabilities = tokenized_input
+            .iter()
+            .map(|tokenized| vec![None; tokenized.token_ids.len()])
+            .collect::<Vec<Vec<Option<f64>>>>();
+        for batch in masked_positions.chunks(batch_size) {
+            let batch_length = batch
+                .iter()
+                .map(|(input_index, _)| tokenized_input[*input_index].token_ids.len())
+                .max()
+                .unwrap();
+            let mut input_ids = Vec::with_capacity(batch.len() * batch_length);
+            let mut attention_mask = Vec::with_capacity(batch.len() * batch_length);
+            let mut target_ids = Vec::with_capacity(batch.len());
+            for &(input_index, position) in batch {
+                let token_ids = &tokenized_input[input_index].token_ids;
+                let padding_length = batch_length - token_ids.len();
+                let mut masked_ids = token_ids.clone();
+                masked_ids[position] = mask_token_id;
+                input_ids.extend(masked_ids);
+                input_ids.extend(vec![pad_id; padding_length]);
+                attention_mask.extend(vec![1i64; token_ids.len()]);
+                attention_mask.extend(vec![0i64; padding_length]);
+                target_ids.push(token_ids[position]);
+            }
+            let shape = [batch.len() as i64, batch_length as i64];
+            let input_ids = Tensor::of_slice(&input_ids).view(shape).to(device);
+            let attention_mask = Tensor::of_slice(&attention_mask).view(shape).to(device);
+            let positions = Tensor::of_slice(
+                &batch
+                    .iter()
+                    .map(|(_, position)| *position as i64)
+                    .collect::<Vec<i64>>(),
+            )
+            .to(device);
+            let target_ids = Tensor::of_slice(&target_ids).to(device);
+
+            let batch_log_probabilities = no_grad(|| {
+                let logits = self.language_encode.forward_t(
+                    Some(&input_ids),
+                    Some(&attention_mask),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                );
+                let vocab_size = logits.size()[2];
+                logits
+                    .gather(
+                        1,
+                        &positions
+                            .view([-1, 1, 1])
+                            .expand(&[-1, 1, vocab_size], true),
+                        false,
+                    )
+                    .squeeze_dim(1)
+                    .log_softmax(-1, Kind::Float)
+                    .gather(1, &target_ids.unsqueeze(-1), false)
+                    .squeeze_dim(-1)
+                    .to(Device::Cpu)
+            });
+            for (&(input_index, position), log_probability) in
+                batch.iter().zip(batch_log_probabilities.iter::<f64>()?)
+            {
+                token_log_probabilities[input_index][position] = Some(log_probability);
+            }
+        }
+
+        Ok(tokenized_input
+            .into_iter()
+            .zip(token_log_probabilities)
+            .map(|(tokenized, token_log_probabilities)| {
+                TextScore::new(tokenized.token_ids, token_log_probabilities)
+            })
+            .collect())
+    }
+
+    /// Replace the custom mask token of a text by the language model mask token, returning the
+    /// character positions of the replaced masks and the length difference between both tokens.
+    fn replace_mask_token_with_positions(
+        &self,
+        text: &str,
+        mask_token: &str,
+    ) -> Result<(String, Vec<u32>, i64), RustBertError> {
+        let model_mask_token = self.tokenizer.get_mask_value().ok_or_else(||
+            RustBertError::InvalidConfigurationError("Tokenizer does ot have a default mask token and no mask token provided in configuration. \
+            Please provide a `mask_token` in the configuration.".into()))?;
+        let model_mask_length = model_mask_token.chars().count() as u32;
+        let mut output = String::with_capacity(text.len());
+        let mut mask_starts = vec![];
+        let mut position = 0;
+        for (index, segment) in text.split(mask_token).enumerate() {
+            if index > 0 {
+                mask_starts.push(position);
+                output.push_str(model_mask_token);
+                position += model_mask_length;
+            }
+            output.push_str(segment);
+            position += segment.chars().count() as u32;
+        }
+        let length_difference = model_mask_length as i64 - mask_token.chars().count() as i64;
+        Ok((output, mask_starts, length_difference))
+    }
+
+    /// Tokenize the target words as they would appear after a white space in the input text.
+    fn tokenize_targets(&self, targets: &[String]) -> Result<Vec<Vec<i64>>, RustBertError> {
+        let mut seen = HashSet::new();
+        let target_ids = targets
+            .iter()
+            .map(|target| {
+                let tokens = self.tokenizer.tokenize(&format!(" {}", target.trim()));
+                self.tokenizer.convert_tokens_to_ids(&tokens)
+            })
+            .filter(|ids| !ids.is_empty() && seen.insert(ids.clone()))
+            .collect::<Vec<Vec<i64>>>();
+        if target_ids.is_empty() {
+            return Err(RustBertError::ValueError(
+                "No valid target provided for the fill-mask prediction".into(),
+            ));
+        }
+        Ok(target_ids)
+    }
+
+    /// Log-probabilities over the vocabulary at the given positions, of shape (*number of sequences*,
+    /// *number of positions*, *vocab_size*), for sequences of identical length processed by batches.
+    fn span_log_probs(
+        &self,
+        sequences: &[Vec<i64>],
+        positions: &[usize],
+        batch_size: usize,
+    ) -> Tensor {
+        let device = self.var_store.device();
+        let positions = Tensor::of_slice(
+            &positions
+                .iter()
+                .map(|&position| position as i64)
+                .collect::<Vec<i64>>(),
+        )
+        .to(device);
+        let log_probs = sequences
+            .chunks(batch_size)
+            .map(|batch| {
+                let input_tensor = Tensor::of_slice(&batch.concat())
+                    .view([batch.len() as i64, -1])
+                    .to(device);
+                no_grad(|| {
+                    self.language_encode
+                        .forward_t(
+                            Some(&input_tensor),
+                            None,
+                            None,
+                            None,
+                            None,
+                            None,
+                            None,
+                            false,
+                        )
+                        .index_select(1, &positions)
+                        .log_softmax(-1, Kind::Float)
+                })
+            })
+            .collect::<Vec<Tensor>>();
+        Tensor::cat(&log_probs, 0)
+    }
+
+    /// Best tokens and their log-probabilities for a vector of log-probabilities
+    fn top_tokens(log_probs: &Tensor, k: usize) -> Result<Vec<(i64, f64)>, RustBertError> {
+        let k = k.min(log_probs.size()[0] as usize) as i64;
+        let (values, indices) = log_probs.topk(k, -1, true, true);
+        Ok(indices.iter::<i64>()?.zip(values.iter::<f64>()?).collect())
+    }
+
+    /// Copy of a sequence with some of its masked positions filled
+    fn fill_positions(token_ids: &[i64], filled: impl Iterator<Item = (usize, i64)>) -> Vec<i64> {
+        let mut sequence = token_ids.to_vec();
+        for (position, token_id) in filled {
+            sequence[position] = token_id;
+        }
+        sequence
+    }
+
+    /// Fill a span from left to right keeping the `num_beams` best hypotheses. If `conditional` is
+    /// false, all positions are scored with the log-probabilities of the initial masked sequence
+    /// (`initial_log_probs`, of shape (*span length*, *vocab_size*)).
+    fn beam_search(
+        &self,
+        token_ids: &[i64],
+        positions: &[usize],
+        initial_log_probs: &Tensor,
+        num_beams: usize,
+        conditional: bool,
+        batch_size: usize,
+    ) -> Result<Vec<(Vec<i64>, f64)>, RustBertError> {
+        let mut beams: Vec<(Vec<i64>, f64)> = vec![(vec![], 0.0)];
+        for (step, &position) in positions.iter().enumerate() {
+            let step_log_probs = if conditional && step > 0 {
+                let sequences = beams
+                    .iter()
+                    .map(|(ids, _)| {
+                        Self::fill_positions(token_ids, positions.iter().copied().zip(ids.clone()))
+                    })
+                    .collect::<Vec<Vec<i64>>>();
+                Some(self.span_log_probs(&sequences, &[position], batch_size))
+            } else {
+                None
+            };
+            let mut expanded = Vec::with_capacity(beams.len() * num_beams);
+            for (beam_index, (ids, score)) in beams.iter().enumerate() {
+                let position_log_probs = match &step_log_probs {
+                    Some(log_probs) => log_probs.get(beam_index as i64).get(0),
+                    None => initial_log_probs.get(step as i64),
+                };
+                for (token_id, token_score) in Self::top_tokens(&position_log_probs, num_beams)? {
+                    let mut new_ids = ids.clone();
+                    new_ids.push(token_id);
+                    expanded.push((new_ids, score + token_score));
+                }
+            }
+            expanded.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+            expanded.truncate(num_beams);
+            beams = expanded;
+        }
+        Ok(beams)
+    }
+
+    /// Fill a span by repeatedly filling its most confident masked token. The `top_k` candidates
+    /// start from the best tokens at the most confident position of the initial sequence
+    /// (`initial_log_probs`, of shape (*span length*, *vocab_size*)).
+    fn iterative_search(
+        &self,
+        token_ids: &[i64],
+        positions: &[usize],
+        initial_log_probs: &Tensor,
+        top_k: usize,
+        batch_size: usize,
+    ) -> Result<Vec<(Vec<i64>, f64)>, RustBertError> {
+        let (first_index, _) = (0..positions.len())
+            .map(|index| initial_log_probs.get(index as i64).max().double_value(&[]))
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .unwrap();
+        let first_log_probs = initial_log_probs.get(first_index as i64);
+        let mut hypotheses = Self::top_tokens(&first_log_probs, top_k)?
+            .into_iter()
+            .map(|(token_id, score)| {
+                let mut filled = vec![None; positions.len()];
+                filled[first_index] = Some(token_id);
+                (filled, score)
+            })
+            .collect::<Vec<(Vec<Option<i64>>, f64)>>();
+
+        for _ in 1..positions.len() {
+            let sequences = hypotheses
+                .iter()
+                .map(|(filled, _)| {
+                    Self::fill_positions(
+                        token_ids,
+                        positions
+                            .iter()
+                            .zip(filled)
+                            .filter_map(|(&position, token_id)| token_id.map(|id| (position, id))),
+                    )
+                })
+                .collect::<Vec<Vec<i64>>>();
+            let log_probs = self.span_log_probs(&sequences, positions, batch_size);
+            for (row, (filled, score)) in hypotheses.iter_mut().enumerate() {
+                let (index, token_id, token_score) = (0..positions.len())
+                    .filter(|index| filled[*index].is_none())
+                    .map(|index| {
+                        let (value, token_id) = log_probs
+                            .get(row as i64)
+                            .get(index as i64)
+                            .max_dim(-1, false);
+                        (index, token_id.int64_value(&[]), value.double_value(&[]))
+                    })
+                    .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal))
+                    .unwrap();
+                filled[index] = Some(token_id);
+                *score += token_score;
+            }
+        }
+        Ok(hypotheses
+            .into_iter()
+            .map(|(filled, score)| (filled.into_iter().flatten().collect(), score))
+            .collect())
+    }
+
+    /// Score the targets matching the span length. If `conditional` is true, the targets are
+    /// scored token by token from left to right, each token conditioned on the previous ones.
+    /// The partially filled sequences are scored by batches of `batch_size`. Otherwise, the targets
+    /// are scored with `initial_log_probs`, of shape (*span length*, *vocab_size*).
+    fn score_targets(
+        &self,
+        token_ids: &[i64],
+        positions: &[usize],
+        initial_log_probs: &Tensor,
+        targets: &[Vec<i64>],
+        conditional: bool,
+        batch_size: usize,
+    ) -> Vec<(Vec<i64>, f64)> {
+        let targets = targets
+            .iter()
+            .filter(|target| target.len() == positions.len())
+            .collect::<Vec<&Vec<i64>>>();
+        if targets.is_empty() {
+            return vec![];
+        }
+        if !conditional || positions.len() == 1 {
+            return targets
+                .into_iter()
+                .map(|target| {
+                    let score = target
+                        .iter()
+                        .enumerate()
+                        .map(|(step, &token_id)| {
+                            initial_log_probs.double_value(&[step as i64, token_id])
+                        })
+                        .sum();
+                    (target.clone(), score)
+                })
+                .collect();
+        }
+        // (target index, step) for each partially filled sequence
+        let rows = (0..targets.len())
+            .flat_map(|target_index| (0..positions.len()).map(move |step| (target_index, step)))
+            .collect::<Vec<(usize, usize)>>();
+        let mut scores = vec![0f64; targets.len()];
+        for batch in rows.chunks(batch_size) {
+            let sequences = batch
+                .iter()
+                .map(|&(target_index, step)| {
+                    Self::fill_positions(
+                        token_ids,
+                        positions[..step]
+                            .iter()
+                            .copied()
+                            .zip(targets[target_index].iter().copied()),
+                    )
+                })
+                .collect::<Vec<Vec<i64>>>();
+            let log_probs = self.span_log_probs(&sequences, positions, batch.len());
+            for (row, &(target_index, step)) in batch.iter().enumerate() {
+                scores[target_index] +=
+                    log_probs.double_value(&[row as i64, step as i64, targets[target_index][step]]);
+            }
+        }
+        targets.into_iter().cloned().zip(scores).collect()
+    }
+}
+
+impl DeviceAndPrecision for MaskedLanguageModel {
+    fn get_var_store(&self) -> &VarStore {
+        &self.var_store
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        &mut self.var_store
+    }
 }
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -121,7 +121,7 @@
 //! telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more
 //! about exoplanets like K2-18b."];
 //!
-//! let output = model.summarize(&input);
+//! let output = model.summarize(&input)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -154,7 +154,7 @@
 //!
 //! let conversation_id =
 //!     conversation_manager.create("Going to the movies tonight - any suggestions?");
-//! let output = conversation_model.generate_responses(&mut conversation_manager);
+//! let output = conversation_model.generate_responses(&mut conversation_manager)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -181,7 +181,7 @@
 //!
 //! let prefix = None; // Optional prefix to append prompts with, will be excluded from the generated output
 //!
-//! let output = model.generate(&[input_context_1, input_context_2], prefix);
+//! let output = model.generate(&[input_context_1, input_context_2], prefix)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -479,9 +479,11 @@ pub mod conversation;
 pub mod generation_utils;
 pub mod keywords_extraction;
 pub mod masked_language;
+pub mod multiple_choice;
 pub mod ner;
 pub mod pos_tagging;
 pub mod question_answering;
+pub mod scoring;
 pub mod sentence_embeddings;
 pub mod sentiment;
 pub mod sequence_classification;
@@ -0,0 +1,727 @@
+// Copyright 2019-present, the HuggingFace Inc. team, The Google AI Language Team and Facebook, Inc.
+// Copyright 2019 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Multiple choice pipeline
+//! Ranks a set of candidate answers for a given context and question (e.g. SWAG or RACE tasks).
+//! Each candidate is encoded together with the context as a pair of sequences
+//! (`context`, `question choice`), and the candidates are scored jointly by the multiple choice head
+//! of the model. The pipeline supports BERT, RoBERTa, XLM-RoBERTa, ALBERT, XLNet, Longformer,
+//! MobileBERT and FNet models fine-tuned on a multiple choice task.
+//!
+//! ```no_run
+//! use rust_bert::bert::{BertConfigResources, BertVocabResources};
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::multiple_choice::{
+//!     MultipleChoiceConfig, MultipleChoiceInput, MultipleChoiceModel,
+//! };
+//! use rust_bert::resources::{LocalResource, RemoteResource};
+//! use std::path::PathBuf;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let config = MultipleChoiceConfig::new(
+//!     ModelType::Bert,
+//!     LocalResource {
+//!         local_path: PathBuf::from("path/to/bert-swag/rust_model.ot"),
+//!     },
+//!     RemoteResource::from_pretrained(BertConfigResources::BERT),
+//!     RemoteResource::from_pretrained(BertVocabResources::BERT),
+//!     None,
+//!     true,
+//!     None,
+//!     None,
+//! );
+//! let multiple_choice_model = MultipleChoiceModel::new(config)?;
+//!
+//! let input = MultipleChoiceInput {
+//!     context: String::from("A woman is outside with a bucket and a dog."),
+//!     question: String::from("The woman"),
+//!     choices: vec![
+//!         String::from("rinses the bucket off with soap and blow dries the dog."),
+//!         String::from("uses a hose to keep the dog from getting soapy."),
+//!         String::from("gets the dog wet, then it runs away again."),
+//!     ],
+//! };
+//! let output = multiple_choice_model.predict(&[input], 8)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Output: \
+//! ```no_run
+//! # use rust_bert::pipelines::multiple_choice::Choice;
+//! # let output =
+//! [[
+//!     Choice {
+//!         index: 2,
+//!         text: String::from("gets the dog wet, then it runs away again."),
+//!         score: 0.8721,
+//!     },
+//!     Choice {
+//!         index: 1,
+//!         text: String::from("uses a hose to keep the dog from getting soapy."),
+//!         score: 0.1047,
+//!     },
+//!     Choice {
+//!         index: 0,
+//!         text: String::from("rinses the bucket off with soap and blow dries the dog."),
+//!         score: 0.0232,
+//!     },
+//! ]]
+//! # ;
+//! ```
+
+use crate::albert::AlbertForMultipleChoice;
+use crate::bert::BertForMultipleChoice;
+use crate::common::error::RustBertError;
+use crate::common::weights::{load_weights_with_mode, WeightsLoadingMode};
+use crate::fnet::FNetForMultipleChoice;
+use crate::longformer::LongformerForMultipleChoice;
+use crate::mobilebert::MobileBertForMultipleChoice;
+use crate::pipelines::common::{ConfigOption, DeviceAndPrecision, ModelType, TokenizerOption};
+use crate::resources::ResourceProvider;
+use crate::roberta::RobertaForMultipleChoice;
+use crate::xlnet::XLNetForMultipleChoice;
+use rust_tokenizers::tokenizer::TruncationStrategy;
+use rust_tokenizers::TokenizedInput;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use tch::nn::VarStore;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// # Input for multiple choice
+/// Includes a context, an optional question (may be empty) and the candidate answers
+pub struct MultipleChoiceInput {
+    /// Context (first sequence of each pair)
+    pub context: String,
+    /// Question or beginning of the answer, prepended to each choice in the second sequence of each pair
+    pub question: String,
+    /// Candidate answers
+    pub choices: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// # Output for multiple choice
+pub struct Choice {
+    /// Position of the choice in the `MultipleChoiceInput` candidates
+    pub index: usize,
+    /// Candidate answer
+    pub text: String,
+    /// Probability of the choice (normalized over the candidates of the input)
+    pub score: f64,
+}
+
+/// # Configuration for multiple choice
+/// Contains information regarding the model to load and device to place the model on.
+pub struct MultipleChoiceConfig {
+    /// Model weights resource
+    pub model_resource: Box<dyn ResourceProvider + Send>,
+    /// Config resource
+    pub config_resource: Box<dyn ResourceProvider + Send>,
+    /// Vocab resource
+    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    /// Merges resource (default: None)
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    /// Device to place the model on (default: CUDA/GPU when available)
+    pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
+    /// Model type
+    pub model_type: ModelType,
+    /// Flag indicating if the model expects a lower casing of the input
+    pub lower_case: bool,
+    /// Flag indicating if the tokenizer should strip accents (normalization). Only used for BERT / ALBERT models
+    pub strip_accents: Option<bool>,
+    /// Flag indicating if the tokenizer should add a white space before each tokenized input (needed for some Roberta models)
+    pub add_prefix_space: Option<bool>,
+    /// Maximum sequence length for each (context, choice) pair. The context is truncated first.
+    pub max_seq_length: usize,
+}
+
+impl MultipleChoiceConfig {
+    /// Instantiate a new multiple choice configuration of the supplied type.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ModelType` indicating the model type to load (must match with the actual data to be loaded!)
+    /// * model_resource - The `ResourceProvider` pointing to the model to load (e.g.  model.ot)
+    /// * config_resource - The `ResourceProvider` pointing to the model configuration to load (e.g. config.json)
+    /// * vocab_resource - The `ResourceProvider` pointing to the tokenizer's vocabulary to load (e.g.  vocab.txt/vocab.json)
+    /// * merges_resource - An optional `ResourceProvider` pointing to the tokenizer's merge file to load (e.g.  merges.txt), needed only for Roberta.
+    /// * lower_case - A `bool` indicating whether the tokenizer should lower case all input (in case of a lower-cased model)
+    pub fn new<RM, RC, RV>(
+        model_type: ModelType,
+        model_resource: RM,
+        config_resource: RC,
+        vocab_resource: RV,
+        merges_resource: Option<RV>,
+        lower_case: bool,
+        strip_accents: impl Into<Option<bool>>,
+        add_prefix_space: impl Into<Option<bool>>,
+    ) -> MultipleChoiceConfig
+    where
+        RM: ResourceProvider + Send + 'static,
+        RC: ResourceProvider + Send + 'static,
+        RV: ResourceProvider + Send + 'static,
+    {
+        MultipleChoiceConfig {
+            model_type,
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(config_resource),
+            vocab_resource: Box::new(vocab_resource),
+            merges_resource: merges_resource.map(|r| Box::new(r) as Box<_>),
+            lower_case,
+            strip_accents: strip_accents.into(),
+            add_prefix_space: add_prefix_space.into(),
+            device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
+            max_seq_length: 256,
+        }
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+/// # Abstraction that holds one particular multiple choice model, for any of the supported models
+pub enum MultipleChoiceOption {
+    /// Bert for Multiple Choice
+    Bert(BertForMultipleChoice),
+    /// Roberta for Multiple Choice
+    Roberta(RobertaForMultipleChoice),
+    /// XLMRoberta for Multiple Choice
+    XLMRoberta(RobertaForMultipleChoice),
+    /// Albert for Multiple Choice
+    Albert(AlbertForMultipleChoice),
+    /// XLNet for Multiple Choice
+    XLNet(XLNetForMultipleChoice),
+    /// Longformer for Multiple Choice
+    Longformer(LongformerForMultipleChoice),
+    /// MobileBert for Multiple Choice
+    MobileBert(MobileBertForMultipleChoice),
+    /// FNet for Multiple Choice
+    FNet(FNetForMultipleChoice),
+}
+
+impl MultipleChoiceOption {
+    /// Instantiate a new multiple choice model of the supplied type.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ModelType` indicating the model type to load (must match with the actual data to be loaded)
+    /// * `p` - `tch::nn::Path` path to the model file to load (e.g. model.ot)
+    /// * `config` - A configuration (the model type of the configuration must be compatible with the value for
+    ///   `model_type`)
+    pub fn new<'p, P>(
+        model_type: ModelType,
+        p: P,
+        config: &ConfigOption,
+    ) -> Result<Self, RustBertError>
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        match model_type {
+            ModelType::Bert => {
+                if let ConfigOption::Bert(config) = config {
+                    Ok(MultipleChoiceOption::Bert(BertForMultipleChoice::new(
+                        p, config,
+                    )))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a BertConfig for Bert!".to_string(),
+                    ))
+                }
+            }
+            ModelType::Roberta => {
+                if let ConfigOption::Roberta(config) = config {
+                    Ok(MultipleChoiceOption::Roberta(
+                        RobertaForMultipleChoice::new(p, config),
+                    ))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a RobertaConfig for Roberta!".to_string(),
+                    ))
+                }
+            }
+            ModelType::XLMRoberta => {
+                if let ConfigOption::Bert(config) = config {
+                    Ok(MultipleChoiceOption::XLMRoberta(
+                        RobertaForMultipleChoice::new(p, config),
+                    ))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a BertConfig for Roberta!".to_string(),
+                    ))
+                }
+            }
+            ModelType::Albert => {
+                if let ConfigOption::Albert(config) = config {
+                    Ok(MultipleChoiceOption::Albert(AlbertForMultipleChoice::new(
+                        p, config,
+                    )))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply an AlbertConfig for Albert!".to_string(),
+                    ))
+                }
+            }
+            ModelType::XLNet => {
+                if let ConfigOption::XLNet(config) = config {
+                    Ok(MultipleChoiceOption::XLNet(XLNetForMultipleChoice::new(
+                        p, config,
+                    )?))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a XLNetConfig for XLNet!".to_string(),
+                    ))
+                }
+            }
+            ModelType::Longformer => {
+                if let ConfigOption::Longformer(config) = config {
+                    Ok(MultipleChoiceOption::Longformer(
+                        LongformerForMultipleChoice::new(p, config),
+                    ))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a LongformerConfig for Longformer!".to_string(),
+                    ))
+                }
+            }
+            ModelType::MobileBert => {
+                if let ConfigOption::MobileBert(config) = config {
+                    Ok(MultipleChoiceOption::MobileBert(
+                        MobileBertForMultipleChoice::new(p, config),
+                    ))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a MobileBertConfig for MobileBert!".to_string(),
+                    ))
+                }
+            }
+            ModelType::FNet => {
+                if let ConfigOption::FNet(config) = config {
+                    Ok(MultipleChoiceOption::FNet(FNetForMultipleChoice::new(
+                        p, config,
+                    )))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a FNetConfig for FNet!".to_string(),
+                    ))
+                }
+            }
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Multiple choice not implemented for {:?}!",
+                model_type
+            ))),
+        }
+    }
+
+    /// Returns the `ModelType` for this MultipleChoiceOption
+    pub fn model_type(&self) -> ModelType {
+        match *self {
+            Self::Bert(_) => ModelType::Bert,
+            Self::Roberta(_) => ModelType::Roberta,
+            Self::XLMRoberta(_) => ModelType::XLMRoberta,
+            Self::Albert(_) => ModelType::Albert,
+            Self::XLNet(_) => ModelType::XLNet,
+            Self::Longformer(_) => ModelType::Longformer,
+            Self::MobileBert(_) => ModelType::MobileBert,
+            Self::FNet(_) => ModelType::FNet,
+        }
+    }
+
+    /// Interface method to forward_t() of the particular models. The inputs are of shape
+    /// (*batch size*, *number of choices*, *sequence length*) and the logits returned of shape
+    /// (*batch size*, *number of choices*). The token type ids are ignored by the RoBERTa-based
+    /// models, which do not use segment embeddings.
+    pub fn forward_t(
+        &self,
+        input_ids: &Tensor,
+        mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        train: bool,
+    ) -> Result<Tensor, RustBertError> {
+        Ok(match *self {
+            Self::Bert(ref model) => {
+                model
+                    .forward_t(input_ids, mask, token_type_ids, None, train)
+                    .logits
+            }
+            Self::Roberta(ref model) | Self::XLMRoberta(ref model) => {
+                model.forward_t(input_ids, mask, None, None, train).logits
+            }
+            Self::Albert(ref model) => {
+                model
+                    .forward_t(Some(input_ids), mask, token_type_ids, None, None, train)?
+                    .logits
+            }
+            Self::XLNet(ref model) => {
+                model
+                    .forward_t(
+                        Some(input_ids),
+                        mask,
+                        None,
+                        None,
+                        None,
+                        token_type_ids,
+                        None,
+                        train,
+                    )
+                    .logits
+            }
+            Self::Longformer(ref model) => {
+                model
+                    .forward_t(Some(input_ids), mask, None, None, None, None, train)?
+                    .logits
+            }
+            Self::MobileBert(ref model) => {
+                model
+                    .forward_t(Some(input_ids), token_type_ids, None, None, mask, train)?
+                    .logits
+            }
+            Self::FNet(ref model) => {
+                model
+                    .forward_t(Some(input_ids), token_type_ids, None, None, train)?
+                    .logits
+            }
+        })
+    }
+}
+
+/// # MultipleChoiceModel to rank candidate answers
+pub struct MultipleChoiceModel {
+    tokenizer: TokenizerOption,
+    pad_idx: i64,
+    max_seq_len: usize,
+    multiple_choice_model: MultipleChoiceOption,
+    var_store: VarStore,
+}
+
+impl MultipleChoiceModel {
+    /// Build a new `MultipleChoiceModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `multiple_choice_config` - `MultipleChoiceConfig` object containing the resource references (model, vocabulary, configuration) and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::bert::{BertConfigResources, BertVocabResources};
+    /// use rust_bert::pipelines::common::ModelType;
+    /// use rust_bert::pipelines::multiple_choice::{MultipleChoiceConfig, MultipleChoiceModel};
+    /// use rust_bert::resources::{LocalResource, RemoteResource};
+    /// use std::path::PathBuf;
+    ///
+    /// let config = MultipleChoiceConfig::new(
+    ///     ModelType::Bert,
+    ///     LocalResource {
+    ///         local_path: PathBuf::from("path/to/bert-swag/rust_model.ot"),
+    ///     },
+    ///     RemoteResource::from_pretrained(BertConfigResources::BERT),
+    ///     RemoteResource::from_pretrained(BertVocabResources::BERT),
+    ///     None,
+    ///     true,
+    ///     None,
+    ///     None,
+    /// );
+    /// let multiple_choice_model = MultipleChoiceModel::new(config)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        multiple_choice_config: MultipleChoiceConfig,
+    ) -> Result<MultipleChoiceModel, RustBertError> {
+        let config_path = multiple_choice_config.config_resource.get_local_path()?;
+        let vocab_path = multiple_choice_config.vocab_resource.get_local_path()?;
+        let weights_path = multiple_choice_config.model_resource.get_local_path()?;
+        let merges_path = if let Some(merges_resource) = &multiple_choice_config.merges_resource {
+            Some(merges_resource.get_local_path()?)
+        } else {
+            None
+        };
+        let device = multiple_choice_config.device;
+
+        let tokenizer = TokenizerOption::from_file(
+            multiple_choice_config.model_type,
+            vocab_path.to_str().unwrap(),
+            merges_path.as_deref().map(|path| path.to_str().unwrap()),
+            multiple_choice_config.lower_case,
+            multiple_choice_config.strip_accents,
+            multiple_choice_config.add_prefix_space,
+        )?;
+        let pad_idx = tokenizer.get_pad_id().ok_or_else(|| {
+            RustBertError::InvalidConfigurationError(
+                "The Tokenizer used for multiple choice should contain a PAD id".to_string(),
+            )
+        })?;
+        let mut var_store = VarStore::new(device);
+        let model_config =
+            ConfigOption::try_from_file(multiple_choice_config.model_type, config_path)?;
+        let multiple_choice_model = MultipleChoiceOption::new(
+            multiple_choice_config.model_type,
+            var_store.root(),
+            &model_config,
+        )?;
+        load_weights_with_mode(
+            weights_path,
+            &mut var_store,
+            multiple_choice_config.weights_loading_mode,
+        )?;
+        Ok(MultipleChoiceModel {
+            tokenizer,
+            pad_idx,
+            max_seq_len: multiple_choice_config.max_seq_length,
+            multiple_choice_model,
+            var_store,
+        })
+    }
+
+    /// Encodes a batch of inputs into tensors of shape (*batch size*, *maximum number of choices*,
+    /// *maximum sequence length*). The inputs with fewer choices than the maximum are padded with
+    /// copies of their first choice, masked from the scores.
+    fn prepare_for_model(&self, inputs: &[MultipleChoiceInput]) -> (Tensor, Tensor, Tensor) {
+        let num_choices = inputs
+            .iter()
+            .map(|input| input.choices.len())
+            .max()
+            .unwrap_or(0);
+
+        let second_sequences = inputs
+            .iter()
+            .map(|input| {
+                input
+                    .choices
+                    .iter()
+                    .map(|choice| {
+                        if input.question.is_empty() {
+                            choice.clone()
+                        } else {
+                            format!("{} {}", input.question, choice)
+                        }
+                    })
+                    .collect::<Vec<String>>()
+            })
+            .collect::<Vec<Vec<String>>>();
+        let text_pair_list = inputs
+            .iter()
+            .zip(second_sequences.iter())
+            .flat_map(|(input, sequences)| {
+                (0..num_choices).map(move |choice_index| {
+                    let sequence = sequences.get(choice_index).unwrap_or(&sequences[0]);
+                    (input.context.as_str(), sequence.as_str())
+                })
+            })
+            .collect::<Vec<(&str, &str)>>();
+
+        let tokenized_input: Vec<TokenizedInput> = self.tokenizer.encode_pair_list(
+            &text_pair_list,
+            self.max_seq_len,
+            &TruncationStrategy::OnlyFirst,
+            0,
+        );
+        let max_len = tokenized_input
+            .iter()
+            .map(|input| input.token_ids.len())
+            .max()
+            .unwrap_or(0);
+
+        let mut input_ids = Vec::with_capacity(tokenized_input.len() * max_len);
+        let mut token_type_ids = Vec::with_capacity(tokenized_input.len() * max_len);
+        let mut attention_mask = Vec::with_capacity(tokenized_input.len() * max_len);
+        for input in tokenized_input {
+            let padding_length = max_len - input.token_ids.len();
+            attention_mask.extend(vec![1i64; input.token_ids.len()]);
+            attention_mask.extend(vec![0i64; padding_length]);
+            input_ids.extend(input.token_ids);
+            input_ids.extend(vec![self.pad_idx; padding_length]);
+            token_type_ids.extend(
+                input
+                    .segment_ids
+                    .iter()
+                    .map(|&segment_id| segment_id as i64),
+            );
+            token_type_ids.extend(vec![0i64; padding_length]);
+        }
+
+        let shape = [inputs.len() as i64, num_choices as i64, max_len as i64];
+        let device = self.var_store.device();
+        (
+            Tensor::of_slice(&input_ids).view(shape).to(device),
+            Tensor::of_slice(&attention_mask).view(shape).to(device),
+            Tensor::of_slice(&token_type_ids).view(shape).to(device),
+        )
+    }
+
+    /// Ranks the candidate answers of a list of `MultipleChoiceInput`
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - `&[MultipleChoiceInput]` Array of multiple choice inputs (context, question and candidate answers)
+    /// * `batch_size` - maximum number of inputs processed in a single forward pass of the model.
+    ///
+    /// # Returns
+    /// * `Result<Vec<Vec<Choice>>, RustBertError>` Vector (same length as `inputs`) of vectors (same length as the input choices) containing the choices sorted by decreasing score.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::bert::{BertConfigResources, BertVocabResources};
+    /// # use rust_bert::pipelines::common::ModelType;
+    /// use rust_bert::pipelines::multiple_choice::{
+    ///     MultipleChoiceConfig, MultipleChoiceInput, MultipleChoiceModel,
+    /// };
+    /// # use rust_bert::resources::{LocalResource, RemoteResource};
+    /// # use std::path::PathBuf;
+    /// # let config = MultipleChoiceConfig::new(
+    /// #     ModelType::Bert,
+    /// #     LocalResource {
+    /// #         local_path: PathBuf::from("path/to/bert-swag/rust_model.ot"),
+    /// #     },
+    /// #     RemoteResource::from_pretrained(BertConfigResources::BERT),
+    /// #     RemoteResource::from_pretrained(BertVocabResources::BERT),
+    /// #     None,
+    /// #     true,
+    /// #     None,
+    /// #     None,
+    /// # );
+    /// let multiple_choice_model = MultipleChoiceModel::new(config)?;
+    ///
+    /// let input_1 = MultipleChoiceInput {
+    ///     context: String::from("The cat was hungry."),
+    ///     question: String::from("What did the cat do?"),
+    ///     choices: vec![
+    ///         String::from("It ate its food."),
+    ///         String::from("It flew to the moon."),
+    ///     ],
+    /// };
+    /// let input_2 = MultipleChoiceInput {
+    ///     context: String::from("It started to rain."),
+    ///     question: String::from("People"),
+    ///     choices: vec![
+    ///         String::from("opened their umbrellas."),
+    ///         String::from("put on sunscreen."),
+    ///         String::from("went swimming in the snow."),
+    ///     ],
+    /// };
+    /// let output = multiple_choice_model.predict(&[input_1, input_2], 8)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict(
+        &self,
+        inputs: &[MultipleChoiceInput],
+        batch_size: usize,
+    ) -> Result<Vec<Vec<Choice>>, RustBertError> {
+        if batch_size == 0 {
+            return Err(RustBertError::ValueError(
+                "The batch size must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(input_index) = inputs.iter().position(|input| input.choices.is_empty()) {
+            return Err(RustBertError::ValueError(format!(
+                "No candidate answer provided for input {}",
+                input_index
+            )));
+        }
+
+        let mut output = Vec::with_capacity(inputs.len());
+        for batch_inputs in inputs.chunks(batch_size) {
+            let (input_ids, attention_mask, token_type_ids) = self.prepare_for_model(batch_inputs);
+            let logits = no_grad(|| {
+                self.multiple_choice_model.forward_t(
+                    &input_ids,
+                    Some(&attention_mask),
+                    Some(&token_type_ids),
+                    false,
+                )
+            })?;
+
+            //    Padding choices are excluded from the normalization of the scores
+            let num_choices = input_ids.size()[1];
+            let choices_mask = Tensor::of_slice(
+                &batch_inputs
+                    .iter()
+                    .flat_map(|input| {
+                        (0..num_choices as usize).map(move |index| index >= input.choices.len())
+                    })
+                    .collect::<Vec<bool>>(),
+            )
+            .view([batch_inputs.len() as i64, num_choices])
+            .to(logits.device());
+            let scores = logits
+                .to_kind(Kind::Float)
+                .view([batch_inputs.len() as i64, num_choices])
+                .masked_fill(&choices_mask, f64::NEG_INFINITY)
+                .softmax(-1, Kind::Float);
+
+            for (input_index, input) in batch_inputs.iter().enumerate() {
+                let mut choices = input
+                    .choices
+                    .iter()
+                    .enumerate()
+                    .map(|(index, text)| Choice {
+                        index,
+                        text: text.clone(),
+                        score: scores.double_value(&[input_index as i64, index as i64]),
+                    })
+                    .collect::<Vec<Choice>>();
+                choices.sort_by(|choice_a, choice_b| {
+                    choice_b
+                        .score
+                        .partial_cmp(&choice_a.score)
+                        .unwrap_or(Ordering::Equal)
+                });
+                output.push(choices);
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl DeviceAndPrecision for MultipleChoiceModel {
+    fn get_var_store(&self) -> &VarStore {
+        &self.var_store
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        &mut self.var_store
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore] // no need to run, compilation is enough to verify it is Send
+    fn test() {
+        let config = MultipleChoiceConfig::new(
+            ModelType::Bert,
+            crate::resources::LocalResource {
+                local_path: Default::default(),
+            },
+            crate::resources::LocalResource {
+                local_path: Default::default(),
+            },
+            crate::resources::LocalResource {
+                local_path: Default::default(),
+            },
+            None,
+            true,
+            None,
+            None,
+        );
+        let _: Box<dyn Send> = Box::new(MultipleChoiceModel::new(config));
+    }
+}
@@ -127,11 +127,13 @@
 //! Dutch| XLM_ROBERTA_NER_NL |
 
 use crate::common::error::RustBertError;
+use crate::pipelines::common::DeviceAndPrecision;
 use crate::pipelines::token_classification::{
     Token, TokenClassificationConfig, TokenClassificationModel,
 };
 use rust_tokenizers::Offset;
 use serde::{Deserialize, Serialize};
+use tch::nn::VarStore;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// # Entity generated by a `NERModel`
@@ -406,6 +408,20 @@ impl Token {
     }
 }
 
+impl DeviceAndPrecision for NERModel {
+    fn get_var_store(&self) -> &VarStore {
+        self.token_classification_model.get_var_store()
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        self.token_classification_model.get_var_store_mut()
+    }
+
+    fn is_quantized(&self) -> bool {
+        self.token_classification_model.is_quantized()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
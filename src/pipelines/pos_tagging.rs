@@ -82,8 +82,12 @@
 //! To run the pipeline for another language, change the POSModel configuration from its default (see the NER pipeline for an illustration).
 
 use crate::common::error::RustBertError;
+use crate::common::quantization::QuantizationMode;
+use crate::common::weights::WeightsLoadingMode;
+use crate::pipelines::common::DeviceAndPrecision;
 use crate::pipelines::token_classification::{TokenClassificationConfig, TokenClassificationModel};
 use serde::{Deserialize, Serialize};
+use tch::nn::VarStore;
 
 #[cfg(feature = "remote")]
 use {
@@ -134,6 +138,8 @@ impl Default for POSConfig {
                 strip_accents: Some(true),
                 add_prefix_space: None,
                 device: Device::cuda_if_available(),
+                weights_loading_mode: WeightsLoadingMode::Strict,
+                quantization: QuantizationMode::None,
                 label_aggregation_function: LabelAggregationOption::First,
                 batch_size: 64,
             },
@@ -235,6 +241,20 @@ impl POSModel {
     }
 }
 
+impl DeviceAndPrecision for POSModel {
+    fn get_var_store(&self) -> &VarStore {
+        self.token_classification_model.get_var_store()
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        self.token_classification_model.get_var_store_mut()
+    }
+
+    fn is_quantized(&self) -> bool {
+        self.token_classification_model.is_quantized()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -46,12 +46,13 @@
 use crate::albert::AlbertForQuestionAnswering;
 use crate::bert::BertForQuestionAnswering;
 use crate::common::error::RustBertError;
+use crate::common::weights::{load_weights_with_mode, WeightsLoadingMode};
 use crate::deberta::DebertaForQuestionAnswering;
 use crate::distilbert::DistilBertForQuestionAnswering;
 use crate::fnet::FNetForQuestionAnswering;
 use crate::longformer::LongformerForQuestionAnswering;
 use crate::mobilebert::MobileBertForQuestionAnswering;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{ConfigOption, DeviceAndPrecision, ModelType, TokenizerOption};
 use crate::reformer::ReformerForQuestionAnswering;
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForQuestionAnswering;
@@ -137,6 +138,8 @@ pub struct QuestionAnsweringConfig {
     pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
     /// Model type
     pub model_type: ModelType,
     /// Flag indicating if the model expects a lower casing of the input
@@ -191,6 +194,7 @@ impl QuestionAnsweringConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
             max_seq_length: 384,
             doc_stride: 128,
             max_query_length: 64,
@@ -241,6 +245,7 @@ impl QuestionAnsweringConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
             max_seq_length: max_seq_length.into().unwrap_or(384),
             doc_stride: doc_stride.into().unwrap_or(128),
             max_query_length: max_query_length.into().unwrap_or(64),
@@ -264,6 +269,7 @@ impl Default for QuestionAnsweringConfig {
             )),
             merges_resource: None,
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
             model_type: ModelType::DistilBert,
             lower_case: false,
             add_prefix_space: None,
@@ -612,7 +618,7 @@ impl QuestionAnsweringModel {
             .expect("The Tokenizer used for Question Answering should contain a SEP id");
         let mut var_store = VarStore::new(device);
         let mut model_config =
-            ConfigOption::from_file(question_answering_config.model_type, config_path);
+            ConfigOption::try_from_file(question_answering_config.model_type, config_path)?;
 
         if let ConfigOption::DistilBert(ref mut config) = model_config {
             config.sinusoidal_pos_embds = false;
@@ -639,7 +645,11 @@ impl QuestionAnsweringModel {
             )));
         }
 
-        var_store.load(weights_path)?;
+        load_weights_with_mode(
+            weights_path,
+            &mut var_store,
+            question_answering_config.weights_loading_mode,
+        )?;
         Ok(QuestionAnsweringModel {
             tokenizer,
             pad_idx,
@@ -749,8 +759,8 @@ impl QuestionAnsweringModel {
                         let start = start_logits.get(feature_idx).masked_fill(&p_mask, -10000);
                         let end = end_logits.get(feature_idx).masked_fill(&p_mask, -10000);
 
-                        let start = start.exp() / start.exp().sum(Float);
-                        let end = end.exp() / end.exp().sum(Float);
+                        let start = start.softmax(-1, Float);
+                        let end = end.softmax(-1, Float);
 
                         let (starts, ends, scores) = self.decode(&start, &end, top_k);
 
@@ -1000,6 +1010,16 @@ pub fn squad_processor(file_path: PathBuf) -> Vec<QaInput> {
     qa_inputs
 }
 
+impl DeviceAndPrecision for QuestionAnsweringModel {
+    fn get_var_store(&self) -> &VarStore {
+        &self.var_store
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        &mut self.var_store
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
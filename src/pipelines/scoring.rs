@@ -0,0 +1,99 @@
+// Copyright 2019 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Text scoring
+//! Scores existing texts with language models (e.g. for reranking, fluency filtering or data cleaning):
+//! - decoder-only language models (GPT2, GPT-Neo, OpenAI GPT, XLNet) compute the log-probability of each token
+//!   given the previous tokens and the perplexity of the text with `LanguageGenerator::score` (or
+//!   `TextGenerationModel::score`). Texts longer than the maximum length of the model are scored with a sliding
+//!   window, each token being scored once with the context available in its window.
+//! - masked language models compute the pseudo-log-likelihood of the text with
+//!   `MaskedLanguageModel::pseudo_log_likelihood`: each token is masked in turn and scored given all other tokens
+//!   ([Salazar et al.](https://arxiv.org/abs/1910.14659)).
+//! - encoder-decoder models (BART, T5, Marian, Pegasus...) compute the log-probability of target texts given
+//!   source texts with `LanguageGenerator::score_conditional`, each target token being scored given the source
+//!   and the previous target tokens (teacher forcing).
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::gpt2::GPT2Generator;
+//! use rust_bert::pipelines::generation_utils::LanguageGenerator;
+//! use rust_bert::pipelines::scoring::ScoringOptions;
+//!
+//! let gpt2_generator = GPT2Generator::new(Default::default())?;
+//! let input = ["The cat sat on the mat.", "Mat the on sat cat the."];
+//!
+//! let output = gpt2_generator.score(&input, &ScoringOptions::default())?;
+//! for text_score in output {
+//!     println!("{:?}", text_score.perplexity);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+/// # Options for the scoring of texts with decoder-only language models
+#[derive(Debug, Clone, Copy)]
+pub struct ScoringOptions {
+    /// Number of sequences (or windows of long sequences) processed in a single forward pass (default: 8)
+    pub batch_size: usize,
+    /// Maximum number of tokens processed by the model at once. Longer texts are scored with a sliding
+    /// window (default: maximum position embeddings of the model)
+    pub max_length: Option<usize>,
+    /// Number of positions the sliding window is moved by, must be lower than `max_length`. The tokens of a
+    /// window already scored in the previous window are only used as context (default: `max_length` / 2)
+    pub stride: Option<usize>,
+}
+
+impl Default for ScoringOptions {
+    fn default() -> Self {
+        ScoringOptions {
+            batch_size: 8,
+            max_length: None,
+            stride: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Scores of a text
+pub struct TextScore {
+    /// Token ids of the text as processed by the model (including special tokens)
+    pub token_ids: Vec<i64>,
+    /// Log-probability of each token, `None` for the tokens that are not scored (special tokens of masked
+    /// language models and first token of decoder-only models)
+    pub token_log_probabilities: Vec<Option<f64>>,
+    /// Sum of the token log-probabilities (pseudo-log-likelihood for masked language models)
+    pub log_likelihood: f64,
+    /// Number of scored tokens
+    pub num_scored_tokens: usize,
+    /// Exponential of the average negative log-likelihood of the scored tokens (pseudo-perplexity for
+    /// masked language models), `None` if no token was scored
+    pub perplexity: Option<f64>,
+}
+
+impl TextScore {
+    pub(crate) fn new(token_ids: Vec<i64>, token_log_probabilities: Vec<Option<f64>>) -> Self {
+        let num_scored_tokens = token_log_probabilities.iter().flatten().count();
+        let log_likelihood = token_log_probabilities.iter().flatten().sum::<f64>();
+        let perplexity = if num_scored_tokens > 0 {
+            Some((-log_likelihood / num_scored_tokens as f64).exp())
+        } else {
+            None
+        };
+        TextScore {
+            token_ids,
+            token_log_probabilities,
+            log_likelihood,
+            num_scored_tokens,
+            perplexity,
+        }
+    }
+}
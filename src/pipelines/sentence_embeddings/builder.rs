@@ -3,6 +3,8 @@ use std::path::PathBuf;
 use serde::Deserialize;
 use tch::Device;
 
+use crate::common::quantization::QuantizationMode;
+use crate::common::weights::WeightsLoadingMode;
 use crate::pipelines::common::ModelType;
 use crate::pipelines::sentence_embeddings::{
     SentenceEmbeddingsConfig, SentenceEmbeddingsModel, SentenceEmbeddingsModulesConfig,
@@ -21,6 +23,7 @@ use crate::{
 /// (configuration and weights).
 pub struct SentenceEmbeddingsBuilder<T> {
     device: Device,
+    quantization: QuantizationMode,
     inner: T,
 }
 
@@ -29,6 +32,11 @@ impl<T> SentenceEmbeddingsBuilder<T> {
         self.device = device;
         self
     }
+
+    pub fn with_quantization(mut self, quantization: QuantizationMode) -> Self {
+        self.quantization = quantization;
+        self
+    }
 }
 
 pub struct Local {
@@ -46,6 +54,7 @@ impl SentenceEmbeddingsBuilder<Local> {
     pub fn local<P: Into<PathBuf>>(model_dir: P) -> Self {
         Self {
             device: Device::cuda_if_available(),
+            quantization: QuantizationMode::None,
             inner: Local {
                 model_dir: model_dir.into(),
             },
@@ -56,10 +65,11 @@ impl SentenceEmbeddingsBuilder<Local> {
         let model_dir = self.inner.model_dir;
 
         let modules_config = model_dir.join("modules.json");
-        let modules = SentenceEmbeddingsModulesConfig::from_file(&modules_config).validate()?;
+        let modules =
+            SentenceEmbeddingsModulesConfig::try_from_file(&modules_config)?.validate()?;
 
         let transformer_config = model_dir.join("config.json");
-        let transformer_type = ModelConfig::from_file(&transformer_config).model_type;
+        let transformer_type = ModelConfig::try_from_file(&transformer_config)?.model_type;
         let transformer_weights = model_dir.join("rust_model.ot");
 
         let pooling_config = model_dir
@@ -107,6 +117,8 @@ impl SentenceEmbeddingsBuilder<Local> {
             tokenizer_vocab_resource: tokenizer_vocab.into(),
             tokenizer_merges_resource: tokenizer_merges.map(|r| r.into()),
             device: self.device,
+            weights_loading_mode: WeightsLoadingMode::Strict,
+            quantization: self.quantization,
         };
 
         SentenceEmbeddingsModel::new(config)
@@ -123,6 +135,7 @@ impl SentenceEmbeddingsBuilder<Remote> {
     pub fn remote(model_type: SentenceEmbeddingsModelType) -> Self {
         Self {
             device: Device::cuda_if_available(),
+            quantization: QuantizationMode::None,
             inner: Remote {
                 config: SentenceEmbeddingsConfig::from(model_type),
             },
@@ -180,6 +193,9 @@ impl SentenceEmbeddingsBuilder<Remote> {
     }
 
     pub fn create_model(self) -> Result<SentenceEmbeddingsModel, RustBertError> {
-        SentenceEmbeddingsModel::new(self.inner.config)
+        let mut config = self.inner.config;
+        config.device = self.device;
+        config.quantization = self.quantization;
+        SentenceEmbeddingsModel::new(config)
     }
 }
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use tch::Device;
 
+use crate::common::quantization::QuantizationMode;
+use crate::common::weights::WeightsLoadingMode;
 use crate::pipelines::common::ModelType;
 use crate::resources::ResourceProvider;
 use crate::{Config, RustBertError};
@@ -55,6 +57,10 @@ pub struct SentenceEmbeddingsConfig {
     pub tokenizer_merges_resource: Option<Box<dyn ResourceProvider + Send>>,
     /// Device to place the transformer model on
     pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
+    /// Quantization mode applied to the transformer model after loading the weights (default: no quantization). Dynamic int8 quantization is supported on CPU for BERT, RoBERTa and DistilBERT models
+    pub quantization: QuantizationMode,
 }
 
 #[cfg(feature = "remote")]
@@ -92,6 +98,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                weights_loading_mode: WeightsLoadingMode::Strict,
+                quantization: QuantizationMode::None,
             },
 
             SentenceEmbeddingsModelType::BertBaseNliMeanTokens => SentenceEmbeddingsConfig {
@@ -121,6 +129,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                weights_loading_mode: WeightsLoadingMode::Strict,
+                quantization: QuantizationMode::None,
             },
 
             SentenceEmbeddingsModelType::AllMiniLmL12V2 => SentenceEmbeddingsConfig {
@@ -150,6 +160,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                weights_loading_mode: WeightsLoadingMode::Strict,
+                quantization: QuantizationMode::None,
             },
 
             SentenceEmbeddingsModelType::AllMiniLmL6V2 => SentenceEmbeddingsConfig {
@@ -179,6 +191,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                weights_loading_mode: WeightsLoadingMode::Strict,
+                quantization: QuantizationMode::None,
             },
 
             SentenceEmbeddingsModelType::AllDistilrobertaV1 => SentenceEmbeddingsConfig {
@@ -210,6 +224,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                     RobertaMergesResources::ALL_DISTILROBERTA_V1,
                 ))),
                 device: Device::cuda_if_available(),
+                weights_loading_mode: WeightsLoadingMode::Strict,
+                quantization: QuantizationMode::None,
             },
 
             SentenceEmbeddingsModelType::ParaphraseAlbertSmallV2 => SentenceEmbeddingsConfig {
@@ -239,6 +255,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                weights_loading_mode: WeightsLoadingMode::Strict,
+                quantization: QuantizationMode::None,
             },
 
             SentenceEmbeddingsModelType::SentenceT5Base => SentenceEmbeddingsConfig {
@@ -272,6 +290,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                weights_loading_mode: WeightsLoadingMode::Strict,
+                quantization: QuantizationMode::None,
             },
         }
     }
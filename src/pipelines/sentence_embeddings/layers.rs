@@ -4,6 +4,7 @@ use serde::{de, Deserialize, Deserializer};
 use tch::{nn, Device, Kind, Tensor};
 
 use crate::common::activations::{Activation, TensorFunction};
+use crate::common::weights::load_weights;
 use crate::{Config, RustBertError};
 
 /// Configuration for [`Pooling`](Pooling) layer.
@@ -113,7 +114,7 @@ where
 pub struct Dense {
     linear: nn::Linear,
     activation: TensorFunction,
-    _var_store: nn::VarStore,
+    var_store: nn::VarStore,
 }
 
 impl Dense {
@@ -138,16 +139,21 @@ impl Dense {
 
         let activation = dense_conf.activation_function.get_function();
 
-        vs_dense.load(dense_weights)?;
+        load_weights(dense_weights, &mut vs_dense)?;
 
         Ok(Dense {
             linear,
             activation,
-            _var_store: vs_dense,
+            var_store: vs_dense,
         })
     }
 
     pub fn forward(&self, x: &Tensor) -> Tensor {
         self.activation.get_fn()(&x.apply(&self.linear))
     }
+
+    /// Moves the layer weights to the device provided. The weights are kept in full precision.
+    pub fn set_device(&mut self, device: Device) {
+        self.var_store.set_device(device);
+    }
 }
@@ -2,12 +2,16 @@ use std::borrow::Borrow;
 use std::convert::TryInto;
 
 use rust_tokenizers::tokenizer::TruncationStrategy;
-use tch::{nn, Tensor};
+use tch::{nn, Device, Kind, Tensor};
 
 use crate::albert::AlbertForSentenceEmbeddings;
 use crate::bert::BertForSentenceEmbeddings;
+use crate::common::quantization::QuantizationMode;
+use crate::common::weights::load_weights_with_mode;
 use crate::distilbert::DistilBertForSentenceEmbeddings;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{
+    check_not_quantized, ConfigOption, DeviceAndPrecision, ModelType, TokenizerOption,
+};
 use crate::pipelines::sentence_embeddings::layers::{Dense, DenseConfig, Pooling, PoolingConfig};
 use crate::pipelines::sentence_embeddings::{
     AttentionHead, AttentionLayer, AttentionOutput, Embedding, SentenceEmbeddingsConfig,
@@ -74,6 +78,25 @@ impl SentenceEmbeddingsOption {
         Ok(option)
     }
 
+    /// Quantizes the encoder stack of the transformer model. Dynamic quantization is supported for
+    /// BERT, RoBERTa and DistilBERT models.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        match self {
+            Self::Bert(transformer) => transformer.quantize(mode),
+            Self::DistilBert(transformer) => transformer.quantize(mode),
+            Self::Roberta(transformer) => transformer.quantize(mode),
+            _ if mode == QuantizationMode::None => Ok(()),
+            _ => Err(RustBertError::InvalidConfigurationError(
+                "Quantization is only supported for BERT, RoBERTa and DistilBERT transformers"
+                    .to_string(),
+            )),
+        }
+    }
+
     /// Interface method to forward() of the particular transformer models.
     pub fn forward(
         &self,
@@ -168,6 +191,7 @@ pub struct SentenceEmbeddingsModel {
     pooling_layer: Pooling,
     dense_layer: Option<Dense>,
     normalize_embeddings: bool,
+    quantization: QuantizationMode,
 }
 
 impl SentenceEmbeddingsModel {
@@ -190,20 +214,23 @@ impl SentenceEmbeddingsModel {
             dense_config_resource,
             dense_weights_resource,
             device,
+            weights_loading_mode,
+            quantization,
         } = config;
 
-        let modules =
-            SentenceEmbeddingsModulesConfig::from_file(modules_config_resource.get_local_path()?)
-                .validate()?;
+        let modules = SentenceEmbeddingsModulesConfig::try_from_file(
+            modules_config_resource.get_local_path()?,
+        )?
+        .validate()?;
 
         // Setup tokenizer
 
-        let tokenizer_config = SentenceEmbeddingsTokenizerConfig::from_file(
+        let tokenizer_config = SentenceEmbeddingsTokenizerConfig::try_from_file(
             tokenizer_config_resource.get_local_path()?,
-        );
-        let sentence_bert_config = SentenceEmbeddingsSentenceBertConfig::from_file(
+        )?;
+        let sentence_bert_config = SentenceEmbeddingsSentenceBertConfig::try_from_file(
             sentence_bert_config_resource.get_local_path()?,
-        );
+        )?;
         let tokenizer = TokenizerOption::from_file(
             transformer_type,
             tokenizer_vocab_resource
@@ -226,24 +253,30 @@ impl SentenceEmbeddingsModel {
         // Setup transformer
 
         let mut var_store = nn::VarStore::new(device);
-        let transformer_config = ConfigOption::from_file(
+        let transformer_config = ConfigOption::try_from_file(
             transformer_type,
             transformer_config_resource.get_local_path()?,
-        );
-        let transformer =
+        )?;
+        let mut transformer =
             SentenceEmbeddingsOption::new(transformer_type, var_store.root(), &transformer_config)?;
-        var_store.load(transformer_weights_resource.get_local_path()?)?;
+        load_weights_with_mode(
+            transformer_weights_resource.get_local_path()?,
+            &mut var_store,
+            weights_loading_mode,
+        )?;
+        transformer.quantize(quantization)?;
 
         // Setup pooling layer
 
-        let pooling_config = PoolingConfig::from_file(pooling_config_resource.get_local_path()?);
+        let pooling_config =
+            PoolingConfig::try_from_file(pooling_config_resource.get_local_path()?)?;
         let pooling_layer = Pooling::new(pooling_config);
 
         // Setup dense layer
 
         let dense_layer = if modules.dense_module().is_some() {
             let dense_config =
-                DenseConfig::from_file(dense_config_resource.unwrap().get_local_path()?);
+                DenseConfig::try_from_file(dense_config_resource.unwrap().get_local_path()?)?;
             Some(Dense::new(
                 dense_config,
                 dense_weights_resource.unwrap().get_local_path()?,
@@ -265,6 +298,7 @@ impl SentenceEmbeddingsModel {
             pooling_layer,
             dense_layer,
             normalize_embeddings,
+            quantization,
         })
     }
 
@@ -342,6 +376,8 @@ impl SentenceEmbeddingsModel {
         let (tokens_embeddings, all_attentions) =
             tch::no_grad(|| self.transformer.forward(&tokens_ids, &tokens_masks))?;
 
+        // The pooling and dense layers are computed in full precision
+        let tokens_embeddings = tokens_embeddings.to_kind(Kind::Float);
         let mean_pool =
             tch::no_grad(|| self.pooling_layer.forward(tokens_embeddings, &tokens_masks));
         let maybe_linear = if let Some(dense_layer) = &self.dense_layer {
@@ -433,7 +469,8 @@ impl SentenceEmbeddingsModel {
                         let attention_slice = layer
                             .slice(0, i, i + 1, 1)
                             .slice(1, head as i64, head as i64 + 1, 1)
-                            .squeeze();
+                            .squeeze()
+                            .to_kind(Kind::Float);
                         let attention_head = AttentionHead::from(attention_slice);
                         attention_layer.push(attention_head);
                     }
@@ -447,6 +484,31 @@ impl SentenceEmbeddingsModel {
     }
 }
 
+impl DeviceAndPrecision for SentenceEmbeddingsModel {
+    fn get_var_store(&self) -> &nn::VarStore {
+        &self.var_store
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut nn::VarStore {
+        &mut self.var_store
+    }
+
+    fn is_quantized(&self) -> bool {
+        self.quantization != QuantizationMode::None
+    }
+
+    fn try_set_device(&mut self, device: Device) -> Result<(), RustBertError> {
+        if device != Device::Cpu {
+            check_not_quantized(self.is_quantized(), &format!("moved to {:?}", device))?;
+        }
+        self.var_store.set_device(device);
+        if let Some(dense_layer) = &mut self.dense_layer {
+            dense_layer.set_device(device);
+        }
+        Ok(())
+    }
+}
+
 /// Container for the SentenceEmbeddings tokenizer output.
 pub struct SentenceEmbeddingsTokenizerOuput {
     pub tokens_ids: Vec<Tensor>,
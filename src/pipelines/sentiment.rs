@@ -55,10 +55,12 @@
 //! ```
 
 use crate::common::error::RustBertError;
+use crate::pipelines::common::DeviceAndPrecision;
 use crate::pipelines::sequence_classification::{
     SequenceClassificationConfig, SequenceClassificationModel,
 };
 use serde::{Deserialize, Serialize};
+use tch::nn::VarStore;
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 /// Enum with the possible sentiment polarities. Note that the pre-trained SST2 model does not include neutral sentiment.
@@ -154,6 +156,21 @@ impl SentimentModel {
         sentiments
     }
 }
+
+impl DeviceAndPrecision for SentimentModel {
+    fn get_var_store(&self) -> &VarStore {
+        self.sequence_classification_model.get_var_store()
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        self.sequence_classification_model.get_var_store_mut()
+    }
+
+    fn is_quantized(&self) -> bool {
+        self.sequence_classification_model.is_quantized()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
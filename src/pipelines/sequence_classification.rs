@@ -61,12 +61,14 @@ use crate::albert::AlbertForSequenceClassification;
 use crate::bart::BartForSequenceClassification;
 use crate::bert::BertForSequenceClassification;
 use crate::common::error::RustBertError;
+use crate::common::quantization::QuantizationMode;
+use crate::common::weights::{load_weights_with_mode, WeightsLoadingMode};
 use crate::deberta::DebertaForSequenceClassification;
 use crate::distilbert::DistilBertModelClassifier;
 use crate::fnet::FNetForSequenceClassification;
 use crate::longformer::LongformerForSequenceClassification;
 use crate::mobilebert::MobileBertForSequenceClassification;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{ConfigOption, DeviceAndPrecision, ModelType, TokenizerOption};
 use crate::reformer::ReformerForSequenceClassification;
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForSequenceClassification;
@@ -121,6 +123,10 @@ pub struct SequenceClassificationConfig {
     pub add_prefix_space: Option<bool>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
+    /// Quantization mode applied to the model after loading the weights (default: no quantization). Dynamic int8 quantization is supported on CPU for BERT, RoBERTa and DistilBERT models
+    pub quantization: QuantizationMode,
 }
 
 impl SequenceClassificationConfig {
@@ -159,6 +165,8 @@ impl SequenceClassificationConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
+            quantization: QuantizationMode::None,
         }
     }
 }
@@ -398,6 +406,25 @@ impl SequenceClassificationOption {
         }
     }
 
+    /// Quantizes the encoder stack of the model. Dynamic quantization is supported for BERT,
+    /// RoBERTa and DistilBERT models.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        match self {
+            Self::Bert(model) => model.quantize(mode),
+            Self::DistilBert(model) => model.quantize(mode),
+            Self::Roberta(model) | Self::XLMRoberta(model) => model.quantize(mode),
+            _ if mode == QuantizationMode::None => Ok(()),
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Quantization is not supported for {:?} models",
+                self.model_type()
+            ))),
+        }
+    }
+
     /// Interface method to forward_t() of the particular models.
     pub fn forward_t(
         &self,
@@ -546,6 +573,7 @@ pub struct SequenceClassificationModel {
     label_mapping: HashMap<i64, String>,
     var_store: VarStore,
     max_length: usize,
+    quantization: QuantizationMode,
 }
 
 impl SequenceClassificationModel {
@@ -587,21 +615,23 @@ impl SequenceClassificationModel {
             config.add_prefix_space,
         )?;
         let mut var_store = VarStore::new(device);
-        let model_config = ConfigOption::from_file(config.model_type, config_path);
+        let model_config = ConfigOption::try_from_file(config.model_type, config_path)?;
         let max_length = model_config
             .get_max_len()
             .map(|v| v as usize)
             .unwrap_or(usize::MAX);
-        let sequence_classifier =
+        let mut sequence_classifier =
             SequenceClassificationOption::new(config.model_type, var_store.root(), &model_config)?;
         let label_mapping = model_config.get_label_mapping().clone();
-        var_store.load(weights_path)?;
+        load_weights_with_mode(weights_path, &mut var_store, config.weights_loading_mode)?;
+        sequence_classifier.quantize(config.quantization)?;
         Ok(SequenceClassificationModel {
             tokenizer,
             sequence_classifier,
             label_mapping,
             var_store,
             max_length,
+            quantization: config.quantization,
         })
     }
 
@@ -743,7 +773,11 @@ impl SequenceClassificationModel {
                 None,
                 false,
             );
-            output.sigmoid().detach().to(Device::Cpu)
+            output
+                .to_kind(Kind::Float)
+                .sigmoid()
+                .detach()
+                .to(Device::Cpu)
         });
         let label_indices = output.as_ref().ge(threshold).nonzero();
 
@@ -778,6 +812,20 @@ impl SequenceClassificationModel {
     }
 }
 
+impl DeviceAndPrecision for SequenceClassificationModel {
+    fn get_var_store(&self) -> &VarStore {
+        &self.var_store
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        &mut self.var_store
+    }
+
+    fn is_quantized(&self) -> bool {
+        self.quantization != QuantizationMode::None
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
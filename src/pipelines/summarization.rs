@@ -47,7 +47,7 @@
 //! telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more
 //! about exoplanets like K2-18b."];
 //!
-//! let output = model.summarize(&input);
+//! let output = model.summarize(&input)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -62,13 +62,16 @@
 //! # ;
 //! ```
 
+use tch::nn::VarStore;
 use tch::Device;
 
 use crate::bart::BartGenerator;
 use crate::common::error::RustBertError;
+use crate::common::weights::WeightsLoadingMode;
 use crate::pegasus::PegasusConditionalGenerator;
-use crate::pipelines::common::ModelType;
-use crate::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
+use crate::pipelines::common::{DeviceAndPrecision, ModelType};
+use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
+use crate::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
 use crate::prophetnet::ProphetNetConditionalGenerator;
 use crate::resources::ResourceProvider;
 use crate::t5::T5Generator;
@@ -121,8 +124,22 @@ pub struct SummarizationConfig {
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Typical sampling mass, following [Meister et al.](https://arxiv.org/abs/2202.00666). If provided and lower than 1, keeps the most typical tokens until their cumulative probability reaches typical_p (sampling only)
+    pub typical_p: Option<f64>,
+    /// Epsilon sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than epsilon_cutoff (sampling only)
+    pub epsilon_cutoff: Option<f64>,
+    /// Eta sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than min(eta_cutoff, sqrt(eta_cutoff) * exp(-entropy)) (sampling only)
+    pub eta_cutoff: Option<f64>,
+    /// Min-p sampling. If provided and higher than 0, removes the tokens with a probability lower than min_p times the probability of the most likely token (sampling only)
+    pub min_p: Option<f64>,
+    /// Degeneration penalty for [contrastive search, Su et al.](https://arxiv.org/abs/2202.06417). If provided and higher than 0, greedy decoding with top_k higher than 1 uses contrastive search
+    pub penalty_alpha: Option<f64>,
+    /// Preallocated key/value cache. If true, the keys and values of the self-attention layers are stored in buffers sized to `max_length`, written in place at each decoding step instead of being concatenated to the cache (GPT2, GPT-Neo and BART-family models, requires a maximum length) (default: false)
+    pub static_cache: bool,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
 }
 
 impl SummarizationConfig {
@@ -167,7 +184,14 @@ impl SummarizationConfig {
             num_return_sequences: 1,
             num_beam_groups: None,
             diversity_penalty: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            min_p: None,
+            penalty_alpha: None,
+            static_cache: false,
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
         }
     }
 }
@@ -208,7 +232,15 @@ impl From<SummarizationConfig> for GenerateConfig {
             num_return_sequences: config.num_return_sequences,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            typical_p: config.typical_p,
+            epsilon_cutoff: config.epsilon_cutoff,
+            eta_cutoff: config.eta_cutoff,
+            min_p: config.min_p,
+            penalty_alpha: config.penalty_alpha,
+            static_cache: config.static_cache,
+            output_attentions: false,
             device: config.device,
+            weights_loading_mode: config.weights_loading_mode,
         }
     }
 }
@@ -256,31 +288,64 @@ impl SummarizationOption {
     }
 
     /// Interface method to generate() of the particular models.
-    pub fn generate<S>(&self, prompt_texts: Option<&[S]>) -> Vec<String>
+    pub fn generate<S>(&self, prompt_texts: Option<&[S]>) -> Result<Vec<String>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
-        match *self {
+        self.generate_with_options(prompt_texts, None)
+    }
+
+    /// Interface method to generate() of the particular models, with generation options overriding the model
+    /// configuration (for example logit bias or constraints).
+    pub fn generate_with_options<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        Ok(match *self {
             Self::Bart(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::T5(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::ProphetNet(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::Pegasus(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
+        })
+    }
+
+    /// Returns a reference to the variables store of the underlying model
+    pub fn get_var_store(&self) -> &VarStore {
+        match self {
+            Self::Bart(model_ref) => model_ref.get_var_store(),
+            Self::T5(model_ref) => model_ref.get_var_store(),
+            Self::ProphetNet(model_ref) => model_ref.get_var_store(),
+            Self::Pegasus(model_ref) => model_ref.get_var_store(),
+        }
+    }
+
+    /// Returns a mutable reference to the variables store of the underlying model
+    pub fn get_var_store_mut(&mut self) -> &mut VarStore {
+        match self {
+            Self::Bart(model_ref) => model_ref.get_var_store_mut(),
+            Self::T5(model_ref) => model_ref.get_var_store_mut(),
+            Self::ProphetNet(model_ref) => model_ref.get_var_store_mut(),
+            Self::Pegasus(model_ref) => model_ref.get_var_store_mut(),
         }
     }
 }
@@ -327,7 +392,7 @@ impl SummarizationModel {
     /// * `input` - `&[&str]` Array of texts to summarize.
     ///
     /// # Returns
-    /// * `Vec<String>` Summarized texts
+    /// * `Result<Vec<String>, RustBertError>` Summarized texts
     ///
     /// # Example
     ///
@@ -359,28 +424,87 @@ impl SummarizationModel {
     /// telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more
     /// about exoplanets like K2-18b."];
     ///
-    /// let output = model.summarize(&input);
+    /// let output = model.summarize(&input)?;
     /// # Ok(())
     /// # }
     /// ```
     /// (New sample credits: [WikiNews](https://en.wikinews.org/wiki/Astronomers_find_water_vapour_in_atmosphere_of_exoplanet_K2-18b))
-    pub fn summarize<S>(&self, texts: &[S]) -> Vec<String>
+    pub fn summarize<S>(&self, texts: &[S]) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        self.summarize_with_options(texts, None)
+    }
+
+    /// Summarize texts provided, with generation options overriding the model configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to summarize.
+    /// * `generate_options` - Optional `GenerateOptions` (for example `constraints` forcing keywords to appear in the summary)
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, RustBertError>` Summarized texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::generation_utils::constraints::Constraint;
+    /// use rust_bert::pipelines::generation_utils::GenerateOptions;
+    /// use rust_bert::pipelines::summarization::SummarizationModel;
+    /// let model = SummarizationModel::new(Default::default())?;
+    ///
+    /// let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists
+    /// from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team
+    /// from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b,
+    /// a planet circling a star in the constellation Leo."];
+    /// // Token ids of a keyword to include in the summary (obtained from the model tokenizer)
+    /// let keyword_token_ids = vec![10653, 29826];
+    /// let constraints = [Constraint::Phrase(keyword_token_ids)];
+    /// let generate_options = GenerateOptions {
+    ///     constraints: Some(&constraints),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let output = model.summarize_with_options(&input, Some(generate_options))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn summarize_with_options<S>(
+        &self,
+        texts: &[S],
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<String>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
         match &self.prefix {
-            None => self.model.generate(Some(texts)),
+            None => self
+                .model
+                .generate_with_options(Some(texts), generate_options),
             Some(prefix) => {
                 let texts = texts
                     .iter()
                     .map(|text| format!("{}{}", prefix, text.as_ref()))
                     .collect::<Vec<String>>();
-                self.model.generate(Some(&texts))
+                self.model
+                    .generate_with_options(Some(&texts), generate_options)
             }
         }
     }
 }
 
+impl DeviceAndPrecision for SummarizationModel {
+    fn get_var_store(&self) -> &VarStore {
+        self.model.get_var_store()
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        self.model.get_var_store_mut()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -31,15 +31,20 @@
 //!
 //! Customized text generation models models can be loaded by overwriting the resources in the configuration.
 //! The dependencies will be downloaded to the user's home directory, e.g. under ~/.cache/.rustbert/gpt2
+use tch::nn::VarStore;
 use tch::Device;
 
 use crate::common::error::RustBertError;
+use crate::common::weights::WeightsLoadingMode;
 use crate::gpt2::GPT2Generator;
 use crate::gpt_neo::GptNeoGenerator;
 use crate::openai_gpt::OpenAIGenerator;
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::common::{DeviceAndPrecision, ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
-use crate::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use crate::pipelines::generation_utils::{
+    GenerateConfig, GenerateOptions, GeneratedToken, LanguageGenerator,
+};
+use crate::pipelines::scoring::{ScoringOptions, TextScore};
 use crate::reformer::ReformerGenerator;
 use crate::resources::ResourceProvider;
 use crate::xlnet::XLNetGenerator;
@@ -92,8 +97,22 @@ pub struct TextGenerationConfig {
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Typical sampling mass, following [Meister et al.](https://arxiv.org/abs/2202.00666). If provided and lower than 1, keeps the most typical tokens until their cumulative probability reaches typical_p (sampling only)
+    pub typical_p: Option<f64>,
+    /// Epsilon sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than epsilon_cutoff (sampling only)
+    pub epsilon_cutoff: Option<f64>,
+    /// Eta sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than min(eta_cutoff, sqrt(eta_cutoff) * exp(-entropy)) (sampling only)
+    pub eta_cutoff: Option<f64>,
+    /// Min-p sampling. If provided and higher than 0, removes the tokens with a probability lower than min_p times the probability of the most likely token (sampling only)
+    pub min_p: Option<f64>,
+    /// Degeneration penalty for [contrastive search, Su et al.](https://arxiv.org/abs/2202.06417). If provided and higher than 0, greedy decoding with top_k higher than 1 uses contrastive search
+    pub penalty_alpha: Option<f64>,
+    /// Preallocated key/value cache. If true, the keys and values of the self-attention layers are stored in buffers sized to `max_length`, written in place at each decoding step instead of being concatenated to the cache (GPT2, GPT-Neo and BART-family models, requires a maximum length) (default: false)
+    pub static_cache: bool,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
 }
 
 impl TextGenerationConfig {
@@ -138,7 +157,14 @@ impl TextGenerationConfig {
             num_return_sequences: 1,
             num_beam_groups: None,
             diversity_penalty: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            min_p: None,
+            penalty_alpha: None,
+            static_cache: false,
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
         }
     }
 }
@@ -179,7 +205,15 @@ impl From<TextGenerationConfig> for GenerateConfig {
             num_return_sequences: config.num_return_sequences,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            typical_p: config.typical_p,
+            epsilon_cutoff: config.epsilon_cutoff,
+            eta_cutoff: config.eta_cutoff,
+            min_p: config.min_p,
+            penalty_alpha: config.penalty_alpha,
+            static_cache: config.static_cache,
+            output_attentions: false,
             device: config.device,
+            weights_loading_mode: config.weights_loading_mode,
         }
     }
 }
@@ -251,7 +285,7 @@ impl TextGenerationOption {
         prompt_texts: Option<&[S]>,
         min_length: Option<i64>,
         max_length: Option<i64>,
-    ) -> Vec<Vec<i64>>
+    ) -> Result<Vec<Vec<i64>>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
@@ -260,62 +294,118 @@ impl TextGenerationOption {
             max_length,
             ..Default::default()
         });
-        match *self {
+        Ok(match *self {
             Self::GPT(ref model) => model
-                .generate_indices(prompt_texts, generate_options)
+                .generate_indices(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.indices)
                 .collect(),
             Self::GPT2(ref model) => model
-                .generate_indices(prompt_texts, generate_options)
+                .generate_indices(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.indices)
                 .collect(),
             Self::GPTNeo(ref model) => model
-                .generate_indices(prompt_texts, generate_options)
+                .generate_indices(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.indices)
                 .collect(),
             Self::XLNet(ref model) => model
-                .generate_indices(prompt_texts, generate_options)
+                .generate_indices(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.indices)
                 .collect(),
             Self::Reformer(ref model) => model
-                .generate_indices(prompt_texts, generate_options)
+                .generate_indices(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.indices)
                 .collect(),
-        }
+        })
     }
 
-    pub fn half(&mut self) {
-        match self {
-            Self::GPT(model_ref) => model_ref.half(),
-            Self::GPT2(model_ref) => model_ref.half(),
-            Self::GPTNeo(model_ref) => model_ref.half(),
-            Self::XLNet(model_ref) => model_ref.half(),
-            Self::Reformer(model_ref) => model_ref.half(),
+    /// Interface method to score() of the particular models.
+    pub fn score<S>(
+        &self,
+        texts: &[S],
+        options: &ScoringOptions,
+    ) -> Result<Vec<TextScore>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        match *self {
+            Self::GPT(ref model) => model.score(texts, options),
+            Self::GPT2(ref model) => model.score(texts, options),
+            Self::GPTNeo(ref model) => model.score(texts, options),
+            Self::XLNet(ref model) => model.score(texts, options),
+            Self::Reformer(ref model) => model.score(texts, options),
         }
     }
 
-    pub fn float(&mut self) {
+    /// Interface method to generate_indices_stream() of the particular models.
+    pub fn generate_indices_stream<S, F>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        min_length: Option<i64>,
+        max_length: Option<i64>,
+        token_callback: F,
+    ) -> Result<Vec<Vec<i64>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+        F: FnMut(&GeneratedToken),
+    {
+        let generate_options = Some(GenerateOptions {
+            min_length,
+            max_length,
+            ..Default::default()
+        });
+        Ok(match *self {
+            Self::GPT(ref model) => model
+                .generate_indices_stream(prompt_texts, generate_options, token_callback)?
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+            Self::GPT2(ref model) => model
+                .generate_indices_stream(prompt_texts, generate_options, token_callback)?
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+            Self::GPTNeo(ref model) => model
+                .generate_indices_stream(prompt_texts, generate_options, token_callback)?
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+            Self::XLNet(ref model) => model
+                .generate_indices_stream(prompt_texts, generate_options, token_callback)?
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+            Self::Reformer(ref model) => model
+                .generate_indices_stream(prompt_texts, generate_options, token_callback)?
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+        })
+    }
+
+    /// Returns a reference to the variables store of the underlying model
+    pub fn get_var_store(&self) -> &VarStore {
         match self {
-            Self::GPT(model_ref) => model_ref.float(),
-            Self::GPT2(model_ref) => model_ref.float(),
-            Self::GPTNeo(model_ref) => model_ref.float(),
-            Self::XLNet(model_ref) => model_ref.float(),
-            Self::Reformer(model_ref) => model_ref.float(),
+            Self::GPT(model_ref) => model_ref.get_var_store(),
+            Self::GPT2(model_ref) => model_ref.get_var_store(),
+            Self::GPTNeo(model_ref) => model_ref.get_var_store(),
+            Self::XLNet(model_ref) => model_ref.get_var_store(),
+            Self::Reformer(model_ref) => model_ref.get_var_store(),
         }
     }
 
-    pub fn set_device(&mut self, device: Device) {
+    /// Returns a mutable reference to the variables store of the underlying model
+    pub fn get_var_store_mut(&mut self) -> &mut VarStore {
         match self {
-            Self::GPT(model_ref) => model_ref.set_device(device),
-            Self::GPT2(model_ref) => model_ref.set_device(device),
-            Self::GPTNeo(model_ref) => model_ref.set_device(device),
-            Self::XLNet(model_ref) => model_ref.set_device(device),
-            Self::Reformer(model_ref) => model_ref.set_device(device),
+            Self::GPT(model_ref) => model_ref.get_var_store_mut(),
+            Self::GPT2(model_ref) => model_ref.get_var_store_mut(),
+            Self::GPTNeo(model_ref) => model_ref.get_var_store_mut(),
+            Self::XLNet(model_ref) => model_ref.get_var_store_mut(),
+            Self::Reformer(model_ref) => model_ref.get_var_store_mut(),
         }
     }
 }
@@ -384,18 +474,6 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
         })
     }
 
-    pub fn half(&mut self) {
-        self.model.half();
-    }
-
-    pub fn float(&mut self) {
-        self.model.float();
-    }
-
-    pub fn set_device(&mut self, device: Device) {
-        self.model.set_device(device);
-    }
-
     /// Generate texts from provided prompts
     ///
     /// # Arguments
@@ -404,7 +482,7 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
     /// * `prefix` - `impl Into<Option<&'a str>>`: Optional string to pass as a prefix for generation. Will be excluded from generated sequences.
     ///
     /// # Returns
-    /// * `Vec<String>` Generated texts
+    /// * `Result<Vec<String>, RustBertError>` Generated texts
     ///
     /// # Example
     ///
@@ -418,11 +496,136 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
     /// let input = ["The dog", "The cat was"];
     /// let prefix = None;
     ///
-    /// let output = model.generate(&input, prefix);
+    /// let output = model.generate(&input, prefix)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn generate<'a, S>(
+        &self,
+        texts: &[S],
+        prefix: impl Into<Option<&'a str>>,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        self.generate_internal(texts, prefix, None)
+    }
+
+    /// Generate texts from provided prompts, streaming the generated tokens to a callback as soon as they are generated.
+    /// Streaming is supported for greedy and sampling decoding (`num_beams` = 1).
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to summarize.
+    /// * `prefix` - `impl Into<Option<&'a str>>`: Optional string to pass as a prefix for generation. Will be excluded from generated sequences.
+    /// * `token_callback` - `FnMut(&GeneratedToken)` Callback called for every newly generated token with the index of the sequence it belongs to and the text it adds to the sequence
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, RustBertError>` Generated texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+    /// use std::io::Write;
+    ///
+    /// let model = TextGenerationModel::new(TextGenerationConfig {
+    ///     num_beams: 1,
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// let input = ["The dog"];
+    ///
+    /// let output = model.generate_stream(&input, None, |token| {
+    ///     print!("{}", token.text);
+    ///     std::io::stdout().flush().unwrap();
+    /// })?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn generate<'a, S>(&self, texts: &[S], prefix: impl Into<Option<&'a str>>) -> Vec<String>
+    pub fn generate_stream<'a, S, F>(
+        &self,
+        texts: &[S],
+        prefix: impl Into<Option<&'a str>>,
+        mut token_callback: F,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+        F: FnMut(&GeneratedToken),
+    {
+        self.generate_internal(texts, prefix, Some(&mut token_callback))
+    }
+
+    /// Score texts with the language model
+    ///
+    /// Computes the log-probability of each token of the texts given the previous tokens and the
+    /// perplexity of the texts. The pipeline prefix is not used for scoring. Scoring is not available
+    /// for Reformer models.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - `&[&str]` Array of texts to score.
+    /// * `options` - `&ScoringOptions` Batch size and sliding window settings for long texts.
+    ///
+    /// # Returns
+    /// * `Result<Vec<TextScore>, RustBertError>` Token log-probabilities, log-likelihood and perplexity of each text
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::scoring::ScoringOptions;
+    /// use rust_bert::pipelines::text_generation::TextGenerationModel;
+    ///
+    /// let model = TextGenerationModel::new(Default::default())?;
+    ///
+    /// let input = ["The cat sat on the mat.", "Mat the on sat cat the."];
+    ///
+    /// let output = model.score(&input, &ScoringOptions::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn score<S>(
+        &self,
+        texts: &[S],
+        options: &ScoringOptions,
+    ) -> Result<Vec<TextScore>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        self.model.score(texts, options)
+    }
+
+    fn generate_indices<S>(
+        &self,
+        texts: &[S],
+        min_length: Option<i64>,
+        max_length: Option<i64>,
+        token_callback: Option<&mut dyn FnMut(&GeneratedToken)>,
+    ) -> Result<Vec<Vec<i64>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        match token_callback {
+            Some(token_callback) => self.model.generate_indices_stream(
+                Some(texts),
+                min_length,
+                max_length,
+                token_callback,
+            ),
+            None => self
+                .model
+                .generate_indices(Some(texts), min_length, max_length),
+        }
+    }
+
+    fn generate_internal<'a, S>(
+        &self,
+        texts: &[S],
+        prefix: impl Into<Option<&'a str>>,
+        token_callback: Option<&mut dyn FnMut(&GeneratedToken)>,
+    ) -> Result<Vec<String>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
@@ -435,18 +638,19 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
             (None, None) => (None, None),
         };
         let generated_indices = match (prefix, prefix_length) {
-            (None, _) => self.model.generate_indices(Some(texts), None, None),
+            (None, _) => self.generate_indices(texts, None, None, token_callback)?,
             (Some(prefix), Some(prefix_length)) => {
                 let texts = texts
                     .as_ref()
                     .iter()
                     .map(|text| format!("{} {}", prefix, text.as_ref()))
                     .collect::<Vec<String>>();
-                self.model.generate_indices(
-                    Some(&texts),
+                self.generate_indices(
+                    &texts,
                     Some(self.min_length + prefix_length),
                     self.max_length.map(|max_length| max_length + prefix_length),
-                )
+                    token_callback,
+                )?
             }
             _ => panic!("Prefix length not defined but prefix provided!"),
         };
@@ -459,7 +663,17 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
                 true,
             ));
         }
-        output
+        Ok(output)
+    }
+}
+
+impl DeviceAndPrecision for TextGenerationModel {
+    fn get_var_store(&self) -> &VarStore {
+        self.model.get_var_store()
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        self.model.get_var_store_mut()
     }
 }
 
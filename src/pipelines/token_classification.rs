@@ -114,13 +114,15 @@
 use crate::albert::AlbertForTokenClassification;
 use crate::bert::BertForTokenClassification;
 use crate::common::error::RustBertError;
+use crate::common::quantization::QuantizationMode;
+use crate::common::weights::{load_weights_with_mode, WeightsLoadingMode};
 use crate::deberta::DebertaForTokenClassification;
 use crate::distilbert::DistilBertForTokenClassification;
 use crate::electra::ElectraForTokenClassification;
 use crate::fnet::FNetForTokenClassification;
 use crate::longformer::LongformerForTokenClassification;
 use crate::mobilebert::MobileBertForTokenClassification;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{ConfigOption, DeviceAndPrecision, ModelType, TokenizerOption};
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForTokenClassification;
 use crate::xlnet::XLNetForTokenClassification;
@@ -237,6 +239,10 @@ pub struct TokenClassificationConfig {
     pub add_prefix_space: Option<bool>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
+    /// Quantization mode applied to the model after loading the weights (default: no quantization). Dynamic int8 quantization is supported on CPU for BERT, RoBERTa and DistilBERT models
+    pub quantization: QuantizationMode,
     /// Sub-tokens aggregation method (default: `LabelAggregationOption::First`)
     pub label_aggregation_function: LabelAggregationOption,
     /// Batch size for predictions
@@ -280,6 +286,8 @@ impl TokenClassificationConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
+            quantization: QuantizationMode::None,
             label_aggregation_function,
             batch_size: 64,
         }
@@ -508,6 +516,25 @@ impl TokenClassificationOption {
         }
     }
 
+    /// Quantizes the encoder stack of the model. Dynamic quantization is supported for BERT,
+    /// RoBERTa and DistilBERT models.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        match self {
+            Self::Bert(model) => model.quantize(mode),
+            Self::DistilBert(model) => model.quantize(mode),
+            Self::Roberta(model) | Self::XLMRoberta(model) => model.quantize(mode),
+            _ if mode == QuantizationMode::None => Ok(()),
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Quantization is not supported for {:?} models",
+                self.model_type()
+            ))),
+        }
+    }
+
     fn forward_t(
         &self,
         input_ids: Option<&Tensor>,
@@ -651,6 +678,7 @@ pub struct TokenClassificationModel {
     label_aggregation_function: LabelAggregationOption,
     max_length: usize,
     batch_size: usize,
+    quantization: QuantizationMode,
 }
 
 impl TokenClassificationModel {
@@ -693,16 +721,17 @@ impl TokenClassificationModel {
             config.add_prefix_space,
         )?;
         let mut var_store = VarStore::new(device);
-        let model_config = ConfigOption::from_file(config.model_type, config_path);
+        let model_config = ConfigOption::try_from_file(config.model_type, config_path)?;
         let max_length = model_config
             .get_max_len()
             .map(|v| v as usize)
             .unwrap_or(usize::MAX);
-        let token_sequence_classifier =
+        let mut token_sequence_classifier =
             TokenClassificationOption::new(config.model_type, var_store.root(), &model_config)?;
         let label_mapping = model_config.get_label_mapping().clone();
         let batch_size = config.batch_size;
-        var_store.load(weights_path)?;
+        load_weights_with_mode(weights_path, &mut var_store, config.weights_loading_mode)?;
+        token_sequence_classifier.quantize(config.quantization)?;
         Ok(TokenClassificationModel {
             tokenizer,
             token_sequence_classifier,
@@ -711,6 +740,7 @@ impl TokenClassificationModel {
             label_aggregation_function,
             max_length,
             batch_size,
+            quantization: config.quantization,
         })
     }
 
@@ -891,10 +921,7 @@ impl TokenClassificationModel {
                     None,
                     false,
                 );
-                let score = output.exp()
-                    / output
-                        .exp()
-                        .sum_dim_intlist([-1].as_slice(), true, Kind::Float);
+                let score = output.softmax(-1, Kind::Float);
                 let label_indices = score.argmax(-1, true);
                 for sentence_idx in 0..label_indices.size()[0] {
                     let labels = label_indices.get(sentence_idx);
@@ -1148,3 +1175,17 @@ impl TokenClassificationModel {
         }
     }
 }
+
+impl DeviceAndPrecision for TokenClassificationModel {
+    fn get_var_store(&self) -> &VarStore {
+        &self.var_store
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        &mut self.var_store
+    }
+
+    fn is_quantized(&self) -> bool {
+        self.quantization != QuantizationMode::None
+    }
+}
@@ -90,6 +90,9 @@
 mod translation_builder;
 mod translation_pipeline;
 
-pub use translation_pipeline::{Language, TranslationConfig, TranslationModel, TranslationOption};
+pub use translation_pipeline::{
+    Language, TranslationConfig, TranslationHypothesis, TranslationModel, TranslationOption,
+    WordAlignment,
+};
 
 pub use translation_builder::TranslationModelBuilder;
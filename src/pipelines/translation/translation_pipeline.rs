@@ -11,18 +11,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use tch::Device;
+use tch::nn::VarStore;
+use tch::{no_grad, Device, Kind, Tensor};
 
 use crate::common::error::RustBertError;
+use crate::common::weights::WeightsLoadingMode;
 use crate::m2m_100::M2M100Generator;
 use crate::marian::MarianGenerator;
 use crate::mbart::MBartGenerator;
-use crate::pipelines::common::ModelType;
+use crate::pipelines::common::{DeviceAndPrecision, ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
-use crate::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use crate::pipelines::generation_utils::{
+    GenerateConfig, GenerateOptions, GeneratedIndicesOutput, LanguageGenerator,
+};
 use crate::resources::ResourceProvider;
 use crate::t5::T5Generator;
+use rust_tokenizers::tokenizer::TruncationStrategy;
 use serde::{Deserialize, Serialize};
+use std::cmp::max;
 use std::collections::HashSet;
 use std::fmt;
 use std::fmt::{Debug, Display};
@@ -411,10 +417,26 @@ pub struct TranslationConfig {
     pub num_return_sequences: i64,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
     /// Number of beam groups for diverse beam generation. If provided and higher than 1, will split the beams into beam subgroups leading to more diverse generation.
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Typical sampling mass, following [Meister et al.](https://arxiv.org/abs/2202.00666). If provided and lower than 1, keeps the most typical tokens until their cumulative probability reaches typical_p (sampling only)
+    pub typical_p: Option<f64>,
+    /// Epsilon sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than epsilon_cutoff (sampling only)
+    pub epsilon_cutoff: Option<f64>,
+    /// Eta sampling cutoff, following [Hewitt et al.](https://arxiv.org/abs/2210.15191). If provided and higher than 0, removes the tokens with a probability lower than min(eta_cutoff, sqrt(eta_cutoff) * exp(-entropy)) (sampling only)
+    pub eta_cutoff: Option<f64>,
+    /// Min-p sampling. If provided and higher than 0, removes the tokens with a probability lower than min_p times the probability of the most likely token (sampling only)
+    pub min_p: Option<f64>,
+    /// Degeneration penalty for [contrastive search, Su et al.](https://arxiv.org/abs/2202.06417). If provided and higher than 0, greedy decoding with top_k higher than 1 uses contrastive search
+    pub penalty_alpha: Option<f64>,
+    /// Preallocated key/value cache. If true, the keys and values of the self-attention layers are stored in buffers sized to `max_length`, written in place at each decoding step instead of being concatenated to the cache (GPT2, GPT-Neo and BART-family models, requires a maximum length) (default: false)
+    pub static_cache: bool,
+    /// Flag indicating if the model should output the cross-attention weights used to compute the word alignments returned by `TranslationModel::translate_with_details` (default: false)
+    pub output_alignments: bool,
 }
 
 impl TranslationConfig {
@@ -487,6 +509,7 @@ impl TranslationConfig {
             source_languages: source_languages.as_ref().iter().cloned().collect(),
             target_languages: target_languages.as_ref().iter().cloned().collect(),
             device,
+            weights_loading_mode: WeightsLoadingMode::Strict,
             min_length: 0,
             max_length: Some(512),
             do_sample: false,
@@ -501,6 +524,13 @@ impl TranslationConfig {
             num_return_sequences: 1,
             num_beam_groups: None,
             diversity_penalty: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            min_p: None,
+            penalty_alpha: None,
+            static_cache: false,
+            output_alignments: false,
         }
     }
 }
@@ -526,7 +556,15 @@ impl From<TranslationConfig> for GenerateConfig {
             num_return_sequences: config.num_return_sequences,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            typical_p: config.typical_p,
+            epsilon_cutoff: config.epsilon_cutoff,
+            eta_cutoff: config.eta_cutoff,
+            min_p: config.min_p,
+            penalty_alpha: config.penalty_alpha,
+            static_cache: config.static_cache,
+            output_attentions: config.output_alignments,
             device: config.device,
+            weights_loading_mode: config.weights_loading_mode,
         }
     }
 }
@@ -731,28 +769,42 @@ impl TranslationOption {
         &self,
         prompt_texts: Option<&[S]>,
         forced_bos_token_id: Option<i64>,
-    ) -> Vec<String>
+    ) -> Result<Vec<String>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
-        match *self {
+        self.generate_with_options(prompt_texts, forced_bos_token_id, None)
+    }
+
+    /// Interface method to generate() of the particular models, with generation options overriding the model
+    /// configuration (for example logit bias or constraints).
+    pub fn generate_with_options<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        forced_bos_token_id: Option<i64>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        Ok(match *self {
             Self::Marian(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::T5(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::MBart(ref model) => {
                 let generate_options = GenerateOptions {
                     forced_bos_token_id,
-                    ..Default::default()
+                    ..generate_options.unwrap_or_default()
                 };
                 model
-                    .generate(prompt_texts, Some(generate_options))
+                    .generate(prompt_texts, Some(generate_options))?
                     .into_iter()
                     .map(|output| output.text)
                     .collect()
@@ -760,18 +812,209 @@ impl TranslationOption {
             Self::M2M100(ref model) => {
                 let generate_options = GenerateOptions {
                     forced_bos_token_id,
-                    ..Default::default()
+                    ..generate_options.unwrap_or_default()
                 };
                 model
-                    .generate(prompt_texts, Some(generate_options))
+                    .generate(prompt_texts, Some(generate_options))?
                     .into_iter()
                     .map(|output| output.text)
                     .collect()
             }
+        })
+    }
+
+    /// Interface method to generate_indices() of the particular models, with generation options overriding the model
+    /// configuration.
+    pub fn generate_indices_with_options<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        forced_bos_token_id: Option<i64>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        match *self {
+            Self::Marian(ref model) => model.generate_indices(prompt_texts, generate_options),
+            Self::T5(ref model) => model.generate_indices(prompt_texts, generate_options),
+            Self::MBart(ref model) => {
+                let generate_options = GenerateOptions {
+                    forced_bos_token_id,
+                    ..generate_options.unwrap_or_default()
+                };
+                model.generate_indices(prompt_texts, Some(generate_options))
+            }
+            Self::M2M100(ref model) => {
+                let generate_options = GenerateOptions {
+                    forced_bos_token_id,
+                    ..generate_options.unwrap_or_default()
+                };
+                model.generate_indices(prompt_texts, Some(generate_options))
+            }
+        }
+    }
+
+    /// Returns the tokenizer of the translation model
+    pub fn get_tokenizer(&self) -> &TokenizerOption {
+        match *self {
+            Self::Marian(ref model) => model._get_tokenizer(),
+            Self::T5(ref model) => model._get_tokenizer(),
+            Self::MBart(ref model) => model._get_tokenizer(),
+            Self::M2M100(ref model) => model._get_tokenizer(),
+        }
+    }
+
+    /// Returns the generation configuration of the translation model
+    pub fn get_generate_config(&self) -> &GenerateConfig {
+        match *self {
+            Self::Marian(ref model) => model.get_config(),
+            Self::T5(ref model) => model.get_config(),
+            Self::MBart(ref model) => model.get_config(),
+            Self::M2M100(ref model) => model.get_config(),
+        }
+    }
+
+    fn get_eos_ids(&self) -> Option<&Vec<i64>> {
+        match *self {
+            Self::Marian(ref model) => model.get_eos_ids(),
+            Self::T5(ref model) => model.get_eos_ids(),
+            Self::MBart(ref model) => model.get_eos_ids(),
+            Self::M2M100(ref model) => model.get_eos_ids(),
+        }
+    }
+
+    fn get_max_positions_embeddings(&self) -> i64 {
+        match *self {
+            Self::Marian(ref model) => model.get_max_positions_embeddings(),
+            Self::T5(ref model) => model.get_max_positions_embeddings(),
+            Self::MBart(ref model) => model.get_max_positions_embeddings(),
+            Self::M2M100(ref model) => model.get_max_positions_embeddings(),
+        }
+    }
+
+    /// Decoder cross-attention weights for the given source and target sequences, averaged over the layers and
+    /// attention heads, of shape (*batch size*, *target sequence length*, *source sequence length*). Returns `None` if
+    /// the model does not output attention weights (see `TranslationConfig::output_alignments`).
+    fn get_cross_attentions(
+        &self,
+        input_ids: &Tensor,
+        decoder_input_ids: &Tensor,
+    ) -> Option<Tensor> {
+        let cross_attentions = no_grad(|| match *self {
+            Self::Marian(ref model) => {
+                model
+                    .get_model()
+                    .forward_t(
+                        Some(input_ids),
+                        None,
+                        None,
+                        Some(decoder_input_ids),
+                        None,
+                        None,
+                        false,
+                    )
+                    .all_decoder_cross_attentions
+            }
+            Self::T5(ref model) => {
+                model
+                    .get_model()
+                    .forward_t(
+                        Some(input_ids),
+                        None,
+                        None,
+                        Some(decoder_input_ids),
+                        None,
+                        None,
+                        None,
+                        None,
+                        false,
+                    )
+                    .all_decoder_cross_attentions
+            }
+            Self::MBart(ref model) => {
+                model
+                    .get_model()
+                    .forward_t(
+                        Some(input_ids),
+                        None,
+                        None,
+                        Some(decoder_input_ids),
+                        None,
+                        None,
+                        false,
+                    )
+                    .all_decoder_cross_attentions
+            }
+            Self::M2M100(ref model) => {
+                model
+                    .get_model()
+                    .forward_t(
+                        Some(input_ids),
+                        None,
+                        None,
+                        Some(decoder_input_ids),
+                        None,
+                        None,
+                        false,
+                    )
+                    .all_decoder_cross_attentions
+            }
+        })?;
+        Some(Tensor::stack(&cross_attentions, 0).mean_dim([0, 2].as_slice(), false, Kind::Float))
+    }
+
+    /// Returns a reference to the variables store of the underlying model
+    pub fn get_var_store(&self) -> &VarStore {
+        match self {
+            Self::Marian(model_ref) => model_ref.get_var_store(),
+            Self::T5(model_ref) => model_ref.get_var_store(),
+            Self::MBart(model_ref) => model_ref.get_var_store(),
+            Self::M2M100(model_ref) => model_ref.get_var_store(),
+        }
+    }
+
+    /// Returns a mutable reference to the variables store of the underlying model
+    pub fn get_var_store_mut(&mut self) -> &mut VarStore {
+        match self {
+            Self::Marian(model_ref) => model_ref.get_var_store_mut(),
+            Self::T5(model_ref) => model_ref.get_var_store_mut(),
+            Self::MBart(model_ref) => model_ref.get_var_store_mut(),
+            Self::M2M100(model_ref) => model_ref.get_var_store_mut(),
         }
     }
 }
 
+/// # Translation hypothesis
+/// Translation returned by `TranslationModel::translate_with_details`, with its scores and word alignments
+#[derive(Debug, Clone)]
+pub struct TranslationHypothesis {
+    /// Translated text
+    pub text: String,
+    /// Score used to rank the hypotheses (sum of the token log-probabilities normalized by the length penalty)
+    pub score: f64,
+    /// Log-probability of the translated sequence (sum of the token log-probabilities)
+    pub log_probability: f64,
+    /// Log-probability of each generated token, including the end of sequence token
+    pub token_scores: Vec<f64>,
+    /// Alignments of the words of the translation to the words of the source text, derived from the decoder
+    /// cross-attention weights. `None` if the model does not output its attention weights (see
+    /// `TranslationConfig::output_alignments`)
+    pub alignments: Option<Vec<WordAlignment>>,
+}
+
+/// # Word alignment
+/// Alignment of a word of the translation to the source word it attends the most to. Words are the whitespace-separated
+/// words of the source text (without the language prefix added for the model) and of the translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WordAlignment {
+    /// Index of the source word
+    pub source_word: usize,
+    /// Index of the target word
+    pub target_word: usize,
+    /// Cross-attention weight of the target word tokens over the source word tokens (averaged over the target word tokens)
+    pub weight: f64,
+}
+
 /// # TranslationModel to perform translation
 pub struct TranslationModel {
     model: TranslationOption,
@@ -888,6 +1131,76 @@ impl TranslationModel {
         source_language: impl Into<Option<Language>>,
         target_language: impl Into<Option<Language>>,
     ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        self.translate_with_options(texts, source_language, target_language, None)
+    }
+
+    /// Translates texts provided, with generation options overriding the model configuration
+    ///
+    /// # Arguments
+    /// * `input` - `&[&str]` Array of texts to translate.
+    /// * `source_language` - Source language of the texts (optional for models with a single source language)
+    /// * `target_language` - Target language (optional for models with a single target language)
+    /// * `generate_options` - Optional `GenerateOptions` (for example a `logit_bias` or `constraints` enforcing a terminology)
+    ///
+    /// # Returns
+    /// * `Vec<String>` Translated texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::marian::{
+    ///     MarianConfigResources, MarianModelResources, MarianSourceLanguages, MarianSpmResources,
+    ///     MarianTargetLanguages, MarianVocabResources,
+    /// };
+    /// use rust_bert::pipelines::common::ModelType;
+    /// use rust_bert::pipelines::generation_utils::GenerateOptions;
+    /// use rust_bert::pipelines::translation::{Language, TranslationConfig, TranslationModel};
+    /// use rust_bert::resources::RemoteResource;
+    /// use std::collections::HashMap;
+    /// use tch::Device;
+    ///
+    /// let model_resource = RemoteResource::from_pretrained(MarianModelResources::ENGLISH2ROMANCE);
+    /// let config_resource = RemoteResource::from_pretrained(MarianConfigResources::ENGLISH2ROMANCE);
+    /// let vocab_resource = RemoteResource::from_pretrained(MarianVocabResources::ENGLISH2ROMANCE);
+    /// let merges_resource = RemoteResource::from_pretrained(MarianSpmResources::ENGLISH2ROMANCE);
+    /// let source_languages = MarianSourceLanguages::ENGLISH2ROMANCE;
+    /// let target_languages = MarianTargetLanguages::ENGLISH2ROMANCE;
+    ///
+    /// let translation_config = TranslationConfig::new(
+    ///     ModelType::Marian,
+    ///     model_resource,
+    ///     config_resource,
+    ///     vocab_resource,
+    ///     Some(merges_resource),
+    ///     source_languages,
+    ///     target_languages,
+    ///     Device::cuda_if_available(),
+    /// );
+    /// let model = TranslationModel::new(translation_config)?;
+    ///
+    /// let input = ["This is a sentence to be translated"];
+    /// let logit_bias = HashMap::from([(1234, 5.0)]);
+    /// let generate_options = GenerateOptions {
+    ///     logit_bias: Some(&logit_bias),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let output =
+    ///     model.translate_with_options(&input, None, Language::French, Some(generate_options));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn translate_with_options<S>(
+        &self,
+        texts: &[S],
+        source_language: impl Into<Option<Language>>,
+        target_language: impl Into<Option<Language>>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<String>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
@@ -898,16 +1211,380 @@ impl TranslationModel {
             &self.supported_target_languages,
         )?;
 
-        Ok(match prefix {
+        match prefix {
             Some(value) => {
                 let texts = texts
                     .iter()
                     .map(|v| format!("{}{}", value, v.as_ref()))
                     .collect::<Vec<String>>();
-                self.model.generate(Some(&texts), forced_bos_token_id)
+                self.model.generate_with_options(
+                    Some(&texts),
+                    forced_bos_token_id,
+                    generate_options,
+                )
+            }
+            None => {
+                self.model
+                    .generate_with_options(Some(texts), forced_bos_token_id, generate_options)
+            }
+        }
+    }
+
+    /// Translates texts provided, returning the top hypotheses for each text with their scores and word alignments
+    ///
+    /// # Arguments
+    /// * `input` - `&[&str]` Array of texts to translate.
+    /// * `source_language` - Source language of the texts (optional for models with a single source language)
+    /// * `target_language` - Target language (optional for models with a single target language)
+    /// * `num_hypotheses` - Number of hypotheses returned for each text. The number of beams is raised to `num_hypotheses` if lower (unless sampling is used).
+    ///
+    /// # Returns
+    /// * `Vec<Vec<TranslationHypothesis>>` Hypotheses for each text, ranked by decreasing score. The word alignments are
+    ///   only provided if the model was created with `output_alignments` enabled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::marian::{
+    ///     MarianConfigResources, MarianModelResources, MarianSourceLanguages, MarianSpmResources,
+    ///     MarianTargetLanguages, MarianVocabResources,
+    /// };
+    /// use rust_bert::pipelines::common::ModelType;
+    /// use rust_bert::pipelines::translation::{Language, TranslationConfig, TranslationModel};
+    /// use rust_bert::resources::RemoteResource;
+    /// use tch::Device;
+    ///
+    /// let model_resource = RemoteResource::from_pretrained(MarianModelResources::ENGLISH2ROMANCE);
+    /// let config_resource = RemoteResource::from_pretrained(MarianConfigResources::ENGLISH2ROMANCE);
+    /// let vocab_resource = RemoteResource::from_pretrained(MarianVocabResources::ENGLISH2ROMANCE);
+    /// let merges_resource = RemoteResource::from_pretrained(MarianSpmResources::ENGLISH2ROMANCE);
+    /// let source_languages = MarianSourceLanguages::ENGLISH2ROMANCE;
+    /// let target_languages = MarianTargetLanguages::ENGLISH2ROMANCE;
+    ///
+    /// let translation_config = TranslationConfig {
+    ///     output_alignments: true,
+    ///     ..TranslationConfig::new(
+    ///         ModelType::Marian,
+    ///         model_resource,
+    ///         config_resource,
+    ///         vocab_resource,
+    ///         Some(merges_resource),
+    ///         source_languages,
+    ///         target_languages,
+    ///         Device::cuda_if_available(),
+    ///     )
+    /// };
+    /// let model = TranslationModel::new(translation_config)?;
+    ///
+    /// let input = ["This is a sentence to be translated"];
+    /// let output = model.translate_with_details(&input, None, Language::French, 3)?;
+    /// for hypothesis in &output[0] {
+    ///     println!("{} ({:.3})", hypothesis.text, hypothesis.log_probability);
+    ///     for alignment in hypothesis.alignments.iter().flatten() {
+    ///         println!("{} -> {}", alignment.source_word, alignment.target_word);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn translate_with_details<S>(
+        &self,
+        texts: &[S],
+        source_language: impl Into<Option<Language>>,
+        target_language: impl Into<Option<Language>>,
+        num_hypotheses: i64,
+    ) -> Result<Vec<Vec<TranslationHypothesis>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if num_hypotheses < 1 {
+            return Err(RustBertError::ValueError(format!(
+                "num_hypotheses must be strictly greater than 0, got {}",
+                num_hypotheses
+            )));
+        }
+        let (prefix, forced_bos_token_id) = self.model.validate_and_get_prefix_and_forced_bos_id(
+            source_language.into().as_ref(),
+            target_language.into().as_ref(),
+            &self.supported_source_languages,
+            &self.supported_target_languages,
+        )?;
+        let prefix = prefix.unwrap_or_default();
+        let prefixed_texts = texts
+            .iter()
+            .map(|text| format!("{}{}", prefix, text.as_ref()))
+            .collect::<Vec<String>>();
+
+        let generate_config = self.model.get_generate_config();
+        let num_beams = if generate_config.do_sample {
+            generate_config.num_beams
+        } else {
+            max(generate_config.num_beams, num_hypotheses)
+        };
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            num_return_sequences: Some(num_hypotheses),
+            output_scores: true,
+            ..Default::default()
+        };
+        let outputs = self.model.generate_indices_with_options(
+            Some(&prefixed_texts),
+            forced_bos_token_id,
+            Some(generate_options),
+        )?;
+
+        let tokenizer = self.model.get_tokenizer();
+        let eos_token_ids = self.model.get_eos_ids();
+        let prefix_length = prefix.chars().count();
+        let mut translations = Vec::with_capacity(texts.len());
+        for ((text, prefixed_text), text_outputs) in texts
+            .iter()
+            .zip(prefixed_texts.iter())
+            .zip(outputs.chunks(num_hypotheses as usize))
+        {
+            let generated_lengths = text_outputs
+                .iter()
+                .map(|output| get_generated_length(&output.indices, eos_token_ids))
+                .collect::<Vec<usize>>();
+            let mut alignments = self
+                .get_word_alignments(
+                    text.as_ref(),
+                    prefixed_text,
+                    prefix_length,
+                    text_outputs,
+                    &generated_lengths,
+                )
+                .map(|alignments| alignments.into_iter());
+
+            let mut hypotheses = Vec::with_capacity(text_outputs.len());
+            for (output, generated_length) in text_outputs.iter().zip(generated_lengths) {
+                let mut token_scores = output.token_scores.clone().unwrap_or_default();
+                token_scores.truncate(generated_length);
+                hypotheses.push(TranslationHypothesis {
+                    text: tokenizer.decode(&output.indices, true, true),
+                    score: output.score.unwrap_or_default(),
+                    log_probability: token_scores.iter().sum(),
+                    token_scores,
+                    alignments: alignments.as_mut().and_then(|alignments| alignments.next()),
+                });
+            }
+            translations.push(hypotheses);
+        }
+        Ok(translations)
+    }
+
+    /// Word alignments of the hypotheses generated for a text, or `None` if the model does not output its attention
+    /// weights
+    fn get_word_alignments(
+        &self,
+        text: &str,
+        prefixed_text: &str,
+        prefix_length: usize,
+        outputs: &[GeneratedIndicesOutput],
+        generated_lengths: &[usize],
+    ) -> Option<Vec<Vec<WordAlignment>>> {
+        let tokenizer = self.model.get_tokenizer();
+        let device = self.device();
+
+        // The source is encoded as in generation (with special tokens, truncated to the maximum source length) so that
+        // the cross-attention weights are computed over the encoder inputs the hypotheses were generated from
+        let source = tokenizer
+            .encode_list(
+                &[prefixed_text],
+                self.model.get_max_positions_embeddings() as usize,
+                &TruncationStrategy::LongestFirst,
+                0,
+            )
+            .pop()
+            .unwrap();
+        let source_ids = source.token_ids;
+        if source_ids.is_empty() || outputs.is_empty() {
+            return Some(vec![vec![]; outputs.len()]);
+        }
+        let source_word_spans = get_word_spans(text);
+        let source_token_words = source
+            .token_offsets
+            .iter()
+            .zip(source.special_tokens_mask.iter())
+            .map(|(offset, is_special_token)| {
+                if *is_special_token == 1 {
+                    return None;
+                }
+                offset
+                    .and_then(|offset| (offset.begin as usize).checked_sub(prefix_length))
+                    .and_then(|position| get_word_index(&source_word_spans, position))
+            })
+            .collect::<Vec<Option<usize>>>();
+
+        let input_ids = Tensor::of_slice(&source_ids)
+            .unsqueeze(0)
+            .repeat(&[outputs.len() as i64, 1])
+            .to(device);
+        let decoder_input_ids = Tensor::stack(
+            &outputs
+                .iter()
+                .map(|output| Tensor::of_slice(&output.indices))
+                .collect::<Vec<Tensor>>(),
+            0,
+        )
+        .to(device);
+        let cross_attentions = self
+            .model
+            .get_cross_attentions(&input_ids, &decoder_input_ids)?;
+
+        let mut alignments = Vec::with_capacity(outputs.len());
+        for (hypothesis_index, (output, generated_length)) in
+            outputs.iter().zip(generated_lengths).enumerate()
+        {
+            let (target_token_words, num_target_words) =
+                get_target_token_words(tokenizer, &output.indices, *generated_length);
+            // The token at position k + 1 is generated from the decoder state at position k
+            let attention_rows = cross_attentions
+                .get(hypothesis_index as i64)
+                .narrow(0, 0, *generated_length as i64)
+                .to_kind(Kind::Double);
+            let attention_rows = (0..*generated_length as i64)
+                .map(|row| {
+                    attention_rows
+                        .get(row)
+                        .iter::<f64>()
+                        .unwrap()
+                        .collect::<Vec<f64>>()
+                })
+                .collect::<Vec<Vec<f64>>>();
+            alignments.push(align_words(
+                &attention_rows,
+                &source_token_words,
+                source_word_spans.len(),
+                &target_token_words,
+                num_target_words,
+            ));
+        }
+        Some(alignments)
+    }
+}
+
+/// Number of tokens generated after the decoder start token, up to the first end of sequence token (included)
+fn get_generated_length(indices: &[i64], eos_token_ids: Option<&Vec<i64>>) -> usize {
+    let generated_indices = indices.get(1..).unwrap_or_default();
+    eos_token_ids
+        .and_then(|eos_token_ids| {
+            generated_indices
+                .iter()
+                .position(|token_id| eos_token_ids.contains(token_id))
+        })
+        .map_or(generated_indices.len(), |eos_position| eos_position + 1)
+}
+
+/// Character spans of the whitespace-separated words of a text
+fn get_word_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut word_spans = Vec::new();
+    let mut word_start = None;
+    let mut num_chars = 0;
+    for (position, character) in text.chars().enumerate() {
+        match (character.is_whitespace(), word_start) {
+            (true, Some(start)) => {
+                word_spans.push((start, position));
+                word_start = None;
+            }
+            (false, None) => word_start = Some(position),
+            _ => {}
+        }
+        num_chars = position + 1;
+    }
+    if let Some(start) = word_start {
+        word_spans.push((start, num_chars));
+    }
+    word_spans
+}
+
+fn get_word_index(word_spans: &[(usize, usize)], position: usize) -> Option<usize> {
+    word_spans
+        .iter()
+        .position(|(start, end)| (*start <= position) & (position < *end))
+}
+
+/// Maps each generated token to the word of the decoded translation it starts in, by decoding the successive prefixes
+/// of the generated sequence. Returns the token words and the number of words of the translation.
+fn get_target_token_words(
+    tokenizer: &TokenizerOption,
+    indices: &[i64],
+    generated_length: usize,
+) -> (Vec<Option<usize>>, usize) {
+    let text = tokenizer.decode(&indices[..=generated_length], true, true);
+    let word_spans = get_word_spans(&text);
+    let mut previous_text = tokenizer
+        .decode(&indices[..1], true, true)
+        .chars()
+        .collect::<Vec<char>>();
+    let mut token_words = Vec::with_capacity(generated_length);
+    for position in 1..=generated_length {
+        let current_text = tokenizer
+            .decode(&indices[..=position], true, true)
+            .chars()
+            .collect::<Vec<char>>();
+        let common_length = previous_text
+            .iter()
+            .zip(current_text.iter())
+            .take_while(|(previous, current)| previous == current)
+            .count();
+        let word_index = current_text[common_length..]
+            .iter()
+            .position(|character| !character.is_whitespace())
+            .and_then(|offset| get_word_index(&word_spans, common_length + offset));
+        token_words.push(word_index);
+        previous_text = current_text;
+    }
+    (token_words, word_spans.len())
+}
+
+/// Aligns each target word to the source word receiving the highest cross-attention weight from its tokens
+fn align_words(
+    attention_rows: &[Vec<f64>],
+    source_token_words: &[Option<usize>],
+    num_source_words: usize,
+    target_token_words: &[Option<usize>],
+    num_target_words: usize,
+) -> Vec<WordAlignment> {
+    let mut word_weights = vec![vec![0f64; num_source_words]; num_target_words];
+    let mut target_word_lengths = vec![0usize; num_target_words];
+    for (attention_row, target_word) in attention_rows.iter().zip(target_token_words) {
+        if let Some(target_word) = *target_word {
+            target_word_lengths[target_word] += 1;
+            for (weight, source_word) in attention_row.iter().zip(source_token_words) {
+                if let Some(source_word) = *source_word {
+                    word_weights[target_word][source_word] += weight;
+                }
             }
-            None => self.model.generate(Some(texts), forced_bos_token_id),
+        }
+    }
+    word_weights
+        .into_iter()
+        .zip(target_word_lengths)
+        .enumerate()
+        .filter(|(_, (_, length))| *length > 0)
+        .filter_map(|(target_word, (weights, length))| {
+            weights
+                .into_iter()
+                .enumerate()
+                .max_by(|(_, weight_a), (_, weight_b)| weight_a.total_cmp(weight_b))
+                .map(|(source_word, weight)| WordAlignment {
+                    source_word,
+                    target_word,
+                    weight: weight / length as f64,
+                })
         })
+        .collect()
+}
+
+impl DeviceAndPrecision for TranslationModel {
+    fn get_var_store(&self) -> &VarStore {
+        self.model.get_var_store()
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        self.model.get_var_store_mut()
     }
 }
 
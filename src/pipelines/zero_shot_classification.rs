@@ -101,11 +101,12 @@
 use crate::albert::AlbertForSequenceClassification;
 use crate::bart::BartForSequenceClassification;
 use crate::bert::BertForSequenceClassification;
+use crate::common::weights::{load_weights_with_mode, WeightsLoadingMode};
 use crate::deberta::DebertaForSequenceClassification;
 use crate::distilbert::DistilBertModelClassifier;
 use crate::longformer::LongformerForSequenceClassification;
 use crate::mobilebert::MobileBertForSequenceClassification;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{ConfigOption, DeviceAndPrecision, ModelType, TokenizerOption};
 use crate::pipelines::sequence_classification::Label;
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForSequenceClassification;
@@ -146,6 +147,8 @@ pub struct ZeroShotClassificationConfig {
     pub add_prefix_space: Option<bool>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Weights loading mode. A partial loading keeps the initial values of the model variables missing from the weights file (default: strict)
+    pub weights_loading_mode: WeightsLoadingMode,
 }
 
 impl ZeroShotClassificationConfig {
@@ -184,6 +187,7 @@ impl ZeroShotClassificationConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
         }
     }
 }
@@ -210,6 +214,7 @@ impl Default for ZeroShotClassificationConfig {
             strip_accents: None,
             add_prefix_space: None,
             device: Device::cuda_if_available(),
+            weights_loading_mode: WeightsLoadingMode::Strict,
         }
     }
 }
@@ -573,10 +578,10 @@ impl ZeroShotClassificationModel {
             config.add_prefix_space,
         )?;
         let mut var_store = VarStore::new(device);
-        let model_config = ConfigOption::from_file(config.model_type, config_path);
+        let model_config = ConfigOption::try_from_file(config.model_type, config_path)?;
         let zero_shot_classifier =
             ZeroShotClassificationOption::new(config.model_type, var_store.root(), &model_config)?;
-        var_store.load(weights_path)?;
+        load_weights_with_mode(weights_path, &mut var_store, config.weights_loading_mode)?;
         Ok(ZeroShotClassificationModel {
             tokenizer,
             zero_shot_classifier,
@@ -902,6 +907,17 @@ impl ZeroShotClassificationModel {
         Ok(output_labels)
     }
 }
+
+impl DeviceAndPrecision for ZeroShotClassificationModel {
+    fn get_var_store(&self) -> &VarStore {
+        &self.var_store
+    }
+
+    fn get_var_store_mut(&mut self) -> &mut VarStore {
+        &mut self.var_store
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -18,6 +18,7 @@ use rust_tokenizers::vocab::{ProphetNetVocab, Vocab};
 use serde::{Deserialize, Serialize};
 use tch::{nn, Kind, Tensor};
 
+use crate::common::weights::load_weights_with_mode;
 use crate::pipelines::common::{ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
@@ -555,6 +556,7 @@ impl ProphetNetForConditionalGeneration {
         Ok(ProphetNetGenerationOutput {
             logits,
             ngram_logits,
+            last_hidden_states: base_model_output.last_hidden_states,
             ngram_hidden_states: base_model_output.ngram_hidden_states,
             all_decoder_hidden_states: base_model_output.all_decoder_hidden_states,
             all_ngram_hidden_states: base_model_output.all_ngram_hidden_states,
@@ -630,6 +632,7 @@ impl LMHeadModel for ProphetNetForConditionalGeneration {
 
         Ok(LMModelOutput {
             lm_logits: base_model_output.logits,
+            hidden_state: Some(base_model_output.last_hidden_states),
             cache: Cache::ProphetNetCache(base_model_output.next_decoder_cache),
         })
     }
@@ -830,6 +833,7 @@ impl ProphetNetForCausalGeneration {
         Ok(ProphetNetGenerationOutput {
             logits,
             ngram_logits,
+            last_hidden_states: base_model_output.hidden_states,
             ngram_hidden_states: base_model_output.ngram_hidden_states,
             all_decoder_hidden_states: base_model_output.all_hidden_states,
             all_ngram_hidden_states: base_model_output.all_ngram_hidden_states,
@@ -867,6 +871,8 @@ pub struct ProphetNetGenerationOutput {
     pub logits: Tensor,
     /// Ngram prediction logits
     pub ngram_logits: Option<Tensor>,
+    /// last decoder layer hidden state
+    pub last_hidden_states: Tensor,
     /// last decoder layer ngram hidden state
     pub ngram_hidden_states: Option<Tensor>,
     /// Hidden states for all intermediate layers
@@ -962,11 +968,15 @@ impl ProphetNetConditionalGenerator {
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate()?;
         let mut var_store = nn::VarStore::new(device);
-        let config = ProphetNetConfig::from_file(config_path);
+        let config = ProphetNetConfig::try_from_file(config_path)?;
         let model = ProphetNetForConditionalGeneration::new(var_store.root(), &config)?;
-        var_store.load(weights_path)?;
+        load_weights_with_mode(
+            weights_path,
+            &mut var_store,
+            generate_config.weights_loading_mode,
+        )?;
 
         let bos_token_id = Some(config.bos_token_id);
         let eos_token_ids = Some(vec![config.eos_token_id]);
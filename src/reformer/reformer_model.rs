@@ -23,6 +23,7 @@ use tch::{nn, Device, Kind, Tensor};
 use crate::common::activations::Activation;
 use crate::common::dropout::Dropout;
 use crate::common::embeddings::get_shape_and_device_from_ids_embeddings_pair;
+use crate::common::weights::load_weights_with_mode;
 use crate::pipelines::common::{ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
@@ -642,6 +643,7 @@ impl ReformerModelWithLMHead {
 
         Ok(ReformerLMModelOutput {
             logits,
+            hidden_states: reformer_output.hidden_states,
             all_hidden_states: reformer_output.all_hidden_states,
             all_attentions: reformer_output.all_attentions,
             next_cache: reformer_output.next_cache,
@@ -682,6 +684,7 @@ impl LMHeadModel for ReformerModelWithLMHead {
 
         Ok(LMModelOutput {
             lm_logits: output.logits,
+            hidden_state: Some(output.hidden_states),
             cache: Cache::ReformerCache(output.next_cache),
         })
     }
@@ -1020,6 +1023,8 @@ impl ReformerForQuestionAnswering {
 pub struct ReformerLMModelOutput {
     /// logits
     pub logits: Tensor,
+    /// Last hidden state (before the language modeling head)
+    pub hidden_states: Tensor,
     /// Hidden states for all intermediate layers
     pub all_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all intermediate layers
@@ -1088,11 +1093,15 @@ impl ReformerGenerator {
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate()?;
         let mut var_store = nn::VarStore::new(device);
-        let config = ReformerConfig::from_file(config_path);
+        let config = ReformerConfig::try_from_file(config_path)?;
         let model = ReformerModelWithLMHead::new(var_store.root(), &config)?;
-        var_store.load(weights_path)?;
+        load_weights_with_mode(
+            weights_path,
+            &mut var_store,
+            generate_config.weights_loading_mode,
+        )?;
 
         let bos_token_id = tokenizer.get_bos_id();
         let eos_token_ids = tokenizer.get_eos_id().map(|id| vec![id]);
@@ -1158,6 +1167,19 @@ impl PrivateLanguageGenerator<ReformerModelWithLMHead, ReformerVocab, ReformerTo
         self.max_position_embeddings
     }
 
+    /// Text scoring is not supported for Reformer: the LSH attention buckets are drawn from random
+    /// rotations over the whole padded batch, so the token log-probabilities would depend on the
+    /// batching of the texts.
+    fn token_log_probabilities(
+        &self,
+        _input_ids: &Tensor,
+        _attention_mask: &Tensor,
+    ) -> Result<Tensor, RustBertError> {
+        Err(RustBertError::ValueError(
+            "Text scoring is not supported for Reformer models".to_string(),
+        ))
+    }
+
     fn prepare_inputs_for_generation<'a>(
         &self,
         input_ids: Tensor,
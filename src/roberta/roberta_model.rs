@@ -15,7 +15,9 @@ use crate::bert::{BertConfig, BertModel};
 use crate::common::activations::_gelu;
 use crate::common::dropout::Dropout;
 use crate::common::linear::{linear_no_bias, LinearNoBias};
+use crate::common::quantization::QuantizationMode;
 use crate::roberta::embeddings::RobertaEmbeddings;
+use crate::RustBertError;
 use std::borrow::Borrow;
 use tch::nn::init::DEFAULT_KAIMING_UNIFORM;
 use tch::{nn, Tensor};
@@ -469,6 +471,16 @@ impl RobertaForSequenceClassification {
         }
     }
 
+    /// Quantizes the linear layers of the encoder stack, the task-specific head keeping full
+    /// precision weights.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.roberta.quantize(mode)
+    }
+
     /// Forward pass through the model
     ///
     /// # Arguments
@@ -760,6 +772,16 @@ impl RobertaForTokenClassification {
         }
     }
 
+    /// Quantizes the linear layers of the encoder stack, the task-specific head keeping full
+    /// precision weights.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `QuantizationMode` to apply. The model weights must be loaded before quantizing the model.
+    pub fn quantize(&mut self, mode: QuantizationMode) -> Result<(), RustBertError> {
+        self.roberta.quantize(mode)
+    }
+
     /// Forward pass through the model
     ///
     /// # Arguments
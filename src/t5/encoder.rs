@@ -469,6 +469,12 @@ impl T5Stack {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> =
+            if self.output_attentions & encoder_hidden_states.is_some() {
+                Some(Vec::with_capacity(self.blocks.len()))
+            } else {
+                None
+            };
         let mut next_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.store_cache {
                 if old_layer_states.is_some() {
@@ -482,6 +488,7 @@ impl T5Stack {
         let mut position_bias = None;
         let mut encoder_decoder_position_bias = None;
         let mut attention_weights: Option<Tensor>;
+        let mut cross_attention_weights: Option<Tensor>;
         let mut hidden_state = input_embeddings.apply_t(&self.dropout, train);
 
         for (layer_idx, layer) in self.blocks.iter().enumerate() {
@@ -504,13 +511,17 @@ impl T5Stack {
                 encoder_decoder_position_bias = block_output.cross_attention_position_bias;
             }
             hidden_state = block_output.hidden_states;
-            attention_weights = block_output.cross_attention_weights;
+            attention_weights = block_output.self_attention_weights;
+            cross_attention_weights = block_output.cross_attention_weights;
             if let Some(hidden_states) = all_hidden_states.borrow_mut() {
                 hidden_states.push(hidden_state.as_ref().copy().transpose(0, 1));
             };
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(std::mem::take(&mut attention_weights.unwrap()));
             };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(std::mem::take(&mut cross_attention_weights.unwrap()));
+            };
             if let Some(value) = &mut next_cache {
                 value[layer_idx] = block_output.cache
             };
@@ -524,6 +535,7 @@ impl T5Stack {
             hidden_state,
             all_hidden_states,
             all_attentions,
+            all_cross_attentions,
             next_cache,
         })
     }
@@ -542,5 +554,6 @@ pub struct T5StackOutput {
     pub hidden_state: Tensor,
     pub all_hidden_states: Option<Vec<Tensor>>,
     pub all_attentions: Option<Vec<Tensor>>,
+    pub all_cross_attentions: Option<Vec<Tensor>>,
     pub next_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
 }
@@ -15,6 +15,7 @@
 use crate::common::activations::Activation;
 use crate::common::dropout::Dropout;
 use crate::common::summary::{SequenceSummary, SummaryConfig, SummaryType};
+use crate::common::weights::load_weights_with_mode;
 use crate::pipelines::common::{ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
@@ -786,6 +787,7 @@ impl XLNetLMHeadModel {
 
         Ok(LMModelOutput {
             lm_logits,
+            hidden_state: Some(base_model_output.hidden_state),
             cache: Cache::XLNetCache(base_model_output.next_cache),
         })
     }
@@ -1644,12 +1646,16 @@ impl XLNetGenerator {
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate()?;
         let mut var_store = nn::VarStore::new(device);
 
-        let config = XLNetConfig::from_file(config_path);
+        let config = XLNetConfig::try_from_file(config_path)?;
         let model = XLNetLMHeadModel::new(var_store.root(), &config);
-        var_store.load(weights_path)?;
+        load_weights_with_mode(
+            weights_path,
+            &mut var_store,
+            generate_config.weights_loading_mode,
+        )?;
 
         let bos_token_id = Some(config.bos_token_id);
         let eos_token_ids = Some(vec![config.eos_token_id]);
@@ -1832,6 +1838,39 @@ impl PrivateLanguageGenerator<XLNetLMHeadModel, XLNetVocab, XLNetTokenizer> for
             }
         }
     }
+
+    fn token_log_probabilities(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+    ) -> Result<Tensor, RustBertError> {
+        let (batch_size, sequence_length) = input_ids.size2()?;
+        let device = input_ids.device();
+        // Each position is predicted by the query stream from the previous positions only
+        let perm_mask = Tensor::ones(&[sequence_length, sequence_length], (Kind::Float, device))
+            .triu(0)
+            .unsqueeze(0)
+            .expand(&[batch_size, sequence_length, sequence_length], true);
+        let target_mapping = Tensor::eye(sequence_length, (Kind::Float, device))
+            .unsqueeze(0)
+            .expand(&[batch_size, sequence_length, sequence_length], true);
+        let output = self.model.forward_t(
+            Some(input_ids),
+            Some(attention_mask),
+            None,
+            Some(&perm_mask),
+            Some(&target_mapping),
+            None,
+            None,
+            false,
+        )?;
+        Ok(output
+            .lm_logits
+            .slice(1, 1, None, 1)
+            .log_softmax(-1, Kind::Float)
+            .gather(2, &input_ids.slice(1, 1, None, 1).unsqueeze(-1), false)
+            .squeeze_dim(-1))
+    }
 }
 
 impl LanguageGenerator<XLNetLMHeadModel, XLNetVocab, XLNetTokenizer> for XLNetGenerator {}
@@ -2,6 +2,8 @@ use rust_bert::bart::{
     BartConfig, BartConfigResources, BartMergesResources, BartModel, BartModelResources,
     BartVocabResources,
 };
+use rust_bert::pipelines::generation_utils::constraints::Constraint;
+use rust_bert::pipelines::generation_utils::GenerateOptions;
 use rust_bert::pipelines::summarization::{SummarizationConfig, SummarizationModel};
 use rust_bert::pipelines::zero_shot_classification::{
     ZeroShotClassificationConfig, ZeroShotClassificationModel,
@@ -126,7 +128,7 @@ telescope — scheduled for launch in 2021 — and the European Space Agency's 2
 about exoplanets like K2-18b."];
 
     //    Credits: WikiNews, CC BY 2.5 license (https://en.wikinews.org/wiki/Astronomers_find_water_vapour_in_atmosphere_of_exoplanet_K2-18b)
-    let output = model.summarize(&input);
+    let output = model.summarize(&input)?;
 
     assert_eq!(output.len(), 1);
     assert_eq!(output[0], " K2-18b is not too hot and not too cold for liquid water to exist. \
@@ -136,6 +138,47 @@ about exoplanets like K2-18b."];
     Ok(())
 }
 
+#[test]
+fn bart_summarization_static_cache() -> anyhow::Result<()> {
+    let summarization_config = |static_cache: bool| SummarizationConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            BartModelResources::DISTILBART_CNN_6_6,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            BartConfigResources::DISTILBART_CNN_6_6,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            BartVocabResources::DISTILBART_CNN_6_6,
+        )),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            BartMergesResources::DISTILBART_CNN_6_6,
+        ))),
+        num_beams: 3,
+        min_length: 12,
+        max_length: Some(48),
+        static_cache,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = SummarizationModel::new(summarization_config(false))?;
+    let static_cache_model = SummarizationModel::new(summarization_config(true))?;
+
+    let input = ["The Montreal team, led by Björn Benneke, used data from the NASA's Hubble telescope to \
+assess changes in the light coming from K2-18b's star as the planet passed between it and Earth. They found that \
+certain wavelengths of light, which are usually absorbed by water, weakened when the planet was in the way, \
+indicating not only does K2-18b have an atmosphere, but the atmosphere contains water in vapour form. The team \
+from UCL then analyzed the Montreal team's data using their own software and confirmed their conclusion."];
+
+    //    Preallocated decoder self-attention buffers return the same summary
+    let reference_output = model.summarize(&input)?;
+    let output = static_cache_model.summarize(&input)?;
+
+    assert_eq!(output.len(), 1);
+    assert_eq!(output, reference_output);
+
+    Ok(())
+}
+
 #[test]
 fn bart_summarization_beam_search() -> anyhow::Result<()> {
     let config_resource = Box::new(RemoteResource::from_pretrained(
@@ -187,7 +230,7 @@ telescope — scheduled for launch in 2021 — and the European Space Agency's 2
 about exoplanets like K2-18b."];
 
     //    Credits: WikiNews, CC BY 2.5 license (https://en.wikinews.org/wiki/Astronomers_find_water_vapour_in_atmosphere_of_exoplanet_K2-18b)
-    let output = model.summarize(&input);
+    let output = model.summarize(&input)?;
 
     assert_eq!(output.len(), 1);
     assert_eq!(output[0], " K2-18b, a planet circling a star in the constellation Leo, is not too hot and not too cold for liquid water to exist. \
@@ -197,6 +240,126 @@ about exoplanets like K2-18b."];
     Ok(())
 }
 
+#[test]
+fn bart_summarization_constraints() -> anyhow::Result<()> {
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        BartConfigResources::DISTILBART_CNN_6_6,
+    ));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(
+        BartVocabResources::DISTILBART_CNN_6_6,
+    ));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(
+        BartMergesResources::DISTILBART_CNN_6_6,
+    ));
+    let model_resource = Box::new(RemoteResource::from_pretrained(
+        BartModelResources::DISTILBART_CNN_6_6,
+    ));
+    let tokenizer = RobertaTokenizer::from_file(
+        vocab_resource.get_local_path()?.to_str().unwrap(),
+        merges_resource.get_local_path()?.to_str().unwrap(),
+        false,
+        false,
+    )?;
+    let summarization_config = SummarizationConfig {
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        num_beams: 4,
+        min_length: 10,
+        max_length: Some(64),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = SummarizationModel::new(summarization_config)?;
+
+    let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+a planet circling a star in the constellation Leo. This is the first such discovery in a planet in its star's \
+habitable zone — not too hot and not too cold for liquid water to exist. The Montreal team, led by Björn Benneke, \
+used data from the NASA's Hubble telescope to assess changes in the light coming from K2-18b's star as the planet \
+passed between it and Earth."];
+
+    let token_ids = |text: &str| tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(text));
+    let constraints = [
+        Constraint::Phrase(token_ids(" Hubble telescope")),
+        Constraint::Disjunctive(vec![token_ids(" Montreal"), token_ids(" UCL")]),
+    ];
+    let generate_options = GenerateOptions {
+        constraints: Some(&constraints),
+        ..Default::default()
+    };
+    let output = model.summarize_with_options(&input, Some(generate_options))?;
+
+    assert_eq!(output.len(), 1);
+    assert!(output[0].contains(" Hubble telescope"));
+    assert!(output[0].contains(" Montreal") | output[0].contains(" UCL"));
+
+    //    Constraints require beam search
+    let generate_options = GenerateOptions {
+        num_beams: Some(1),
+        constraints: Some(&constraints),
+        ..Default::default()
+    };
+    assert!(model
+        .summarize_with_options(&input, Some(generate_options))
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn bart_summarization_sampling_seed() -> anyhow::Result<()> {
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        BartConfigResources::DISTILBART_CNN_6_6,
+    ));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(
+        BartVocabResources::DISTILBART_CNN_6_6,
+    ));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(
+        BartMergesResources::DISTILBART_CNN_6_6,
+    ));
+    let model_resource = Box::new(RemoteResource::from_pretrained(
+        BartModelResources::DISTILBART_CNN_6_6,
+    ));
+    let summarization_config = SummarizationConfig {
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: true,
+        num_beams: 1,
+        min_length: 10,
+        max_length: Some(40),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = SummarizationModel::new(summarization_config)?;
+
+    let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+a planet circling a star in the constellation Leo. This is the first such discovery in a planet in its star's \
+habitable zone — not too hot and not too cold for liquid water to exist."];
+
+    let generate_options = |seed: Option<u64>| GenerateOptions {
+        seed,
+        ..Default::default()
+    };
+    let output = model.summarize_with_options(&input, Some(generate_options(Some(42))))?;
+
+    //    Draws from the global random number generator do not change the seeded output
+    tch::manual_seed(0);
+    let _ = model.summarize_with_options(&input, Some(generate_options(None)))?;
+    let replayed_output = model.summarize_with_options(&input, Some(generate_options(Some(42))))?;
+
+    assert_eq!(output.len(), 1);
+    assert_eq!(replayed_output, output);
+
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(not(feature = "all-tests"), ignore)]
 fn bart_zero_shot_classification() -> anyhow::Result<()> {
@@ -6,14 +6,21 @@ use rust_bert::bert::{
     BertForQuestionAnswering, BertForSequenceClassification, BertForTokenClassification,
     BertModelResources, BertVocabResources,
 };
-use rust_bert::pipelines::common::ModelType;
-use rust_bert::pipelines::masked_language::{MaskedLanguageConfig, MaskedLanguageModel};
+use rust_bert::pipelines::common::{DeviceAndPrecision, ModelType};
+use rust_bert::pipelines::masked_language::{
+    FillMaskOptions, MaskDecoding, MaskedLanguageConfig, MaskedLanguageModel,
+};
+use rust_bert::pipelines::multiple_choice::{
+    MultipleChoiceConfig, MultipleChoiceInput, MultipleChoiceModel,
+};
 use rust_bert::pipelines::ner::NERModel;
 use rust_bert::pipelines::question_answering::{
     QaInput, QuestionAnsweringConfig, QuestionAnsweringModel,
 };
-use rust_bert::resources::{RemoteResource, ResourceProvider};
-use rust_bert::Config;
+use rust_bert::pipelines::token_classification::TokenClassificationConfig;
+use rust_bert::quantization::QuantizationMode;
+use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
+use rust_bert::{Config, RustBertError};
 use rust_tokenizers::tokenizer::{BertTokenizer, MultiThreadedTokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::Vocab;
 use std::collections::HashMap;
@@ -141,6 +148,159 @@ fn bert_masked_lm_pipeline() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn bert_masked_lm_pipeline_with_options() -> anyhow::Result<()> {
+    //    Set-up model
+    let config = MaskedLanguageConfig::new(
+        ModelType::Bert,
+        RemoteResource::from_pretrained(BertModelResources::BERT),
+        RemoteResource::from_pretrained(BertConfigResources::BERT),
+        RemoteResource::from_pretrained(BertVocabResources::BERT),
+        None,
+        true,
+        None,
+        None,
+        Some(String::from("<m>")),
+    );
+
+    let mask_language_model = MaskedLanguageModel::new(config)?;
+    //    Define input (the `<m>` mask token is shorter than the `[MASK]` token of the model)
+    let input = [
+        "Paris is the <m> of France. It is <m> in Europe.",
+        "Hello I am a <m> <m> student",
+    ];
+
+    //    Top-k candidates
+    let options = FillMaskOptions {
+        top_k: 3,
+        ..Default::default()
+    };
+    let output = mask_language_model.predict_with_options(input, &options)?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].len(), 2);
+    assert_eq!(output[0][0].num_masks, 1);
+    assert_eq!(output[0][0].candidates.len(), 3);
+    assert_eq!(output[0][0].candidates[0].text, "capital");
+    assert_eq!(output[0][0].candidates[0].ids, vec![3007]);
+    let offset = output[0][0].offset.unwrap();
+    assert_eq!((offset.begin, offset.end), (13, 16));
+    let offset = output[0][1].offset.unwrap();
+    assert_eq!((offset.begin, offset.end), (34, 37));
+    assert_eq!(output[1].len(), 1);
+    assert_eq!(output[1][0].num_masks, 2);
+    let offset = output[1][0].offset.unwrap();
+    assert_eq!((offset.begin, offset.end), (13, 20));
+    for span in output.iter().flatten() {
+        for candidate in &span.candidates {
+            assert_eq!(candidate.ids.len(), span.num_masks);
+            assert!(candidate.score > 0.0 && candidate.score <= 1.0);
+        }
+        for pair in span.candidates.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+        }
+    }
+
+    //    Target words
+    let options = FillMaskOptions {
+        top_k: 2,
+        targets: Some(vec![
+            "city".to_string(),
+            "capital".to_string(),
+            "center".to_string(),
+        ]),
+        ..Default::default()
+    };
+    let output = mask_language_model.predict_with_options(&input[..1], &options)?;
+    assert_eq!(output[0][0].candidates.len(), 2);
+    assert_eq!(output[0][0].candidates[0].text, "capital");
+
+    //    Joint decoding of consecutive masks
+    for decoding in [MaskDecoding::Iterative, MaskDecoding::Beam { num_beams: 4 }] {
+        let options = FillMaskOptions {
+            top_k: 2,
+            decoding,
+            ..Default::default()
+        };
+        let output = mask_language_model.predict_with_options(&input[1..], &options)?;
+        assert_eq!(output[0][0].candidates.len(), 2);
+        assert!(output[0][0].candidates[0].score >= output[0][0].candidates[1].score);
+        assert_eq!(output[0][0].candidates[0].ids.len(), 2);
+    }
+
+    //    Batched inputs and chunked joint decoding
+    let options = FillMaskOptions {
+        top_k: 2,
+        decoding: MaskDecoding::Beam { num_beams: 4 },
+        ..Default::default()
+    };
+    let batched_output = mask_language_model.predict_with_options(input, &options)?;
+    let options = FillMaskOptions {
+        batch_size: 1,
+        ..options
+    };
+    for (text, batched_spans) in input.iter().zip(batched_output) {
+        let output = mask_language_model.predict_with_options([*text], &options)?;
+        assert_eq!(output[0].len(), batched_spans.len());
+        for (span, batched_span) in output[0].iter().zip(batched_spans) {
+            for (candidate, batched_candidate) in
+                span.candidates.iter().zip(batched_span.candidates)
+            {
+                assert_eq!(candidate.ids, batched_candidate.ids);
+                assert!((candidate.score - batched_candidate.score).abs() < 1e-4);
+            }
+        }
+    }
+
+    //    Invalid options
+    for options in [
+        FillMaskOptions {
+            top_k: 0,
+            ..Default::default()
+        },
+        FillMaskOptions {
+            batch_size: 0,
+            ..Default::default()
+        },
+    ] {
+        assert!(mask_language_model
+            .predict_with_options(input, &options)
+            .is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn bert_pseudo_log_likelihood() -> anyhow::Result<()> {
+    //    Set-up model
+    let mask_language_model = MaskedLanguageModel::new(Default::default())?;
+
+    //    Define input
+    let input = ["The cat sat on the mat.", "Mat the on sat cat the."];
+
+    //    Run model
+    let output = mask_language_model.pseudo_log_likelihood(input, 16)?;
+
+    assert_eq!(output.len(), 2);
+    for text_score in &output {
+        // [CLS] and [SEP] are not scored
+        let num_tokens = text_score.token_ids.len();
+        assert!(text_score.token_log_probabilities[0].is_none());
+        assert!(text_score.token_log_probabilities[num_tokens - 1].is_none());
+        assert_eq!(text_score.num_scored_tokens, num_tokens - 2);
+        assert!(text_score.log_likelihood < 0.0);
+    }
+    assert!(output[0].log_likelihood > output[1].log_likelihood);
+
+    //    Batching and padding do not change the scores
+    let unbatched_output = mask_language_model.pseudo_log_likelihood(input, 1)?;
+    for (text_score, unbatched_text_score) in output.iter().zip(unbatched_output.iter()) {
+        assert!((text_score.log_likelihood - unbatched_text_score.log_likelihood).abs() < 1e-3);
+    }
+    assert!(mask_language_model.pseudo_log_likelihood(input, 0).is_err());
+    Ok(())
+}
+
 #[test]
 fn bert_for_sequence_classification() -> anyhow::Result<()> {
     //    Resources paths
@@ -261,6 +421,98 @@ fn bert_for_multiple_choice() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn bert_multiple_choice_pipeline() -> anyhow::Result<()> {
+    //    Save a small randomly initialized model
+    let model_dir = tempfile::tempdir()?;
+    let config_path = model_dir.path().join("config.json");
+    let weights_path = model_dir.path().join("rust_model.ot");
+    let mut config = BertConfig::from_file(
+        RemoteResource::from_pretrained(BertConfigResources::BERT).get_local_path()?,
+    );
+    config.hidden_size = 32;
+    config.intermediate_size = 64;
+    config.num_attention_heads = 2;
+    config.num_hidden_layers = 2;
+    std::fs::write(&config_path, serde_json::to_string(&config)?)?;
+    let vs = nn::VarStore::new(Device::Cpu);
+    let _ = BertForMultipleChoice::new(vs.root(), &config);
+    vs.save(&weights_path)?;
+
+    //    Set-up model
+    let mut multiple_choice_config = MultipleChoiceConfig::new(
+        ModelType::Bert,
+        LocalResource {
+            local_path: weights_path,
+        },
+        LocalResource {
+            local_path: config_path,
+        },
+        RemoteResource::from_pretrained(BertVocabResources::BERT),
+        None,
+        true,
+        None,
+        None,
+    );
+    multiple_choice_config.device = Device::Cpu;
+    let multiple_choice_model = MultipleChoiceModel::new(multiple_choice_config)?;
+
+    //    Define input
+    let inputs = [
+        MultipleChoiceInput {
+            context: String::from("It started to rain during the walk."),
+            question: String::from("The family"),
+            choices: vec![
+                String::from("opened their umbrellas."),
+                String::from("put on sunscreen."),
+                String::from("went back home to get their coats and boots."),
+            ],
+        },
+        MultipleChoiceInput {
+            context: String::from("The cat was hungry."),
+            question: String::new(),
+            choices: vec![
+                String::from("It ate its food."),
+                String::from("It flew to the moon."),
+            ],
+        },
+    ];
+
+    //    Batched predictions (padding the number of choices and sequence lengths)
+    let output = multiple_choice_model.predict(&inputs, 2)?;
+    assert_eq!(output.len(), 2);
+    for (input, choices) in inputs.iter().zip(output.iter()) {
+        assert_eq!(choices.len(), input.choices.len());
+        let total_score = choices.iter().map(|choice| choice.score).sum::<f64>();
+        assert!((total_score - 1.0).abs() < 1e-4);
+        for window in choices.windows(2) {
+            assert!(window[0].score >= window[1].score);
+        }
+        for choice in choices {
+            assert_eq!(choice.text, input.choices[choice.index]);
+        }
+    }
+
+    //    The scores do not depend on the padding of the batch
+    let unbatched_output = multiple_choice_model.predict(&inputs, 1)?;
+    for (choices, unbatched_choices) in output.iter().zip(unbatched_output.iter()) {
+        for (choice, unbatched_choice) in choices.iter().zip(unbatched_choices.iter()) {
+            assert_eq!(choice.index, unbatched_choice.index);
+            assert!((choice.score - unbatched_choice.score).abs() < 1e-4);
+        }
+    }
+
+    //    Inputs without candidate answers are rejected
+    let empty_input = MultipleChoiceInput {
+        context: String::from("The cat was hungry."),
+        question: String::new(),
+        choices: vec![],
+    };
+    assert!(multiple_choice_model.predict(&[empty_input], 2).is_err());
+
+    Ok(())
+}
+
 #[test]
 fn bert_for_token_classification() -> anyhow::Result<()> {
     //    Resources paths
@@ -419,6 +671,67 @@ fn bert_pre_trained_ner() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn bert_pre_trained_ner_quantized() -> anyhow::Result<()> {
+    //    Set-up full precision and quantized models
+    let ner_model = NERModel::new(TokenClassificationConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    })?;
+    let mut quantized_ner_model = NERModel::new(TokenClassificationConfig {
+        device: Device::Cpu,
+        quantization: QuantizationMode::DynamicInt8,
+        ..Default::default()
+    })?;
+
+    //    Define input
+    let input = [
+        "My name is Amy. I live in Paris.",
+        "Paris is a city in France.",
+        "Asked John Smith about Acme Corp",
+        "Let's go to New York!",
+    ];
+
+    //    Run models
+    let output = ner_model.predict(&input);
+    let quantized_output = quantized_ner_model.predict(&input);
+
+    assert_eq!(quantized_output.len(), output.len());
+    for (quantized_entities, entities) in quantized_output.iter().zip(output.iter()) {
+        assert_eq!(quantized_entities.len(), entities.len());
+        for (quantized_entity, entity) in quantized_entities.iter().zip(entities.iter()) {
+            assert_eq!(quantized_entity.word, entity.word);
+            assert_eq!(quantized_entity.label, entity.label);
+            assert_eq!(quantized_entity.offset, entity.offset);
+            assert!((quantized_entity.score - entity.score).abs() < 5e-2);
+        }
+    }
+
+    //    The quantized weights are not moved or converted with the variables store
+    assert!(quantized_ner_model.is_quantized());
+    assert!(!ner_model.is_quantized());
+    assert!(matches!(
+        quantized_ner_model.try_set_device(Device::Cuda(0)),
+        Err(RustBertError::ValueError(_))
+    ));
+    assert!(quantized_ner_model.try_half().is_err());
+    assert!(quantized_ner_model.try_bfloat16().is_err());
+    quantized_ner_model.set_device(Device::Cuda(0));
+    quantized_ner_model.half();
+    assert_eq!(quantized_ner_model.device(), Device::Cpu);
+    let output = quantized_ner_model.predict(&input);
+    assert_eq!(output.len(), quantized_output.len());
+    for (entities, quantized_entities) in output.iter().zip(quantized_output.iter()) {
+        assert_eq!(entities.len(), quantized_entities.len());
+        for (entity, quantized_entity) in entities.iter().zip(quantized_entities.iter()) {
+            assert_eq!(entity.label, quantized_entity.label);
+            assert!((entity.score - quantized_entity.score).abs() < 1e-6);
+        }
+    }
+
+    Ok(())
+}
+
 #[test]
 fn bert_pre_trained_ner_full_entities() -> anyhow::Result<()> {
     //    Set-up model
@@ -3,9 +3,16 @@ use rust_bert::distilbert::{
     DistilBertForTokenClassification, DistilBertModelMaskedLM, DistilBertModelResources,
     DistilBertVocabResources,
 };
+use rust_bert::pipelines::common::DeviceAndPrecision;
+use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::masked_language::{
+    FillMaskOptions, MaskedLanguageConfig, MaskedLanguageModel,
+};
 use rust_bert::pipelines::question_answering::{QaInput, QuestionAnsweringModel};
 use rust_bert::pipelines::sentiment::{SentimentModel, SentimentPolarity};
-use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::pipelines::sequence_classification::SequenceClassificationConfig;
+use rust_bert::quantization::QuantizationMode;
+use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
 use rust_bert::Config;
 use rust_tokenizers::tokenizer::{BertTokenizer, MultiThreadedTokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::Vocab;
@@ -39,6 +46,83 @@ fn distilbert_sentiment_classifier() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn distilbert_sentiment_classifier_quantized() -> anyhow::Result<()> {
+    //    Set-up full precision and quantized classifiers
+    let sentiment_classifier = SentimentModel::new(SequenceClassificationConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    })?;
+    let quantized_sentiment_classifier = SentimentModel::new(SequenceClassificationConfig {
+        device: Device::Cpu,
+        quantization: QuantizationMode::DynamicInt8,
+        ..Default::default()
+    })?;
+
+    //    Get sentiments
+    let input = [
+        "Probably my all-time favorite movie, a story of selflessness, sacrifice and dedication to a noble cause, but it's not preachy or boring.",
+        "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
+        "If you like original gut wrenching laughter you will like this movie. If you are young or old then you will love this movie, hell even my mom liked it.",
+        "The plot was predictable and the acting was wooden.",
+        "A charming, funny and beautifully shot film.",
+        "I want my two hours back.",
+    ];
+
+    let output = sentiment_classifier.predict(input);
+    let quantized_output = quantized_sentiment_classifier.predict(input);
+
+    assert_eq!(quantized_output.len(), output.len());
+    for (quantized_sentiment, sentiment) in quantized_output.iter().zip(output.iter()) {
+        assert_eq!(quantized_sentiment.polarity, sentiment.polarity);
+        assert!((quantized_sentiment.score - sentiment.score).abs() < 2e-2);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sentiment_classifier_bfloat16() -> anyhow::Result<()> {
+    //    Set-up full precision and bfloat16 classifiers
+    let sentiment_classifier = SentimentModel::new(SequenceClassificationConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    })?;
+    let mut bfloat16_sentiment_classifier = SentimentModel::new(SequenceClassificationConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    })?;
+    bfloat16_sentiment_classifier.bfloat16();
+    assert_eq!(bfloat16_sentiment_classifier.device(), Device::Cpu);
+
+    //    Get sentiments
+    let input = [
+        "Probably my all-time favorite movie, a story of selflessness, sacrifice and dedication to a noble cause, but it's not preachy or boring.",
+        "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
+        "The plot was predictable and the acting was wooden.",
+        "A charming, funny and beautifully shot film.",
+    ];
+
+    let output = sentiment_classifier.predict(input);
+    let bfloat16_output = bfloat16_sentiment_classifier.predict(input);
+
+    assert_eq!(bfloat16_output.len(), output.len());
+    for (bfloat16_sentiment, sentiment) in bfloat16_output.iter().zip(output.iter()) {
+        assert_eq!(bfloat16_sentiment.polarity, sentiment.polarity);
+        assert!((bfloat16_sentiment.score - sentiment.score).abs() < 5e-2);
+    }
+
+    //    Computations in full precision with the bfloat16-rounded weights
+    bfloat16_sentiment_classifier.float();
+    let float_output = bfloat16_sentiment_classifier.predict(input);
+    for (float_sentiment, sentiment) in float_output.iter().zip(output.iter()) {
+        assert_eq!(float_sentiment.polarity, sentiment.polarity);
+        assert!((float_sentiment.score - sentiment.score).abs() < 5e-2);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn distilbert_masked_lm() -> anyhow::Result<()> {
     //    Resources paths
@@ -276,3 +360,111 @@ fn distilbert_question_answering() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn distilbert_masked_lm_pipeline() -> anyhow::Result<()> {
+    //    Save a small randomly initialized model
+    let model_dir = tempfile::tempdir()?;
+    let config_path = model_dir.path().join("config.json");
+    let weights_path = model_dir.path().join("rust_model.ot");
+    let mut config = DistilBertConfig::from_file(
+        RemoteResource::from_pretrained(DistilBertConfigResources::DISTIL_BERT).get_local_path()?,
+    );
+    config.dim = 32;
+    config.hidden_dim = 64;
+    config.n_heads = 2;
+    config.n_layers = 2;
+    std::fs::write(&config_path, serde_json::to_string(&config)?)?;
+    let vs = nn::VarStore::new(Device::Cpu);
+    let _ = DistilBertModelMaskedLM::new(vs.root(), &config);
+    vs.save(&weights_path)?;
+
+    //    Set-up model
+    let mut masked_lm_config = MaskedLanguageConfig::new(
+        ModelType::DistilBert,
+        LocalResource {
+            local_path: weights_path,
+        },
+        LocalResource {
+            local_path: config_path,
+        },
+        RemoteResource::from_pretrained(DistilBertVocabResources::DISTIL_BERT),
+        None,
+        true,
+        None,
+        None,
+        Some(String::from("<m>")),
+    );
+    masked_lm_config.device = Device::Cpu;
+    let masked_lm_model = MaskedLanguageModel::new(masked_lm_config)?;
+
+    //    Define input (the `<m>` mask token is shorter than the mask token of the model)
+    let input = [
+        "Hello I am a <m> student",
+        "Paris is the <m> of France. It is <m> in Europe.",
+    ];
+
+    //    Run model
+    let output = masked_lm_model.predict(input)?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].len(), 1);
+    assert_eq!(output[1].len(), 2);
+    for token in output.iter().flatten() {
+        assert!((0..config.vocab_size).contains(&token.id));
+    }
+
+    //    Mask offsets and candidate probabilities
+    let output = masked_lm_model.predict_with_options(input, &FillMaskOptions::default())?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].len(), 1);
+    assert_eq!(output[1].len(), 2);
+    let offsets = output
+        .iter()
+        .flatten()
+        .map(|span| span.offset.map(|offset| (offset.begin, offset.end)))
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, [Some((13, 16)), Some((13, 16)), Some((34, 37))]);
+    for span in output.iter().flatten() {
+        assert_eq!(span.num_masks, 1);
+        assert_eq!(span.candidates.len(), 1);
+        assert!(span.candidates[0].score > 0.0 && span.candidates[0].score <= 1.0);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn distilbert_masked_lm_pipeline_pretrained() -> anyhow::Result<()> {
+    //    Set-up model
+    let config = MaskedLanguageConfig::new(
+        ModelType::DistilBert,
+        RemoteResource::from_pretrained(DistilBertModelResources::DISTIL_BERT),
+        RemoteResource::from_pretrained(DistilBertConfigResources::DISTIL_BERT),
+        RemoteResource::from_pretrained(DistilBertVocabResources::DISTIL_BERT),
+        None,
+        true,
+        None,
+        None,
+        None,
+    );
+    let masked_lm_model = MaskedLanguageModel::new(config)?;
+
+    //    Define input, using the [MASK] mask token of the tokenizer
+    let input = [
+        "Looks like one [MASK] is missing",
+        "It\'s like comparing [MASK] to apples",
+    ];
+
+    //    Run model
+    let output = masked_lm_model.predict(input)?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].len(), 1);
+    assert_eq!(output[0][0].text.trim(), "person");
+    assert_eq!(output[1].len(), 1);
+    assert_eq!(output[1][0].text.trim(), "pear");
+
+    Ok(())
+}
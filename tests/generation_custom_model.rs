@@ -0,0 +1,274 @@
+use rust_bert::gpt2::{Gpt2MergesResources, Gpt2VocabResources};
+use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+use rust_bert::pipelines::generation_utils::{
+    Cache, CustomCache, CustomLanguageGenerator, GenerateConfig, GenerateOptions, LMHeadModel,
+    LMModelOutput, LanguageGenerator, PreparedInput,
+};
+use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::RustBertError;
+use rust_tokenizers::tokenizer::Gpt2Tokenizer;
+use rust_tokenizers::vocab::Gpt2Vocab;
+use std::any::Any;
+use tch::{nn, Device, Kind, Tensor};
+
+const VOCAB_SIZE: i64 = 50257;
+const HIDDEN_SIZE: i64 = 16;
+
+/// Toy language model defined outside of the crate: the hidden state at each position is the
+/// running sum of the token embeddings up to this position.
+struct ToyLanguageModel {
+    embeddings: nn::Embedding,
+    lm_head: nn::Linear,
+}
+
+impl ToyLanguageModel {
+    fn new<'p, P>(p: P) -> ToyLanguageModel
+    where
+        P: std::borrow::Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let embeddings = nn::embedding(
+            p / "embeddings",
+            VOCAB_SIZE,
+            HIDDEN_SIZE,
+            Default::default(),
+        );
+        let lm_head = nn::linear(p / "lm_head", HIDDEN_SIZE, VOCAB_SIZE, Default::default());
+        ToyLanguageModel {
+            embeddings,
+            lm_head,
+        }
+    }
+}
+
+/// Cache of the toy model, holding the running sum of the embeddings of the processed tokens
+#[derive(Debug)]
+struct RunningSumCache {
+    hidden_sum: Tensor,
+}
+
+impl CustomCache for RunningSumCache {
+    fn reorder(&mut self, batch_indices: &Tensor) {
+        self.hidden_sum = self.hidden_sum.index_select(0, batch_indices);
+    }
+
+    fn concat(&mut self, other: &dyn CustomCache) -> bool {
+        match other.as_any().downcast_ref::<RunningSumCache>() {
+            Some(other) => {
+                self.hidden_sum = Tensor::cat(&[&self.hidden_sum, &other.hidden_sum], 0);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl LMHeadModel for ToyLanguageModel {
+    fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        layer_past: Cache,
+        _attention_mask: Option<&Tensor>,
+        _token_type_ids: Option<&Tensor>,
+        _position_ids: Option<&Tensor>,
+        _input_embeds: Option<&Tensor>,
+        _encoder_outputs: Option<&Tensor>,
+        _decoder_input_ids: Option<&Tensor>,
+        _train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let input_ids = input_ids
+            .ok_or_else(|| RustBertError::ValueError("input_ids must be provided".to_string()))?;
+        let mut hidden_state = input_ids.apply(&self.embeddings).cumsum(1, Kind::Float);
+        if let Cache::Custom(cache) = &layer_past {
+            if let Some(cache) = cache.as_any().downcast_ref::<RunningSumCache>() {
+                hidden_state += cache.hidden_sum.unsqueeze(1);
+            }
+        }
+        let lm_logits = hidden_state.apply(&self.lm_head);
+        let cache = Cache::Custom(Box::new(RunningSumCache {
+            hidden_sum: hidden_state.select(1, -1),
+        }));
+        Ok(LMModelOutput {
+            lm_logits,
+            hidden_state: Some(hidden_state),
+            cache,
+        })
+    }
+}
+
+struct ToyGenerator {
+    model: ToyLanguageModel,
+    tokenizer: TokenizerOption,
+    var_store: nn::VarStore,
+    generate_config: GenerateConfig,
+}
+
+impl ToyGenerator {
+    fn new(generate_config: GenerateConfig) -> anyhow::Result<ToyGenerator> {
+        let vocab_path =
+            RemoteResource::from_pretrained(Gpt2VocabResources::GPT2).get_local_path()?;
+        let merges_path =
+            RemoteResource::from_pretrained(Gpt2MergesResources::GPT2).get_local_path()?;
+        let tokenizer = TokenizerOption::from_file(
+            ModelType::GPT2,
+            vocab_path.to_str().unwrap(),
+            Some(merges_path.to_str().unwrap()),
+            false,
+            None,
+            None,
+        )?;
+        tch::manual_seed(42);
+        let var_store = nn::VarStore::new(generate_config.device);
+        let model = ToyLanguageModel::new(var_store.root());
+        Ok(ToyGenerator {
+            model,
+            tokenizer,
+            var_store,
+            generate_config,
+        })
+    }
+}
+
+impl CustomLanguageGenerator<ToyLanguageModel, Gpt2Vocab, Gpt2Tokenizer> for ToyGenerator {
+    fn get_model(&self) -> &ToyLanguageModel {
+        &self.model
+    }
+    fn get_tokenizer(&self) -> &TokenizerOption {
+        &self.tokenizer
+    }
+    fn get_var_store(&self) -> &nn::VarStore {
+        &self.var_store
+    }
+    fn get_var_store_mut(&mut self) -> &mut nn::VarStore {
+        &mut self.var_store
+    }
+    fn get_config(&self) -> &GenerateConfig {
+        &self.generate_config
+    }
+    fn get_bos_id(&self) -> Option<i64> {
+        None
+    }
+    fn get_eos_ids(&self) -> Option<&Vec<i64>> {
+        None
+    }
+    fn get_pad_id(&self) -> Option<i64> {
+        None
+    }
+    fn is_encoder_decoder(&self) -> bool {
+        false
+    }
+    fn get_vocab_size(&self) -> i64 {
+        VOCAB_SIZE
+    }
+    fn get_decoder_start_id(&self) -> Option<i64> {
+        None
+    }
+    fn get_max_positions_embeddings(&self) -> i64 {
+        1024
+    }
+
+    fn prepare_inputs_for_generation<'a>(
+        &self,
+        input_ids: Tensor,
+        _encoder_outputs: Option<&'a Tensor>,
+        past: Cache,
+        attention_mask: Tensor,
+    ) -> PreparedInput<'a> {
+        let prepared_input = match past {
+            Cache::Custom(_) => input_ids.select(1, -1).unsqueeze(-1),
+            _ => input_ids,
+        };
+        PreparedInput {
+            prepared_input: Some(prepared_input),
+            prepared_attention_mask: Some(attention_mask),
+            prepared_encoder_output: None,
+            prepared_decoder_input: None,
+            prepared_position_ids: None,
+            prepared_past: past,
+        }
+    }
+}
+
+impl LanguageGenerator<ToyLanguageModel, Gpt2Vocab, Gpt2Tokenizer> for ToyGenerator {}
+
+fn toy_generate_config() -> GenerateConfig {
+    GenerateConfig {
+        max_length: None,
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn custom_model_greedy_generation_with_cache() -> anyhow::Result<()> {
+    let generator = ToyGenerator::new(toy_generate_config())?;
+
+    let input_ids = Tensor::of_slice(&[464i64, 3290, 318]).unsqueeze(0);
+    let generate_options = GenerateOptions {
+        max_new_tokens: Some(8),
+        ..Default::default()
+    };
+    let output =
+        generator.generate_from_ids_and_past(input_ids.copy(), None, Some(generate_options))?;
+
+    // Reference greedy decoding recomputing the full sequence at each step
+    let mut reference_ids = input_ids;
+    for _ in 0..8 {
+        let lm_logits = generator
+            .get_model()
+            .forward_t(
+                Some(&reference_ids),
+                Cache::None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )?
+            .lm_logits;
+        let next_token = lm_logits.select(1, -1).argmax(-1, true);
+        reference_ids = Tensor::cat(&[&reference_ids, &next_token], 1);
+    }
+
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0].indices, Vec::<i64>::from(reference_ids.get(0)));
+
+    Ok(())
+}
+
+#[test]
+fn custom_model_beam_search_and_sampling() -> anyhow::Result<()> {
+    let generator = ToyGenerator::new(toy_generate_config())?;
+
+    let beam_search_options = GenerateOptions {
+        max_new_tokens: Some(6),
+        num_beams: Some(3),
+        num_return_sequences: Some(2),
+        ..Default::default()
+    };
+    let output =
+        generator.generate(Some(&["The dog", "The cat was"]), Some(beam_search_options))?;
+    assert_eq!(output.len(), 4);
+
+    let sampling_options = GenerateOptions {
+        max_new_tokens: Some(6),
+        do_sample: Some(true),
+        top_k: Some(10),
+        ..Default::default()
+    };
+    let input_ids = Tensor::of_slice(&[464i64, 3290]).unsqueeze(0);
+    let output = generator.generate_from_ids_and_past(input_ids, None, Some(sampling_options))?;
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0].indices.len(), 8);
+
+    Ok(())
+}
@@ -2,17 +2,27 @@ use rust_bert::gpt2::{
     GPT2Generator, GPT2LMHeadModel, Gpt2Config, Gpt2ConfigResources, Gpt2MergesResources,
     Gpt2ModelResources, Gpt2VocabResources,
 };
-use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
 use rust_bert::pipelines::conversation::{
-    ConversationConfig, ConversationManager, ConversationModel,
+    ConversationCache, ConversationConfig, ConversationManager, ConversationModel,
 };
+use rust_bert::pipelines::generation_utils::logits_processor::{
+    LogitsProcessor, LogitsProcessorContext,
+};
+use rust_bert::pipelines::generation_utils::scheduler::{
+    GenerationScheduler, RequestId, RequestStatus,
+};
+use rust_bert::pipelines::generation_utils::speculative::DraftModel;
 use rust_bert::pipelines::generation_utils::{
-    Cache, GenerateConfig, GenerateOptions, LMHeadModel, LanguageGenerator,
+    Cache, GenerateConfig, GenerateOptions, LMHeadModel, LanguageGenerator, RowGenerateOptions,
 };
+use rust_bert::pipelines::scoring::ScoringOptions;
 use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
 use rust_bert::resources::{RemoteResource, ResourceProvider};
-use rust_bert::Config;
+use rust_bert::{Config, RustBertError};
 use rust_tokenizers::tokenizer::{Gpt2Tokenizer, Tokenizer, TruncationStrategy};
+use std::collections::HashMap;
+use std::thread;
 use tch::{nn, Device, Tensor};
 
 #[test]
@@ -131,7 +141,7 @@ fn gpt2_generation_greedy() -> anyhow::Result<()> {
     let model = TextGenerationModel::new(generate_config)?;
 
     let input_context = "The cat";
-    let output = model.generate(&[input_context], None);
+    let output = model.generate(&[input_context], None)?;
 
     assert_eq!(output.len(), 1);
     assert_eq!(output[0], "The cat was found in a field near the town of Keflavik, about 30 miles (48 kilometers) south-east of Moscow.\n\n\n");
@@ -164,7 +174,7 @@ fn gpt2_generation_beam_search() -> anyhow::Result<()> {
     let model = TextGenerationModel::new(generate_config)?;
 
     let input_context = "The dog";
-    let output = model.generate(&[input_context], None);
+    let output = model.generate(&[input_context], None)?;
 
     assert_eq!(output.len(), 3);
     assert_eq!(
@@ -209,7 +219,7 @@ fn gpt2_generation_beam_search_multiple_prompts_without_padding() -> anyhow::Res
 
     let input_context_1 = "The dog";
     let input_context_2 = "The cat";
-    let output = model.generate(&[input_context_1, input_context_2], None);
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
 
     assert_eq!(output.len(), 6);
     assert_eq!(
@@ -265,7 +275,7 @@ fn gpt2_generation_beam_search_multiple_prompts_with_padding() -> anyhow::Result
 
     let input_context_1 = "The dog";
     let input_context_2 = "The cat was";
-    let output = model.generate(&[input_context_1, input_context_2], None);
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
 
     assert_eq!(output.len(), 6);
     assert_eq!(
@@ -323,7 +333,7 @@ fn gpt2_diverse_beam_search_multiple_prompts_with_padding() -> anyhow::Result<()
 
     let input_context_1 = "It was a nice and";
     let input_context_2 = "Language models can generate";
-    let output = model.generate(&[input_context_1, input_context_2], None);
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
 
     assert_eq!(output.len(), 6);
     assert_eq!(
@@ -402,7 +412,7 @@ fn gpt2_prefix_allowed_token_greedy() -> anyhow::Result<()> {
     let output = model.generate(
         Some(&[input_context_1, input_context_2]),
         Some(generate_options),
-    );
+    )?;
 
     assert_eq!(output.len(), 2);
     assert_eq!(
@@ -465,8 +475,9 @@ fn gpt2_bad_tokens_greedy() -> anyhow::Result<()> {
         ..Default::default()
     };
 
-    let baseline_output = model.generate(Some(&[input_context_1]), Some(baseline_generate_options));
-    let output = model.generate(Some(&[input_context_1]), Some(test_generate_options));
+    let baseline_output =
+        model.generate(Some(&[input_context_1]), Some(baseline_generate_options))?;
+    let output = model.generate(Some(&[input_context_1]), Some(test_generate_options))?;
 
     assert_eq!(baseline_output.len(), 1);
     assert_eq!(
@@ -531,8 +542,9 @@ fn gpt2_bad_tokens_beam_search() -> anyhow::Result<()> {
         ..Default::default()
     };
 
-    let baseline_output = model.generate(Some(&[input_context_1]), Some(baseline_generate_options));
-    let output = model.generate(Some(&[input_context_1]), Some(test_generate_options));
+    let baseline_output =
+        model.generate(Some(&[input_context_1]), Some(baseline_generate_options))?;
+    let output = model.generate(Some(&[input_context_1]), Some(test_generate_options))?;
 
     assert_eq!(baseline_output.len(), 1);
     assert_eq!(
@@ -599,7 +611,7 @@ fn gpt2_prefix_allowed_token_beam_search() -> anyhow::Result<()> {
     let output = model.generate(
         Some(&[input_context_1, input_context_2]),
         Some(generate_options),
-    );
+    )?;
 
     assert_eq!(output.len(), 2);
     assert_eq!(
@@ -648,7 +660,7 @@ fn gpt2_greedy_token_scores() -> anyhow::Result<()> {
     let output = model.generate_indices(
         Some(&[input_context_1, input_context_2]),
         Some(generate_options),
-    );
+    )?;
 
     assert_eq!(output.len(), 2);
     assert_eq!(
@@ -673,7 +685,7 @@ fn gpt2_greedy_token_scores() -> anyhow::Result<()> {
 }
 
 #[test]
-fn gpt2_beam_search_token_scores() -> anyhow::Result<()> {
+fn gpt2_scoring() -> anyhow::Result<()> {
     //    Resources definition
     let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
     let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
@@ -681,200 +693,1323 @@ fn gpt2_beam_search_token_scores() -> anyhow::Result<()> {
     let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
 
     let generate_config = GenerateConfig {
-        max_length: Some(16),
         model_resource,
         config_resource,
         vocab_resource,
         merges_resource: Some(merges_resource),
-        do_sample: false,
-        num_beams: 2,
         device: Device::Cpu,
         ..Default::default()
     };
     let model = GPT2Generator::new(generate_config)?;
 
-    let input_context_1 = "Hello, my name is";
-    let input_context_2 = "It is a beautiful";
-
-    let generate_options = GenerateOptions {
-        output_scores: true,
-        ..Default::default()
-    };
+    let input = [
+        "The cat sat on the mat and looked out of the window at the birds in the garden.",
+        "Mat the on sat cat the.",
+    ];
 
-    let output = model.generate_indices(
-        Some(&[input_context_1, input_context_2]),
-        Some(generate_options),
-    );
+    let output = model.score(&input, &ScoringOptions::default())?;
 
     assert_eq!(output.len(), 2);
-    assert_eq!(
-        output[0].indices,
-        vec![15496, 11, 616, 1438, 318, 1757, 11, 290, 314, 716, 257, 2888, 286, 262, 1578, 1829,]
-    );
-    assert!((output[0].score.unwrap() - (-1.1913)).abs() < 1e-4);
-    assert!((output[0].token_scores.as_ref().unwrap()[0] - (-4.6114)).abs() < 1e-4);
-    assert!((output[0].token_scores.as_ref().unwrap()[1] - (-2.1742)).abs() < 1e-4);
-    assert!((output[0].token_scores.as_ref().unwrap()[2] - (-0.7571)).abs() < 1e-4);
+    for text_score in &output {
+        // The beginning of sequence token is prepended and used as context only
+        assert_eq!(text_score.token_ids[0], 50256);
+        assert!(text_score.token_log_probabilities[0].is_none());
+        assert_eq!(text_score.num_scored_tokens, text_score.token_ids.len() - 1);
+        assert!(text_score
+            .token_log_probabilities
+            .iter()
+            .flatten()
+            .all(|log_probability| *log_probability <= 0.0));
+    }
+    assert!(output[0].perplexity.unwrap() < output[1].perplexity.unwrap());
+
+    //    Batching and padding do not change the scores
+    let unbatched_output = model.score(
+        &input,
+        &ScoringOptions {
+            batch_size: 1,
+            ..Default::default()
+        },
+    )?;
+    for (text_score, unbatched_text_score) in output.iter().zip(unbatched_output.iter()) {
+        assert!((text_score.log_likelihood - unbatched_text_score.log_likelihood).abs() < 1e-3);
+    }
 
-    assert_eq!(
-        output[1].indices,
-        vec![50256, 1026, 318, 257, 4950, 1517, 284, 766, 13, 632, 318, 257, 845, 4950, 1517, 13]
-    );
-    assert!((output[1].score.unwrap() - (-1.1160)).abs() < 1e-4);
-    assert!((output[1].token_scores.as_ref().unwrap()[0] - (-2.6287)).abs() < 1e-4);
-    assert!((output[1].token_scores.as_ref().unwrap()[1] - (-1.3033)).abs() < 1e-4);
-    assert!((output[1].token_scores.as_ref().unwrap()[2] - (-0.6780)).abs() < 1e-4);
+    //    Sliding window: every token is scored once, with a shorter context
+    let windowed_output = model.score(
+        &input,
+        &ScoringOptions {
+            batch_size: 4,
+            max_length: Some(8),
+            stride: Some(4),
+        },
+    )?;
+    for (text_score, windowed_text_score) in output.iter().zip(windowed_output.iter()) {
+        assert_eq!(text_score.token_ids, windowed_text_score.token_ids);
+        assert_eq!(
+            text_score.num_scored_tokens,
+            windowed_text_score.num_scored_tokens
+        );
+        // Tokens of the first window have the same context
+        for position in 1..8.min(text_score.token_ids.len()) {
+            assert!(
+                (text_score.token_log_probabilities[position].unwrap()
+                    - windowed_text_score.token_log_probabilities[position].unwrap())
+                .abs()
+                    < 1e-3
+            );
+        }
+    }
+
+    //    Invalid options
+    assert!(model
+        .score(
+            &input,
+            &ScoringOptions {
+                max_length: Some(8),
+                stride: Some(8),
+                ..Default::default()
+            },
+        )
+        .is_err());
 
     Ok(())
 }
 
 #[test]
-#[cfg_attr(not(feature = "all-tests"), ignore)]
-fn dialogpt_single_multi_turn_conversation() -> anyhow::Result<()> {
-    //    Set-up conversation model
-    let conversation_config = ConversationConfig {
+fn gpt2_invalid_generate_options() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
         do_sample: false,
+        num_beams: 1,
         device: Device::Cpu,
         ..Default::default()
     };
-    let conversation_model = ConversationModel::new(conversation_config)?;
-
-    // Set-up conversation manager and add a conversation
-    let mut conversation_manager = ConversationManager::new();
-    let conversation_id =
-        conversation_manager.create("Going to the movies tonight - any suggestions?");
+    let model = GPT2Generator::new(generate_config)?;
 
-    // Turn 1
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 1);
-    assert_eq!(output.get(&conversation_id).unwrap(), &"The Big Lebowski");
+    let input_context = "Hello, my name is";
 
-    // Turn 2
-    let _ = conversation_manager
-        .get(&conversation_id)
-        .unwrap()
-        .add_user_input("Is it an action movie?");
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 1);
-    assert_eq!(output.get(&conversation_id).unwrap(), &"It\'s a comedy.");
+    //    Multiple sequences cannot be returned with greedy decoding
+    let generate_options = GenerateOptions {
+        num_return_sequences: Some(3),
+        ..Default::default()
+    };
+    assert!(matches!(
+        model.generate(Some(&[input_context]), Some(generate_options)),
+        Err(RustBertError::ValueError(_))
+    ));
 
-    // Turn 3 (no new user input)
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 0);
+    //    Temperature must be strictly positive
+    let generate_options = GenerateOptions {
+        do_sample: Some(true),
+        temperature: Some(0.0),
+        ..Default::default()
+    };
+    assert!(matches!(
+        model.generate(Some(&[input_context]), Some(generate_options)),
+        Err(RustBertError::ValueError(_))
+    ));
 
     Ok(())
 }
 
 #[test]
-#[cfg_attr(not(feature = "all-tests"), ignore)]
-fn dialogpt_multiple_multi_turn_conversation() -> anyhow::Result<()> {
-    //    Set-up conversation model
-    let conversation_config = ConversationConfig {
+fn gpt2_greedy_token_streaming() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
         do_sample: false,
+        num_beams: 1,
         device: Device::Cpu,
         ..Default::default()
     };
-    let conversation_model = ConversationModel::new(conversation_config)?;
+    let model = GPT2Generator::new(generate_config)?;
 
-    // Set-up conversation manager and add a conversation
-    let mut conversation_manager = ConversationManager::new();
-    let conversation_1_id =
-        conversation_manager.create("Going to the movies tonight - any suggestions?");
-    let conversation_2_id = conversation_manager.create("What's the last book you have read?");
+    let input_context_1 = "The dog";
+    let input_context_2 = "The cat was";
 
-    // Turn 1
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 2);
-    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
-    assert_eq!(
-        output.get(&conversation_2_id).unwrap(),
-        &"The Last Question"
-    );
+    let mut streamed_tokens = vec![vec![]; 2];
+    let mut streamed_texts = vec![String::new(); 2];
+    let output = model.generate_indices_stream(
+        Some(&[input_context_1, input_context_2]),
+        None,
+        |token| {
+            streamed_tokens[token.sequence_index].push(token.token_id);
+            streamed_texts[token.sequence_index].push_str(&token.text);
+        },
+    )?;
+    let reference_output =
+        model.generate_indices(Some(&[input_context_1, input_context_2]), None)?;
 
-    // Turn 2
-    let _ = conversation_manager
-        .get(&conversation_1_id)
-        .unwrap()
-        .add_user_input("Is it an action movie?");
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 1);
-    assert_eq!(output.get(&conversation_1_id).unwrap(), &"It\'s a comedy.");
+    assert_eq!(output.len(), 2);
+    for (sequence_index, (generated, reference)) in
+        output.iter().zip(reference_output.iter()).enumerate()
+    {
+        assert_eq!(generated.indices, reference.indices);
+        assert!(generated
+            .indices
+            .ends_with(&streamed_tokens[sequence_index]));
+        assert_eq!(
+            streamed_texts[sequence_index],
+            model
+                .get_tokenizer()
+                .decode(&streamed_tokens[sequence_index], true, true)
+        );
+    }
 
-    // Turn 3 (no new user input)
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 0);
+    //    Stop strings are not streamed and the streamed text adds up to the generated text
+    let reference_text = model
+        .generate(Some(&[input_context_1]), None)?
+        .remove(0)
+        .text;
+    let stop_string = format!(
+        " {}",
+        reference_text[input_context_1.len()..]
+            .split_whitespace()
+            .nth(1)
+            .unwrap()
+    );
+    let generate_options = GenerateOptions {
+        stop_strings: Some(&[stop_string.as_str()]),
+        ..Default::default()
+    };
+    let mut streamed_text = String::new();
+    let output =
+        model.generate_stream(Some(&[input_context_1]), Some(generate_options), |token| {
+            streamed_text.push_str(&token.text)
+        })?;
+    assert_eq!(streamed_text, output[0].text[input_context_1.len()..]);
+    assert!(!streamed_text.contains(&stop_string));
+
+    //    Streaming is not available for beam search
+    let generate_options = GenerateOptions {
+        num_beams: Some(2),
+        ..Default::default()
+    };
+    assert!(matches!(
+        model.generate_stream(Some(&[input_context_1]), Some(generate_options), |_| {}),
+        Err(RustBertError::ValueError(_))
+    ));
 
     Ok(())
 }
 
 #[test]
-#[cfg_attr(not(feature = "all-tests"), ignore)]
-fn dialogpt_multiple_multi_turn_conversation_with_truncation() -> anyhow::Result<()> {
-    //    Set-up conversation model
-    let conversation_config = ConversationConfig {
-        max_length: Some(36),
-        min_length_for_response: 24,
+fn gpt2_stop_conditions() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
         do_sample: false,
+        num_beams: 1,
         device: Device::Cpu,
         ..Default::default()
     };
-    let conversation_model = ConversationModel::new(conversation_config)?;
+    let model = GPT2Generator::new(generate_config)?;
 
-    // Set-up conversation manager and add a conversation
-    let mut conversation_manager = ConversationManager::new();
-    let conversation_1_id =
-        conversation_manager.create("Going to the movies tonight - any suggestions?");
-    let conversation_2_id = conversation_manager.create("Hello how are you?");
+    let input_context = "The dog";
+    let prompt_length = 2;
+    let reference_indices = model.generate_indices(Some(&[input_context]), None)?[0]
+        .indices
+        .clone();
+    let reference_text = model.generate(Some(&[input_context]), None)?[0]
+        .text
+        .clone();
+
+    //    Stop token sequence
+    let stop_token_ids = vec![reference_indices[5..7].to_vec()];
+    let stop_position = (prompt_length + 1..reference_indices.len())
+        .find(|&position| reference_indices[position - 1..=position] == stop_token_ids[0][..])
+        .unwrap();
+    let generate_options = GenerateOptions {
+        stop_token_ids: Some(&stop_token_ids),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
+    assert_eq!(output[0].indices, reference_indices[..=stop_position]);
+
+    //    Custom stopping criteria
+    let stopping_criteria =
+        |_sequence_index: usize, generated_ids: &[i64], _tokenizer: &TokenizerOption| {
+            generated_ids.len() >= 3
+        };
+    let generate_options = GenerateOptions {
+        stopping_criteria: Some(&stopping_criteria),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
+    assert_eq!(output[0].indices, reference_indices[..prompt_length + 3]);
 
-    // Turn 1
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 2);
-    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
+    //    Stop string, trimmed from the output
+    let stop_string = model
+        .get_tokenizer()
+        .decode(&reference_indices[6..7], true, false);
+    let stop_string = stop_string.trim();
+    let generate_options = GenerateOptions {
+        stop_strings: Some(&[stop_string]),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&[input_context]), Some(generate_options))?;
+    assert!(output[0].text.len() < reference_text.len());
+    assert!(reference_text.starts_with(&output[0].text));
+    assert!(!output[0].text[input_context.len()..].contains(stop_string));
+
+    //    Multi-byte stop string (characters split over several byte-level tokens), trimmed from its
+    //    first occurrence in the generated text
+    let input_context = "東京は日本の首都です。大阪は";
+    let generate_options = GenerateOptions {
+        max_new_tokens: Some(16),
+        ..Default::default()
+    };
+    let reference_text = model.generate(Some(&[input_context]), Some(generate_options))?[0]
+        .text
+        .clone();
+    let generated_text = &reference_text[input_context.len()..];
+    let stop_string_start = generated_text
+        .char_indices()
+        .find(|(_, character)| !character.is_ascii() && (*character != '\u{FFFD}'))
+        .map(|(position, _)| position)
+        .unwrap();
+    let stop_string = generated_text[stop_string_start..]
+        .chars()
+        .take_while(|character| *character != '\u{FFFD}')
+        .take(3)
+        .collect::<String>();
+    let generate_options = GenerateOptions {
+        stop_strings: Some(&[stop_string.as_str()]),
+        ..generate_options
+    };
+    let output = model.generate(Some(&[input_context]), Some(generate_options))?;
+    let stop_position = generated_text.find(stop_string.as_str()).unwrap();
     assert_eq!(
-        output.get(&conversation_2_id).unwrap(),
-        &"I'm good, how are you?"
+        output[0].text,
+        reference_text[..input_context.len() + stop_position]
     );
 
-    // Turn 2
-    let _ = conversation_manager
-        .get(&conversation_1_id)
-        .unwrap()
-        .add_user_input("Is it an action movie?");
-    let _ = conversation_manager
-        .get(&conversation_2_id)
-        .unwrap()
-        .add_user_input("Fine.");
+    Ok(())
+}
 
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 2);
-    assert_eq!(output.get(&conversation_1_id).unwrap(), &"It\'s a comedy.");
+#[test]
+fn gpt2_custom_logits_processor() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
 
-    // Turn 3 (no new user input)
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 0);
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    struct BanTokenProcessor {
+        token_id: i64,
+    }
+
+    impl LogitsProcessor for BanTokenProcessor {
+        fn process(
+            &self,
+            _input_ids: &Tensor,
+            scores: &mut Tensor,
+            _context: &LogitsProcessorContext,
+        ) {
+            let _ = scores.select(1, self.token_id).fill_(f64::NEG_INFINITY);
+        }
+    }
+
+    let input_context = "The dog";
+    let prompt_length = 2;
+    let reference_indices = model.generate_indices(Some(&[input_context]), None)?[0]
+        .indices
+        .clone();
+
+    //    Ban the first generated token for both greedy decoding and beam search
+    let ban_token_processor = BanTokenProcessor {
+        token_id: reference_indices[prompt_length],
+    };
+    let logits_processors: [&dyn LogitsProcessor; 1] = [&ban_token_processor];
+    for num_beams in [1, 3] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            logits_processors: Some(&logits_processors),
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
+        assert_eq!(
+            output[0].indices[..prompt_length],
+            reference_indices[..prompt_length]
+        );
+        assert!(!output[0].indices[prompt_length..].contains(&ban_token_processor.token_id));
+    }
 
     Ok(())
 }
 
 #[test]
-#[cfg_attr(not(feature = "all-tests"), ignore)]
-fn dialogpt_multiple_multi_turn_conversation_with_conversation_deletion() -> anyhow::Result<()> {
-    //    Set-up conversation model
-    let conversation_config = ConversationConfig {
+fn gpt2_additional_sampling_strategies() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
         do_sample: false,
+        num_beams: 1,
         device: Device::Cpu,
         ..Default::default()
     };
-    let conversation_model = ConversationModel::new(conversation_config)?;
+    let model = GPT2Generator::new(generate_config)?;
 
-    // Set-up conversation manager and add a conversation
-    let mut conversation_manager = ConversationManager::new();
+    let input_context_1 = "The dog";
+    let input_context_2 = "The cat was";
+    let greedy_output = model.generate_indices(Some(&[input_context_1, input_context_2]), None)?;
+
+    //    Min-p sampling keeping only the most likely token matches greedy decoding
+    let generate_options = GenerateOptions {
+        do_sample: Some(true),
+        top_p: Some(1.0),
+        min_p: Some(1.0),
+        ..Default::default()
+    };
+    let output = model.generate_indices(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    )?;
+    assert_eq!(output[0].indices, greedy_output[0].indices);
+    assert_eq!(output[1].indices, greedy_output[1].indices);
+
+    //    Typical, epsilon and eta sampling
+    let generate_options = GenerateOptions {
+        do_sample: Some(true),
+        top_p: Some(1.0),
+        typical_p: Some(0.2),
+        epsilon_cutoff: Some(3e-4),
+        eta_cutoff: Some(3e-4),
+        ..Default::default()
+    };
+    let output = model.generate(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    )?;
+    assert_eq!(output.len(), 2);
+    assert!(output[0].text.starts_with(input_context_1));
+    assert!(output[1].text.starts_with(input_context_2));
+
+    //    Contrastive search with a negligible degeneration penalty matches greedy decoding
+    let generate_options = GenerateOptions {
+        top_k: Some(4),
+        penalty_alpha: Some(1e-9),
+        ..Default::default()
+    };
+    let output = model.generate_indices(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    )?;
+    assert_eq!(output[0].indices, greedy_output[0].indices);
+    assert_eq!(output[1].indices, greedy_output[1].indices);
+
+    //    Contrastive search is deterministic
+    let generate_options = GenerateOptions {
+        top_k: Some(4),
+        penalty_alpha: Some(0.6),
+        ..Default::default()
+    };
+    let output = model.generate_indices(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    )?;
+    let repeated_output = model.generate_indices(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    )?;
+    assert_eq!(output[0].indices, repeated_output[0].indices);
+    assert_eq!(output[1].indices, repeated_output[1].indices);
+
+    //    Invalid settings are rejected
+    let generate_options = GenerateOptions {
+        typical_p: Some(0.0),
+        ..Default::default()
+    };
+    assert!(model
+        .generate(Some(&[input_context_1]), Some(generate_options))
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_logit_bias() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "The dog";
+    let prompt_length = 2;
+    let reference_indices = model.generate_indices(Some(&[input_context]), None)?[0]
+        .indices
+        .clone();
+
+    //    A negative infinite bias bans a token, a large positive bias forces it
+    let banned_token_id = reference_indices[prompt_length];
+    let forced_token_id = 11;
+    let logit_bias = HashMap::from([
+        (banned_token_id, f64::NEG_INFINITY),
+        (forced_token_id, 100.0),
+    ]);
+    for num_beams in [1, 3] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            logit_bias: Some(&logit_bias),
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
+        assert!(!output[0].indices[prompt_length..].contains(&banned_token_id));
+        assert_eq!(output[0].indices[prompt_length], forced_token_id);
+    }
+
+    //    Token ids outside of the vocabulary are rejected
+    let logit_bias = HashMap::from([(-1, 1.0)]);
+    let generate_options = GenerateOptions {
+        logit_bias: Some(&logit_bias),
+        ..Default::default()
+    };
+    assert!(model
+        .generate(Some(&[input_context]), Some(generate_options))
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_speculative_decoding() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(24),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let draft_generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            Gpt2ModelResources::DISTIL_GPT2,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            Gpt2ConfigResources::DISTIL_GPT2,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            Gpt2VocabResources::DISTIL_GPT2,
+        )),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            Gpt2MergesResources::DISTIL_GPT2,
+        ))),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let draft_generator = GPT2Generator::new(draft_generate_config)?;
+    let draft_model = DraftModel::new(&draft_generator, 4)?;
+
+    //    Greedy speculative decoding returns the same output as greedy decoding
+    let input_context_1 = "The dog";
+    let input_context_2 = "The cat was in";
+    let reference_output = model.generate(Some(&[input_context_1, input_context_2]), None)?;
+    let generate_options = GenerateOptions {
+        draft_model: Some(&draft_model),
+        ..Default::default()
+    };
+    let output = model.generate(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    )?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].text, reference_output[0].text);
+    assert_eq!(output[1].text, reference_output[1].text);
+
+    //    Sampling generates sequences up to the maximum length
+    let generate_options = GenerateOptions {
+        do_sample: Some(true),
+        top_k: Some(50),
+        num_return_sequences: Some(3),
+        draft_model: Some(&draft_model),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context_1]), Some(generate_options))?;
+    assert_eq!(output.len(), 3);
+    for sequence in output {
+        assert!(sequence.indices.len() <= 24);
+        assert!(sequence.indices.iter().all(|token_id| *token_id < 50257));
+    }
+
+    //    Speculative decoding is not available for beam search
+    let generate_options = GenerateOptions {
+        num_beams: Some(3),
+        draft_model: Some(&draft_model),
+        ..Default::default()
+    };
+    assert!(model
+        .generate(Some(&[input_context_1]), Some(generate_options))
+        .is_err());
+    assert!(DraftModel::new(&draft_generator, 0).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_scheduler() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    //    Reference greedy generation for each prompt
+    let prompts = [
+        ("The dog", 12),
+        ("The cat was sitting in the garden when", 4),
+        ("Hello, my name is", 8),
+        ("It was a bright cold day in April", 6),
+    ];
+    let mut references = vec![];
+    for (prompt, max_new_tokens) in prompts {
+        let generate_options = GenerateOptions {
+            max_new_tokens: Some(max_new_tokens),
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[prompt]), Some(generate_options))?;
+        let prompt_length = model.get_tokenizer().tokenize(prompt).len();
+        references.push(output[0].indices[prompt_length..].to_vec());
+    }
+
+    //    Requests submitted from multiple threads, with a batch smaller than the number of requests
+    let scheduler = GenerationScheduler::new(model, 2)?;
+    let request_ids = thread::scope(|scope| {
+        let handles = prompts
+            .iter()
+            .map(|(prompt, max_new_tokens)| {
+                let scheduler = &scheduler;
+                scope.spawn(move || scheduler.submit(prompt, Some(*max_new_tokens), None))
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Result<Vec<RequestId>, RustBertError>>()
+    })?;
+    assert!(matches!(
+        scheduler.poll(request_ids[3]),
+        Some(RequestStatus::Queued)
+    ));
+
+    while scheduler.step()? > 0 {}
+
+    for (request_id, reference) in request_ids.into_iter().zip(references) {
+        match scheduler.poll(request_id) {
+            Some(RequestStatus::Finished(output)) => assert_eq!(output.token_ids, reference),
+            status => panic!("Unexpected request status: {:?}", status),
+        }
+        assert!(scheduler.poll(request_id).is_none());
+    }
+    assert!(scheduler.submit("", Some(8), None).is_err());
+    assert!(scheduler.submit("  ", Some(8), None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_scheduler_admission() -> anyhow::Result<()> {
+    for do_sample in [false, true] {
+        //    Resources definition
+        let generate_config = GenerateConfig {
+            model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+            config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+            vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+            merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+                Gpt2MergesResources::GPT2,
+            ))),
+            do_sample,
+            num_beams: 1,
+            device: Device::Cpu,
+            ..Default::default()
+        };
+        let model = GPT2Generator::new(generate_config)?;
+
+        //    Reference generation for each prompt, with the seed of the request
+        let prompts = [
+            ("The dog", 3, 1u64),
+            ("The cat was sitting in the garden when", 10, 2u64),
+            ("Hello, my name is", 5, 3u64),
+        ];
+        let mut references = vec![];
+        for (prompt, max_new_tokens, seed) in prompts {
+            let generate_options = GenerateOptions {
+                max_new_tokens: Some(max_new_tokens),
+                seed: Some(seed),
+                ..Default::default()
+            };
+            let output = model.generate_indices(Some(&[prompt]), Some(generate_options))?;
+            let prompt_length = model.get_tokenizer().tokenize(prompt).len();
+            references.push(output[0].indices[prompt_length..].to_vec());
+        }
+
+        //    The short request is evicted while the long request is still running
+        let scheduler = GenerationScheduler::new(model, 2)?;
+        let (prompt, max_new_tokens, seed) = prompts[0];
+        let first_request = scheduler.submit(prompt, Some(max_new_tokens), Some(seed))?;
+        let (prompt, max_new_tokens, seed) = prompts[1];
+        let second_request = scheduler.submit(prompt, Some(max_new_tokens), Some(seed))?;
+        let first_output = loop {
+            scheduler.step()?;
+            if let Some(RequestStatus::Finished(output)) = scheduler.poll(first_request) {
+                break output;
+            }
+        };
+        assert!(matches!(
+            scheduler.poll(second_request),
+            Some(RequestStatus::Running)
+        ));
+
+        //    A new request is admitted in the running batch
+        let (prompt, max_new_tokens, seed) = prompts[2];
+        let third_request = scheduler.submit(prompt, Some(max_new_tokens), Some(seed))?;
+        while scheduler.step()? > 0 {}
+
+        assert_eq!(first_output.token_ids, references[0]);
+        for (request_id, reference) in [second_request, third_request].iter().zip(&references[1..])
+        {
+            match scheduler.poll(*request_id) {
+                Some(RequestStatus::Finished(output)) => assert_eq!(&output.token_ids, reference),
+                status => panic!("Unexpected request status: {:?}", status),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_static_cache() -> anyhow::Result<()> {
+    //    Resources definition
+    let generate_config = |static_cache: bool| GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+        config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            Gpt2MergesResources::GPT2,
+        ))),
+        max_length: Some(20),
+        do_sample: false,
+        num_beams: 1,
+        static_cache,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config(false))?;
+    let static_cache_model = GPT2Generator::new(generate_config(true))?;
+
+    //    Preallocated buffers return the same outputs as the cache extended at each step
+    let input_context_1 = "The dog";
+    let input_context_2 = "The cat was sitting in the garden when";
+    for num_beams in [1, 3] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            ..Default::default()
+        };
+        let reference_output = model.generate(
+            Some(&[input_context_1, input_context_2]),
+            Some(generate_options),
+        )?;
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            ..Default::default()
+        };
+        let output = static_cache_model.generate(
+            Some(&[input_context_1, input_context_2]),
+            Some(generate_options),
+        )?;
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].text, reference_output[0].text);
+        assert_eq!(output[1].text, reference_output[1].text);
+    }
+
+    //    The buffers are sized to the maximum length, which must be provided
+    let static_cache_model = GPT2Generator::new(GenerateConfig {
+        max_length: None,
+        ..generate_config(true)
+    })?;
+    assert!(static_cache_model
+        .generate(Some(&[input_context_1]), None)
+        .is_err());
+    let generate_options = GenerateOptions {
+        max_new_tokens: Some(8),
+        ..Default::default()
+    };
+    let output =
+        static_cache_model.generate_indices(Some(&[input_context_1]), Some(generate_options))?;
+    assert_eq!(output[0].indices.len(), 10);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_beam_search_token_scores() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 2,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "Hello, my name is";
+    let input_context_2 = "It is a beautiful";
+
+    let generate_options = GenerateOptions {
+        output_scores: true,
+        ..Default::default()
+    };
+
+    let output = model.generate_indices(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    )?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(
+        output[0].indices,
+        vec![15496, 11, 616, 1438, 318, 1757, 11, 290, 314, 716, 257, 2888, 286, 262, 1578, 1829,]
+    );
+    assert!((output[0].score.unwrap() - (-1.1913)).abs() < 1e-4);
+    assert!((output[0].token_scores.as_ref().unwrap()[0] - (-4.6114)).abs() < 1e-4);
+    assert!((output[0].token_scores.as_ref().unwrap()[1] - (-2.1742)).abs() < 1e-4);
+    assert!((output[0].token_scores.as_ref().unwrap()[2] - (-0.7571)).abs() < 1e-4);
+
+    assert_eq!(
+        output[1].indices,
+        vec![50256, 1026, 318, 257, 4950, 1517, 284, 766, 13, 632, 318, 257, 845, 4950, 1517, 13]
+    );
+    assert!((output[1].score.unwrap() - (-1.1160)).abs() < 1e-4);
+    assert!((output[1].token_scores.as_ref().unwrap()[0] - (-2.6287)).abs() < 1e-4);
+    assert!((output[1].token_scores.as_ref().unwrap()[1] - (-1.3033)).abs() < 1e-4);
+    assert!((output[1].token_scores.as_ref().unwrap()[2] - (-0.6780)).abs() < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_seed() -> anyhow::Result<()> {
+    //    Resources definition
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+        config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            Gpt2MergesResources::GPT2,
+        ))),
+        max_length: None,
+        do_sample: true,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "The dog";
+    for num_beams in [1, 3] {
+        let generate_options = |seed: Option<u64>| GenerateOptions {
+            max_new_tokens: Some(16),
+            num_beams: Some(num_beams),
+            seed,
+            ..Default::default()
+        };
+        let output = model.generate(Some(&[input_context]), Some(generate_options(Some(42))))?;
+
+        //    Draws from the global random number generator do not change the seeded output
+        tch::manual_seed(0);
+        let _ = model.generate(Some(&[input_context]), Some(generate_options(None)))?;
+        let replayed_output =
+            model.generate(Some(&[input_context]), Some(generate_options(Some(42))))?;
+        let other_seed_output =
+            model.generate(Some(&[input_context]), Some(generate_options(Some(7))))?;
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(replayed_output[0].text, output[0].text);
+        assert_ne!(other_seed_output[0].text, output[0].text);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_row_options() -> anyhow::Result<()> {
+    //    Resources definition
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+        config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            Gpt2MergesResources::GPT2,
+        ))),
+        max_length: None,
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+    let pad_token_id = 50256;
+    let new_tokens = |indices: &[i64]| {
+        indices[2..]
+            .iter()
+            .copied()
+            .filter(|token_id| *token_id != pad_token_id)
+            .collect::<Vec<i64>>()
+    };
+
+    //    Reference outputs generated separately
+    let bad_word_ids = vec![vec![373i64]];
+    let first_reference = model.generate_indices(
+        Some(&["The dog"]),
+        Some(GenerateOptions {
+            max_new_tokens: Some(4),
+            ..Default::default()
+        }),
+    )?;
+    let second_reference = model.generate_indices(
+        Some(&["The cat"]),
+        Some(GenerateOptions {
+            max_new_tokens: Some(8),
+            bad_word_ids: Some(&bad_word_ids),
+            ..Default::default()
+        }),
+    )?;
+
+    //    Batched generation with per-row options
+    let row_options = [
+        RowGenerateOptions {
+            max_new_tokens: Some(4),
+            ..Default::default()
+        },
+        RowGenerateOptions {
+            max_new_tokens: Some(8),
+            bad_word_ids: Some(&bad_word_ids),
+            ..Default::default()
+        },
+    ];
+    let output = model.generate_indices(
+        Some(&["The dog", "The cat"]),
+        Some(GenerateOptions {
+            row_options: Some(&row_options),
+            ..Default::default()
+        }),
+    )?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(new_tokens(&output[0].indices).len(), 4);
+    assert_eq!(new_tokens(&output[1].indices).len(), 8);
+    assert_eq!(
+        new_tokens(&output[0].indices),
+        new_tokens(&first_reference[0].indices)
+    );
+    assert_eq!(
+        new_tokens(&output[1].indices),
+        new_tokens(&second_reference[0].indices)
+    );
+
+    //    Sampling with a greedy row (top_k = 1) and a high-temperature nucleus sampling row
+    let row_options = [
+        RowGenerateOptions {
+            top_k: Some(1),
+            max_new_tokens: Some(4),
+            ..Default::default()
+        },
+        RowGenerateOptions {
+            temperature: Some(1.5),
+            top_p: Some(0.9),
+            ..Default::default()
+        },
+    ];
+    let output = model.generate_indices(
+        Some(&["The dog", "The cat"]),
+        Some(GenerateOptions {
+            do_sample: Some(true),
+            max_new_tokens: Some(6),
+            row_options: Some(&row_options),
+            seed: Some(42),
+            ..Default::default()
+        }),
+    )?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(
+        new_tokens(&output[0].indices),
+        new_tokens(&first_reference[0].indices)
+    );
+    assert!(new_tokens(&output[1].indices).len() <= 6);
+
+    //    Sampling rows with their own seed and settings matches separate calls with the same seed
+    let row_settings = [
+        ("The dog", 0.7, 50, 1.0, 7u64),
+        ("The cat", 1.5, 0, 0.9, 11u64),
+    ];
+    let row_options = row_settings
+        .iter()
+        .map(|(_, temperature, top_k, top_p, seed)| RowGenerateOptions {
+            temperature: Some(*temperature),
+            top_k: Some(*top_k),
+            top_p: Some(*top_p),
+            seed: Some(*seed),
+            ..Default::default()
+        })
+        .collect::<Vec<RowGenerateOptions>>();
+    let output = model.generate_indices(
+        Some(&["The dog", "The cat"]),
+        Some(GenerateOptions {
+            do_sample: Some(true),
+            max_new_tokens: Some(6),
+            row_options: Some(&row_options),
+            ..Default::default()
+        }),
+    )?;
+
+    assert_eq!(output.len(), 2);
+    for ((prompt, temperature, top_k, top_p, seed), row_output) in
+        row_settings.iter().zip(output.iter())
+    {
+        let reference = model.generate_indices(
+            Some(&[*prompt]),
+            Some(GenerateOptions {
+                do_sample: Some(true),
+                max_new_tokens: Some(6),
+                temperature: Some(*temperature),
+                top_k: Some(*top_k),
+                top_p: Some(*top_p),
+                seed: Some(*seed),
+                ..Default::default()
+            }),
+        )?;
+        assert_eq!(
+            new_tokens(&row_output.indices),
+            new_tokens(&reference[0].indices)
+        );
+    }
+
+    //    The return sequences of a seeded row share its generator, as in a separate call
+    let output = model.generate_indices(
+        Some(&["The dog", "The cat"]),
+        Some(GenerateOptions {
+            do_sample: Some(true),
+            max_new_tokens: Some(6),
+            num_return_sequences: Some(2),
+            row_options: Some(&row_options),
+            ..Default::default()
+        }),
+    )?;
+
+    assert_eq!(output.len(), 4);
+    for ((prompt, temperature, top_k, top_p, seed), row_outputs) in
+        row_settings.iter().zip(output.chunks(2))
+    {
+        let reference = model.generate_indices(
+            Some(&[*prompt]),
+            Some(GenerateOptions {
+                do_sample: Some(true),
+                max_new_tokens: Some(6),
+                num_return_sequences: Some(2),
+                temperature: Some(*temperature),
+                top_k: Some(*top_k),
+                top_p: Some(*top_p),
+                seed: Some(*seed),
+                ..Default::default()
+            }),
+        )?;
+        assert_eq!(reference.len(), 2);
+        for (row_output, reference_output) in row_outputs.iter().zip(reference.iter()) {
+            assert_eq!(
+                new_tokens(&row_output.indices),
+                new_tokens(&reference_output.indices)
+            );
+        }
+    }
+
+    //    The number of per-row options must match the number of inputs
+    let output = model.generate_indices(
+        Some(&["The dog"]),
+        Some(GenerateOptions {
+            row_options: Some(&row_options),
+            ..Default::default()
+        }),
+    );
+    assert!(output.is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_single_multi_turn_conversation() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add a conversation
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+
+    // Turn 1
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 1);
+    assert_eq!(output.get(&conversation_id).unwrap(), &"The Big Lebowski");
+
+    // Turn 2
+    let _ = conversation_manager
+        .get(&conversation_id)
+        .unwrap()
+        .add_user_input("Is it an action movie?");
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 1);
+    assert_eq!(output.get(&conversation_id).unwrap(), &"It\'s a comedy.");
+
+    // Turn 3 (no new user input)
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 0);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_conversation_streaming() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add a conversation
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+
+    let mut streamed_response = String::new();
+    let output = conversation_model.generate_responses_stream(
+        &mut conversation_manager,
+        |uuid, token| {
+            assert_eq!(uuid, &conversation_id);
+            streamed_response.push_str(&token.text);
+        },
+    )?;
+    assert_eq!(output.len(), 1);
+    assert_eq!(output.get(&conversation_id).unwrap(), &"The Big Lebowski");
+    assert_eq!(streamed_response, "The Big Lebowski");
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_multiple_multi_turn_conversation() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add a conversation
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_1_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+    let conversation_2_id = conversation_manager.create("What's the last book you have read?");
+
+    // Turn 1
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
+    assert_eq!(
+        output.get(&conversation_2_id).unwrap(),
+        &"The Last Question"
+    );
+
+    // Turn 2
+    let _ = conversation_manager
+        .get(&conversation_1_id)
+        .unwrap()
+        .add_user_input("Is it an action movie?");
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 1);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"It\'s a comedy.");
+
+    // Turn 3 (no new user input)
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 0);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_multiple_multi_turn_conversation_with_truncation() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        max_length: Some(36),
+        min_length_for_response: 24,
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add a conversation
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_1_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+    let conversation_2_id = conversation_manager.create("Hello how are you?");
+
+    // Turn 1
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
+    assert_eq!(
+        output.get(&conversation_2_id).unwrap(),
+        &"I'm good, how are you?"
+    );
+
+    // Turn 2
+    let _ = conversation_manager
+        .get(&conversation_1_id)
+        .unwrap()
+        .add_user_input("Is it an action movie?");
+    let _ = conversation_manager
+        .get(&conversation_2_id)
+        .unwrap()
+        .add_user_input("Fine.");
+
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"It\'s a comedy.");
+
+    // Turn 3 (no new user input)
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 0);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_multiple_multi_turn_conversation_with_conversation_deletion() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add a conversation
+    let mut conversation_manager = ConversationManager::new();
     let conversation_1_id =
         conversation_manager.create("Going to the movies tonight - any suggestions?");
     let conversation_2_id = conversation_manager.create("What's the last book you have read?");
 
     // Turn 1
-    let output = conversation_model.generate_responses(&mut conversation_manager);
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
     assert_eq!(output.len(), 2);
     assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
     assert_eq!(
@@ -888,7 +2023,7 @@ fn dialogpt_multiple_multi_turn_conversation_with_conversation_deletion() -> any
         .get(&conversation_2_id)
         .unwrap()
         .add_user_input("Why do you recommend it?");
-    let output = conversation_model.generate_responses(&mut conversation_manager);
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
     assert_eq!(output.len(), 1);
     assert_eq!(
         output.get(&conversation_2_id).unwrap(),
@@ -896,8 +2031,108 @@ fn dialogpt_multiple_multi_turn_conversation_with_conversation_deletion() -> any
     );
 
     // Turn 3 (no new user input)
-    let output = conversation_model.generate_responses(&mut conversation_manager);
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
     assert_eq!(output.len(), 0);
 
     Ok(())
 }
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_multi_turn_conversation_with_saved_cache() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add conversations keeping their cache
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_1_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+    let conversation_2_id = conversation_manager.create("What's the last book you have read?");
+    for conversation_id in [conversation_1_id, conversation_2_id] {
+        conversation_manager
+            .get(&conversation_id)
+            .unwrap()
+            .retain_cache = true;
+    }
+
+    // Turn 1
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
+    assert_eq!(
+        output.get(&conversation_2_id).unwrap(),
+        &"The Last Question"
+    );
+
+    // Save the cache of the first conversation and evict it
+    let cache_dir = tempfile::tempdir()?;
+    let cache_path = cache_dir.path().join("conversation_cache.ot");
+    let conversation = conversation_manager.remove(&conversation_1_id).unwrap();
+    let history = conversation
+        .history
+        .iter()
+        .flatten()
+        .copied()
+        .collect::<Vec<i64>>();
+    let cache = conversation.cache.as_ref().unwrap();
+    assert!(!cache.token_ids().is_empty());
+    assert!(history.starts_with(cache.token_ids()));
+    cache.save(&cache_path)?;
+
+    // Turn 2: resume the first conversation from its history and saved cache
+    let texts = [
+        "Going to the movies tonight - any suggestions?",
+        "The Big Lebowski",
+        "Is it an action movie?",
+    ];
+    let mut history_ids = conversation.history.clone();
+    history_ids.extend(conversation_model.encode_prompts(&texts[2..]));
+    let conversation_1_id = conversation_manager.create_empty();
+    let restored_conversation = conversation_manager.get(&conversation_1_id).unwrap();
+    restored_conversation.load_from_history(&texts, &history_ids);
+    restored_conversation.retain_cache = true;
+    restored_conversation.cache = Some(ConversationCache::load(&cache_path, Device::Cpu)?);
+    let _ = conversation_manager
+        .get(&conversation_2_id)
+        .unwrap()
+        .add_user_input("Why do you recommend it?");
+
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"It\'s a comedy.");
+    assert_eq!(
+        output.get(&conversation_2_id).unwrap(),
+        &"It's a good book."
+    );
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_invalid_config() -> anyhow::Result<()> {
+    let config_dir = tempfile::tempdir()?;
+
+    //    Missing configuration file
+    let missing_config_path = config_dir.path().join("missing_config.json");
+    assert!(matches!(
+        Gpt2Config::try_from_file(&missing_config_path),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    //    Invalid value for a configuration key
+    let invalid_config_path = config_dir.path().join("config.json");
+    std::fs::write(&invalid_config_path, r#"{"vocab_size": "50257"}"#)?;
+    match ConfigOption::try_from_file(ModelType::GPT2, &invalid_config_path) {
+        Err(RustBertError::InvalidConfigurationError(message)) => {
+            assert!(message.contains("invalid type"))
+        }
+        _ => panic!("Expected an invalid configuration error"),
+    };
+
+    Ok(())
+}
@@ -143,7 +143,54 @@ fn test_generation_gpt_neo() -> anyhow::Result<()> {
 
     let input_context_1 = "It was a very nice and sunny";
     let input_context_2 = "It was a gloom winter night, and";
-    let output = model.generate(&[input_context_1, input_context_2], None);
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0], "It was a very nice and sunny day. The sun was shining through the clouds, and the sky was clear. The wind was blowing through the trees,");
+    assert_eq!(output[1], "It was a gloom winter night, and the sky was dark and cold, and the wind was blowing thick and heavy.\n\n\"What\'s the matter?\"");
+
+    Ok(())
+}
+
+#[test]
+fn test_generation_gpt_neo_static_cache() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        GptNeoConfigResources::GPT_NEO_125M,
+    ));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(
+        GptNeoVocabResources::GPT_NEO_125M,
+    ));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(
+        GptNeoMergesResources::GPT_NEO_125M,
+    ));
+    let model_resource = Box::new(RemoteResource::from_pretrained(
+        GptNeoModelResources::GPT_NEO_125M,
+    ));
+
+    //    Set-up model
+    let generation_config = TextGenerationConfig {
+        model_type: ModelType::GPTNeo,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        min_length: 10,
+        max_length: Some(32),
+        do_sample: false,
+        early_stopping: true,
+        num_beams: 4,
+        num_return_sequences: 1,
+        static_cache: true,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+
+    let model = TextGenerationModel::new(generation_config)?;
+
+    let input_context_1 = "It was a very nice and sunny";
+    let input_context_2 = "It was a gloom winter night, and";
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
 
     assert_eq!(output.len(), 2);
     assert_eq!(output[0], "It was a very nice and sunny day. The sun was shining through the clouds, and the sky was clear. The wind was blowing through the trees,");
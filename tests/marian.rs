@@ -1,10 +1,11 @@
 use rust_bert::marian::{
-    MarianConfigResources, MarianModelResources, MarianSourceLanguages, MarianSpmResources,
-    MarianTargetLanguages, MarianVocabResources,
+    MarianConfigResources, MarianGenerator, MarianModelResources, MarianSourceLanguages,
+    MarianSpmResources, MarianTargetLanguages, MarianVocabResources,
 };
 use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
 use rust_bert::pipelines::translation::{
-    Language, TranslationConfig, TranslationModel, TranslationModelBuilder,
+    Language, TranslationConfig, TranslationHypothesis, TranslationModel, TranslationModelBuilder,
 };
 use rust_bert::resources::RemoteResource;
 use tch::Device;
@@ -72,3 +73,145 @@ fn test_translation_builder() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+// #[cfg_attr(not(feature = "all-tests"), ignore)]
+fn test_translation_with_details() -> anyhow::Result<()> {
+    //    Set-up translation model
+    let model_resource = RemoteResource::from_pretrained(MarianModelResources::ENGLISH2ROMANCE);
+    let config_resource = RemoteResource::from_pretrained(MarianConfigResources::ENGLISH2ROMANCE);
+    let vocab_resource = RemoteResource::from_pretrained(MarianVocabResources::ENGLISH2ROMANCE);
+    let merges_resource = RemoteResource::from_pretrained(MarianSpmResources::ENGLISH2ROMANCE);
+
+    let source_languages = MarianSourceLanguages::ENGLISH2ROMANCE;
+    let target_languages = MarianTargetLanguages::ENGLISH2ROMANCE;
+
+    let translation_config = TranslationConfig {
+        output_alignments: true,
+        ..TranslationConfig::new(
+            ModelType::Marian,
+            model_resource,
+            config_resource,
+            vocab_resource,
+            Some(merges_resource),
+            source_languages,
+            target_languages,
+            Device::cuda_if_available(),
+        )
+    };
+    let model = TranslationModel::new(translation_config)?;
+
+    let input_context_1 = "The quick brown fox jumps over the lazy dog";
+    let input_context_2 = "The dog did not wake up";
+
+    let outputs = model.translate_with_details(
+        &[input_context_1, input_context_2],
+        None,
+        Language::French,
+        3,
+    )?;
+
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(
+        outputs[0][0].text,
+        " Le rapide renard brun saute sur le chien paresseux"
+    );
+    assert_eq!(outputs[1][0].text, " Le chien ne s'est pas réveillé");
+    for (input_context, hypotheses) in [input_context_1, input_context_2].iter().zip(&outputs) {
+        assert_eq!(hypotheses.len(), 3);
+        assert!(hypotheses
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        let num_source_words = input_context.split_whitespace().count();
+        for hypothesis in hypotheses {
+            assert!(!hypothesis.token_scores.is_empty());
+            assert!(hypothesis.log_probability < 0.0);
+            let num_target_words = hypothesis.text.split_whitespace().count();
+            let alignments = hypothesis.alignments.as_ref().unwrap();
+            assert_eq!(alignments.len(), num_target_words);
+            assert!(alignments.iter().all(|alignment| {
+                (alignment.source_word < num_source_words)
+                    & (alignment.target_word < num_target_words)
+            }));
+        }
+    }
+
+    //    Known word pairs are aligned ("renard" -> "fox", "chien" -> "dog")
+    let aligned_source_word = |hypothesis: &TranslationHypothesis, target_word: usize| {
+        hypothesis
+            .alignments
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|alignment| alignment.target_word == target_word)
+            .map(|alignment| alignment.source_word)
+    };
+    assert_eq!(aligned_source_word(&outputs[0][0], 2), Some(3));
+    assert_eq!(aligned_source_word(&outputs[1][0], 1), Some(1));
+
+    Ok(())
+}
+
+#[test]
+fn test_conditional_scoring() -> anyhow::Result<()> {
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            MarianModelResources::ENGLISH2ROMANCE,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            MarianConfigResources::ENGLISH2ROMANCE,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            MarianVocabResources::ENGLISH2ROMANCE,
+        )),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            MarianSpmResources::ENGLISH2ROMANCE,
+        ))),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = MarianGenerator::new(generate_config)?;
+
+    let source = ">>fr<< The dog did not wake up";
+    let sources = [source, source, source];
+    let targets = [
+        "Le chien ne s'est pas réveillé",
+        "Le chat dort sur le canapé depuis ce matin",
+        "Il pleut",
+    ];
+
+    let output = model.score_conditional(&sources, &targets, 8)?;
+
+    assert_eq!(output.len(), 3);
+    for text_score in &output {
+        // All target tokens are scored, including the end of sequence token
+        assert_eq!(text_score.num_scored_tokens, text_score.token_ids.len());
+        assert!(text_score.log_likelihood < 0.0);
+    }
+    assert!(output[0].perplexity.unwrap() < output[1].perplexity.unwrap());
+    assert!(output[0].perplexity.unwrap() < output[2].perplexity.unwrap());
+
+    //    Padding of the sources and targets does not change the scores
+    let unbatched_output = model.score_conditional(&sources, &targets, 1)?;
+    for (text_score, unbatched_text_score) in output.iter().zip(unbatched_output.iter()) {
+        assert_eq!(text_score.token_ids, unbatched_text_score.token_ids);
+        assert!((text_score.log_likelihood - unbatched_text_score.log_likelihood).abs() < 1e-3);
+    }
+
+    //    Sources of different lengths are padded without changing the scores
+    let sources = [
+        ">>fr<< The dog did not wake up",
+        ">>fr<< The cat has been sleeping on the sofa since this morning",
+        ">>fr<< It rains",
+    ];
+    let output = model.score_conditional(&sources, &targets, 8)?;
+    let unbatched_output = model.score_conditional(&sources, &targets, 1)?;
+    for (text_score, unbatched_text_score) in output.iter().zip(unbatched_output.iter()) {
+        assert_eq!(text_score.token_ids, unbatched_text_score.token_ids);
+        assert!((text_score.log_likelihood - unbatched_text_score.log_likelihood).abs() < 1e-3);
+    }
+
+    assert!(model.score_conditional(&sources, &targets[..2], 8).is_err());
+
+    Ok(())
+}
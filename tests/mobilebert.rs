@@ -4,8 +4,12 @@ use rust_bert::mobilebert::{
     MobileBertForSequenceClassification, MobileBertForTokenClassification,
     MobileBertModelResources, MobileBertVocabResources,
 };
+use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::masked_language::{
+    FillMaskOptions, MaskedLanguageConfig, MaskedLanguageModel,
+};
 use rust_bert::pipelines::pos_tagging::POSModel;
-use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
 use rust_bert::Config;
 use rust_tokenizers::tokenizer::{BertTokenizer, MultiThreadedTokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::Vocab;
@@ -381,3 +385,115 @@ fn mobilebert_part_of_speech_tagging() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn mobilebert_masked_lm_pipeline() -> anyhow::Result<()> {
+    //    Save a small randomly initialized model
+    let model_dir = tempfile::tempdir()?;
+    let config_path = model_dir.path().join("config.json");
+    let weights_path = model_dir.path().join("rust_model.ot");
+    let mut config = MobileBertConfig::from_file(
+        RemoteResource::from_pretrained(MobileBertConfigResources::MOBILEBERT_UNCASED)
+            .get_local_path()?,
+    );
+    config.embedding_size = 16;
+    config.hidden_size = 64;
+    config.intra_bottleneck_size = Some(32);
+    config.intermediate_size = 32;
+    config.num_attention_heads = 2;
+    config.num_hidden_layers = 2;
+    config.num_feedforward_networks = Some(2);
+    std::fs::write(&config_path, serde_json::to_string(&config)?)?;
+    let vs = nn::VarStore::new(Device::Cpu);
+    let _ = MobileBertForMaskedLM::new(vs.root(), &config);
+    vs.save(&weights_path)?;
+
+    //    Set-up model
+    let mut masked_lm_config = MaskedLanguageConfig::new(
+        ModelType::MobileBert,
+        LocalResource {
+            local_path: weights_path,
+        },
+        LocalResource {
+            local_path: config_path,
+        },
+        RemoteResource::from_pretrained(MobileBertVocabResources::MOBILEBERT_UNCASED),
+        None,
+        true,
+        None,
+        None,
+        Some(String::from("<m>")),
+    );
+    masked_lm_config.device = Device::Cpu;
+    let masked_lm_model = MaskedLanguageModel::new(masked_lm_config)?;
+
+    //    Define input (the `<m>` mask token is shorter than the mask token of the model)
+    let input = [
+        "Hello I am a <m> student",
+        "Paris is the <m> of France. It is <m> in Europe.",
+    ];
+
+    //    Run model
+    let output = masked_lm_model.predict(input)?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].len(), 1);
+    assert_eq!(output[1].len(), 2);
+    for token in output.iter().flatten() {
+        assert!((0..config.vocab_size).contains(&token.id));
+    }
+
+    //    Mask offsets and candidate probabilities
+    let output = masked_lm_model.predict_with_options(input, &FillMaskOptions::default())?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].len(), 1);
+    assert_eq!(output[1].len(), 2);
+    let offsets = output
+        .iter()
+        .flatten()
+        .map(|span| span.offset.map(|offset| (offset.begin, offset.end)))
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, [Some((13, 16)), Some((13, 16)), Some((34, 37))]);
+    for span in output.iter().flatten() {
+        assert_eq!(span.num_masks, 1);
+        assert_eq!(span.candidates.len(), 1);
+        assert!(span.candidates[0].score > 0.0 && span.candidates[0].score <= 1.0);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn mobilebert_masked_lm_pipeline_pretrained() -> anyhow::Result<()> {
+    //    Set-up model
+    let config = MaskedLanguageConfig::new(
+        ModelType::MobileBert,
+        RemoteResource::from_pretrained(MobileBertModelResources::MOBILEBERT_UNCASED),
+        RemoteResource::from_pretrained(MobileBertConfigResources::MOBILEBERT_UNCASED),
+        RemoteResource::from_pretrained(MobileBertVocabResources::MOBILEBERT_UNCASED),
+        None,
+        true,
+        None,
+        None,
+        None,
+    );
+    let masked_lm_model = MaskedLanguageModel::new(config)?;
+
+    //    Define input, using the [MASK] mask token of the tokenizer
+    let input = [
+        "Looks like one [MASK] is missing",
+        "It was a very nice and [MASK] day",
+    ];
+
+    //    Run model
+    let output = masked_lm_model.predict(input)?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].len(), 1);
+    assert_eq!(output[0][0].text.trim(), "thing");
+    assert_eq!(output[1].len(), 1);
+    assert_eq!(output[1][0].text.trim(), "sunny");
+
+    Ok(())
+}
@@ -1,11 +1,14 @@
 use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
+use rust_bert::pipelines::scoring::ScoringOptions;
 use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
 use rust_bert::reformer::{
     ReformerConfig, ReformerConfigResources, ReformerForQuestionAnswering,
-    ReformerForSequenceClassification, ReformerModelResources, ReformerVocabResources,
+    ReformerForSequenceClassification, ReformerGenerator, ReformerModelResources,
+    ReformerVocabResources,
 };
 use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
-use rust_bert::Config;
+use rust_bert::{Config, RustBertError};
 use rust_tokenizers::tokenizer::{MultiThreadedTokenizer, ReformerTokenizer, TruncationStrategy};
 use std::collections::HashMap;
 use std::fs::File;
@@ -64,7 +67,7 @@ fn test_generation_reformer() -> anyhow::Result<()> {
 
     let input_context_1 = "The really great men must, I think,";
     let input_context_2 = "It was a gloom winter night, and";
-    let output = model.generate(&[input_context_1, input_context_2], None);
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
 
     assert_eq!(output.len(), 2);
     assert_eq!(output[0], " The really great men must, I think, anyway waiting for some unknown reason, but Nikodim Fomitch and Ilya Petrovitch looked at him anguish invitable incidently at him. He could not resist an impression which might be setting");
@@ -73,6 +76,33 @@ fn test_generation_reformer() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn reformer_scoring_not_supported() -> anyhow::Result<()> {
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            ReformerModelResources::CRIME_AND_PUNISHMENT,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            ReformerConfigResources::CRIME_AND_PUNISHMENT,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            ReformerVocabResources::CRIME_AND_PUNISHMENT,
+        )),
+        merges_resource: None,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = ReformerGenerator::new(generate_config)?;
+
+    let output = model.score(
+        &["It was a gloom winter night, and"],
+        &ScoringOptions::default(),
+    );
+    assert!(matches!(output, Err(RustBertError::ValueError(_))));
+
+    Ok(())
+}
+
 #[test]
 fn reformer_for_sequence_classification() -> anyhow::Result<()> {
     //    Resources paths
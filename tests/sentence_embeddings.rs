@@ -4,6 +4,8 @@ use rust_bert::pipelines::keywords_extraction::{
 use rust_bert::pipelines::sentence_embeddings::{
     SentenceEmbeddingsBuilder, SentenceEmbeddingsConfig, SentenceEmbeddingsModelType,
 };
+use rust_bert::quantization::QuantizationMode;
+use tch::Device;
 
 #[test]
 fn sbert_distilbert() -> anyhow::Result<()> {
@@ -32,6 +34,49 @@ fn sbert_distilbert() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn sbert_distilbert_quantized() -> anyhow::Result<()> {
+    let model = SentenceEmbeddingsBuilder::remote(
+        SentenceEmbeddingsModelType::DistiluseBaseMultilingualCased,
+    )
+    .with_device(Device::Cpu)
+    .create_model()?;
+    let quantized_model = SentenceEmbeddingsBuilder::remote(
+        SentenceEmbeddingsModelType::DistiluseBaseMultilingualCased,
+    )
+    .with_device(Device::Cpu)
+    .with_quantization(QuantizationMode::DynamicInt8)
+    .create_model()?;
+
+    let sentences = [
+        "This is an example sentence",
+        "Each sentence is converted",
+        "Paris is the capital of France",
+        "The weather is lovely today",
+    ];
+    let embeddings = model.encode(&sentences)?;
+    let quantized_embeddings = quantized_model.encode(&sentences)?;
+
+    assert_eq!(quantized_embeddings.len(), embeddings.len());
+    for (quantized_embedding, embedding) in quantized_embeddings.iter().zip(embeddings.iter()) {
+        let dot_product: f32 = quantized_embedding
+            .iter()
+            .zip(embedding.iter())
+            .map(|(a, b)| a * b)
+            .sum();
+        let quantized_norm = quantized_embedding
+            .iter()
+            .map(|a| a * a)
+            .sum::<f32>()
+            .sqrt();
+        let norm = embedding.iter().map(|a| a * a).sum::<f32>().sqrt();
+        let cosine_similarity = dot_product / (quantized_norm * norm);
+        assert!(cosine_similarity > 0.98);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn sbert_bert() -> anyhow::Result<()> {
     let model =
@@ -0,0 +1,210 @@
+extern crate anyhow;
+
+use rust_bert::weights::{
+    load_weights, load_weights_with_mode, read_pytorch_checkpoint, ShapeMismatch,
+    WeightsLoadingMode, WeightsLoadingReport,
+};
+use rust_bert::RustBertError;
+use safetensors::tensor::{serialize_to_file, Dtype, TensorView};
+use std::fs::File;
+use std::io::Write;
+use tch::{nn, Device, Kind, Tensor};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Pickled state dictionary of a checkpoint saved with `torch.save` (protocol 2), holding views on
+/// a float storage (`data/0`, 6 elements) and an int64 storage (`data/1`, 4 elements):
+/// - `dense.weight`: size (3, 2), stride (2, 1)
+/// - `dense.weight_t`: size (2, 3), stride (1, 2) (transposed view of the same storage)
+/// - `LayerNorm.gamma`: size (3,), offset 3
+/// - `position_ids`: size (1, 4)
+const STATE_DICT_PICKLE: &[u8] =
+    b"\x80\x02ccollections\x0aOrderedDict\x0aq\x00)Rq\x01(X\x0c\x00\x00\x00dense.weightq\x02ctorc\
+      h._utils\x0a_rebuild_tensor_v2\x0aq\x03((X\x07\x00\x00\x00storageq\x04ctorch\x0aFloatStorag\
+      e\x0aq\x05X\x01\x00\x00\x000q\x06X\x03\x00\x00\x00cpuq\x07K\x06tq\x08QK\x00K\x03K\x02\x86q\
+      \x09K\x02K\x01\x86q\x0a\x89h\x00)Rq\x0btq\x0cRq\x0dX\x0e\x00\x00\x00dense.weight_tq\x0eh\
+      \x03((h\x04h\x05h\x06h\x07K\x06tq\x0fQK\x00K\x02K\x03\x86q\x10K\x01K\x02\x86q\x11\x89h\x00)\
+      Rq\x12tq\x13Rq\x14X\x0f\x00\x00\x00LayerNorm.gammaq\x15h\x03((h\x04h\x05h\x06h\x07K\x06tq\
+      \x16QK\x03K\x03\x85q\x17K\x01\x85q\x18\x89h\x00)Rq\x19tq\x1aRq\x1bX\x0c\x00\x00\x00position\
+      _idsq\x1ch\x03((h\x04ctorch\x0aLongStorage\x0aq\x1dX\x01\x00\x00\x001q\x1eh\x07K\x04tq\x1fQ\
+      K\x00K\x01K\x04\x86q\x20K\x04K\x01\x86q!\x89h\x00)Rq\x22tq#Rq$u}q%X\x09\x00\x00\x00_metadat\
+      aq&h\x00)Rq'X\x00\x00\x00\x00q(}q)X\x07\x00\x00\x00versionq*K\x01sssb.";
+
+fn to_bytes(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<u8>>()
+}
+
+#[test]
+fn safetensors_weights_loading() -> anyhow::Result<()> {
+    //    Save a set of Pytorch-style named weights to a safetensors file
+    let weights_dir = tempfile::tempdir()?;
+    let weights_path = weights_dir.path().join("model.safetensors");
+
+    let dense_weight = to_bytes(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    let layer_norm_gamma = to_bytes(&[2.0, 2.0, 2.0]);
+    let layer_norm_beta = to_bytes(&[-1.0, -1.0, -1.0]);
+    serialize_to_file(
+        vec![
+            (
+                "model.dense.weight",
+                TensorView::new(Dtype::F32, vec![3, 2], &dense_weight)?,
+            ),
+            (
+                "model.LayerNorm.gamma",
+                TensorView::new(Dtype::F32, vec![3], &layer_norm_gamma)?,
+            ),
+            (
+                "model.LayerNorm.beta",
+                TensorView::new(Dtype::F32, vec![3], &layer_norm_beta)?,
+            ),
+        ],
+        &None,
+        &weights_path,
+    )?;
+
+    //    Set-up model and load weights
+    let mut vs = nn::VarStore::new(Device::Cpu);
+    let dense = nn::linear(
+        &vs.root() / "dense",
+        2,
+        3,
+        nn::LinearConfig {
+            bias: false,
+            ..Default::default()
+        },
+    );
+    let layer_norm = nn::layer_norm(&vs.root() / "LayerNorm", vec![3], Default::default());
+    load_weights(&weights_path, &mut vs)?;
+
+    assert_eq!(dense.ws.size(), vec![3, 2]);
+    assert!((dense.ws.double_value(&[0, 1]) - 1.0).abs() < 1e-6);
+    assert!((dense.ws.double_value(&[2, 1]) - 5.0).abs() < 1e-6);
+    assert!((layer_norm.ws.unwrap().double_value(&[1]) - 2.0).abs() < 1e-6);
+    assert!((layer_norm.bs.unwrap().double_value(&[1]) + 1.0).abs() < 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn weights_loading_report() -> anyhow::Result<()> {
+    //    Save a set of weights with a shape mismatch, a missing and unexpected tensors. Prefixed
+    //    names are only matched for safetensors files: `encoder.LayerNorm.bias` is not loaded.
+    let weights_dir = tempfile::tempdir()?;
+    let weights_path = weights_dir.path().join("rust_model.ot");
+    Tensor::save_multi(
+        &[
+            ("dense.weight", Tensor::ones(&[2, 2], tch::kind::FLOAT_CPU)),
+            ("LayerNorm.weight", Tensor::of_slice(&[2f32, 2.0, 2.0])),
+            ("pooler.weight", Tensor::ones(&[3, 3], tch::kind::FLOAT_CPU)),
+            (
+                "encoder.LayerNorm.bias",
+                Tensor::of_slice(&[1f32, 1.0, 1.0]),
+            ),
+        ],
+        &weights_path,
+    )?;
+
+    let mut vs = nn::VarStore::new(Device::Cpu);
+    let dense = nn::linear(
+        &vs.root() / "dense",
+        2,
+        3,
+        nn::LinearConfig {
+            bias: false,
+            ..Default::default()
+        },
+    );
+    let layer_norm = nn::layer_norm(&vs.root() / "LayerNorm", vec![3], Default::default());
+    let initial_dense_weight = dense.ws.copy();
+
+    let expected_report = WeightsLoadingReport {
+        missing: vec!["LayerNorm.bias".to_string()],
+        unexpected: vec![
+            "encoder.LayerNorm.bias".to_string(),
+            "pooler.weight".to_string(),
+        ],
+        shape_mismatches: vec![ShapeMismatch {
+            name: "dense.weight".to_string(),
+            expected: vec![3, 2],
+            found: vec![2, 2],
+        }],
+    };
+
+    //    Strict loading fails without updating the variables
+    match load_weights(&weights_path, &mut vs) {
+        Err(RustBertError::WeightsLoadingError(report)) => assert_eq!(report, expected_report),
+        _ => panic!("Expected a weights loading error"),
+    }
+    assert!((layer_norm.ws.as_ref().unwrap().double_value(&[1]) - 1.0).abs() < 1e-6);
+
+    //    Partial loading keeps the initial values of the variables that cannot be loaded
+    let report = load_weights_with_mode(&weights_path, &mut vs, WeightsLoadingMode::Partial)?;
+    assert_eq!(report, expected_report);
+    assert!(!report.is_complete());
+    assert!((layer_norm.ws.unwrap().double_value(&[1]) - 2.0).abs() < 1e-6);
+    assert!(layer_norm.bs.unwrap().double_value(&[1]).abs() < 1e-6);
+    assert!(dense.ws.equal(&initial_dense_weight));
+
+    Ok(())
+}
+
+#[test]
+fn pytorch_checkpoint_reading() -> anyhow::Result<()> {
+    //    Save a Pytorch checkpoint archive: pickled state dictionary and tensor storages
+    let weights_dir = tempfile::tempdir()?;
+    let weights_path = weights_dir.path().join("pytorch_model.bin");
+    let mut archive = ZipWriter::new(File::create(&weights_path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    archive.start_file("archive/data.pkl", options)?;
+    archive.write_all(STATE_DICT_PICKLE)?;
+    archive.start_file("archive/data/0", options)?;
+    archive.write_all(&to_bytes(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]))?;
+    archive.start_file("archive/data/1", options)?;
+    archive.write_all(
+        &[0i64, 1, 2, 3]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<u8>>(),
+    )?;
+    archive.start_file("archive/version", options)?;
+    archive.write_all(b"3\n")?;
+    archive.finish()?;
+
+    let tensors = read_pytorch_checkpoint(&weights_path)?;
+    let names = tensors
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(
+        names,
+        vec![
+            "dense.weight",
+            "dense.weight_t",
+            "LayerNorm.gamma",
+            "position_ids"
+        ]
+    );
+
+    let (_, dense_weight) = &tensors[0];
+    assert_eq!(dense_weight.size(), vec![3, 2]);
+    assert_eq!(dense_weight.kind(), Kind::Float);
+    assert!((dense_weight.double_value(&[2, 1]) - 5.0).abs() < 1e-6);
+    let (_, dense_weight_t) = &tensors[1];
+    assert_eq!(dense_weight_t.size(), vec![2, 3]);
+    assert!(dense_weight_t.equal(&dense_weight.transpose(0, 1)));
+    let (_, layer_norm_gamma) = &tensors[2];
+    assert!(layer_norm_gamma.equal(&Tensor::of_slice(&[3f32, 4.0, 5.0])));
+    let (_, position_ids) = &tensors[3];
+    assert_eq!(position_ids.kind(), Kind::Int64);
+    assert!(position_ids.equal(&Tensor::of_slice(&[0i64, 1, 2, 3]).view([1, 4])));
+
+    //    Files that are not zip archives (e.g. legacy Pytorch checkpoints) are rejected
+    let legacy_path = weights_dir.path().join("legacy_model.bin");
+    std::fs::write(&legacy_path, STATE_DICT_PICKLE)?;
+    assert!(read_pytorch_checkpoint(&legacy_path).is_err());
+
+    Ok(())
+}
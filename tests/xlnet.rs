@@ -1,4 +1,5 @@
 use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::scoring::ScoringOptions;
 use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
 use rust_bert::resources::{RemoteResource, ResourceProvider};
 use rust_bert::xlnet::{
@@ -222,7 +223,7 @@ fn xlnet_generation_beam_search() -> anyhow::Result<()> {
     let model = TextGenerationModel::new(generate_config)?;
 
     let input_context = "Once upon a time,";
-    let output = model.generate(&[input_context], None);
+    let output = model.generate(&[input_context], None)?;
 
     assert_eq!(output.len(), 1);
     assert_eq!(
@@ -233,6 +234,60 @@ fn xlnet_generation_beam_search() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn xlnet_scoring() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        XLNetConfigResources::XLNET_BASE_CASED,
+    ));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(
+        XLNetVocabResources::XLNET_BASE_CASED,
+    ));
+    let model_resource = Box::new(RemoteResource::from_pretrained(
+        XLNetModelResources::XLNET_BASE_CASED,
+    ));
+
+    let generate_config = TextGenerationConfig {
+        model_type: ModelType::XLNet,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: None,
+        ..Default::default()
+    };
+    let model = TextGenerationModel::new(generate_config)?;
+
+    let input = [
+        "Once upon a time, there was a little girl who lived in a village near the forest.",
+        "Time a upon once.",
+    ];
+    let output = model.score(&input, &ScoringOptions::default())?;
+
+    assert_eq!(output.len(), 2);
+    for text_score in &output {
+        assert_eq!(
+            text_score.token_ids.len(),
+            text_score.token_log_probabilities.len()
+        );
+        assert!(text_score.token_log_probabilities[0].is_none());
+        assert_eq!(text_score.num_scored_tokens, text_score.token_ids.len() - 1);
+        assert!(text_score.log_likelihood < 0.0);
+    }
+
+    let unbatched_output = model.score(
+        &input,
+        &ScoringOptions {
+            batch_size: 1,
+            ..Default::default()
+        },
+    )?;
+    for (text_score, unbatched_text_score) in output.iter().zip(unbatched_output.iter()) {
+        assert!((text_score.log_likelihood - unbatched_text_score.log_likelihood).abs() < 1e-3);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn xlnet_for_sequence_classification() -> anyhow::Result<()> {
     //    Resources paths